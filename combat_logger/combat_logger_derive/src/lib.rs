@@ -0,0 +1,183 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitInt};
+
+/// Derives `RemoteField` for a struct laid out at fixed offsets in the memory
+/// of another process
+///
+/// Every field needs an `#[offset(0x40)]` attribute. Fields of type
+/// `RemotePtr<T>` are marked with `#[pointer]` and only store the address,
+/// which is followed later with `RemotePtr::read`. The struct size defaults to
+/// the end of its last field and can be set with `#[remote(size = 0x200)]`.
+///
+/// ```ignore
+/// #[derive(RemoteStruct)]
+/// #[remote(size = 0x200)]
+/// struct Player {
+///     #[offset(0x40)]
+///     level: u32,
+///
+///     #[offset(0x10)]
+///     #[pointer]
+///     actor: RemotePtr<Actor>,
+/// }
+/// ```
+#[proc_macro_derive(RemoteStruct, attributes(offset, pointer, remote))]
+pub fn derive_remote_struct(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// A field of the derived struct with its parsed attributes
+struct RemoteFieldInfo<'a> {
+    /// Field name
+    ident: &'a syn::Ident,
+
+    /// Field type
+    ty: &'a syn::Type,
+
+    /// Offset from the start of the struct
+    offset: usize,
+
+    /// Whether the field is a pointer followed lazily
+    pointer: bool,
+}
+
+/// Generates the `RemoteField` implementation
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&input.generics,
+            "RemoteStruct does not support generic structs"));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(name,
+                "RemoteStruct requires a struct with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(name,
+            "RemoteStruct can only be derived for structs")),
+    };
+
+    let fields = fields.iter()
+        .map(parse_field)
+        .collect::<syn::Result<Vec<_>>>()?;
+    let size = parse_size(&input.attrs)?;
+
+    // Size and decoding expression of every field
+    let field_sizes: Vec<TokenStream2> = fields.iter()
+        .map(|field| {
+            let ty = field.ty;
+            if field.pointer {
+                quote!(::combat_logger::memory::POINTER_SIZE)
+            }
+            else {
+                quote!(<#ty as ::combat_logger::memory::RemoteField>::SIZE)
+            }
+        })
+        .collect();
+    let decoders = fields.iter().zip(&field_sizes).map(|(field, size)| {
+        let ident = field.ident;
+        let ty = field.ty;
+        let offset = field.offset;
+        let bytes = quote!(&bytes[#offset..#offset + #size]);
+        if field.pointer {
+            quote!(#ident: <#ty>::from_bytes(#bytes))
+        }
+        else {
+            quote! {
+                #ident: <#ty as ::combat_logger::memory::RemoteField>::decode(
+                    #bytes)
+            }
+        }
+    });
+
+    // Either check the fields against the declared size or derive the size
+    // from the end of the last field
+    let ends: Vec<TokenStream2> = fields.iter().zip(&field_sizes)
+        .map(|(field, size)| {
+            let offset = field.offset;
+            quote!(#offset + #size)
+        })
+        .collect();
+    let (size, checks) = match size {
+        Some(size) => {
+            let checks = fields.iter().zip(&ends).map(|(field, end)| {
+                let message = format!("field `{}` of `{}` ends past the \
+                    struct size", field.ident, name);
+                quote!(assert!(#end <= #size, #message);)
+            });
+            (quote!(#size), quote!(const _: () = { #(#checks)* };))
+        }
+        None => {
+            let size = quote! {{
+                let mut size = 0;
+                #(
+                    if #ends > size {
+                        size = #ends;
+                    }
+                )*
+                size
+            }};
+            (size, quote!())
+        }
+    };
+
+    Ok(quote! {
+        impl ::combat_logger::memory::RemoteField for #name {
+            const SIZE: usize = #size;
+
+            fn decode(bytes: &[u8]) -> Self {
+                Self {
+                    #(#decoders,)*
+                }
+            }
+        }
+
+        #checks
+    })
+}
+
+/// Parses the `#[offset]` and `#[pointer]` attributes of a field
+fn parse_field(field: &syn::Field) -> syn::Result<RemoteFieldInfo<'_>> {
+    let ident = field.ident.as_ref().unwrap();
+    let mut offset = None;
+    let mut pointer = false;
+
+    for attr in &field.attrs {
+        if attr.path().is_ident("offset") {
+            offset = Some(attr.parse_args::<LitInt>()?.base10_parse()?);
+        }
+        else if attr.path().is_ident("pointer") {
+            attr.meta.require_path_only()?;
+            pointer = true;
+        }
+    }
+
+    let offset = offset.ok_or_else(|| syn::Error::new_spanned(ident,
+        "RemoteStruct fields need an #[offset(...)] attribute"))?;
+    Ok(RemoteFieldInfo { ident, ty: &field.ty, offset, pointer })
+}
+
+/// Parses the optional `#[remote(size = ...)]` attribute of the struct
+fn parse_size(attrs: &[syn::Attribute]) -> syn::Result<Option<usize>> {
+    let mut size = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("remote")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("size") {
+                size = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                Ok(())
+            }
+            else {
+                Err(meta.error("unknown remote attribute"))
+            }
+        })?;
+    }
+
+    Ok(size)
+}
@@ -0,0 +1,509 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Duration;
+use serde::Serialize;
+use crate::logger::{CombatEvent, EventKind, Timestamp};
+use super::{format_percent, format_time, Encounter, EncounterDetector};
+
+/// Time a buff was active with a constant stack count
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BuffInterval {
+    /// Actor identifier of the target the buff was on
+    pub target: u64,
+
+    /// Time the stack count was reached
+    pub start: Timestamp,
+
+    /// Time the buff expired or changed its stack count
+    pub end: Timestamp,
+
+    /// Stack count during the interval
+    pub stacks: u32,
+}
+
+/// Uptime of a buff over an encounter, across every target it was on
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BuffUptime {
+    /// Name of the buff
+    pub buff: String,
+
+    /// Whether the buff is harmful to its targets
+    pub debuff: bool,
+
+    /// Number of targets the buff was on during the encounter
+    pub targets: usize,
+
+    /// Number of times the buff was applied or refreshed
+    pub applications: u64,
+
+    /// Fraction of the encounter the buff was on at least one target
+    pub uptime: f64,
+
+    /// Fraction of the encounter the buff was on each target, averaged over
+    /// the targets of the encounter, where a buff on the player counts for
+    /// every target
+    pub target_uptime: f64,
+
+    /// Stack count averaged over the time the buff was active
+    pub average_stacks: f64,
+
+    /// Longest time in seconds the buff was on no target
+    pub longest_gap: f64,
+}
+
+/// Uptime of a set of buffs all being active at once
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CombinationUptime {
+    /// Names of the buffs
+    pub buffs: Vec<String>,
+
+    /// Fraction of the encounter at least one target had every buff at once,
+    /// where a buff on the player counts for every target
+    pub uptime: f64,
+}
+
+/// Buff uptimes of a single encounter
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EncounterBuffs {
+    /// Encounter the report covers
+    pub encounter: Encounter,
+
+    /// Uptime of every buff active during the encounter, by name
+    pub buffs: Vec<BuffUptime>,
+
+    /// Uptime of every registered combination
+    pub combinations: Vec<CombinationUptime>,
+}
+
+/// Buff uptimes of every encounter of a session
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BuffReport {
+    /// Report of every encounter
+    pub encounters: Vec<EncounterBuffs>,
+}
+
+/// Tracks buff and debuff intervals from apply, refresh and expire events
+///
+/// A buff ends at its expire event or, when that never arrives, once its
+/// duration runs out. Applying a buff that is already active refreshes its
+/// duration and stack count.
+///
+/// Damage only gets every multiplier when they all apply to the same hit,
+/// so buffs are combined per target: debuffs count on the target they are
+/// on, and buffs on the player count on every target.
+#[derive(Debug, Clone)]
+pub struct BuffTracker {
+    /// Splits the session into encounters
+    detector: EncounterDetector,
+
+    /// Buffs currently active, by name and target
+    active: HashMap<(String, u64), ActiveBuff>,
+
+    /// Finished intervals and applications of every buff, by name
+    history: BTreeMap<String, BuffHistory>,
+
+    /// Sets of buffs whose combined uptime is reported
+    combinations: Vec<Vec<String>>,
+
+    /// Actors the player fought, by encounter index
+    opponents: BTreeSet<(usize, u64)>,
+
+    /// Time of the latest event
+    now: Timestamp,
+}
+
+/// A buff currently on a target
+#[derive(Debug, Clone)]
+struct ActiveBuff {
+    /// Time the current stack count was reached
+    since: Timestamp,
+
+    /// Current stack count
+    stacks: u32,
+
+    /// Time the buff runs out unless refreshed, if it has a duration
+    expires: Option<Timestamp>,
+}
+
+/// Everything recorded about a buff
+#[derive(Debug, Clone, Default)]
+struct BuffHistory {
+    /// Whether the buff is harmful to its targets
+    debuff: bool,
+
+    /// Finished intervals on every target
+    intervals: Vec<BuffInterval>,
+
+    /// Time of every application
+    applications: Vec<Timestamp>,
+}
+
+/// Clips intervals to a window and merges the overlapping ones, returning
+/// ordered, disjoint ranges
+fn merged(intervals: &[BuffInterval], window: &Encounter)
+        -> Vec<(u64, u64)> {
+    union(intervals.iter()
+        .map(|interval| (interval.start.0.max(window.start.0),
+            interval.end.0.min(window.end.0)))
+        .filter(|(start, end)| start < end)
+        .collect())
+}
+
+/// Merges overlapping ranges, returning ordered, disjoint ranges
+fn union(mut ranges: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+    ranges.sort_unstable();
+
+    let mut merged: Vec<(u64, u64)> = vec![];
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Intersects two sets of ordered, disjoint ranges
+fn intersect(a: &[(u64, u64)], b: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    let mut result = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let start = a[i].0.max(b[j].0);
+        let end = a[i].1.min(b[j].1);
+        if start < end {
+            result.push((start, end));
+        }
+        if a[i].1 < b[j].1 {
+            i += 1;
+        }
+        else {
+            j += 1;
+        }
+    }
+    result
+}
+
+/// Total length of ranges in microseconds
+fn total(ranges: &[(u64, u64)]) -> u64 {
+    ranges.iter().map(|(start, end)| end - start).sum()
+}
+
+impl BuffTracker {
+    /// Create a new tracker for the player with the given actor identifier
+    pub fn new(player: u64) -> Self {
+        Self {
+            detector: EncounterDetector::new(player),
+            active: HashMap::new(),
+            history: BTreeMap::new(),
+            combinations: vec![],
+            opponents: BTreeSet::new(),
+            now: Timestamp(0),
+        }
+    }
+
+    /// Sets the idle time that ends an encounter
+    pub fn idle_gap(mut self, idle_gap: Duration) -> Self {
+        self.detector = self.detector.idle_gap(idle_gap);
+        self
+    }
+
+    /// Also reports the time all of the given buffs are active at once,
+    /// such as `["Vulnerable", "Berserking"]`
+    pub fn combination(mut self, buffs: &[&str]) -> Self {
+        self.combinations.push(buffs.iter().map(|buff| buff.to_string())
+            .collect());
+        self
+    }
+
+    /// Feeds the next event of the session
+    pub fn push(&mut self, event: &CombatEvent) {
+        self.now = self.now.max(event.timestamp);
+        self.expire(event.timestamp);
+        let encounter = self.detector.push(event);
+
+        match &event.kind {
+            EventKind::Damage(damage) => {
+                let player = self.detector.player();
+                let opponent = if damage.source.id == player {
+                    damage.target.id
+                }
+                else {
+                    damage.source.id
+                };
+                if let Some(index) = encounter {
+                    self.opponents.insert((index, opponent));
+                }
+            }
+            EventKind::BuffApplied(applied) => {
+                let key = (applied.buff.clone(), applied.target.id);
+                self.end(&key, event.timestamp);
+
+                let history = self.history.entry(applied.buff.clone())
+                    .or_default();
+                history.debuff = applied.debuff;
+                history.applications.push(event.timestamp);
+
+                let expires = applied.duration.map(|duration| {
+                    Timestamp(event.timestamp.0 + (duration * 1e6) as u64)
+                });
+                self.active.insert(key, ActiveBuff {
+                    since: event.timestamp,
+                    stacks: applied.stacks,
+                    expires,
+                });
+            }
+            EventKind::BuffExpired(expired) => {
+                let key = (expired.buff.clone(), expired.target.id);
+                self.end(&key, event.timestamp);
+            }
+            _ => {}
+        }
+    }
+
+    /// Every interval of a buff so far, on every target, with active buffs
+    /// cut off at the latest event
+    pub fn intervals(&self, buff: &str) -> Vec<BuffInterval> {
+        let mut intervals = self.history.get(buff)
+            .map_or(vec![], |history| history.intervals.clone());
+        intervals.extend(self.active.iter()
+            .filter(|((name, _), _)| name == buff)
+            .filter_map(|((_, target), active)| self.open_interval(*target,
+                active)));
+        intervals.sort_by_key(|interval| (interval.start, interval.target));
+        intervals
+    }
+
+    /// Report over everything fed so far
+    pub fn report(&self) -> BuffReport {
+        let intervals: BTreeMap<&str, Vec<BuffInterval>> = self.history.keys()
+            .map(|buff| (buff.as_str(), self.intervals(buff)))
+            .collect();
+
+        let encounters = self.detector.encounters().iter()
+            .map(|encounter| {
+                let targets = self.targets(&intervals, encounter);
+                let buffs = self.history.iter()
+                    .filter_map(|(buff, history)| self.uptime(buff, history,
+                        &intervals[buff.as_str()], &targets, encounter))
+                    .collect();
+                let combinations = self.combinations.iter()
+                    .map(|buffs| CombinationUptime {
+                        buffs: buffs.clone(),
+                        uptime: self.combined_uptime(buffs, &intervals,
+                            &targets, encounter),
+                    })
+                    .collect();
+                EncounterBuffs { encounter: *encounter, buffs, combinations }
+            })
+            .collect();
+
+        BuffReport { encounters }
+    }
+
+    /// Ends every active buff whose duration ran out by the given time
+    fn expire(&mut self, now: Timestamp) {
+        let expired: Vec<((String, u64), Timestamp)> = self.active.iter()
+            .filter_map(|(key, active)| active.expires
+                .filter(|expires| *expires <= now)
+                .map(|expires| (key.clone(), expires)))
+            .collect();
+        for (key, expires) in expired {
+            self.end(&key, expires);
+        }
+    }
+
+    /// Ends a buff at the given time, if it is active
+    fn end(&mut self, key: &(String, u64), end: Timestamp) {
+        if let Some(active) = self.active.remove(key) {
+            let (buff, target) = key;
+            let history = self.history.entry(buff.clone()).or_default();
+            if active.since < end {
+                history.intervals.push(BuffInterval {
+                    target: *target,
+                    start: active.since,
+                    end,
+                    stacks: active.stacks,
+                });
+            }
+        }
+    }
+
+    /// Interval of an active buff up to the latest event
+    fn open_interval(&self, target: u64, active: &ActiveBuff)
+            -> Option<BuffInterval> {
+        let end = active.expires
+            .map_or(self.now, |expires| expires.min(self.now));
+        (active.since < end).then_some(BuffInterval {
+            target,
+            start: active.since,
+            end,
+            stacks: active.stacks,
+        })
+    }
+
+    /// Targets of an encounter: the actors the player fought and any other
+    /// actor a buff was on, or only the player when there are none
+    fn targets(&self, intervals: &BTreeMap<&str, Vec<BuffInterval>>,
+            encounter: &Encounter) -> Vec<u64> {
+        let player = self.detector.player();
+        let mut targets: BTreeSet<u64> = self.opponents
+            .range((encounter.index, 0)..=(encounter.index, u64::MAX))
+            .map(|(_, opponent)| *opponent)
+            .collect();
+        targets.extend(intervals.values()
+            .flatten()
+            .filter(|interval| interval.target != player
+                && interval.start < encounter.end
+                && encounter.start < interval.end)
+            .map(|interval| interval.target));
+        if targets.is_empty() {
+            targets.insert(player);
+        }
+        targets.into_iter().collect()
+    }
+
+    /// Merged ranges of a buff during an encounter on one target, which
+    /// includes the time it was on the player
+    fn on_target(&self, intervals: &[BuffInterval], target: u64,
+            encounter: &Encounter) -> Vec<(u64, u64)> {
+        let player = self.detector.player();
+        let on_target: Vec<BuffInterval> = intervals.iter()
+            .filter(|interval| interval.target == target
+                || interval.target == player)
+            .copied()
+            .collect();
+        merged(&on_target, encounter)
+    }
+
+    /// Uptime of a buff over an encounter, or `None` when it was never
+    /// active or applied during it
+    fn uptime(&self, buff: &str, history: &BuffHistory,
+            intervals: &[BuffInterval], targets: &[u64],
+            encounter: &Encounter) -> Option<BuffUptime> {
+        let applications = history.applications.iter()
+            .filter(|timestamp| encounter.contains(**timestamp))
+            .count() as u64;
+        let clipped: Vec<(BuffInterval, u64)> = intervals.iter()
+            .filter_map(|interval| {
+                let start = interval.start.0.max(encounter.start.0);
+                let end = interval.end.0.min(encounter.end.0);
+                (start < end).then(|| (*interval, end - start))
+            })
+            .collect();
+        if clipped.is_empty() && applications == 0 {
+            return None;
+        }
+
+        let mut buff_targets: Vec<u64> = clipped.iter()
+            .map(|(interval, _)| interval.target)
+            .collect();
+        buff_targets.sort_unstable();
+        buff_targets.dedup();
+
+        // Stacks are weighted by time across every target
+        let active: u64 = clipped.iter().map(|(_, length)| length).sum();
+        let stack_time: f64 = clipped.iter()
+            .map(|(interval, length)| interval.stacks as f64 * *length as f64)
+            .sum();
+
+        // Gaps lie between the merged ranges and the encounter bounds
+        let ranges = merged(intervals, encounter);
+        let mut bounds = vec![encounter.start.0];
+        for (start, end) in &ranges {
+            bounds.push(*start);
+            bounds.push(*end);
+        }
+        bounds.push(encounter.end.0);
+        let longest_gap = bounds.chunks(2)
+            .map(|gap| gap[1] - gap[0])
+            .max()
+            .unwrap_or(0);
+
+        let target_time: u64 = targets.iter()
+            .map(|target| total(&self.on_target(intervals, *target,
+                encounter)))
+            .sum();
+
+        Some(BuffUptime {
+            buff: buff.to_string(),
+            debuff: history.debuff,
+            targets: buff_targets.len(),
+            applications,
+            uptime: Self::fraction(total(&ranges), encounter),
+            target_uptime: Self::fraction(target_time, encounter)
+                / targets.len() as f64,
+            average_stacks: if active > 0 {
+                stack_time / active as f64
+            }
+            else {
+                0.0
+            },
+            longest_gap: longest_gap as f64 / 1e6,
+        })
+    }
+
+    /// Fraction of an encounter at least one target had all of the buffs
+    /// at once
+    fn combined_uptime(&self, buffs: &[String],
+            intervals: &BTreeMap<&str, Vec<BuffInterval>>, targets: &[u64],
+            encounter: &Encounter) -> f64 {
+        if buffs.is_empty() {
+            return 0.0;
+        }
+
+        let mut any_target = vec![];
+        for target in targets {
+            let mut combined: Option<Vec<(u64, u64)>> = None;
+            for buff in buffs {
+                let ranges = intervals.get(buff.as_str())
+                    .map_or(vec![], |intervals| self.on_target(intervals,
+                        *target, encounter));
+                combined = Some(match combined {
+                    Some(combined) => intersect(&combined, &ranges),
+                    None => ranges,
+                });
+            }
+            any_target.extend(combined.unwrap_or_default());
+        }
+
+        Self::fraction(total(&union(any_target)), encounter)
+    }
+
+    /// Fraction of an encounter covered by a length in microseconds
+    fn fraction(length: u64, encounter: &Encounter) -> f64 {
+        let duration = encounter.end.0 - encounter.start.0;
+        if duration == 0 {
+            return 0.0;
+        }
+        length as f64 / duration as f64
+    }
+}
+
+impl std::fmt::Display for BuffReport {
+    /// Formats the report as a terminal table per encounter
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (i, report) in self.encounters.iter().enumerate() {
+            let encounter = &report.encounter;
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "Encounter {}  {} - {}", encounter.index + 1,
+                format_time(encounter.start), format_time(encounter.end))?;
+            writeln!(f, "{:<24} {:>7} {:>10} {:>8} {:>8} {:>8} {:>8}",
+                "Buff", "Uptime", "Per target", "Stacks", "Longest", "Applied",
+                "Targets")?;
+            for buff in &report.buffs {
+                writeln!(f,
+                    "{:<24} {:>7} {:>10} {:>8.1} {:>7.1}s {:>8} {:>8}",
+                    buff.buff, format_percent(buff.uptime),
+                    format_percent(buff.target_uptime), buff.average_stacks,
+                    buff.longest_gap, buff.applications, buff.targets)?;
+            }
+            for combination in &report.combinations {
+                writeln!(f, "{:<24} {:>7}", combination.buffs.join(" + "),
+                    format_percent(combination.uptime))?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,588 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::time::Duration;
+use serde::Serialize;
+use crate::logger::CombatEvent;
+use super::{
+    format_amount, format_percent, ratio, BuffTracker, DefenseTracker,
+    DpsMeter,
+};
+
+/// Number of bootstrap resamples by default
+pub const DEFAULT_RESAMPLES: usize = 2000;
+
+/// Confidence level of the reported intervals by default
+pub const DEFAULT_CONFIDENCE: f64 = 0.95;
+
+/// Seed of the resampling by default, so that reports are reproducible
+const DEFAULT_SEED: u64 = 0x4449_4142_4c4f_3456;
+
+/// Metrics of one session that are compared between groups
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionMetrics {
+    /// Identifier of the session
+    pub session_id: String,
+
+    /// Damage per second spent in encounters
+    pub dps: f64,
+
+    /// Damage per second spent in encounters of every skill
+    pub skill_dps: BTreeMap<String, f64>,
+
+    /// Fraction of the time in encounters every buff was active
+    pub buff_uptime: BTreeMap<String, f64>,
+
+    /// Damage taken per second spent in encounters
+    pub damage_taken: f64,
+}
+
+/// What a compared metric measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricKind {
+    /// Damage per second of the player
+    Dps,
+
+    /// Damage per second of one skill
+    SkillDps,
+
+    /// Uptime of one buff
+    BuffUptime,
+
+    /// Damage taken per second
+    DamageTaken,
+}
+
+/// Mean of a metric over a group of sessions with its confidence interval
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Estimate {
+    /// Mean over the sessions
+    pub mean: f64,
+
+    /// Lower bound of the confidence interval
+    pub low: f64,
+
+    /// Upper bound of the confidence interval
+    pub high: f64,
+}
+
+/// Comparison of one metric between the two groups
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricComparison {
+    /// What the metric measures
+    pub kind: MetricKind,
+
+    /// Skill or buff the metric is about, or the name of the metric
+    pub name: String,
+
+    /// Estimate over the first group
+    pub a: Estimate,
+
+    /// Estimate over the second group
+    pub b: Estimate,
+
+    /// Estimate of the second group minus the first
+    pub difference: Estimate,
+
+    /// Difference relative to the first group, when it is not zero
+    pub change: Option<f64>,
+
+    /// Two-sided bootstrap p-value of the difference, when both groups have
+    /// at least two sessions
+    pub p_value: Option<f64>,
+
+    /// Whether the difference holds after correcting for the number of
+    /// metrics compared
+    pub significant: bool,
+}
+
+/// Sessions of one group
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupSummary {
+    /// Name of the group in the report
+    pub label: String,
+
+    /// Identifiers of the sessions in the group
+    pub sessions: Vec<String>,
+}
+
+/// Comparison of two groups of sessions, such as before and after a change
+/// of gear
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComparisonReport {
+    /// First group
+    pub a: GroupSummary,
+
+    /// Second group
+    pub b: GroupSummary,
+
+    /// Number of bootstrap resamples
+    pub resamples: usize,
+
+    /// Confidence level of the intervals
+    pub confidence: f64,
+
+    /// Comparison of every metric, overall DPS first
+    pub metrics: Vec<MetricComparison>,
+}
+
+/// Compares the metrics of two groups of sessions
+///
+/// Confidence intervals come from bootstrap resampling of the sessions of
+/// each group, so they reflect how much the metrics vary between sessions.
+/// With dozens of skills and buffs compared at once, some intervals exclude
+/// zero by chance, so a difference is only flagged as significant when its
+/// p-value passes a Holm-Bonferroni correction over every metric. That needs
+/// at least two sessions in each group.
+#[derive(Debug, Clone)]
+pub struct BuildComparison {
+    /// Sessions of the first group
+    a: Vec<SessionMetrics>,
+
+    /// Sessions of the second group
+    b: Vec<SessionMetrics>,
+
+    /// Names of the groups in the report
+    labels: (String, String),
+
+    /// Number of bootstrap resamples
+    resamples: usize,
+
+    /// Confidence level of the intervals
+    confidence: f64,
+
+    /// Seed of the resampling
+    seed: u64,
+}
+
+/// SplitMix64 generator, which is plenty for resampling
+struct Rng(u64);
+
+impl Rng {
+    /// Next 64 random bits
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Random index below a bound
+    fn below(&mut self, bound: usize) -> usize {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as usize
+    }
+}
+
+/// Mean of some values, zero when there are none
+fn mean(values: &[f64]) -> f64 {
+    ratio(values.iter().sum(), values.len() as f64)
+}
+
+/// Mean of a resample drawn with replacement
+fn resample_mean(values: &[f64], rng: &mut Rng) -> f64 {
+    let sum: f64 = (0..values.len())
+        .map(|_| values[rng.below(values.len())])
+        .sum();
+    ratio(sum, values.len() as f64)
+}
+
+/// Two-sided p-value of resampled differences against no difference at all
+fn p_value(differences: &[f64]) -> f64 {
+    let below = differences.iter().filter(|&&value| value <= 0.0).count();
+    let above = differences.iter().filter(|&&value| value >= 0.0).count();
+    (2.0 * ratio(below.min(above) as f64, differences.len() as f64)).min(1.0)
+}
+
+/// Flags the metrics whose p-value passes a Holm-Bonferroni correction at
+/// the given level, which tests the smallest p-value against the level split
+/// over every metric and stops at the first that fails
+fn holm(metrics: &mut [MetricComparison], level: f64) {
+    let mut tested: Vec<(f64, &mut MetricComparison)> = metrics.iter_mut()
+        .filter_map(|metric| metric.p_value.map(|p_value| (p_value, metric)))
+        .collect();
+    tested.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    let count = tested.len();
+    for (rank, (p_value, metric)) in tested.into_iter().enumerate() {
+        if p_value > level / (count - rank) as f64 {
+            break;
+        }
+        metric.significant = true;
+    }
+}
+
+/// Percentile interval of resampled statistics around an observed value
+fn interval(mean: f64, mut samples: Vec<f64>, confidence: f64) -> Estimate {
+    if samples.is_empty() {
+        return Estimate { mean, low: mean, high: mean };
+    }
+
+    samples.sort_by(f64::total_cmp);
+    let tail = (1.0 - confidence) / 2.0;
+    let at = |quantile: f64| {
+        let index = (quantile * (samples.len() - 1) as f64).round() as usize;
+        samples[index.min(samples.len() - 1)]
+    };
+    Estimate { mean, low: at(tail), high: at(1.0 - tail) }
+}
+
+impl SessionMetrics {
+    /// Computes the metrics of a session from its events
+    pub fn from_events(session_id: &str, player: u64, idle_gap: Duration,
+            events: &[CombatEvent]) -> Self {
+        let mut meter = DpsMeter::new(player).idle_gap(idle_gap);
+        let mut buffs = BuffTracker::new(player).idle_gap(idle_gap);
+        let mut defense = DefenseTracker::new(player);
+        for event in events {
+            meter.push(event);
+            buffs.push(event);
+            defense.push(event);
+        }
+
+        let dps = meter.report();
+        let mut skill_dps = BTreeMap::new();
+        for skill in dps.encounters.iter().flat_map(|report| &report.skills) {
+            *skill_dps.entry(skill.skill.clone()).or_insert(0.0) +=
+                ratio(skill.damage, dps.duration);
+        }
+
+        // Encounters without a buff count as time it was down
+        let buffs = buffs.report();
+        let seconds: f64 = buffs.encounters.iter()
+            .map(|report| report.encounter.active_secs())
+            .sum();
+        let mut buff_uptime = BTreeMap::new();
+        for report in &buffs.encounters {
+            let weight = ratio(report.encounter.active_secs(), seconds);
+            for buff in &report.buffs {
+                *buff_uptime.entry(buff.buff.clone()).or_insert(0.0) +=
+                    buff.uptime * weight;
+            }
+        }
+
+        Self {
+            session_id: session_id.to_string(),
+            dps: dps.dps,
+            skill_dps,
+            buff_uptime,
+            damage_taken: ratio(defense.report().damage, dps.duration),
+        }
+    }
+}
+
+impl MetricKind {
+    /// Heading of the metrics of this kind in a report
+    fn heading(self) -> &'static str {
+        match self {
+            MetricKind::Dps => "DPS",
+            MetricKind::SkillDps => "Skill DPS",
+            MetricKind::BuffUptime => "Buff uptime",
+            MetricKind::DamageTaken => "Damage taken per second",
+        }
+    }
+
+    /// Value of the metric in a session
+    fn value(self, name: &str, session: &SessionMetrics) -> f64 {
+        let keyed = |values: &BTreeMap<String, f64>| values.get(name)
+            .copied()
+            .unwrap_or(0.0);
+        match self {
+            MetricKind::Dps => session.dps,
+            MetricKind::SkillDps => keyed(&session.skill_dps),
+            MetricKind::BuffUptime => keyed(&session.buff_uptime),
+            MetricKind::DamageTaken => session.damage_taken,
+        }
+    }
+
+    /// Formats a value of the metric
+    fn format(self, value: f64) -> String {
+        match self {
+            MetricKind::BuffUptime => format_percent(value),
+            _ => format_amount(value),
+        }
+    }
+}
+
+impl Estimate {
+    /// Formats the mean followed by the interval
+    fn format(&self, kind: MetricKind, signed: bool) -> String {
+        let format = |value: f64| {
+            let sign = if signed && value > 0.0 { "+" } else { "" };
+            format!("{}{}", sign, kind.format(value))
+        };
+        format!("{} ({} to {})", format(self.mean), format(self.low),
+            format(self.high))
+    }
+}
+
+impl MetricComparison {
+    /// Cells of the row of the metric in a report table
+    fn cells(&self) -> [String; 6] {
+        let change = self.change.map_or_else(|| "-".to_string(), |change| {
+            let sign = if change > 0.0 { "+" } else { "" };
+            format!("{}{}", sign, format_percent(change))
+        });
+        [
+            self.name.clone(),
+            self.a.format(self.kind, false),
+            self.b.format(self.kind, false),
+            self.difference.format(self.kind, true),
+            change,
+            if self.significant { "yes" } else { "no" }.to_string(),
+        ]
+    }
+}
+
+impl BuildComparison {
+    /// Compares two groups of sessions
+    pub fn new(a: Vec<SessionMetrics>, b: Vec<SessionMetrics>) -> Self {
+        Self {
+            a,
+            b,
+            labels: ("A".to_string(), "B".to_string()),
+            resamples: DEFAULT_RESAMPLES,
+            confidence: DEFAULT_CONFIDENCE,
+            seed: DEFAULT_SEED,
+        }
+    }
+
+    /// Sets the names of the groups in the report
+    pub fn labels(mut self, a: &str, b: &str) -> Self {
+        self.labels = (a.to_string(), b.to_string());
+        self
+    }
+
+    /// Sets the number of bootstrap resamples
+    ///
+    /// Panics if the number is zero.
+    pub fn resamples(mut self, resamples: usize) -> Self {
+        assert!(resamples > 0, "Bootstrap resamples must not be zero");
+        self.resamples = resamples;
+        self
+    }
+
+    /// Sets the confidence level of the intervals, between 0 and 1
+    ///
+    /// Panics if the level is not strictly between 0 and 1.
+    pub fn confidence(mut self, confidence: f64) -> Self {
+        assert!(confidence > 0.0 && confidence < 1.0,
+            "Confidence level must be between 0 and 1");
+        self.confidence = confidence;
+        self
+    }
+
+    /// Sets the seed of the resampling
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Compares every metric
+    pub fn report(&self) -> ComparisonReport {
+        let sessions = || self.a.iter().chain(&self.b);
+        let skills: BTreeSet<&String> = sessions()
+            .flat_map(|session| session.skill_dps.keys())
+            .collect();
+        let buffs: BTreeSet<&String> = sessions()
+            .flat_map(|session| session.buff_uptime.keys())
+            .collect();
+
+        let mut metrics = vec![self.compare(MetricKind::Dps, "DPS")];
+        let mut keyed = |kind: MetricKind, names: BTreeSet<&String>| {
+            let mut compared: Vec<MetricComparison> = names.into_iter()
+                .map(|name| self.compare(kind, name))
+                .collect();
+            compared.sort_by(|x, y| (y.a.mean + y.b.mean)
+                .total_cmp(&(x.a.mean + x.b.mean)));
+            metrics.extend(compared);
+        };
+        keyed(MetricKind::SkillDps, skills);
+        keyed(MetricKind::BuffUptime, buffs);
+        metrics.push(self.compare(MetricKind::DamageTaken, "Damage taken"));
+        holm(&mut metrics, 1.0 - self.confidence);
+
+        let summary = |label: &str, group: &[SessionMetrics]| GroupSummary {
+            label: label.to_string(),
+            sessions: group.iter()
+                .map(|session| session.session_id.clone())
+                .collect(),
+        };
+        ComparisonReport {
+            a: summary(&self.labels.0, &self.a),
+            b: summary(&self.labels.1, &self.b),
+            resamples: self.resamples,
+            confidence: self.confidence,
+            metrics,
+        }
+    }
+
+    /// Compares one metric, resampling both groups with the same seed for
+    /// every metric
+    fn compare(&self, kind: MetricKind, name: &str) -> MetricComparison {
+        let values = |group: &[SessionMetrics]| -> Vec<f64> {
+            group.iter().map(|session| kind.value(name, session)).collect()
+        };
+        let (a, b) = (values(&self.a), values(&self.b));
+        let (mean_a, mean_b) = (mean(&a), mean(&b));
+
+        let mut rng = Rng(self.seed);
+        let mut samples_a = vec![];
+        let mut samples_b = vec![];
+        let mut differences = vec![];
+        if !a.is_empty() && !b.is_empty() {
+            for _ in 0..self.resamples {
+                let resampled_a = resample_mean(&a, &mut rng);
+                let resampled_b = resample_mean(&b, &mut rng);
+                samples_a.push(resampled_a);
+                samples_b.push(resampled_b);
+                differences.push(resampled_b - resampled_a);
+            }
+        }
+
+        // A single session says nothing about the spread of its group, and
+        // significance is only decided over every metric at once
+        let p_value = (a.len() >= 2 && b.len() >= 2)
+            .then(|| p_value(&differences));
+        let difference = interval(mean_b - mean_a, differences,
+            self.confidence);
+        MetricComparison {
+            kind,
+            name: name.to_string(),
+            a: interval(mean_a, samples_a, self.confidence),
+            b: interval(mean_b, samples_b, self.confidence),
+            difference,
+            change: (mean_a != 0.0).then(|| (mean_b - mean_a) / mean_a),
+            p_value,
+            significant: false,
+        }
+    }
+}
+
+/// Escapes text for HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl ComparisonReport {
+    /// Headers of the metric tables
+    fn headers(&self) -> [String; 6] {
+        [
+            "Metric".to_string(),
+            self.a.label.clone(),
+            self.b.label.clone(),
+            "Difference".to_string(),
+            "Change".to_string(),
+            "Significant".to_string(),
+        ]
+    }
+
+    /// Metrics grouped by kind, in report order
+    fn sections(&self) -> Vec<(MetricKind, Vec<&MetricComparison>)> {
+        let mut sections: Vec<(MetricKind, Vec<&MetricComparison>)> = vec![];
+        for metric in &self.metrics {
+            match sections.last_mut() {
+                Some((kind, metrics)) if *kind == metric.kind =>
+                    metrics.push(metric),
+                _ => sections.push((metric.kind, vec![metric])),
+            }
+        }
+        sections
+    }
+
+    /// Lines describing the groups and how the intervals were computed
+    fn description(&self) -> Vec<String> {
+        let group = |group: &GroupSummary| {
+            let count = group.sessions.len();
+            format!("{}: {} session{} ({})", group.label, count,
+                if count == 1 { "" } else { "s" }, group.sessions.join(", "))
+        };
+        let tested = self.metrics.iter()
+            .filter(|metric| metric.p_value.is_some())
+            .count();
+        vec![
+            group(&self.a),
+            group(&self.b),
+            format!("Means with {} bootstrap intervals from {} resamples. \
+                Differences are {} minus {}. As {} metrics are compared at \
+                once, a difference is only significant when its p-value \
+                passes a Holm correction at the {} level, so an interval \
+                that excludes zero is not enough on its own.",
+                format_percent(self.confidence), self.resamples, self.b.label,
+                self.a.label, tested, format_percent(1.0 - self.confidence)),
+        ]
+    }
+
+    /// Writes the report as Markdown
+    pub fn write_markdown<W: Write>(&self, mut writer: W)
+            -> std::io::Result<()> {
+        // Pipes would end a table cell early
+        let row = |cells: &[String]| cells.iter()
+            .map(|cell| cell.replace('|', "\\|"))
+            .collect::<Vec<String>>()
+            .join(" | ");
+
+        writeln!(writer, "# Build comparison")?;
+        writeln!(writer)?;
+        for line in self.description() {
+            writeln!(writer, "{}", line.replace('|', "\\|"))?;
+            writeln!(writer)?;
+        }
+        for (kind, metrics) in self.sections() {
+            writeln!(writer, "## {}", kind.heading())?;
+            writeln!(writer)?;
+            writeln!(writer, "| {} |", row(&self.headers()))?;
+            writeln!(writer, "|---|---:|---:|---:|---:|:---:|")?;
+            for metric in metrics {
+                writeln!(writer, "| {} |", row(&metric.cells()))?;
+            }
+            writeln!(writer)?;
+        }
+
+        writer.flush()
+    }
+
+    /// Writes the report as a standalone HTML page, with significant
+    /// differences highlighted
+    pub fn write_html<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "<!DOCTYPE html>")?;
+        writeln!(writer, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+        writeln!(writer, "<title>Build comparison</title>")?;
+        writeln!(writer, "<style>\
+            body {{ font-family: sans-serif; }} \
+            table {{ border-collapse: collapse; }} \
+            th, td {{ padding: 4px 8px; border-bottom: 1px solid #ccc; }} \
+            td {{ text-align: right; }} \
+            td:first-child {{ text-align: left; }} \
+            tr.significant {{ background: #fff3c4; }}\
+            </style>")?;
+        writeln!(writer, "</head>\n<body>\n<h1>Build comparison</h1>")?;
+        for line in self.description() {
+            writeln!(writer, "<p>{}</p>", escape_html(&line))?;
+        }
+        for (kind, metrics) in self.sections() {
+            writeln!(writer, "<h2>{}</h2>\n<table>", kind.heading())?;
+            let headers: String = self.headers().iter()
+                .map(|header| format!("<th>{}</th>", escape_html(header)))
+                .collect();
+            writeln!(writer, "<tr>{}</tr>", headers)?;
+            for metric in metrics {
+                let cells: String = metric.cells().iter()
+                    .map(|cell| format!("<td>{}</td>", escape_html(cell)))
+                    .collect();
+                let class = match metric.significant {
+                    true => " class=\"significant\"",
+                    false => "",
+                };
+                writeln!(writer, "<tr{}>{}</tr>", class, cells)?;
+            }
+            writeln!(writer, "</table>")?;
+        }
+        writeln!(writer, "</body>\n</html>")?;
+
+        writer.flush()
+    }
+}
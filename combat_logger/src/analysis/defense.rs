@@ -0,0 +1,611 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use serde::Serialize;
+use crate::logger::{
+    Actor, CombatEvent, Damage, DamageType, EventKind, ResourceKind, Timestamp,
+};
+use super::{format_amount, format_percent, format_time, hit_damage, ratio};
+
+/// Time before a death covered by its recap by default
+pub const DEFAULT_RECAP: Duration = Duration::from_secs(10);
+
+/// Fraction of maximum life below which the player nearly died by default
+pub const DEFAULT_NEAR_DEATH: f64 = 0.2;
+
+/// Label of heals without a known skill
+const UNKNOWN_SOURCE: &str = "unknown";
+
+/// Damage taken from monsters of one name
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SourceDamage {
+    /// Name of the monsters
+    pub source: String,
+
+    /// Damage taken, including what barriers absorbed
+    pub damage: f64,
+
+    /// Fraction of all damage taken
+    pub share: f64,
+
+    /// Number of hits
+    pub hits: u64,
+
+    /// Largest hit
+    pub max_hit: f64,
+}
+
+/// Damage taken of one element
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TypeDamage {
+    /// Element of the hits
+    pub damage_type: DamageType,
+
+    /// Damage taken, including what barriers absorbed
+    pub damage: f64,
+
+    /// Fraction of all damage taken
+    pub share: f64,
+
+    /// Number of hits
+    pub hits: u64,
+
+    /// Fraction of the damage before mitigation that was prevented, over
+    /// the hits exposing it
+    pub reduction: Option<f64>,
+}
+
+/// Healing received from one skill or effect
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealingSource {
+    /// Skill, potion or effect doing the healing
+    pub source: String,
+
+    /// Life restored
+    pub healing: f64,
+
+    /// Healing beyond maximum life
+    pub overheal: f64,
+
+    /// Fraction of all life restored
+    pub share: f64,
+
+    /// Number of heals
+    pub heals: u64,
+}
+
+/// A dip of life below the near-death threshold that the player survived
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct NearDeath {
+    /// Time life fell below the threshold
+    pub timestamp: Timestamp,
+
+    /// Lowest fraction of maximum life reached
+    pub lowest: f64,
+
+    /// Time life rose above the threshold again, if it did yet
+    pub recovered: Option<Timestamp>,
+}
+
+/// An event leading up to a death
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecapEntry {
+    /// Time of the event
+    pub timestamp: Timestamp,
+
+    /// Seconds before the death
+    pub before: f64,
+
+    /// What happened
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+/// What happened to the player in the seconds before a death
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeathRecap {
+    /// Time of the death
+    pub timestamp: Timestamp,
+
+    /// Actor landing the killing blow, when known
+    pub killer: Option<Actor>,
+
+    /// Damage taken during the recap, including what barriers absorbed
+    pub damage: f64,
+
+    /// Life restored during the recap
+    pub healing: f64,
+
+    /// Hits, heals, buffs, casts and life changes of the player, in order
+    pub events: Vec<RecapEntry>,
+}
+
+/// Damage taken, mitigation and healing of the player over a session
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DefenseReport {
+    /// Actor identifier of the player
+    pub player: u64,
+
+    /// Damage taken, including what barriers absorbed
+    pub damage: f64,
+
+    /// Number of hits taken
+    pub hits: u64,
+
+    /// Damage absorbed by barriers
+    pub absorbed: f64,
+
+    /// Fraction of the damage before mitigation that was prevented, over
+    /// the hits exposing it
+    pub reduction: Option<f64>,
+
+    /// Fraction of the damage taken while fortified
+    pub fortified_share: f64,
+
+    /// Life restored
+    pub healing: f64,
+
+    /// Healing beyond maximum life
+    pub overheal: f64,
+
+    /// Damage per monster name, largest first
+    pub sources: Vec<SourceDamage>,
+
+    /// Damage per element, largest first
+    pub damage_types: Vec<TypeDamage>,
+
+    /// Healing per skill or effect, largest first
+    pub healing_sources: Vec<HealingSource>,
+
+    /// Every time life fell below the threshold without a death
+    pub near_deaths: Vec<NearDeath>,
+
+    /// Recap of every death
+    pub deaths: Vec<DeathRecap>,
+}
+
+/// Analyzes the damage the player takes and how it is mitigated and healed
+///
+/// Life, barrier and fortify come from resource events of the player, which
+/// is fortified while fortify is at least its current life. Every death
+/// produces a recap of the events leading up to it.
+#[derive(Debug, Clone)]
+pub struct DefenseTracker {
+    /// Actor identifier of the player
+    player: u64,
+
+    /// Time before a death covered by its recap
+    recap: Duration,
+
+    /// Fraction of maximum life below which the player nearly died
+    near_death: f64,
+
+    /// Totals of every hit taken
+    totals: TakenTotals,
+
+    /// Totals per monster name
+    sources: HashMap<String, TakenTotals>,
+
+    /// Totals per element
+    damage_types: HashMap<DamageType, TakenTotals>,
+
+    /// Damage taken while fortified
+    fortified: f64,
+
+    /// Healing per skill or effect
+    healing: HashMap<String, HealingTotals>,
+
+    /// Current and maximum life of the player
+    life: Option<(f64, f64)>,
+
+    /// Current fortify of the player
+    fortify: f64,
+
+    /// Survived dips below the near-death threshold
+    near_deaths: Vec<NearDeath>,
+
+    /// Dip below the near-death threshold in progress
+    dip: Option<NearDeath>,
+
+    /// Recent events of the player, for death recaps
+    recent: VecDeque<CombatEvent>,
+
+    /// Recap of every death so far
+    deaths: Vec<DeathRecap>,
+}
+
+/// Running totals of a set of hits taken
+#[derive(Debug, Clone, Default)]
+struct TakenTotals {
+    /// Damage taken, including what barriers absorbed
+    damage: f64,
+
+    /// Number of hits
+    hits: u64,
+
+    /// Largest hit
+    max_hit: f64,
+
+    /// Damage prevented by mitigation, over the hits exposing it
+    mitigated: f64,
+
+    /// Damage before mitigation, over the hits exposing it
+    unmitigated: f64,
+
+    /// Damage absorbed by barriers
+    absorbed: f64,
+}
+
+/// Running totals of a set of heals
+#[derive(Debug, Clone, Default)]
+struct HealingTotals {
+    /// Life restored
+    healing: f64,
+
+    /// Healing beyond maximum life
+    overheal: f64,
+
+    /// Number of heals
+    heals: u64,
+}
+
+/// Describes an event of a death recap in a few words
+fn describe(kind: &EventKind) -> String {
+    match kind {
+        EventKind::Damage(damage) => {
+            let mut text = format!("{} hit for {} {:?}", damage.source.name,
+                format_amount(hit_damage(damage)), damage.damage_type);
+            if let Some(skill) = &damage.skill {
+                text += &format!(" ({})", skill);
+            }
+            if damage.absorbed > 0.0 {
+                text += &format!(", {} absorbed",
+                    format_amount(damage.absorbed));
+            }
+            if damage.critical {
+                text += ", critical";
+            }
+            text
+        }
+        EventKind::Heal(heal) => format!("Healed {} by {}",
+            format_amount(heal.amount),
+            heal.skill.as_deref().unwrap_or(&heal.source.name)),
+        EventKind::BuffApplied(applied) => format!("{} {} applied",
+            if applied.debuff { "Debuff" } else { "Buff" }, applied.buff),
+        EventKind::BuffExpired(expired) => format!("{} expired",
+            expired.buff),
+        EventKind::Resource(resource) => format!("{:?} {} / {}",
+            resource.resource, format_amount(resource.value),
+            format_amount(resource.max)),
+        EventKind::Cast(cast) => format!("Cast {}", cast.skill),
+        EventKind::Kill(kill) => format!("{} died", kill.victim.name),
+        EventKind::ZoneChange(change) => format!("Entered {}", change.zone),
+    }
+}
+
+impl TakenTotals {
+    /// Adds a hit
+    fn add(&mut self, damage: &Damage) {
+        let amount = hit_damage(damage);
+        self.damage += amount;
+        self.hits += 1;
+        self.max_hit = self.max_hit.max(amount);
+        self.absorbed += damage.absorbed;
+        if let Some(mitigated) = damage.mitigated {
+            self.mitigated += mitigated;
+            self.unmitigated += amount + mitigated;
+        }
+    }
+
+    /// Fraction of the damage before mitigation that was prevented, or
+    /// `None` when no hit exposed it
+    fn reduction(&self) -> Option<f64> {
+        (self.unmitigated > 0.0).then(|| self.mitigated / self.unmitigated)
+    }
+}
+
+impl DefenseTracker {
+    /// Create a new tracker for the player with the given actor identifier
+    pub fn new(player: u64) -> Self {
+        Self {
+            player,
+            recap: DEFAULT_RECAP,
+            near_death: DEFAULT_NEAR_DEATH,
+            totals: TakenTotals::default(),
+            sources: HashMap::new(),
+            damage_types: HashMap::new(),
+            fortified: 0.0,
+            healing: HashMap::new(),
+            life: None,
+            fortify: 0.0,
+            near_deaths: vec![],
+            dip: None,
+            recent: VecDeque::new(),
+            deaths: vec![],
+        }
+    }
+
+    /// Sets the time before a death covered by its recap
+    pub fn recap(mut self, recap: Duration) -> Self {
+        self.recap = recap;
+        self
+    }
+
+    /// Sets the fraction of maximum life below which the player nearly died
+    pub fn near_death(mut self, fraction: f64) -> Self {
+        self.near_death = fraction;
+        self
+    }
+
+    /// Feeds the next event of the session
+    pub fn push(&mut self, event: &CombatEvent) {
+        match &event.kind {
+            EventKind::Damage(damage) if damage.target.id == self.player => {
+                self.totals.add(damage);
+                self.sources.entry(damage.source.name.clone())
+                    .or_default()
+                    .add(damage);
+                self.damage_types.entry(damage.damage_type)
+                    .or_default()
+                    .add(damage);
+                if self.is_fortified() {
+                    self.fortified += hit_damage(damage);
+                }
+            }
+            EventKind::Heal(heal) if heal.target.id == self.player => {
+                let totals = self.healing.entry(heal.skill.clone()
+                        .unwrap_or_else(|| UNKNOWN_SOURCE.to_string()))
+                    .or_default();
+                totals.healing += heal.amount;
+                totals.overheal += heal.overheal;
+                totals.heals += 1;
+            }
+            EventKind::Resource(resource)
+                    if resource.actor.id == self.player => {
+                match resource.resource {
+                    ResourceKind::Life => {
+                        self.life = Some((resource.value, resource.max));
+                        self.track_life(event.timestamp, resource.value,
+                            resource.max);
+                    }
+                    ResourceKind::Fortify => self.fortify = resource.value,
+                    _ => {}
+                }
+            }
+            EventKind::Kill(kill) if kill.victim.id == self.player => {
+                self.dip = None;
+                self.deaths.push(self.death_recap(event.timestamp,
+                    kill.killer.clone()));
+                self.recent.clear();
+                return;
+            }
+            _ => {}
+        }
+
+        if self.concerns_player(&event.kind) {
+            self.recent.push_back(event.clone());
+        }
+        while let Some(oldest) = self.recent.front() {
+            if event.timestamp.as_duration()
+                    .saturating_sub(oldest.timestamp.as_duration())
+                    <= self.recap {
+                break;
+            }
+            self.recent.pop_front();
+        }
+    }
+
+    /// Report over everything fed so far
+    pub fn report(&self) -> DefenseReport {
+        let damage = self.totals.damage;
+
+        let mut sources: Vec<SourceDamage> = self.sources.iter()
+            .map(|(source, totals)| SourceDamage {
+                source: source.clone(),
+                damage: totals.damage,
+                share: ratio(totals.damage, damage),
+                hits: totals.hits,
+                max_hit: totals.max_hit,
+            })
+            .collect();
+        sources.sort_by(|a, b| b.damage.total_cmp(&a.damage)
+            .then_with(|| a.source.cmp(&b.source)));
+
+        let mut damage_types: Vec<TypeDamage> = self.damage_types.iter()
+            .map(|(damage_type, totals)| TypeDamage {
+                damage_type: *damage_type,
+                damage: totals.damage,
+                share: ratio(totals.damage, damage),
+                hits: totals.hits,
+                reduction: totals.reduction(),
+            })
+            .collect();
+        damage_types.sort_by(|a, b| b.damage.total_cmp(&a.damage)
+            .then_with(|| (a.damage_type as u8).cmp(&(b.damage_type as u8))));
+
+        let healing: f64 = self.healing.values()
+            .map(|totals| totals.healing)
+            .sum();
+        let mut healing_sources: Vec<HealingSource> = self.healing.iter()
+            .map(|(source, totals)| HealingSource {
+                source: source.clone(),
+                healing: totals.healing,
+                overheal: totals.overheal,
+                share: ratio(totals.healing, healing),
+                heals: totals.heals,
+            })
+            .collect();
+        healing_sources.sort_by(|a, b| b.healing.total_cmp(&a.healing)
+            .then_with(|| a.source.cmp(&b.source)));
+
+        let mut near_deaths = self.near_deaths.clone();
+        near_deaths.extend(self.dip);
+
+        DefenseReport {
+            player: self.player,
+            damage,
+            hits: self.totals.hits,
+            absorbed: self.totals.absorbed,
+            reduction: self.totals.reduction(),
+            fortified_share: ratio(self.fortified, damage),
+            healing,
+            overheal: self.healing.values()
+                .map(|totals| totals.overheal)
+                .sum(),
+            sources,
+            damage_types,
+            healing_sources,
+            near_deaths,
+            deaths: self.deaths.clone(),
+        }
+    }
+
+    /// Checks whether the player is fortified, which takes fortify of at
+    /// least its current life
+    fn is_fortified(&self) -> bool {
+        match self.life {
+            Some((life, _)) => life > 0.0 && self.fortify >= life,
+            None => false,
+        }
+    }
+
+    /// Checks whether an event belongs in a death recap
+    fn concerns_player(&self, kind: &EventKind) -> bool {
+        match kind {
+            EventKind::Damage(damage) => damage.target.id == self.player,
+            EventKind::Heal(heal) => heal.target.id == self.player,
+            EventKind::BuffApplied(applied) =>
+                applied.target.id == self.player,
+            EventKind::BuffExpired(expired) =>
+                expired.target.id == self.player,
+            EventKind::Resource(resource) => resource.actor.id == self.player
+                && matches!(resource.resource, ResourceKind::Life
+                    | ResourceKind::Barrier | ResourceKind::Fortify),
+            EventKind::Cast(cast) => cast.source.id == self.player,
+            EventKind::Kill(_) | EventKind::ZoneChange(_) => false,
+        }
+    }
+
+    /// Follows life through dips below the near-death threshold
+    fn track_life(&mut self, timestamp: Timestamp, life: f64, max: f64) {
+        if max <= 0.0 {
+            return;
+        }
+        let fraction = life / max;
+
+        if fraction < self.near_death {
+            let dip = self.dip.get_or_insert(NearDeath {
+                timestamp,
+                lowest: fraction,
+                recovered: None,
+            });
+            dip.lowest = dip.lowest.min(fraction);
+        }
+        else if let Some(mut dip) = self.dip.take() {
+            dip.recovered = Some(timestamp);
+            self.near_deaths.push(dip);
+        }
+    }
+
+    /// Builds the recap of a death from the recent events
+    fn death_recap(&self, timestamp: Timestamp, killer: Option<Actor>)
+            -> DeathRecap {
+        let events: Vec<RecapEntry> = self.recent.iter()
+            .filter(|event| timestamp.as_duration()
+                .saturating_sub(event.timestamp.as_duration()) <= self.recap)
+            .map(|event| RecapEntry {
+                timestamp: event.timestamp,
+                before: timestamp.as_duration()
+                    .saturating_sub(event.timestamp.as_duration())
+                    .as_secs_f64(),
+                kind: event.kind.clone(),
+            })
+            .collect();
+
+        let damage = events.iter()
+            .map(|entry| match &entry.kind {
+                EventKind::Damage(damage) => hit_damage(damage),
+                _ => 0.0,
+            })
+            .sum();
+        let healing = events.iter()
+            .map(|entry| match &entry.kind {
+                EventKind::Heal(heal) => heal.amount,
+                _ => 0.0,
+            })
+            .sum();
+
+        DeathRecap { timestamp, killer, damage, healing, events }
+    }
+}
+
+impl std::fmt::Display for DefenseReport {
+    /// Formats the report as terminal tables followed by the death recaps
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Taken {} in {} hits, {} absorbed, {} while fortified",
+            format_amount(self.damage), self.hits,
+            format_amount(self.absorbed),
+            format_percent(self.fortified_share))?;
+        if let Some(reduction) = self.reduction {
+            writeln!(f, "Effective damage reduction {}",
+                format_percent(reduction))?;
+        }
+        writeln!(f, "Healed {}, {} overheal", format_amount(self.healing),
+            format_amount(self.overheal))?;
+
+        writeln!(f)?;
+        writeln!(f, "{:<24} {:>9} {:>7} {:>6} {:>9}",
+            "Source", "Damage", "Share", "Hits", "Max hit")?;
+        for source in &self.sources {
+            writeln!(f, "{:<24} {:>9} {:>7} {:>6} {:>9}", source.source,
+                format_amount(source.damage), format_percent(source.share),
+                source.hits, format_amount(source.max_hit))?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "{:<24} {:>9} {:>7} {:>6} {:>9}",
+            "Damage type", "Damage", "Share", "Hits", "Reduced")?;
+        for damage_type in &self.damage_types {
+            writeln!(f, "{:<24} {:>9} {:>7} {:>6} {:>9}",
+                format!("{:?}", damage_type.damage_type),
+                format_amount(damage_type.damage),
+                format_percent(damage_type.share), damage_type.hits,
+                damage_type.reduction
+                    .map_or("-".to_string(), format_percent))?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "{:<24} {:>9} {:>7} {:>6} {:>9}",
+            "Healing", "Healed", "Share", "Heals", "Overheal")?;
+        for source in &self.healing_sources {
+            writeln!(f, "{:<24} {:>9} {:>7} {:>6} {:>9}", source.source,
+                format_amount(source.healing), format_percent(source.share),
+                source.heals, format_amount(source.overheal))?;
+        }
+
+        if !self.near_deaths.is_empty() {
+            writeln!(f)?;
+            for near_death in &self.near_deaths {
+                writeln!(f, "Near death at {}, down to {} life",
+                    format_time(near_death.timestamp),
+                    format_percent(near_death.lowest))?;
+            }
+        }
+
+        for death in &self.deaths {
+            writeln!(f)?;
+            writeln!(f, "Death at {}{}, {} taken and {} healed before",
+                format_time(death.timestamp),
+                death.killer.as_ref()
+                    .map_or(String::new(), |killer| {
+                        format!(" to {}", killer.name)
+                    }),
+                format_amount(death.damage), format_amount(death.healing))?;
+            for entry in &death.events {
+                writeln!(f, "{:>7.1}s  {}", -entry.before,
+                    describe(&entry.kind))?;
+            }
+        }
+
+        Ok(())
+    }
+}
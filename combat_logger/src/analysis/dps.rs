@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use serde::Serialize;
+use crate::logger::{CombatEvent, Damage, EventKind, Timestamp};
+use super::{
+    format_amount, format_percent, format_time, hit_damage, ratio, Encounter,
+    EncounterDetector,
+};
+
+/// Time rolling DPS is averaged over by default
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(5);
+
+/// Label of hits without a known skill
+const UNKNOWN_SKILL: &str = "unknown";
+
+/// Damage dealt with a single skill
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkillStats {
+    /// Name of the skill
+    pub skill: String,
+
+    /// Total damage
+    pub damage: f64,
+
+    /// Fraction of the damage of the encounter
+    pub share: f64,
+
+    /// Number of hits
+    pub hits: u64,
+
+    /// Fraction of hits that were critical strikes
+    pub crit_rate: f64,
+
+    /// Fraction of hits that were overpowered
+    pub overpower_rate: f64,
+
+    /// Average damage per hit
+    pub average_hit: f64,
+
+    /// Largest hit
+    pub max_hit: f64,
+}
+
+/// Rolling DPS at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RollingSample {
+    /// Time of the sample
+    pub timestamp: Timestamp,
+
+    /// Damage per second over the window ending at the sample
+    pub dps: f64,
+}
+
+/// Damage dealt by the player in one encounter
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EncounterReport {
+    /// Encounter the report covers
+    pub encounter: Encounter,
+
+    /// Total damage
+    pub damage: f64,
+
+    /// Damage per second over the whole encounter
+    pub dps: f64,
+
+    /// Highest rolling DPS
+    pub peak_dps: f64,
+
+    /// Number of hits
+    pub hits: u64,
+
+    /// Fraction of hits that were critical strikes
+    pub crit_rate: f64,
+
+    /// Fraction of hits that were overpowered
+    pub overpower_rate: f64,
+
+    /// Average damage per hit
+    pub average_hit: f64,
+
+    /// Largest hit
+    pub max_hit: f64,
+
+    /// Damage per skill, largest first
+    pub skills: Vec<SkillStats>,
+
+    /// Rolling DPS sampled every second of the encounter
+    pub rolling: Vec<RollingSample>,
+}
+
+/// Damage dealt by the player over a session
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DpsReport {
+    /// Actor identifier of the player
+    pub player: u64,
+
+    /// Seconds rolling DPS is averaged over
+    pub window: f64,
+
+    /// Total damage of every encounter
+    pub damage: f64,
+
+    /// Seconds spent in encounters
+    pub duration: f64,
+
+    /// Damage per second spent in encounters
+    pub dps: f64,
+
+    /// Report of every encounter
+    pub encounters: Vec<EncounterReport>,
+}
+
+/// Computes DPS and per-skill damage from the combat events of a session
+///
+/// Events are fed one at a time, and a report can be taken at any point.
+#[derive(Debug, Clone)]
+pub struct DpsMeter {
+    /// Splits the session into encounters
+    detector: EncounterDetector,
+
+    /// Time rolling DPS is averaged over
+    window: Duration,
+
+    /// Damage dealt in every encounter
+    encounters: Vec<EncounterDamage>,
+}
+
+/// Running totals of a set of hits
+#[derive(Debug, Clone, Default)]
+struct HitTotals {
+    /// Total damage
+    damage: f64,
+
+    /// Number of hits
+    hits: u64,
+
+    /// Number of critical strikes
+    crits: u64,
+
+    /// Number of overpowered hits
+    overpowers: u64,
+
+    /// Largest hit
+    max_hit: f64,
+}
+
+/// Damage dealt in a single encounter
+#[derive(Debug, Clone, Default)]
+struct EncounterDamage {
+    /// Totals of every hit
+    totals: HitTotals,
+
+    /// Totals per skill
+    skills: HashMap<String, HitTotals>,
+
+    /// Time and damage of every hit, for rolling DPS
+    hits: Vec<(Timestamp, f64)>,
+}
+
+impl HitTotals {
+    /// Adds a hit
+    fn add(&mut self, damage: &Damage) {
+        let amount = hit_damage(damage);
+        self.damage += amount;
+        self.hits += 1;
+        self.crits += damage.critical as u64;
+        self.overpowers += damage.overpower as u64;
+        self.max_hit = self.max_hit.max(amount);
+    }
+
+    /// Fraction of hits counted in a counter
+    fn rate(&self, count: u64) -> f64 {
+        ratio(count as f64, self.hits as f64)
+    }
+
+    /// Average damage per hit
+    fn average_hit(&self) -> f64 {
+        ratio(self.damage, self.hits as f64)
+    }
+}
+
+impl EncounterDamage {
+    /// Damage per second over the window ending at the given time
+    fn rolling_dps(&self, timestamp: Timestamp, window: Duration) -> f64 {
+        let end = self.hits.partition_point(|(hit, _)| *hit <= timestamp);
+        let start = timestamp.0 as i128 - window.as_micros() as i128;
+        let damage: f64 = self.hits[..end].iter()
+            .rev()
+            .take_while(|(hit, _)| hit.0 as i128 > start)
+            .map(|(_, amount)| amount)
+            .sum();
+        damage / window.as_secs_f64()
+    }
+}
+
+impl DpsMeter {
+    /// Create a new meter for the player with the given actor identifier
+    pub fn new(player: u64) -> Self {
+        Self {
+            detector: EncounterDetector::new(player),
+            window: DEFAULT_WINDOW,
+            encounters: vec![],
+        }
+    }
+
+    /// Sets the time rolling DPS is averaged over
+    ///
+    /// Panics if the window is zero.
+    pub fn window(mut self, window: Duration) -> Self {
+        assert!(!window.is_zero(), "Rolling DPS window must not be zero");
+        self.window = window;
+        self
+    }
+
+    /// Sets the idle time that ends an encounter
+    pub fn idle_gap(mut self, idle_gap: Duration) -> Self {
+        self.detector = self.detector.idle_gap(idle_gap);
+        self
+    }
+
+    /// Feeds the next event of the session
+    pub fn push(&mut self, event: &CombatEvent) {
+        let index = match self.detector.push(event) {
+            Some(index) => index,
+            None => return,
+        };
+        if index == self.encounters.len() {
+            self.encounters.push(EncounterDamage::default());
+        }
+
+        // Hits taken only mark the encounter as ongoing
+        let damage = match &event.kind {
+            EventKind::Damage(damage)
+                if damage.source.id == self.detector.player() => damage,
+            _ => return,
+        };
+        let encounter = &mut self.encounters[index];
+        encounter.totals.add(damage);
+        encounter.skills
+            .entry(damage.skill.clone()
+                .unwrap_or_else(|| UNKNOWN_SKILL.to_string()))
+            .or_default()
+            .add(damage);
+        encounter.hits.push((event.timestamp, hit_damage(damage)));
+    }
+
+    /// Rolling DPS of the current encounter at the given time, which is
+    /// zero outside of combat
+    pub fn rolling_dps(&self, now: Timestamp) -> f64 {
+        self.encounters.last()
+            .map_or(0.0, |encounter| encounter.rolling_dps(now, self.window))
+    }
+
+    /// Report over everything fed so far
+    pub fn report(&self) -> DpsReport {
+        let encounters: Vec<EncounterReport> = self.detector.encounters()
+            .iter()
+            .zip(&self.encounters)
+            .map(|(encounter, damage)| self.encounter_report(encounter, damage))
+            .collect();
+        let damage = encounters.iter().map(|report| report.damage).sum();
+        let duration = encounters.iter()
+            .map(|report| report.encounter.active_secs())
+            .sum();
+
+        DpsReport {
+            player: self.detector.player(),
+            window: self.window.as_secs_f64(),
+            damage,
+            duration,
+            dps: ratio(damage, duration),
+            encounters,
+        }
+    }
+
+    /// Builds the report of a single encounter
+    fn encounter_report(&self, encounter: &Encounter,
+            damage: &EncounterDamage) -> EncounterReport {
+        let totals = &damage.totals;
+
+        let mut skills: Vec<SkillStats> = damage.skills.iter()
+            .map(|(skill, stats)| SkillStats {
+                skill: skill.clone(),
+                damage: stats.damage,
+                share: ratio(stats.damage, totals.damage),
+                hits: stats.hits,
+                crit_rate: stats.rate(stats.crits),
+                overpower_rate: stats.rate(stats.overpowers),
+                average_hit: stats.average_hit(),
+                max_hit: stats.max_hit,
+            })
+            .collect();
+        skills.sort_by(|a, b| b.damage.total_cmp(&a.damage)
+            .then_with(|| a.skill.cmp(&b.skill)));
+
+        // Rolling DPS only rises at a hit, so its peak is at one of them
+        let peak_dps = damage.hits.iter()
+            .map(|&(timestamp, _)| damage.rolling_dps(timestamp, self.window))
+            .fold(0.0, f64::max);
+        let seconds = encounter.duration().as_secs();
+        let rolling = (0..=seconds)
+            .map(|second| {
+                let timestamp = Timestamp(
+                    encounter.start.0 + second * 1_000_000);
+                RollingSample {
+                    timestamp,
+                    dps: damage.rolling_dps(timestamp, self.window),
+                }
+            })
+            .collect();
+
+        EncounterReport {
+            encounter: *encounter,
+            damage: totals.damage,
+            dps: totals.damage / encounter.active_secs(),
+            peak_dps,
+            hits: totals.hits,
+            crit_rate: totals.rate(totals.crits),
+            overpower_rate: totals.rate(totals.overpowers),
+            average_hit: totals.average_hit(),
+            max_hit: totals.max_hit,
+            skills,
+            rolling,
+        }
+    }
+}
+
+impl std::fmt::Display for DpsReport {
+    /// Formats the report as a terminal table per encounter
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Total {} over {:.1}s, {} DPS", format_amount(self.damage),
+            self.duration, format_amount(self.dps))?;
+
+        for report in &self.encounters {
+            let encounter = &report.encounter;
+            writeln!(f)?;
+            writeln!(f, "Encounter {}  {} - {}  {} DPS, peak {} ({:.0}s window)",
+                encounter.index + 1, format_time(encounter.start),
+                format_time(encounter.end), format_amount(report.dps),
+                format_amount(report.peak_dps), self.window)?;
+            writeln!(f, "{:<24} {:>9} {:>7} {:>6} {:>7} {:>7} {:>9} {:>9}",
+                "Skill", "Damage", "Share", "Hits", "Crit", "Overpw",
+                "Avg hit", "Max hit")?;
+            for skill in &report.skills {
+                writeln!(f,
+                    "{:<24} {:>9} {:>7} {:>6} {:>7} {:>7} {:>9} {:>9}",
+                    skill.skill, format_amount(skill.damage),
+                    format_percent(skill.share), skill.hits,
+                    format_percent(skill.crit_rate),
+                    format_percent(skill.overpower_rate),
+                    format_amount(skill.average_hit),
+                    format_amount(skill.max_hit))?;
+            }
+            writeln!(f, "{:<24} {:>9} {:>7} {:>6} {:>7} {:>7} {:>9} {:>9}",
+                "Total", format_amount(report.damage), format_percent(1.0),
+                report.hits, format_percent(report.crit_rate),
+                format_percent(report.overpower_rate),
+                format_amount(report.average_hit),
+                format_amount(report.max_hit))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+use serde::Serialize;
+use crate::logger::{CombatEvent, EventKind, Timestamp};
+
+/// Idle time after which the next hit starts a new encounter
+pub const DEFAULT_IDLE_GAP: Duration = Duration::from_secs(5);
+
+/// A stretch of continuous combat
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Encounter {
+    /// Position among the encounters of the session, starting at zero
+    pub index: usize,
+
+    /// Time of the first hit
+    pub start: Timestamp,
+
+    /// Time of the last hit so far
+    pub end: Timestamp,
+}
+
+/// Splits a session into encounters at idle gaps
+///
+/// Only hits dealt or taken by the player count as combat; an encounter
+/// ends once no hit happened for longer than the idle gap.
+#[derive(Debug, Clone)]
+pub struct EncounterDetector {
+    /// Actor identifier of the player
+    player: u64,
+
+    /// Idle time that ends an encounter
+    idle_gap: Duration,
+
+    /// Encounters seen so far, the last one possibly still running
+    encounters: Vec<Encounter>,
+}
+
+/// Checks whether an event is a hit dealt or taken by the player
+pub fn is_combat(event: &CombatEvent, player: u64) -> bool {
+    match &event.kind {
+        EventKind::Damage(damage) =>
+            damage.source.id == player || damage.target.id == player,
+        _ => false,
+    }
+}
+
+impl Encounter {
+    /// Length of the encounter
+    pub fn duration(&self) -> Duration {
+        self.end.as_duration().saturating_sub(self.start.as_duration())
+    }
+
+    /// Length used to compute rates, which is at least a second so that an
+    /// encounter of a single hit does not produce an absurd rate
+    pub fn active_secs(&self) -> f64 {
+        self.duration().as_secs_f64().max(1.0)
+    }
+
+    /// Checks whether a time falls inside the encounter
+    pub fn contains(&self, timestamp: Timestamp) -> bool {
+        self.start <= timestamp && timestamp <= self.end
+    }
+}
+
+impl EncounterDetector {
+    /// Create a new detector for the player with the given actor identifier
+    pub fn new(player: u64) -> Self {
+        Self { player, idle_gap: DEFAULT_IDLE_GAP, encounters: vec![] }
+    }
+
+    /// Sets the idle time that ends an encounter
+    pub fn idle_gap(mut self, idle_gap: Duration) -> Self {
+        self.idle_gap = idle_gap;
+        self
+    }
+
+    /// Actor identifier of the player
+    pub fn player(&self) -> u64 {
+        self.player
+    }
+
+    /// Feeds the next event, returning the index of the encounter it
+    /// belongs to when it is combat
+    pub fn push(&mut self, event: &CombatEvent) -> Option<usize> {
+        if !is_combat(event, self.player) {
+            return None;
+        }
+
+        let timestamp = event.timestamp;
+        match self.encounters.last_mut() {
+            Some(current) if timestamp.as_duration()
+                    .saturating_sub(current.end.as_duration())
+                    <= self.idle_gap => {
+                current.end = current.end.max(timestamp);
+            }
+            _ => self.encounters.push(Encounter {
+                index: self.encounters.len(),
+                start: timestamp,
+                end: timestamp,
+            }),
+        }
+
+        Some(self.encounters.len() - 1)
+    }
+
+    /// Checks whether a time belongs to an encounter, counting the idle gap
+    /// before its first hit, where the casts that open the fight land
+    ///
+    /// Encounters are further apart than the idle gap, so a time belongs to
+    /// at most one of them.
+    pub fn covers(&self, encounter: &Encounter, timestamp: Timestamp)
+            -> bool {
+        let lead_in = encounter.start.as_duration()
+            .saturating_sub(self.idle_gap);
+        lead_in <= timestamp.as_duration() && timestamp <= encounter.end
+    }
+
+    /// Encounters seen so far, the last one possibly still running
+    pub fn encounters(&self) -> &[Encounter] {
+        &self.encounters
+    }
+
+    /// Splits a whole recorded session into encounters
+    pub fn detect<'a>(mut self,
+            events: impl IntoIterator<Item = &'a CombatEvent>)
+            -> Vec<Encounter> {
+        for event in events {
+            self.push(event);
+        }
+        self.encounters
+    }
+}
@@ -0,0 +1,70 @@
+use crate::logger::{Damage, Timestamp};
+
+mod buffs;
+mod compare;
+mod defense;
+mod dps;
+mod encounter;
+mod rotation;
+
+pub use buffs::{
+    BuffInterval, BuffReport, BuffTracker, BuffUptime, CombinationUptime,
+    EncounterBuffs,
+};
+pub use compare::{
+    BuildComparison, ComparisonReport, Estimate, GroupSummary, MetricComparison,
+    MetricKind, SessionMetrics, DEFAULT_CONFIDENCE, DEFAULT_RESAMPLES,
+};
+pub use defense::{
+    DeathRecap, DefenseReport, DefenseTracker, HealingSource, NearDeath,
+    RecapEntry, SourceDamage, TypeDamage, DEFAULT_NEAR_DEATH, DEFAULT_RECAP,
+};
+pub use dps::{
+    DpsMeter, DpsReport, EncounterReport, RollingSample, SkillStats,
+    DEFAULT_WINDOW,
+};
+pub use encounter::{is_combat, Encounter, EncounterDetector, DEFAULT_IDLE_GAP};
+pub use rotation::{
+    CastRecord, CastSequence, EncounterRotation, RotationReport,
+    RotationTracker, SkillUsage, DEFAULT_SEQUENCE_LENGTH, DEFAULT_TOP_SEQUENCES,
+};
+
+/// Damage of a hit, including what barriers absorbed
+fn hit_damage(damage: &Damage) -> f64 {
+    damage.amount + damage.absorbed
+}
+
+/// Fraction of a total, zero when the total is
+fn ratio(part: f64, total: f64) -> f64 {
+    if total > 0.0 { part / total } else { 0.0 }
+}
+
+/// Formats an amount with a k, M or B suffix, as the game does
+fn format_amount(amount: f64) -> String {
+    // Sums of nothing are negative zero, which should not print a sign
+    let amount = amount + 0.0;
+    let magnitude = amount.abs();
+    if magnitude >= 1e9 {
+        format!("{:.2}B", amount / 1e9)
+    }
+    else if magnitude >= 1e6 {
+        format!("{:.2}M", amount / 1e6)
+    }
+    else if magnitude >= 1e3 {
+        format!("{:.1}k", amount / 1e3)
+    }
+    else {
+        format!("{:.0}", amount)
+    }
+}
+
+/// Formats a fraction as a percentage
+fn format_percent(fraction: f64) -> String {
+    format!("{:.1}%", fraction * 100.0)
+}
+
+/// Formats a timestamp as minutes and seconds into the session
+fn format_time(timestamp: Timestamp) -> String {
+    let tenths = timestamp.0 / 100_000;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
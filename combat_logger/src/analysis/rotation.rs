@@ -0,0 +1,378 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::time::Duration;
+use serde::Serialize;
+use crate::logger::{CombatEvent, EventKind, Timestamp};
+use super::{format_percent, format_time, Encounter, EncounterDetector};
+
+/// Length of the cast sequences counted by default
+pub const DEFAULT_SEQUENCE_LENGTH: usize = 3;
+
+/// Number of cast sequences reported per encounter by default
+pub const DEFAULT_TOP_SEQUENCES: usize = 5;
+
+/// A cast on the timeline, with the cooldown state of its skill
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CastRecord {
+    /// Time of the cast
+    pub timestamp: Timestamp,
+
+    /// Name of the skill
+    pub skill: String,
+
+    /// Cooldown in seconds the cast started, if the skill has one
+    pub cooldown: Option<f64>,
+
+    /// Time the previous cast of the skill came off cooldown, if it had one
+    pub ready: Option<Timestamp>,
+
+    /// Seconds the skill was ready but unused before this cast
+    pub delay: Option<f64>,
+}
+
+/// Use of a single skill in an encounter
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkillUsage {
+    /// Name of the skill
+    pub skill: String,
+
+    /// Number of casts
+    pub casts: u64,
+
+    /// Casts per minute of the encounter
+    pub casts_per_minute: f64,
+
+    /// Cooldown in seconds of the latest cast, if the skill has one
+    pub cooldown: Option<f64>,
+
+    /// Seconds the skill was off cooldown but unused, if it has a cooldown
+    pub wasted: Option<f64>,
+
+    /// Fraction of the encounter the skill was off cooldown but unused
+    pub wasted_share: Option<f64>,
+}
+
+/// A sequence of consecutive casts and how often it occurred
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CastSequence {
+    /// Names of the skills, in cast order
+    pub skills: Vec<String>,
+
+    /// Number of times the sequence occurred
+    pub count: u64,
+}
+
+/// Rotation of the player in one encounter
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EncounterRotation {
+    /// Encounter the report covers
+    pub encounter: Encounter,
+
+    /// Number of casts
+    pub casts: u64,
+
+    /// Seconds the rates are computed over, from the opening cast or the
+    /// first hit, whichever came first, to the end of the encounter
+    pub active_secs: f64,
+
+    /// Casts per minute of the encounter
+    pub apm: f64,
+
+    /// Use of every skill, most cast first
+    pub skills: Vec<SkillUsage>,
+
+    /// Most frequent cast sequences, most frequent first
+    pub sequences: Vec<CastSequence>,
+}
+
+/// Rotation of the player over a session
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RotationReport {
+    /// Actor identifier of the player
+    pub player: u64,
+
+    /// Casts per minute spent in encounters
+    pub apm: f64,
+
+    /// Every cast of the session, in order
+    pub timeline: Vec<CastRecord>,
+
+    /// Report of every encounter
+    pub encounters: Vec<EncounterRotation>,
+}
+
+/// Rebuilds the cast timeline of the player and analyzes the rotation
+///
+/// Logs do not say when a skill was put on the bar, so a skill is only
+/// known to be available from its first cast on, and is left out of the
+/// encounters before it. A skill with a cooldown counts as wasted from the
+/// moment it comes off cooldown until it is cast again or the encounter
+/// ends.
+#[derive(Debug, Clone)]
+pub struct RotationTracker {
+    /// Splits the session into encounters
+    detector: EncounterDetector,
+
+    /// Length of the cast sequences counted
+    sequence_length: usize,
+
+    /// Number of cast sequences reported per encounter
+    top_sequences: usize,
+
+    /// Every cast so far
+    timeline: Vec<CastRecord>,
+
+    /// Time every skill comes off cooldown, by name
+    ready: HashMap<String, Timestamp>,
+}
+
+/// Time a cast comes off cooldown
+fn ready_at(timestamp: Timestamp, cooldown: Option<f64>) -> Option<Timestamp> {
+    cooldown.map(|cooldown| Timestamp(timestamp.0
+        + (cooldown.max(0.0) * 1e6) as u64))
+}
+
+/// Microseconds a range overlaps an encounter
+fn overlap(start: u64, end: u64, encounter: &Encounter) -> u64 {
+    end.min(encounter.end.0)
+        .saturating_sub(start.max(encounter.start.0))
+}
+
+impl RotationTracker {
+    /// Create a new tracker for the player with the given actor identifier
+    pub fn new(player: u64) -> Self {
+        Self {
+            detector: EncounterDetector::new(player),
+            sequence_length: DEFAULT_SEQUENCE_LENGTH,
+            top_sequences: DEFAULT_TOP_SEQUENCES,
+            timeline: vec![],
+            ready: HashMap::new(),
+        }
+    }
+
+    /// Sets the idle time that ends an encounter
+    pub fn idle_gap(mut self, idle_gap: Duration) -> Self {
+        self.detector = self.detector.idle_gap(idle_gap);
+        self
+    }
+
+    /// Sets the length of the cast sequences counted
+    ///
+    /// Panics if the length is zero.
+    pub fn sequence_length(mut self, length: usize) -> Self {
+        assert!(length > 0, "Cast sequence length must not be zero");
+        self.sequence_length = length;
+        self
+    }
+
+    /// Sets the number of cast sequences reported per encounter
+    pub fn top_sequences(mut self, count: usize) -> Self {
+        self.top_sequences = count;
+        self
+    }
+
+    /// Feeds the next event of the session
+    pub fn push(&mut self, event: &CombatEvent) {
+        self.detector.push(event);
+
+        let cast = match &event.kind {
+            EventKind::Cast(cast)
+                if cast.source.id == self.detector.player() => cast,
+            _ => return,
+        };
+        let ready = self.ready.get(&cast.skill).copied();
+        let delay = ready.map(|ready| event.timestamp.as_duration()
+            .saturating_sub(ready.as_duration())
+            .as_secs_f64());
+
+        match ready_at(event.timestamp, cast.cooldown) {
+            Some(next) => self.ready.insert(cast.skill.clone(), next),
+            None => self.ready.remove(&cast.skill),
+        };
+        self.timeline.push(CastRecord {
+            timestamp: event.timestamp,
+            skill: cast.skill.clone(),
+            cooldown: cast.cooldown,
+            ready,
+            delay,
+        });
+    }
+
+    /// Every cast so far, in order
+    pub fn timeline(&self) -> &[CastRecord] {
+        &self.timeline
+    }
+
+    /// Report over everything fed so far
+    pub fn report(&self) -> RotationReport {
+        let encounters: Vec<EncounterRotation> = self.detector.encounters()
+            .iter()
+            .map(|encounter| self.encounter_rotation(encounter))
+            .collect();
+        let casts: u64 = encounters.iter().map(|report| report.casts).sum();
+        let minutes: f64 = encounters.iter()
+            .map(|report| report.active_secs / 60.0)
+            .sum();
+
+        RotationReport {
+            player: self.detector.player(),
+            apm: if minutes > 0.0 { casts as f64 / minutes } else { 0.0 },
+            timeline: self.timeline.clone(),
+            encounters,
+        }
+    }
+
+    /// Builds the report of a single encounter
+    fn encounter_rotation(&self, encounter: &Encounter) -> EncounterRotation {
+        // The cast opening a fight comes before its first hit
+        let casts: Vec<&CastRecord> = self.timeline.iter()
+            .filter(|cast| self.detector.covers(encounter, cast.timestamp))
+            .collect();
+        let start = casts.first()
+            .map_or(encounter.start, |cast| cast.timestamp.min(encounter.start));
+        let active_secs = encounter.end.as_duration()
+            .saturating_sub(start.as_duration())
+            .as_secs_f64()
+            .max(1.0);
+        let minutes = active_secs / 60.0;
+
+        // Skills with a cooldown are reported even when unused, as their
+        // availability was wasted, but only once they have been seen
+        let mut by_skill: BTreeMap<&str, Vec<&CastRecord>> = BTreeMap::new();
+        for cast in &self.timeline {
+            by_skill.entry(&cast.skill).or_default().push(cast);
+        }
+        by_skill.retain(|_, all_casts| all_casts[0].timestamp <= encounter.end);
+        let mut skills: Vec<SkillUsage> = by_skill.iter()
+            .filter_map(|(skill, all_casts)| {
+                let count = casts.iter()
+                    .filter(|cast| cast.skill == *skill)
+                    .count() as u64;
+                let wasted = Self::wasted(all_casts, encounter);
+                if count == 0 && wasted.is_none() {
+                    return None;
+                }
+                let cooldown = all_casts.iter()
+                    .rev()
+                    .find(|cast| cast.timestamp <= encounter.end)
+                    .and_then(|cast| cast.cooldown);
+
+                Some(SkillUsage {
+                    skill: skill.to_string(),
+                    casts: count,
+                    casts_per_minute: count as f64 / minutes,
+                    cooldown,
+                    wasted,
+                    wasted_share: wasted.map(|wasted|
+                        wasted / encounter.active_secs()),
+                })
+            })
+            .collect();
+        skills.sort_by(|a, b| b.casts.cmp(&a.casts)
+            .then_with(|| a.skill.cmp(&b.skill)));
+
+        EncounterRotation {
+            encounter: *encounter,
+            casts: casts.len() as u64,
+            active_secs,
+            apm: casts.len() as f64 / minutes,
+            skills,
+            sequences: self.sequences(&casts),
+        }
+    }
+
+    /// Seconds a skill was off cooldown but unused during an encounter, or
+    /// `None` when it never had a cooldown
+    fn wasted(casts: &[&CastRecord], encounter: &Encounter) -> Option<f64> {
+        if casts.iter().all(|cast| cast.cooldown.is_none()) {
+            return None;
+        }
+
+        // Only known to be available from the first cast on
+        let mut ready = casts[0].timestamp.0;
+        let mut wasted = 0;
+        for cast in casts {
+            wasted += overlap(ready, cast.timestamp.0, encounter);
+            ready = ready_at(cast.timestamp, cast.cooldown)
+                .unwrap_or(cast.timestamp).0;
+        }
+        wasted += overlap(ready, encounter.end.0, encounter);
+
+        Some(wasted as f64 / 1e6)
+    }
+
+    /// Most frequent sequences of consecutive casts
+    fn sequences(&self, casts: &[&CastRecord]) -> Vec<CastSequence> {
+        let mut counts: HashMap<Vec<&str>, u64> = HashMap::new();
+        for window in casts.windows(self.sequence_length) {
+            let skills = window.iter().map(|cast| cast.skill.as_str())
+                .collect();
+            *counts.entry(skills).or_default() += 1;
+        }
+
+        let mut sequences: Vec<CastSequence> = counts.into_iter()
+            .map(|(skills, count)| CastSequence {
+                skills: skills.into_iter().map(str::to_string).collect(),
+                count,
+            })
+            .collect();
+        sequences.sort_by(|a, b| b.count.cmp(&a.count)
+            .then_with(|| a.skills.cmp(&b.skills)));
+        sequences.truncate(self.top_sequences);
+        sequences
+    }
+}
+
+impl RotationReport {
+    /// Writes the cast timeline as CSV, with times in seconds, for
+    /// comparison against rotation simulations
+    pub fn write_timeline<W: Write>(&self, mut writer: W)
+            -> std::io::Result<()> {
+        writeln!(writer, "time,skill,cooldown,ready,delay")?;
+        for cast in &self.timeline {
+            let optional = |value: Option<f64>| value
+                .map_or(String::new(), |value| format!("{:.3}", value));
+            writeln!(writer, "{:.3},{},{},{},{}", cast.timestamp.as_secs_f64(),
+                cast.skill.replace(',', " "), optional(cast.cooldown),
+                optional(cast.ready.map(|ready| ready.as_secs_f64())),
+                optional(cast.delay))?;
+        }
+
+        writer.flush()
+    }
+}
+
+impl std::fmt::Display for RotationReport {
+    /// Formats the report as a terminal table per encounter
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "{} casts, {:.1} APM", self.timeline.len(), self.apm)?;
+
+        for report in &self.encounters {
+            let encounter = &report.encounter;
+            writeln!(f)?;
+            writeln!(f, "Encounter {}  {} - {}  {} casts, {:.1} APM",
+                encounter.index + 1, format_time(encounter.start),
+                format_time(encounter.end), report.casts, report.apm)?;
+            writeln!(f, "{:<24} {:>6} {:>7} {:>9} {:>8} {:>7}",
+                "Skill", "Casts", "CPM", "Cooldown", "Wasted", "Share")?;
+            for skill in &report.skills {
+                let seconds = |value: Option<f64>| value
+                    .map_or("-".to_string(), |value| format!("{:.1}s", value));
+                writeln!(f, "{:<24} {:>6} {:>7.1} {:>9} {:>8} {:>7}",
+                    skill.skill, skill.casts, skill.casts_per_minute,
+                    seconds(skill.cooldown), seconds(skill.wasted),
+                    skill.wasted_share
+                        .map_or("-".to_string(), format_percent))?;
+            }
+            if !report.sequences.is_empty() {
+                writeln!(f, "Frequent sequences")?;
+                for sequence in &report.sequences {
+                    writeln!(f, "{:>6}x  {}", sequence.count,
+                        sequence.skills.join(" > "))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use crate::memory::{Error, LayoutSet, RemotePtr, RemoteStruct, Result};
+
+/// Layout files shipped for each game build, oldest first
+///
+/// Only layouts whose offsets were confirmed on their build belong here, none
+/// are yet.
+const BUILDS: &[(&str, &str)] = &[];
+
+/// Template new layout files start from, with placeholder offsets
+const TEMPLATE: &str = include_str!("../layouts/template.toml");
+
+/// Lists the game builds layouts are shipped for, oldest first
+pub fn builds() -> impl Iterator<Item = &'static str> {
+    BUILDS.iter().map(|(build, _)| *build)
+}
+
+/// Loads the struct layouts shipped for a game build
+pub fn layouts(build: &str) -> Result<LayoutSet> {
+    let (_, contents) = BUILDS.iter()
+        .find(|(known, _)| *known == build)
+        .ok_or_else(|| Error::Layout(
+            format!("No layouts shipped for game build '{}'", build)))?;
+    LayoutSet::from_toml(contents)
+}
+
+/// Loads the struct layouts of the most recent known game build
+pub fn latest_layouts() -> Result<LayoutSet> {
+    let (_, contents) = BUILDS.last()
+        .ok_or_else(|| Error::Layout("No layouts shipped".to_string()))?;
+    LayoutSet::from_toml(contents)
+}
+
+/// Loads the template layouts, whose offsets are unconfirmed placeholders
+pub fn template_layouts() -> Result<LayoutSet> {
+    LayoutSet::from_toml(TEMPLATE)
+}
+
+// Typed mirrors of `layouts/template.toml`, with the same placeholder offsets
+// that were not confirmed on any game build. They show how game objects are
+// declared until a confirmed build replaces them.
+
+/// Position in world space
+#[derive(Debug, Clone, Copy, PartialEq, RemoteStruct)]
+pub struct Vector3 {
+    #[offset(0x0)]
+    pub x: f32,
+
+    #[offset(0x4)]
+    pub y: f32,
+
+    #[offset(0x8)]
+    pub z: f32,
+}
+
+/// Buff or debuff applied to an actor
+#[derive(Debug, Clone, Copy, PartialEq, RemoteStruct)]
+#[remote(size = 0x20)]
+pub struct Buff {
+    /// Power identifier of the buff
+    #[offset(0x0)]
+    pub id: u32,
+
+    #[offset(0x4)]
+    pub stacks: u32,
+
+    /// Full duration in seconds
+    #[offset(0x8)]
+    pub duration: f32,
+
+    /// Remaining duration in seconds
+    #[offset(0xC)]
+    pub remaining: f32,
+
+    /// Actor that applied the buff
+    #[offset(0x10)]
+    pub source_id: u32,
+}
+
+/// Player, monster or other actor
+#[derive(Debug, Clone, Copy, PartialEq, RemoteStruct)]
+#[remote(size = 0x400)]
+pub struct Actor {
+    #[offset(0x8)]
+    pub id: u32,
+
+    #[offset(0xC)]
+    pub type_id: u32,
+
+    #[offset(0xD0)]
+    pub position: Vector3,
+
+    #[offset(0x1E0)]
+    pub health: f32,
+
+    #[offset(0x1E4)]
+    pub max_health: f32,
+
+    #[offset(0x1E8)]
+    pub barrier: f32,
+
+    #[offset(0x1F0)]
+    pub is_elite: bool,
+
+    /// Number of used entries in `buffs`
+    #[offset(0x2F8)]
+    pub buff_count: u32,
+
+    #[offset(0x300)]
+    #[pointer]
+    pub buffs: RemotePtr<[Buff; 16]>,
+
+    #[offset(0x308)]
+    #[pointer]
+    pub target: RemotePtr<Actor>,
+}
+
+/// Local player
+#[derive(Debug, Clone, Copy, PartialEq, RemoteStruct)]
+#[remote(size = 0x200)]
+pub struct Player {
+    #[offset(0x10)]
+    #[pointer]
+    pub actor: RemotePtr<Actor>,
+
+    #[offset(0x40)]
+    pub level: u32,
+
+    #[offset(0x44)]
+    pub paragon_level: u32,
+
+    #[offset(0x48)]
+    pub world_tier: u32,
+
+    #[offset(0x60)]
+    pub resource: f32,
+
+    #[offset(0x64)]
+    pub max_resource: f32,
+
+    #[offset(0x80)]
+    pub gold: u64,
+}
@@ -0,0 +1,79 @@
+use super::{Error, Result};
+
+/// Milliseconds in a day
+pub const DAY_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+/// Years a date may fall in, which keeps the day arithmetic from overflowing
+const YEARS: std::ops::RangeInclusive<i64> = 1970..=9999;
+
+/// Days since the Unix epoch of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_of_year = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_of_year + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100
+        + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Date in the proleptic Gregorian calendar of a number of days since the
+/// Unix epoch
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era
+        - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_of_year = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_of_year + 2) / 5 + 1) as u32;
+    let month = if month_of_year < 10 {
+        month_of_year + 3
+    }
+    else {
+        month_of_year - 9
+    } as u32;
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+/// Parses a `YYYY-MM-DD` date into milliseconds since the Unix epoch at the
+/// start of that day in UTC
+pub fn parse_date(date: &str) -> Result<u64> {
+    let bad_date = || Error::BadDate(date.to_string());
+    let parts: Vec<&str> = date.trim().split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return Err(bad_date());
+    };
+    let year: i64 = year.parse().ok()
+        .filter(|year| YEARS.contains(year))
+        .ok_or_else(bad_date)?;
+    let month: u32 = month.parse().map_err(|_| bad_date())?;
+    let day: u32 = day.parse().map_err(|_| bad_date())?;
+
+    // Round trip the date to reject days past the end of the month
+    let days = days_from_civil(year, month, day);
+    if !(1..=12).contains(&month) || civil_from_days(days) != (year, month, day)
+            || days < 0 {
+        return Err(bad_date());
+    }
+    Ok(days as u64 * DAY_MILLIS)
+}
+
+/// Parses a `YYYY-MM-DD` date into milliseconds since the Unix epoch at the
+/// end of that day in UTC, so that the whole day is included
+pub fn parse_date_end(date: &str) -> Result<u64> {
+    parse_date(date)?.checked_add(DAY_MILLIS)
+        .ok_or_else(|| Error::BadDate(date.to_string()))
+}
+
+/// Formats milliseconds since the Unix epoch as a UTC date and time
+pub fn format_date(millis: u64) -> String {
+    let (year, month, day) = civil_from_days((millis / DAY_MILLIS) as i64);
+    let minutes = millis % DAY_MILLIS / 60_000;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, minutes / 60,
+        minutes % 60)
+}
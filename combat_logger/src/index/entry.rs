@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use crate::logger::{CombatEvent, EventKind, LogHeader};
+
+/// Log file of a session as it was when indexed
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct IndexedFile {
+    /// Name of the file within the log directory
+    pub name: String,
+
+    /// Size of the file in bytes
+    pub size: u64,
+
+    /// Last modification time in milliseconds since the Unix epoch
+    pub modified: u64,
+}
+
+/// Totals of a session for the player
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionTotals {
+    /// Number of events logged
+    pub events: u64,
+
+    /// Damage dealt by the player, including what barriers absorbed
+    pub damage_dealt: f64,
+
+    /// Damage taken by the player, including what barriers absorbed
+    pub damage_taken: f64,
+
+    /// Life restored by the player, without overhealing
+    pub healing: f64,
+
+    /// Number of monsters killed by the player
+    pub kills: u64,
+
+    /// Number of times the player died
+    pub deaths: u64,
+
+    /// Number of skills cast by the player
+    pub casts: u64,
+}
+
+/// Summary of one recorded session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionEntry {
+    /// Identifier shared by every log of the session
+    pub session_id: String,
+
+    /// Wall clock time of the session start in milliseconds since the Unix
+    /// epoch
+    pub started_at: u64,
+
+    /// Build of the game the session was recorded with
+    pub build: String,
+
+    /// Name of the character played
+    pub character: Option<String>,
+
+    /// Class of the character played
+    pub class: Option<String>,
+
+    /// Level of the character played
+    pub level: Option<u32>,
+
+    /// World tier the session was played on
+    pub world_tier: Option<u32>,
+
+    /// Time between the session start and its last event in seconds
+    pub duration: f64,
+
+    /// Zones visited, in the order they were first entered
+    pub zones: Vec<String>,
+
+    /// Totals for the player
+    pub totals: SessionTotals,
+
+    /// Hash of the equipped items, identifying sessions played with the same
+    /// gear
+    pub gear_hash: Option<String>,
+
+    /// Log files of the session, in order
+    pub files: Vec<IndexedFile>,
+}
+
+/// Hashes the equipped items with 64-bit FNV-1a, or `None` when they are not
+/// known
+pub fn gear_hash(gear: &[String]) -> Option<String> {
+    if gear.is_empty() {
+        return None;
+    }
+
+    // Terminate every item so moving text between slots changes the hash
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for item in gear {
+        for byte in item.bytes().chain([0]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    Some(format!("{:016x}", hash))
+}
+
+impl SessionEntry {
+    /// Starts an entry from the header of the first log of a session
+    pub(super) fn new(header: &LogHeader) -> Self {
+        let character = header.game.character.as_ref();
+        Self {
+            session_id: header.session_id.clone(),
+            started_at: header.started_at,
+            build: header.game.build.clone(),
+            character: character.map(|character| character.name.clone()),
+            class: character.map(|character| character.class.clone()),
+            level: character.map(|character| character.level),
+            world_tier: header.game.world_tier,
+            duration: 0.0,
+            zones: vec![],
+            totals: SessionTotals::default(),
+            gear_hash: gear_hash(&header.game.gear),
+            files: vec![],
+        }
+    }
+
+    /// Takes the game details of a later log of the session where the first
+    /// ones did not have them
+    pub(super) fn merge_header(&mut self, header: &LogHeader) {
+        if let Some(character) = &header.game.character {
+            self.character.get_or_insert_with(|| character.name.clone());
+            self.class.get_or_insert_with(|| character.class.clone());
+            self.level = self.level.max(Some(character.level));
+        }
+        if self.world_tier.is_none() {
+            self.world_tier = header.game.world_tier;
+        }
+        if self.gear_hash.is_none() {
+            self.gear_hash = gear_hash(&header.game.gear);
+        }
+    }
+
+    /// Adds an event to the totals of the player
+    pub(super) fn push(&mut self, player: Option<u64>, event: &CombatEvent) {
+        self.duration = self.duration.max(event.timestamp.as_secs_f64());
+        let totals = &mut self.totals;
+        totals.events += 1;
+
+        let is_player = |id: u64| Some(id) == player;
+        match &event.kind {
+            EventKind::ZoneChange(change)
+                    if !self.zones.contains(&change.zone) => {
+                self.zones.push(change.zone.clone());
+            }
+            EventKind::Damage(damage) => {
+                let amount = damage.amount + damage.absorbed;
+                if is_player(damage.source.id) {
+                    totals.damage_dealt += amount;
+                }
+                if is_player(damage.target.id) {
+                    totals.damage_taken += amount;
+                }
+            }
+            EventKind::Heal(heal) if is_player(heal.source.id) => {
+                totals.healing += heal.amount;
+            }
+            EventKind::Kill(kill) => {
+                if is_player(kill.victim.id) {
+                    totals.deaths += 1;
+                }
+                else if kill.killer.as_ref()
+                        .is_some_and(|killer| is_player(killer.id)) {
+                    totals.kills += 1;
+                }
+            }
+            EventKind::Cast(cast) if is_player(cast.source.id) => {
+                totals.casts += 1;
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,66 @@
+mod date;
+mod entry;
+mod query;
+mod store;
+
+pub use date::{format_date, parse_date, parse_date_end, DAY_MILLIS};
+pub use entry::{gear_hash, IndexedFile, SessionEntry, SessionTotals};
+pub use query::SessionQuery;
+pub use store::{ScanSummary, SessionIndex, INDEX_FILE};
+
+#[derive(Debug)]
+pub enum Error {
+    /// Date is not in `YYYY-MM-DD` form
+    BadDate(String),
+
+    /// Session filter is not a known `key=value` pair
+    BadQuery(String),
+
+    /// Log could not be read
+    Log(crate::logger::Error),
+
+    /// JSON error
+    Json(serde_json::Error),
+
+    /// IO error
+    IOError(std::io::Error),
+}
+
+/// Implement the formatter for our custom error type
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::BadDate(date) =>
+                write!(f, "Bad date '{}', expected YYYY-MM-DD", date),
+            Error::BadQuery(filter) =>
+                write!(f, "Bad session filter '{}'", filter),
+            Error::Log(e) =>
+                write!(f, "Log error: {}", e),
+            Error::Json(e) =>
+                write!(f, "JSON error: {}", e),
+            Error::IOError(e) =>
+                write!(f, "IO error: {}", e),
+        }
+    }
+}
+
+/// Implement standard error trait and conversion from other error types
+impl std::error::Error for Error {}
+impl From<crate::logger::Error> for Error {
+    fn from(err: crate::logger::Error) -> Self {
+        Error::Log(err)
+    }
+}
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::IOError(err)
+    }
+}
+
+/// Custom Result type alias
+pub type Result<T> = std::result::Result<T, Error>;
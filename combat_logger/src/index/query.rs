@@ -0,0 +1,143 @@
+use super::{parse_date, parse_date_end, Error, Result, SessionEntry};
+
+/// Conditions on indexed sessions, all of which must hold for a session to
+/// match
+///
+/// Text is compared ignoring case, and times are milliseconds since the Unix
+/// epoch.
+#[derive(Debug, Clone, Default)]
+pub struct SessionQuery {
+    /// Name of the character played
+    character: Option<String>,
+
+    /// Class of the character played
+    class: Option<String>,
+
+    /// Level of the character played
+    level: Option<u32>,
+
+    /// World tier played on
+    world_tier: Option<u32>,
+
+    /// Zone visited during the session
+    zone: Option<String>,
+
+    /// Earliest session start
+    since: Option<u64>,
+
+    /// Time before which sessions must have started
+    until: Option<u64>,
+
+    /// Hash of the equipped items
+    gear_hash: Option<String>,
+}
+
+/// Whether an optional text equals the expected one, ignoring case
+fn text_matches(actual: Option<&str>, expected: &Option<String>) -> bool {
+    match expected {
+        Some(expected) => actual
+            .is_some_and(|actual| actual.eq_ignore_ascii_case(expected)),
+        None => true,
+    }
+}
+
+impl SessionQuery {
+    /// Query matching every session
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only matches sessions played with this character
+    pub fn character(mut self, character: &str) -> Self {
+        self.character = Some(character.to_string());
+        self
+    }
+
+    /// Only matches sessions played with this class
+    pub fn class(mut self, class: &str) -> Self {
+        self.class = Some(class.to_string());
+        self
+    }
+
+    /// Only matches sessions played at this level
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Only matches sessions played on this world tier
+    pub fn world_tier(mut self, world_tier: u32) -> Self {
+        self.world_tier = Some(world_tier);
+        self
+    }
+
+    /// Only matches sessions that visited this zone
+    pub fn zone(mut self, zone: &str) -> Self {
+        self.zone = Some(zone.to_string());
+        self
+    }
+
+    /// Only matches sessions started at or after this time
+    pub fn since(mut self, since: u64) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Only matches sessions started before this time
+    pub fn until(mut self, until: u64) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    /// Only matches sessions played with this gear
+    pub fn gear_hash(mut self, gear_hash: &str) -> Self {
+        self.gear_hash = Some(gear_hash.to_string());
+        self
+    }
+
+    /// Checks whether a session satisfies every condition
+    pub fn matches(&self, entry: &SessionEntry) -> bool {
+        text_matches(entry.character.as_deref(), &self.character)
+            && text_matches(entry.class.as_deref(), &self.class)
+            && text_matches(entry.gear_hash.as_deref(), &self.gear_hash)
+            && self.level.is_none_or(|level| entry.level == Some(level))
+            && self.world_tier
+                .is_none_or(|tier| entry.world_tier == Some(tier))
+            && (self.zone.is_none() || entry.zones.iter()
+                .any(|zone| text_matches(Some(zone), &self.zone)))
+            && self.since.is_none_or(|since| entry.started_at >= since)
+            && self.until.is_none_or(|until| entry.started_at < until)
+    }
+}
+
+impl std::str::FromStr for SessionQuery {
+    type Err = Error;
+
+    /// Parses comma-separated `key=value` filters, such as
+    /// `class=Sorcerer,since=2026-10-01`
+    ///
+    /// The keys are `character`, `class`, `level`, `world_tier`, `zone`,
+    /// `since`, `until` and `gear`, and `until` includes the whole day.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut query = Self::new();
+        for filter in s.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let bad_query = || Error::BadQuery(filter.to_string());
+            let (key, value) = filter.split_once('=').ok_or_else(bad_query)?;
+            let (key, value) = (key.trim(), value.trim());
+            query = match key {
+                "character" => query.character(value),
+                "class" => query.class(value),
+                "level" =>
+                    query.level(value.parse().map_err(|_| bad_query())?),
+                "world_tier" =>
+                    query.world_tier(value.parse().map_err(|_| bad_query())?),
+                "zone" => query.zone(value),
+                "since" => query.since(parse_date(value)?),
+                "until" => query.until(parse_date_end(value)?),
+                "gear" => query.gear_hash(value),
+                _ => return Err(bad_query()),
+            };
+        }
+        Ok(query)
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use serde::{Deserialize, Serialize};
+use crate::logger::{LogFile, LogFormat, LogReader, COMPRESSED_EXTENSION};
+use super::{IndexedFile, Result, SessionEntry, SessionQuery};
+
+/// Name of the index file kept in the log directory
+pub const INDEX_FILE: &str = "sessions.json";
+
+/// Version of the index file written by this build
+const INDEX_VERSION: u32 = 1;
+
+/// Index of the sessions recorded in a log directory
+///
+/// The index is stored next to the logs and brought up to date by a scan,
+/// which only reads the logs that were added or changed since the last one.
+pub struct SessionIndex {
+    /// Directory holding the logs and the index file
+    directory: PathBuf,
+
+    /// Indexed sessions, in the order they started
+    sessions: Vec<SessionEntry>,
+}
+
+/// Outcome of a scan of the log directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScanSummary {
+    /// Number of sessions in the index after the scan
+    pub sessions: usize,
+
+    /// Number of sessions added or rebuilt because their logs changed
+    pub updated: usize,
+
+    /// Number of sessions dropped because their logs are gone
+    pub removed: usize,
+
+    /// Number of files skipped because their header could not be read
+    pub skipped: usize,
+}
+
+/// Contents of the index file
+#[derive(Serialize, Deserialize)]
+struct IndexContents {
+    /// Version of the index file
+    version: u32,
+
+    /// Indexed sessions
+    sessions: Vec<SessionEntry>,
+}
+
+/// Whether a file name is that of a log, compressed or not
+fn is_log(name: &str) -> bool {
+    let name = name
+        .strip_suffix(&format!(".{}", COMPRESSED_EXTENSION))
+        .unwrap_or(name);
+    [LogFormat::Json, LogFormat::Binary].iter()
+        .any(|format| name.ends_with(&format!(".{}", format.extension())))
+}
+
+/// Every log in a directory as it is now
+fn list_logs(directory: &Path) -> Result<Vec<IndexedFile>> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() || !is_log(&name) {
+            continue;
+        }
+
+        let modified = metadata.modified()?.duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        files.push(IndexedFile { name, size: metadata.len(), modified });
+    }
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
+
+impl SessionIndex {
+    /// Opens the index of a log directory, starting an empty one if the
+    /// directory was never scanned
+    pub fn open(directory: impl AsRef<Path>) -> Result<Self> {
+        let directory = directory.as_ref().to_path_buf();
+        let path = directory.join(INDEX_FILE);
+
+        // An index written by another build is rebuilt rather than misread,
+        // so check the version first, as its entries may not parse at all
+        let sessions = match std::fs::read(&path) {
+            Ok(bytes) => {
+                let value: serde_json::Value = serde_json::from_slice(&bytes)?;
+                let version = value.get("version")
+                    .and_then(serde_json::Value::as_u64);
+                if version == Some(INDEX_VERSION as u64) {
+                    serde_json::from_value::<IndexContents>(value)?.sessions
+                }
+                else {
+                    vec![]
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { directory, sessions })
+    }
+
+    /// Directory holding the logs and the index file
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Every indexed session, in the order they started
+    pub fn entries(&self) -> &[SessionEntry] {
+        &self.sessions
+    }
+
+    /// Indexed sessions matching a query, in the order they started
+    pub fn query(&self, query: &SessionQuery) -> Vec<&SessionEntry> {
+        self.sessions.iter()
+            .filter(|entry| query.matches(entry))
+            .collect()
+    }
+
+    /// Brings the index up to date with the log directory and saves it
+    ///
+    /// Sessions whose logs are unchanged are kept as they are, so only new
+    /// or growing logs are read.
+    pub fn scan(&mut self) -> Result<ScanSummary> {
+        let files = list_logs(&self.directory)?;
+        let mut summary = ScanSummary::default();
+
+        // Logs already indexed and unchanged keep their session, the others
+        // are looked up by their header
+        let known: HashMap<&IndexedFile, &str> = self.sessions.iter()
+            .flat_map(|entry| entry.files.iter()
+                .map(|file| (file, entry.session_id.as_str())))
+            .collect();
+        let mut groups: HashMap<String, Vec<IndexedFile>> = HashMap::new();
+        let mut changed = HashSet::new();
+        for file in files {
+            let session_id = match known.get(&file) {
+                Some(session_id) => session_id.to_string(),
+                None => match LogReader::open(self.directory.join(&file.name)) {
+                    Ok(reader) => {
+                        let session_id = reader.header().session_id.clone();
+                        changed.insert(session_id.clone());
+                        session_id
+                    }
+                    Err(_) => {
+                        summary.skipped += 1;
+                        continue;
+                    }
+                },
+            };
+            groups.entry(session_id).or_default().push(file);
+        }
+
+        let mut previous: HashMap<String, SessionEntry> =
+            std::mem::take(&mut self.sessions).into_iter()
+                .map(|entry| (entry.session_id.clone(), entry))
+                .collect();
+        for (session_id, files) in groups {
+            let entry = previous.remove(&session_id)
+                .filter(|entry| !changed.contains(&session_id)
+                    && entry.files.len() == files.len());
+            match entry {
+                Some(entry) => self.sessions.push(entry),
+                None => match self.index_session(&files) {
+                    Some(entry) => {
+                        self.sessions.push(entry);
+                        summary.updated += 1;
+                    }
+                    None => summary.skipped += files.len(),
+                },
+            }
+        }
+        summary.removed = previous.len();
+
+        self.sessions.sort_by(|a, b| a.started_at.cmp(&b.started_at)
+            .then_with(|| a.session_id.cmp(&b.session_id)));
+        summary.sessions = self.sessions.len();
+        if summary.updated > 0 || summary.removed > 0
+                || !self.directory.join(INDEX_FILE).exists() {
+            self.save()?;
+        }
+        Ok(summary)
+    }
+
+    /// Reads every log of a session into a new entry, or `None` if none of
+    /// their headers can be read
+    fn index_session(&self, files: &[IndexedFile]) -> Option<SessionEntry> {
+        let mut readers: Vec<(LogReader<LogFile>, &IndexedFile)> = files.iter()
+            .filter_map(|file| {
+                LogReader::open(self.directory.join(&file.name)).ok()
+                    .map(|reader| (reader, file))
+            })
+            .collect();
+        readers.sort_by_key(|(reader, _)| {
+            (reader.header().segment, reader.header().part)
+        });
+
+        let mut entry: Option<SessionEntry> = None;
+        for (reader, file) in readers {
+            let header = reader.header().clone();
+            let entry = entry.get_or_insert_with(|| SessionEntry::new(&header));
+            entry.merge_header(&header);
+            entry.files.push(file.clone());
+
+            // A log cut off by a crash still counts up to its last good record
+            let player = header.game.character.map(|character| character.id);
+            for event in reader.map_while(|event| event.ok()) {
+                entry.push(player, &event);
+            }
+        }
+        entry
+    }
+
+    /// Writes the index file, replacing the old one only once the new one is
+    /// complete
+    fn save(&self) -> Result<()> {
+        let contents = IndexContents {
+            version: INDEX_VERSION,
+            sessions: self.sessions.clone(),
+        };
+        let path = self.directory.join(INDEX_FILE);
+        let temporary = path.with_extension("json.tmp");
+        std::fs::write(&temporary, serde_json::to_vec(&contents)?)?;
+        std::fs::rename(&temporary, &path)?;
+        Ok(())
+    }
+}
@@ -1 +1,8 @@
+extern crate self as combat_logger;
+
+pub mod analysis;
+pub mod game_data;
+pub mod index;
+pub mod logger;
 pub mod memory;
+pub mod server;
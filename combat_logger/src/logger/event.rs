@@ -0,0 +1,272 @@
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+/// Time since the start of a session in microseconds
+///
+/// Taken from a monotonic clock, so timestamps never go backwards even when
+/// the system clock is adjusted during a session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+    Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Timestamp(pub u64);
+
+/// A player, monster or other entity taking part in combat
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Actor {
+    /// Identifier of the actor in game memory, stable while it exists
+    pub id: u64,
+
+    /// Display name
+    pub name: String,
+}
+
+/// Element of a hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DamageType {
+    Physical,
+    Fire,
+    Cold,
+    Lightning,
+    Poison,
+    Shadow,
+}
+
+/// Resource tracked on an actor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceKind {
+    Life,
+    Barrier,
+    Fortify,
+    Mana,
+    Fury,
+    Spirit,
+    Energy,
+    Essence,
+    Vigor,
+}
+
+/// A single hit, dealt or taken depending on which side the player is on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Damage {
+    /// Actor dealing the damage
+    pub source: Actor,
+
+    /// Actor receiving the damage
+    pub target: Actor,
+
+    /// Skill dealing the damage, or `None` when it is not known, such as
+    /// for some damage over time
+    pub skill: Option<String>,
+
+    /// Element of the hit
+    pub damage_type: DamageType,
+
+    /// Damage taken off life after mitigation and absorption
+    pub amount: f64,
+
+    /// Damage prevented by damage reduction, armor and resistances, when
+    /// the memory exposes the value before mitigation
+    pub mitigated: Option<f64>,
+
+    /// Damage absorbed by barrier
+    pub absorbed: f64,
+
+    /// Hit was a critical strike
+    pub critical: bool,
+
+    /// Hit was overpowered
+    pub overpower: bool,
+
+    /// Target was vulnerable
+    pub vulnerable: bool,
+}
+
+/// Life restored to an actor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Heal {
+    /// Actor doing the healing
+    pub source: Actor,
+
+    /// Actor healed
+    pub target: Actor,
+
+    /// Skill, potion or effect doing the healing, when known
+    pub skill: Option<String>,
+
+    /// Life restored
+    pub amount: f64,
+
+    /// Healing beyond maximum life
+    pub overheal: f64,
+}
+
+/// Buff or debuff applied to an actor, which refreshes it when it is
+/// already active
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuffApplied {
+    /// Actor the buff is on
+    pub target: Actor,
+
+    /// Actor that applied the buff, when known
+    pub source: Option<Actor>,
+
+    /// Name of the buff, such as `Vulnerable` or `Berserking`
+    pub buff: String,
+
+    /// Whether the buff is harmful to its target
+    pub debuff: bool,
+
+    /// Stack count after applying
+    pub stacks: u32,
+
+    /// Duration in seconds, or `None` for buffs lasting until removed
+    pub duration: Option<f64>,
+}
+
+/// Buff or debuff that expired or was removed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuffExpired {
+    /// Actor the buff was on
+    pub target: Actor,
+
+    /// Name of the buff
+    pub buff: String,
+}
+
+/// New value of a resource of an actor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceChange {
+    /// Actor owning the resource
+    pub actor: Actor,
+
+    /// Resource that changed
+    pub resource: ResourceKind,
+
+    /// Current value
+    pub value: f64,
+
+    /// Maximum value
+    pub max: f64,
+}
+
+/// Skill cast by an actor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cast {
+    /// Actor casting the skill
+    pub source: Actor,
+
+    /// Name of the skill
+    pub skill: String,
+
+    /// Cooldown started by the cast in seconds, or `None` for skills
+    /// without a cooldown
+    pub cooldown: Option<f64>,
+}
+
+/// Actor that died
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Kill {
+    /// Actor landing the killing blow, when known
+    pub killer: Option<Actor>,
+
+    /// Actor that died, which is the player on a death
+    pub victim: Actor,
+}
+
+/// Player entering another zone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZoneChange {
+    /// Name of the zone entered
+    pub zone: String,
+}
+
+/// What happened in a combat event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EventKind {
+    Damage(Damage),
+    Heal(Heal),
+    BuffApplied(BuffApplied),
+    BuffExpired(BuffExpired),
+    Resource(ResourceChange),
+    Cast(Cast),
+    Kill(Kill),
+    ZoneChange(ZoneChange),
+}
+
+/// A combat event with the time it was observed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CombatEvent {
+    /// Time since the start of the session
+    pub timestamp: Timestamp,
+
+    /// What happened
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+impl Timestamp {
+    /// Time elapsed since the session origin
+    pub fn since(origin: Instant) -> Self {
+        Self::from_duration(origin.elapsed())
+    }
+
+    /// Converts a duration since the session start
+    pub fn from_duration(duration: Duration) -> Self {
+        Self(duration.as_micros() as u64)
+    }
+
+    /// Time since the session start
+    pub fn as_duration(self) -> Duration {
+        Duration::from_micros(self.0)
+    }
+
+    /// Seconds since the session start
+    pub fn as_secs_f64(self) -> f64 {
+        self.0 as f64 / 1e6
+    }
+}
+
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:.3}s", self.as_secs_f64())
+    }
+}
+
+impl Actor {
+    /// Create a new actor
+    pub fn new(id: u64, name: &str) -> Self {
+        Self { id, name: name.to_string() }
+    }
+}
+
+impl EventKind {
+    /// Name of every kind, as written in the `kind` field of JSON logs
+    pub const NAMES: &'static [&'static str] = &[
+        "damage", "heal", "buff_applied", "buff_expired", "resource", "cast",
+        "kill", "zone_change",
+    ];
+
+    /// Name of the kind, as written in the `kind` field of JSON logs
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::Damage(_) => "damage",
+            EventKind::Heal(_) => "heal",
+            EventKind::BuffApplied(_) => "buff_applied",
+            EventKind::BuffExpired(_) => "buff_expired",
+            EventKind::Resource(_) => "resource",
+            EventKind::Cast(_) => "cast",
+            EventKind::Kill(_) => "kill",
+            EventKind::ZoneChange(_) => "zone_change",
+        }
+    }
+}
+
+impl CombatEvent {
+    /// Create a new event observed at the given time
+    pub fn new(timestamp: Timestamp, kind: EventKind) -> Self {
+        Self { timestamp, kind }
+    }
+}
@@ -0,0 +1,437 @@
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use super::{CombatEvent, Error, EventKind, LogFile, Result, Timestamp};
+
+/// Version of the log format written by this build
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Bytes every binary log starts with
+pub(super) const BINARY_MAGIC: &[u8; 4] = b"D4CL";
+
+/// Longest header of a binary log, far above any real one, so that a corrupt
+/// length cannot make the reader allocate gigabytes
+pub(super) const MAX_HEADER_LEN: usize = 1 << 20;
+
+/// Longest record of a binary log, far above any real event
+pub(super) const MAX_RECORD_LEN: usize = 1 << 20;
+
+/// Encoding of a combat log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One JSON object per line, starting with the header
+    #[default]
+    Json,
+
+    /// Magic bytes and a JSON header followed by length-prefixed,
+    /// checksummed MessagePack records
+    Binary,
+}
+
+/// Character the session was recorded with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Character {
+    /// Actor identifier of the character in game memory
+    pub id: u64,
+
+    /// Character name
+    pub name: String,
+
+    /// Class, such as `Sorcerer`
+    pub class: String,
+
+    /// Character level
+    pub level: u32,
+}
+
+/// What is known about the game when a segment starts
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameInfo {
+    /// Game build the segment was recorded with
+    pub build: String,
+
+    /// Character being played, when known
+    pub character: Option<Character>,
+
+    /// World tier, when known
+    pub world_tier: Option<u32>,
+
+    /// Identifiers of the equipped items in slot order, when known
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gear: Vec<String>,
+}
+
+/// First record of every log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogHeader {
+    /// Format version the log was written with
+    pub version: u32,
+
+    /// Identifier shared by every segment of a session
+    pub session_id: String,
+
+    /// Number of the segment within the session, which grows every time
+    /// the game restarts
+    pub segment: u64,
+
+    /// Number of the file within the segment, which grows every time the
+    /// segment is rotated to a new file
+    #[serde(default)]
+    pub part: u32,
+
+    /// Wall clock time of the session start in milliseconds since the Unix
+    /// epoch, which event timestamps are relative to
+    pub started_at: u64,
+
+    /// Game and character the segment was recorded with
+    #[serde(flatten)]
+    pub game: GameInfo,
+}
+
+/// Writes a combat log to any byte sink
+///
+/// Logs are append-only: the header is written on creation and every event
+/// after it is a self-contained record.
+pub struct LogWriter<W: Write> {
+    /// Sink the log is written to
+    writer: W,
+
+    /// Encoding of the records
+    format: LogFormat,
+
+    /// Header written at the start
+    header: LogHeader,
+
+    /// Number of bytes written so far, including the header
+    written: u64,
+}
+
+/// Reads a combat log written in either format
+pub struct LogReader<R: BufRead> {
+    /// Source the log is read from
+    reader: R,
+
+    /// Encoding detected from the first bytes
+    format: LogFormat,
+
+    /// Header read at the start
+    header: LogHeader,
+
+    /// Offset of the first record, right after the header
+    data_start: u64,
+
+    /// Offset of the next record, reported with errors
+    offset: u64,
+}
+
+impl LogFormat {
+    /// File extension of logs in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            LogFormat::Json => "jsonl",
+            LogFormat::Binary => "d4log",
+        }
+    }
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" | "jsonl" => Ok(LogFormat::Json),
+            "binary" | "d4log" => Ok(LogFormat::Binary),
+            _ => Err(Error::BadArgument(
+                format!("Unknown log format '{}'", s))),
+        }
+    }
+}
+
+impl LogHeader {
+    /// Create a header for the current format version
+    pub fn new(session_id: &str, segment: u64, started_at: u64,
+            game: GameInfo) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            session_id: session_id.to_string(),
+            segment,
+            part: 0,
+            started_at,
+            game,
+        }
+    }
+
+    /// Parses a JSON header, rejecting versions newer than this build
+    fn from_json(bytes: &[u8]) -> Result<Self> {
+        // Check the version first, as newer headers may not parse at all
+        let value: serde_json::Value = serde_json::from_slice(bytes)
+            .map_err(|e| Error::BadHeader(e.to_string()))?;
+        let version = value.get("version")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or_else(|| Error::BadHeader("No format version".to_string()))?;
+        if version > FORMAT_VERSION as u64 {
+            return Err(Error::UnsupportedVersion(version as u32));
+        }
+
+        serde_json::from_value(value)
+            .map_err(|e| Error::BadHeader(e.to_string()))
+    }
+}
+
+impl<W: Write> LogWriter<W> {
+    /// Starts a log by writing its header
+    pub fn new(mut writer: W, format: LogFormat, header: LogHeader)
+            -> Result<Self> {
+        let json = serde_json::to_vec(&header)?;
+        if format == LogFormat::Binary && json.len() > MAX_HEADER_LEN {
+            return Err(Error::Encode(format!(
+                "Header of {} bytes is too long", json.len())));
+        }
+        let written = match format {
+            LogFormat::Json => {
+                writer.write_all(&json)?;
+                writer.write_all(b"\n")?;
+                json.len() + 1
+            }
+            LogFormat::Binary => {
+                writer.write_all(BINARY_MAGIC)?;
+                writer.write_all(&header.version.to_le_bytes())?;
+                writer.write_all(&(json.len() as u32).to_le_bytes())?;
+                writer.write_all(&json)?;
+                BINARY_MAGIC.len() + 8 + json.len()
+            }
+        };
+
+        Ok(Self { writer, format, header, written: written as u64 })
+    }
+
+    /// Appends an event
+    pub fn write(&mut self, event: &CombatEvent) -> Result<()> {
+        match self.format {
+            LogFormat::Json => {
+                let mut line = serde_json::to_vec(event)?;
+                line.push(b'\n');
+                self.writer.write_all(&line)?;
+                self.written += line.len() as u64;
+            }
+            LogFormat::Binary => {
+                let payload = rmp_serde::to_vec(&(event.timestamp, &event.kind))
+                    .map_err(|e| Error::Encode(e.to_string()))?;
+                if payload.len() > MAX_RECORD_LEN {
+                    return Err(Error::Encode(format!(
+                        "Record of {} bytes is too long", payload.len())));
+                }
+                let mut record = Vec::with_capacity(payload.len() + 8);
+                record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+                record.extend_from_slice(
+                    &crc32fast::hash(&payload).to_le_bytes());
+                record.extend_from_slice(&payload);
+                self.writer.write_all(&record)?;
+                self.written += record.len() as u64;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Flushes buffered records to the sink
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+
+    /// Header written at the start of the log
+    pub fn header(&self) -> &LogHeader {
+        &self.header
+    }
+
+    /// Encoding of the log
+    pub fn format(&self) -> LogFormat {
+        self.format
+    }
+
+    /// Number of bytes written so far, including the header
+    pub fn bytes_written(&self) -> u64 {
+        self.written
+    }
+
+    /// Sink the log is written to
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns the sink, flushing it first
+    pub fn into_inner(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl LogReader<LogFile> {
+    /// Opens a log file, which may be gzipped
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::new(LogFile::open(path)?)
+    }
+}
+
+impl<R: BufRead> LogReader<R> {
+    /// Reads the header, detecting the format from the first bytes
+    pub fn new(mut reader: R) -> Result<Self> {
+        let binary = reader.fill_buf()?.starts_with(BINARY_MAGIC);
+        if binary {
+            let mut prefix = [0u8; 12];
+            reader.read_exact(&mut prefix)
+                .map_err(|_| Error::BadHeader("Truncated header".to_string()))?;
+            let version = u32::from_le_bytes(prefix[4..8].try_into().unwrap());
+            if version > FORMAT_VERSION {
+                return Err(Error::UnsupportedVersion(version));
+            }
+
+            let len = u32::from_le_bytes(prefix[8..].try_into().unwrap());
+            if len as usize > MAX_HEADER_LEN {
+                return Err(Error::Corrupt(prefix.len() as u64, format!(
+                    "header length {} exceeds the maximum", len)));
+            }
+            let mut json = vec![0u8; len as usize];
+            reader.read_exact(&mut json)
+                .map_err(|_| Error::BadHeader("Truncated header".to_string()))?;
+            Ok(Self {
+                reader,
+                format: LogFormat::Binary,
+                header: LogHeader::from_json(&json)?,
+                data_start: prefix.len() as u64 + len as u64,
+                offset: prefix.len() as u64 + len as u64,
+            })
+        }
+        else {
+            let mut line = String::new();
+            let offset = reader.read_line(&mut line)? as u64;
+            if line.is_empty() {
+                return Err(Error::BadHeader("Empty log".to_string()));
+            }
+            Ok(Self {
+                reader,
+                format: LogFormat::Json,
+                header: LogHeader::from_json(line.as_bytes())?,
+                data_start: offset,
+                offset,
+            })
+        }
+    }
+
+    /// Header read at the start of the log
+    pub fn header(&self) -> &LogHeader {
+        &self.header
+    }
+
+    /// Encoding detected from the first bytes
+    pub fn format(&self) -> LogFormat {
+        self.format
+    }
+
+    /// Offset of the next record
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Reads the next event, or `None` at the end of the log
+    ///
+    /// A record cut off by the end of the log, as left behind by a crash
+    /// while writing, is reported as `Error::Truncated`.
+    pub fn read_event(&mut self) -> Result<Option<CombatEvent>> {
+        match self.format {
+            LogFormat::Json => self.read_json(),
+            LogFormat::Binary => self.read_binary(),
+        }
+    }
+
+    /// Reads the next line of a JSON log
+    fn read_json(&mut self) -> Result<Option<CombatEvent>> {
+        let mut line = Vec::new();
+        loop {
+            let offset = self.offset;
+            let read = self.reader.read_until(b'\n', &mut line)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.offset += read as u64;
+
+            // Blank lines carry nothing, so skip them
+            if line.iter().all(u8::is_ascii_whitespace) {
+                line.clear();
+                continue;
+            }
+
+            return match serde_json::from_slice(&line) {
+                Ok(event) => Ok(Some(event)),
+                Err(_) if line.last() != Some(&b'\n') =>
+                    Err(Error::Truncated(offset)),
+                Err(e) => Err(Error::Corrupt(offset, e.to_string())),
+            };
+        }
+    }
+
+    /// Reads the next record of a binary log
+    fn read_binary(&mut self) -> Result<Option<CombatEvent>> {
+        let offset = self.offset;
+        let mut prefix = [0u8; 8];
+        match read_full(&mut self.reader, &mut prefix)? {
+            0 => return Ok(None),
+            8 => {}
+            _ => return Err(Error::Truncated(offset)),
+        }
+        let len = u32::from_le_bytes(prefix[..4].try_into().unwrap()) as usize;
+        let checksum = u32::from_le_bytes(prefix[4..].try_into().unwrap());
+        if len > MAX_RECORD_LEN {
+            return Err(Error::Corrupt(offset, format!(
+                "record length {} exceeds the maximum", len)));
+        }
+
+        let mut payload = vec![0u8; len];
+        if read_full(&mut self.reader, &mut payload)? < len {
+            return Err(Error::Truncated(offset));
+        }
+        self.offset += (prefix.len() + len) as u64;
+
+        if crc32fast::hash(&payload) != checksum {
+            return Err(Error::Corrupt(offset, "checksum mismatch".to_string()));
+        }
+        let (timestamp, kind): (Timestamp, EventKind) =
+            rmp_serde::from_slice(&payload)
+                .map_err(|e| Error::Corrupt(offset, e.to_string()))?;
+        Ok(Some(CombatEvent { timestamp, kind }))
+    }
+}
+
+impl<R: BufRead + Seek> LogReader<R> {
+    /// Moves back to the first record
+    pub fn rewind(&mut self) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(self.data_start))?;
+        self.offset = self.data_start;
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for LogReader<R> {
+    type Item = Result<CombatEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_event().transpose()
+    }
+}
+
+/// Reads until the buffer is full or the input ends, returning the number
+/// of bytes read
+pub(super) fn read_full(reader: &mut impl Read, buffer: &mut [u8])
+        -> Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(filled)
+}
@@ -0,0 +1,233 @@
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use crate::memory::{self, AttachEvent, Attachment};
+use super::{CombatEvent, EventKind, GameInfo, SessionWriter, Timestamp};
+
+/// Time between polls of the event source
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Reads combat events out of game memory
+pub trait EventSource: Send + 'static {
+    /// Prepares for a newly attached process, returning what is known about
+    /// the game and character
+    fn attach(&mut self, attachment: &Attachment) -> memory::Result<GameInfo>;
+
+    /// Reads the events that happened since the previous poll
+    fn poll(&mut self, attachment: &Attachment)
+        -> memory::Result<Vec<EventKind>>;
+}
+
+/// Counters describing a running logger
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoggerStats {
+    /// Number of events logged
+    pub events: u64,
+
+    /// Number of segments started
+    pub segments: u64,
+
+    /// Number of failed attach preparations and polls of the event source
+    pub source_errors: u64,
+
+    /// Number of failed writes to the session files
+    pub write_errors: u64,
+}
+
+/// Logs combat events of the game, starting a new segment every time the
+/// attacher reports a new process
+///
+/// Events are timestamped against a single monotonic origin for the whole
+/// session, so timestamps keep growing across game restarts.
+pub struct Logger<E: EventSource> {
+    /// Source the events are read from
+    source: E,
+
+    /// Attach events of the attacher following the game
+    attach_events: mpsc::Receiver<AttachEvent>,
+
+    /// Session files the events are recorded to, if any
+    writer: Option<SessionWriter>,
+
+    /// Time between polls of the event source
+    poll_interval: Duration,
+}
+
+/// Handle to a running logger, which stops the thread when dropped
+pub struct LoggerHandle {
+    /// Channel used to wake up and stop the thread
+    stop: Option<mpsc::Sender<()>>,
+
+    /// Logging thread
+    thread: Option<JoinHandle<()>>,
+
+    /// Counters shared with the thread
+    stats: Arc<Mutex<LoggerStats>>,
+}
+
+impl<E: EventSource> Logger<E> {
+    /// Create a new logger reading events from the source for every process
+    /// the attacher reports
+    pub fn new(source: E, attach_events: mpsc::Receiver<AttachEvent>) -> Self {
+        Self {
+            source,
+            attach_events,
+            writer: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    /// Records every event to the session files of the writer
+    pub fn record(mut self, writer: SessionWriter) -> Self {
+        self.writer = Some(writer);
+        self
+    }
+
+    /// Sets the time between polls of the event source
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Starts logging on a background thread, returning a handle to stop it
+    /// and the channel events arrive on
+    pub fn start(self) -> (LoggerHandle, mpsc::Receiver<CombatEvent>) {
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let (event_tx, event_rx) = mpsc::channel();
+        let stats = Arc::new(Mutex::new(LoggerStats::default()));
+
+        let thread_stats = Arc::clone(&stats);
+        let thread = std::thread::spawn(move || {
+            self.run(stop_rx, event_tx, thread_stats);
+        });
+
+        let handle = LoggerHandle {
+            stop: Some(stop_tx),
+            thread: Some(thread),
+            stats,
+        };
+        (handle, event_rx)
+    }
+
+    /// Logging loop of the background thread
+    fn run(mut self, stop: mpsc::Receiver<()>,
+            events: mpsc::Sender<CombatEvent>,
+            stats: Arc<Mutex<LoggerStats>>) {
+        let origin = Instant::now();
+        let mut attachment: Option<Box<Attachment>> = None;
+        let mut prepared = false;
+
+        loop {
+            let woken = stop.recv_timeout(self.poll_interval);
+            if !matches!(woken, Err(mpsc::RecvTimeoutError::Timeout)) {
+                break;
+            }
+
+            // Follow the attacher, ending the segment when the game exits.
+            // A stopped attacher leaves the current attachment in place.
+            while let Ok(event) = self.attach_events.try_recv() {
+                match event {
+                    AttachEvent::Attached(next) => {
+                        attachment = Some(next);
+                        prepared = false;
+                    }
+                    AttachEvent::Exited(_) => {
+                        attachment = None;
+                        if let Some(writer) = &mut self.writer {
+                            if writer.finish_segment().is_err() {
+                                stats.lock().unwrap().write_errors += 1;
+                            }
+                        }
+                    }
+                    AttachEvent::Failed(_) => {}
+                }
+            }
+            let attachment = match &attachment {
+                Some(attachment) => attachment,
+                None => continue,
+            };
+
+            // Prepare the source once per process, which may take a few
+            // tries while the game is still loading
+            if !prepared {
+                let game = match self.source.attach(attachment) {
+                    Ok(game) => game,
+                    Err(_) => {
+                        stats.lock().unwrap().source_errors += 1;
+                        continue;
+                    }
+                };
+                prepared = true;
+
+                let mut stats = stats.lock().unwrap();
+                stats.segments += 1;
+                if let Some(writer) = &mut self.writer {
+                    if writer.start_segment(attachment.generation, game)
+                            .is_err() {
+                        stats.write_errors += 1;
+                    }
+                }
+            }
+
+            // A restarted game may have handed its pid to another process
+            let kinds = match attachment.reader.verify_identity()
+                    .and_then(|_| self.source.poll(attachment)) {
+                Ok(kinds) => kinds,
+                Err(_) => {
+                    stats.lock().unwrap().source_errors += 1;
+                    continue;
+                }
+            };
+
+            // Every event of a poll was observed at the same time
+            let timestamp = Timestamp::since(origin);
+            let mut stats = stats.lock().unwrap();
+            for kind in kinds {
+                let event = CombatEvent::new(timestamp, kind);
+                if let Some(writer) = &mut self.writer {
+                    if writer.write(&event).is_err() {
+                        stats.write_errors += 1;
+                    }
+                }
+                if events.send(event).is_err() {
+                    return;
+                }
+                stats.events += 1;
+            }
+            if let Some(writer) = &mut self.writer {
+                if writer.flush().is_err() {
+                    stats.write_errors += 1;
+                }
+            }
+        }
+    }
+}
+
+impl LoggerHandle {
+    /// Snapshot of the logger counters
+    pub fn stats(&self) -> LoggerStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// Stops the logger and waits for its thread to exit
+    pub fn stop(mut self) -> LoggerStats {
+        self.shutdown();
+        self.stats()
+    }
+
+    /// Signals the thread to stop and joins it
+    fn shutdown(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for LoggerHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -0,0 +1,96 @@
+mod event;
+mod format;
+mod live;
+mod replay;
+mod session;
+mod storage;
+
+pub use event::{
+    Actor, BuffApplied, BuffExpired, Cast, CombatEvent, Damage, DamageType,
+    EventKind, Heal, Kill, ResourceChange, ResourceKind, Timestamp,
+    ZoneChange,
+};
+pub use format::{
+    Character, GameInfo, LogFormat, LogHeader, LogReader, LogWriter,
+    FORMAT_VERSION,
+};
+pub use live::{EventSource, Logger, LoggerHandle, LoggerStats};
+pub use replay::{Playback, Replay, ReplayHandle};
+pub use session::{SessionWriter, DEFAULT_SYNC_INTERVAL};
+pub use storage::{
+    compress_log, recover_directory, recover_log, LogFile, Recovery,
+    COMPRESSED_EXTENSION,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    /// Log was written by a newer format version
+    UnsupportedVersion(u32),
+
+    /// Log header is missing or invalid
+    BadHeader(String),
+
+    /// Record starting at the given offset was cut off by the end of the log
+    Truncated(u64),
+
+    /// Record starting at the given offset cannot be decoded
+    Corrupt(u64, String),
+
+    /// Event was written while no segment was open
+    NoSegment,
+
+    /// Invalid option, such as an unknown event kind
+    BadArgument(String),
+
+    /// Failed to encode a binary record
+    Encode(String),
+
+    /// JSON error
+    Json(serde_json::Error),
+
+    /// IO error
+    IOError(std::io::Error),
+}
+
+/// Implement the formatter for our custom error type
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::UnsupportedVersion(version) =>
+                write!(f, "Log format version {} is newer than supported \
+                    version {}", version, FORMAT_VERSION),
+            Error::BadHeader(e) =>
+                write!(f, "Bad log header: {}", e),
+            Error::Truncated(offset) =>
+                write!(f, "Record at offset {} is truncated", offset),
+            Error::Corrupt(offset, e) =>
+                write!(f, "Record at offset {} is corrupt: {}", offset, e),
+            Error::NoSegment =>
+                write!(f, "No log segment is open"),
+            Error::BadArgument(e) =>
+                write!(f, "Bad argument: {}", e),
+            Error::Encode(e) =>
+                write!(f, "Encode error: {}", e),
+            Error::Json(e) =>
+                write!(f, "JSON error: {}", e),
+            Error::IOError(e) =>
+                write!(f, "IO error: {}", e),
+        }
+    }
+}
+
+/// Implement standard error trait and conversion from other error types
+impl std::error::Error for Error {}
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::IOError(err)
+    }
+}
+
+/// Custom Result type alias
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,257 @@
+use std::io::{BufRead, Seek};
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use super::{
+    CombatEvent, Error, EventKind, LogFile, LogHeader, LogReader, Result,
+    Timestamp,
+};
+
+/// Pace events of a replay are emitted at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Playback {
+    /// Same spacing as when the events were recorded
+    RealTime,
+
+    /// Recorded spacing divided by a factor, so `Speed(10.0)` plays ten
+    /// times faster than recorded
+    Speed(f64),
+
+    /// Every event as fast as it can be read
+    Instant,
+}
+
+/// Plays back a recorded combat log
+///
+/// Events come out of the iterator, or out of the channel of `start` just
+/// like from a live `Logger`, so analysis code cannot tell the two apart.
+pub struct Replay<R: BufRead> {
+    /// Log being played
+    reader: LogReader<R>,
+
+    /// Pace of the playback
+    playback: Playback,
+
+    /// Names of the event kinds let through, or `None` for all of them
+    kinds: Option<Vec<&'static str>>,
+
+    /// Events before this time are skipped
+    from: Timestamp,
+
+    /// Instant the first emitted event went out along with its timestamp,
+    /// which later events are paced against
+    clock: Option<(Instant, Timestamp)>,
+}
+
+/// Handle to a replay running on a background thread, which stops the
+/// thread when dropped
+pub struct ReplayHandle {
+    /// Channel used to wake up and stop the thread
+    stop: Option<mpsc::Sender<()>>,
+
+    /// Replay thread, returning the number of events sent
+    thread: Option<JoinHandle<Result<u64>>>,
+}
+
+impl std::str::FromStr for Playback {
+    type Err = Error;
+
+    /// Parses `realtime`, `instant` or a speed factor such as `10x`
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "realtime" => Ok(Playback::RealTime),
+            "instant" => Ok(Playback::Instant),
+            _ => {
+                let factor = s.strip_suffix('x').unwrap_or(s);
+                match factor.parse::<f64>() {
+                    Ok(factor) if factor > 0.0 && factor.is_finite() =>
+                        Ok(Playback::Speed(factor)),
+                    _ => Err(Error::BadArgument(
+                        format!("Invalid playback speed '{}'", s))),
+                }
+            }
+        }
+    }
+}
+
+impl Replay<LogFile> {
+    /// Opens a log file for playback
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(LogReader::open(path)?))
+    }
+}
+
+impl<R: BufRead> Replay<R> {
+    /// Create a new replay of a log, played instantly unless a pace is set
+    pub fn new(reader: LogReader<R>) -> Self {
+        Self {
+            reader,
+            playback: Playback::Instant,
+            kinds: None,
+            from: Timestamp(0),
+            clock: None,
+        }
+    }
+
+    /// Sets the pace of the playback
+    ///
+    /// Panics if the speed factor is not positive.
+    pub fn playback(mut self, playback: Playback) -> Self {
+        if let Playback::Speed(factor) = playback {
+            assert!(factor > 0.0, "Playback speed must be positive");
+        }
+        self.playback = playback;
+        self
+    }
+
+    /// Only lets events of the given kinds through, named as in the `kind`
+    /// field of JSON logs
+    pub fn kinds(mut self, kinds: &[&str]) -> Result<Self> {
+        let kinds = kinds.iter()
+            .map(|kind| EventKind::NAMES.iter()
+                .find(|name| *name == kind)
+                .copied()
+                .ok_or_else(|| Error::BadArgument(
+                    format!("Unknown event kind '{}'", kind))))
+            .collect::<Result<Vec<_>>>()?;
+        self.kinds = Some(kinds);
+        Ok(self)
+    }
+
+    /// Header of the log being played
+    pub fn header(&self) -> &LogHeader {
+        self.reader.header()
+    }
+
+    /// Reads the next event that passes the filters, paced by the playback
+    pub fn next_event(&mut self) -> Result<Option<CombatEvent>> {
+        let event = match self.next_matching()? {
+            Some(event) => event,
+            None => return Ok(None),
+        };
+        std::thread::sleep(self.delay(event.timestamp));
+        Ok(Some(event))
+    }
+
+    /// Starts playing on a background thread, returning a handle to stop it
+    /// and the channel events arrive on
+    ///
+    /// The thread stops at the end of the log, on the first error, or once
+    /// the event receiver is dropped.
+    pub fn start(self) -> (ReplayHandle, mpsc::Receiver<CombatEvent>)
+            where R: Send + 'static {
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let (event_tx, event_rx) = mpsc::channel();
+        let thread = std::thread::spawn(move || self.run(stop_rx, event_tx));
+
+        let handle = ReplayHandle {
+            stop: Some(stop_tx),
+            thread: Some(thread),
+        };
+        (handle, event_rx)
+    }
+
+    /// Playback loop of the background thread
+    fn run(mut self, stop: mpsc::Receiver<()>,
+            events: mpsc::Sender<CombatEvent>) -> Result<u64> {
+        let mut sent = 0;
+        while let Some(event) = self.next_matching()? {
+            // Wait for the event, waking up early to stop
+            let delay = self.delay(event.timestamp);
+            if !delay.is_zero() {
+                let woken = stop.recv_timeout(delay);
+                if !matches!(woken, Err(mpsc::RecvTimeoutError::Timeout)) {
+                    break;
+                }
+            }
+            else if !matches!(stop.try_recv(),
+                    Err(mpsc::TryRecvError::Empty)) {
+                break;
+            }
+
+            if events.send(event).is_err() {
+                break;
+            }
+            sent += 1;
+        }
+
+        Ok(sent)
+    }
+
+    /// Reads the next event that passes the filters, without pacing
+    fn next_matching(&mut self) -> Result<Option<CombatEvent>> {
+        while let Some(event) = self.reader.read_event()? {
+            let wanted = event.timestamp >= self.from
+                && self.kinds.as_ref()
+                    .is_none_or(|kinds| kinds.contains(&event.kind.name()));
+            if wanted {
+                return Ok(Some(event));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Time left until an event is due under the playback pace
+    fn delay(&mut self, timestamp: Timestamp) -> Duration {
+        let speed = match self.playback {
+            Playback::RealTime => 1.0,
+            Playback::Speed(factor) => factor,
+            Playback::Instant => return Duration::ZERO,
+        };
+
+        let (start, first) = *self.clock
+            .get_or_insert_with(|| (Instant::now(), timestamp));
+        let offset = timestamp.0.saturating_sub(first.0) as f64 / speed;
+        (start + Duration::from_micros(offset as u64))
+            .saturating_duration_since(Instant::now())
+    }
+}
+
+impl<R: BufRead + Seek> Replay<R> {
+    /// Continues the playback from the first event at or after the given
+    /// time, which may lie before or after the current position
+    pub fn seek(&mut self, timestamp: Timestamp) -> Result<()> {
+        self.reader.rewind()?;
+        self.from = timestamp;
+        self.clock = None;
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for Replay<R> {
+    type Item = Result<CombatEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+impl ReplayHandle {
+    /// Waits for the replay to finish, returning the number of events sent
+    /// or the error that ended it
+    pub fn wait(mut self) -> Result<u64> {
+        self.thread.take()
+            .map_or(Ok(0), |thread| thread.join().unwrap())
+    }
+
+    /// Stops the replay and waits for its thread to exit
+    pub fn stop(mut self) -> Result<u64> {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        self.wait()
+    }
+}
+
+impl Drop for ReplayHandle {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
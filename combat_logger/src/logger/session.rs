@@ -0,0 +1,311 @@
+use std::fs::{File, OpenOptions};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use super::storage::try_lock;
+use super::{
+    compress_log, recover_directory, CombatEvent, Error, EventKind, GameInfo,
+    LogFormat, LogHeader, LogWriter, Recovery, Result,
+};
+
+/// Time between syncs of the current file to disk by default
+pub const DEFAULT_SYNC_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Writes every segment of a session to its own file in a directory
+///
+/// Segments are named `<session id>-<segment>.<extension>`, so the files of
+/// a session sort in the order they were recorded. A segment rotated to a
+/// new file continues in `<session id>-<segment>_<part>.<extension>`, which
+/// sorts right after it.
+///
+/// Logs of earlier runs that were cut off by a crash are recovered when the
+/// writer is created, so their complete records stay readable. Every file
+/// being written is locked, so that a logger started next to this one
+/// leaves it alone.
+pub struct SessionWriter {
+    /// Directory the segments are written to
+    directory: PathBuf,
+
+    /// Encoding of the segments
+    format: LogFormat,
+
+    /// Identifier shared by every segment
+    session_id: String,
+
+    /// Wall clock time of the session start in milliseconds since the Unix
+    /// epoch
+    started_at: u64,
+
+    /// File currently being written
+    current: Option<OpenPart>,
+
+    /// Path of every file written so far
+    segments: Vec<PathBuf>,
+
+    /// Size after which a file is rotated, if any
+    max_size: Option<u64>,
+
+    /// Age after which a file is rotated, if any
+    max_age: Option<Duration>,
+
+    /// Whether a zone change starts a new file
+    rotate_on_zone: bool,
+
+    /// Whether closed files are compressed
+    compress: bool,
+
+    /// Time between syncs of the current file to disk
+    sync_interval: Duration,
+
+    /// Logs of earlier runs cut back to their last complete record
+    recovered: Vec<Recovery>,
+}
+
+/// File of a segment being written
+struct OpenPart {
+    /// Writer of the file
+    writer: LogWriter<BufWriter<File>>,
+
+    /// Number of the segment within the session
+    segment: u64,
+
+    /// Number of the file within the segment
+    part: u32,
+
+    /// Game and character the segment was recorded with
+    game: GameInfo,
+
+    /// Time the file was opened
+    opened: Instant,
+
+    /// Time the file was last synced to disk
+    synced: Instant,
+
+    /// Number of events written to the file
+    events: u64,
+}
+
+/// Milliseconds since the Unix epoch
+fn unix_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+impl SessionWriter {
+    /// Starts a new session in the directory, creating it if needed and
+    /// recovering logs left behind by a crash
+    pub fn new(directory: impl AsRef<Path>, format: LogFormat)
+            -> Result<Self> {
+        let directory = directory.as_ref().to_path_buf();
+        std::fs::create_dir_all(&directory)?;
+        let recovered = recover_directory(&directory)?;
+
+        // The start time alone could collide for two loggers started in the
+        // same millisecond, so add the pid
+        let started_at = unix_millis();
+        Ok(Self {
+            directory,
+            format,
+            session_id: format!("{}-{}", started_at, std::process::id()),
+            started_at,
+            current: None,
+            segments: vec![],
+            max_size: None,
+            max_age: None,
+            rotate_on_zone: false,
+            compress: false,
+            sync_interval: DEFAULT_SYNC_INTERVAL,
+            recovered,
+        })
+    }
+
+    /// Starts a new file once the current one reaches the given size in
+    /// bytes
+    pub fn max_file_size(mut self, max_size: u64) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Starts a new file once the current one has been open for the given
+    /// time
+    pub fn max_file_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Starts a new file at every zone change, which becomes the first
+    /// event of the file
+    pub fn rotate_on_zone(mut self) -> Self {
+        self.rotate_on_zone = true;
+        self
+    }
+
+    /// Gzips every file once it is closed
+    pub fn compress(mut self) -> Self {
+        self.compress = true;
+        self
+    }
+
+    /// Sets the time between syncs of the current file to disk, which bounds
+    /// the events lost to a system crash
+    pub fn sync_interval(mut self, sync_interval: Duration) -> Self {
+        self.sync_interval = sync_interval;
+        self
+    }
+
+    /// Identifier shared by every segment
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Wall clock time of the session start in milliseconds since the Unix
+    /// epoch
+    pub fn started_at(&self) -> u64 {
+        self.started_at
+    }
+
+    /// Path of every file written so far, under its compressed name once
+    /// it was closed and compressed
+    pub fn segments(&self) -> &[PathBuf] {
+        &self.segments
+    }
+
+    /// Logs of earlier runs that were cut back to their last complete record,
+    /// or found damaged before their end, when the writer was created
+    pub fn recovered(&self) -> &[Recovery] {
+        &self.recovered
+    }
+
+    /// Whether a segment is currently open
+    pub fn in_segment(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Finishes the current segment and starts a new one, returning its
+    /// path
+    pub fn start_segment(&mut self, segment: u64, game: GameInfo)
+            -> Result<PathBuf> {
+        self.finish_segment()?;
+        self.open_part(segment, 0, game)
+    }
+
+    /// Appends an event to the current segment, first rotating to a new
+    /// file if needed
+    pub fn write(&mut self, event: &CombatEvent) -> Result<()> {
+        let current = self.current.as_ref().ok_or(Error::NoSegment)?;
+        if self.needs_rotation(current, event) {
+            let (segment, part) = (current.segment, current.part);
+            let game = current.game.clone();
+            self.finish_segment()?;
+            self.open_part(segment, part + 1, game)?;
+        }
+
+        let current = self.current.as_mut().ok_or(Error::NoSegment)?;
+        current.writer.write(event)?;
+        current.events += 1;
+        Ok(())
+    }
+
+    /// Flushes the current segment to its file, syncing it to disk when the
+    /// sync interval passed
+    pub fn flush(&mut self) -> Result<()> {
+        let current = match &mut self.current {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+
+        current.writer.flush()?;
+        if current.synced.elapsed() >= self.sync_interval {
+            current.writer.get_ref().get_ref().sync_data()?;
+            current.synced = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Flushes, syncs and closes the current segment, if any, compressing
+    /// it when enabled
+    pub fn finish_segment(&mut self) -> Result<()> {
+        let current = match self.current.take() {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+
+        let file = current.writer.into_inner()?
+            .into_inner()
+            .map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        drop(file);
+
+        if self.compress {
+            if let Some(path) = self.segments.last_mut() {
+                *path = compress_log(&*path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks whether the current file should be rotated before writing an
+    /// event, which never leaves a file without events
+    fn needs_rotation(&self, current: &OpenPart, event: &CombatEvent)
+            -> bool {
+        if current.events == 0 {
+            return false;
+        }
+
+        self.max_size
+                .is_some_and(|max| current.writer.bytes_written() >= max)
+            || self.max_age
+                .is_some_and(|max| current.opened.elapsed() >= max)
+            || (self.rotate_on_zone
+                && matches!(event.kind, EventKind::ZoneChange(_)))
+    }
+
+    /// Opens a file of a segment, returning its path
+    fn open_part(&mut self, segment: u64, part: u32, game: GameInfo)
+            -> Result<PathBuf> {
+        let name = match part {
+            0 => format!("{}-{:03}", self.session_id, segment),
+            _ => format!("{}-{:03}_{:03}", self.session_id, segment, part),
+        };
+        let path = self.directory.join(format!("{}.{}", name,
+            self.format.extension()));
+
+        let mut header = LogHeader::new(&self.session_id, segment,
+            self.started_at, game.clone());
+        header.part = part;
+        // Lock before truncating, so a file another writer holds is never
+        // cut short
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        if !try_lock(&file)? {
+            return Err(Error::IOError(std::io::Error::new(
+                std::io::ErrorKind::WouldBlock,
+                format!("{} is locked by another writer", path.display()))));
+        }
+        file.set_len(0)?;
+        let writer = LogWriter::new(BufWriter::new(file), self.format,
+            header)?;
+        self.segments.push(path.clone());
+
+        let now = Instant::now();
+        self.current = Some(OpenPart {
+            writer,
+            segment,
+            part,
+            game,
+            opened: now,
+            synced: now,
+            events: 0,
+        });
+        Ok(path)
+    }
+}
+
+impl Drop for SessionWriter {
+    fn drop(&mut self) {
+        let _ = self.finish_segment();
+    }
+}
@@ -0,0 +1,349 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use super::format::{read_full, BINARY_MAGIC, MAX_HEADER_LEN, MAX_RECORD_LEN};
+use super::{EventKind, LogFormat, LogReader, Result, Timestamp};
+
+/// Bytes every gzip stream starts with
+const GZIP_MAGIC: &[u8; 2] = &[0x1f, 0x8b];
+
+/// Extension added to compressed logs
+pub const COMPRESSED_EXTENSION: &str = "gz";
+
+/// A log file on disk, decompressed on the fly when it is gzipped
+pub enum LogFile {
+    /// Uncompressed log
+    Plain(BufReader<File>),
+
+    /// Gzipped log, along with its path to restart decompression on seeks
+    Gzip(Box<BufReader<GzDecoder<File>>>, PathBuf),
+}
+
+/// What recovery did to a log left behind by a crash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovery {
+    /// Path of the log
+    pub path: PathBuf,
+
+    /// Length of the log before recovery
+    pub original_len: u64,
+
+    /// Length of the log after recovery, zero when the header itself was
+    /// cut off and the log was removed
+    pub recovered_len: u64,
+
+    /// Offset of a damaged record found before the end of the log, which
+    /// was left in place for readers to report
+    pub damaged_at: Option<u64>,
+}
+
+impl LogFile {
+    /// Opens a log, detecting gzip compression from the first bytes
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(File::open(path)?);
+        if reader.fill_buf()?.starts_with(GZIP_MAGIC) {
+            // Start over, as the magic check buffered the first bytes
+            let mut file = reader.into_inner();
+            file.rewind()?;
+            Ok(LogFile::Gzip(Box::new(BufReader::new(GzDecoder::new(file))),
+                path.to_path_buf()))
+        }
+        else {
+            Ok(LogFile::Plain(reader))
+        }
+    }
+
+    /// Whether the log is gzipped
+    pub fn is_compressed(&self) -> bool {
+        matches!(self, LogFile::Gzip(..))
+    }
+}
+
+impl Read for LogFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            LogFile::Plain(reader) => reader.read(buf),
+            LogFile::Gzip(reader, _) => reader.read(buf),
+        }
+    }
+}
+
+impl BufRead for LogFile {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self {
+            LogFile::Plain(reader) => reader.fill_buf(),
+            LogFile::Gzip(reader, _) => reader.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amount: usize) {
+        match self {
+            LogFile::Plain(reader) => reader.consume(amount),
+            LogFile::Gzip(reader, _) => reader.consume(amount),
+        }
+    }
+}
+
+impl Seek for LogFile {
+    /// Seeks within the uncompressed log
+    ///
+    /// Gzipped logs only support seeking from the start, which decompresses
+    /// the log again up to the position.
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        match self {
+            LogFile::Plain(reader) => reader.seek(position),
+            LogFile::Gzip(reader, path) => {
+                let offset = match position {
+                    SeekFrom::Start(offset) => offset,
+                    _ => return Err(io::Error::new(io::ErrorKind::Unsupported,
+                        "Compressed logs only seek from the start")),
+                };
+                let mut restarted = Box::new(BufReader::new(
+                    GzDecoder::new(File::open(&*path)?)));
+                let skipped = io::copy(&mut (&mut *restarted).take(offset),
+                    &mut io::sink())?;
+                *reader = restarted;
+                Ok(skipped)
+            }
+        }
+    }
+}
+
+/// Compresses a closed log next to itself and removes the original,
+/// returning the path of the compressed log
+pub fn compress_log(path: impl AsRef<Path>) -> Result<PathBuf> {
+    let path = path.as_ref();
+    let mut compressed_path = path.as_os_str().to_owned();
+    compressed_path.push(".");
+    compressed_path.push(COMPRESSED_EXTENSION);
+    let compressed_path = PathBuf::from(compressed_path);
+
+    let mut input = File::open(path)?;
+    let output = BufWriter::new(File::create(&compressed_path)?);
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    let file = encoder.finish()?
+        .into_inner()
+        .map_err(|e| e.into_error())?;
+
+    // Only drop the original once the copy is safely on disk
+    file.sync_all()?;
+    std::fs::remove_file(path)?;
+    Ok(compressed_path)
+}
+
+/// Takes an exclusive advisory lock on a log without waiting, returning
+/// whether it was taken
+///
+/// The lock is held until the file is closed. Writers hold it on the file
+/// they append to, which keeps recovery in another logger away from it.
+pub(super) fn try_lock(file: &File) -> Result<bool> {
+    let result = unsafe {
+        libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB)
+    };
+    if result == 0 {
+        return Ok(true);
+    }
+
+    let error = io::Error::last_os_error();
+    if error.raw_os_error() == Some(libc::EWOULDBLOCK) {
+        Ok(false)
+    }
+    else {
+        Err(error.into())
+    }
+}
+
+/// Cuts a log back to its last complete record, as needed after a crash
+/// while writing, returning what was done or `None` when the log was intact
+///
+/// Only a damaged record that runs to the end of the log is cut, as that is
+/// all a crash can leave behind. A record damaged before the end is left in
+/// place along with everything after it, and only reported, so that readers
+/// still get the records that follow it. Compressed logs are always complete
+/// and left alone, as are logs another writer holds a lock on and files
+/// whose header is complete but not one we can read. A log whose header is
+/// cut off holds nothing and is removed.
+pub fn recover_log(path: impl AsRef<Path>) -> Result<Option<Recovery>> {
+    let path = path.as_ref();
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    if !try_lock(&file)? {
+        return Ok(None);
+    }
+    let original_len = file.metadata()?.len();
+
+    let mut magic = [0u8; 4];
+    let magic_len = read_full(&mut file, &mut magic)?;
+    if magic[..magic_len].starts_with(GZIP_MAGIC) {
+        return Ok(None);
+    }
+    let binary = magic_len == magic.len() && &magic == BINARY_MAGIC;
+    file.rewind()?;
+
+    let (end, damaged_at) =
+        if header_complete(&mut file, original_len, binary)? {
+            file.rewind()?;
+            let data_start = match LogReader::new(BufReader::new(&file)) {
+                Ok(reader) => reader.offset(),
+                Err(_) => return Ok(None),
+            };
+            if binary {
+                binary_end(&mut file, data_start, original_len)?
+            }
+            else {
+                (json_end(&mut file, data_start, original_len)?, None)
+            }
+        }
+        else {
+            (0, None)
+        };
+
+    if end == original_len && damaged_at.is_none() {
+        return Ok(None);
+    }
+    if end == 0 {
+        std::fs::remove_file(path)?;
+    }
+    else if end < original_len {
+        file.set_len(end)?;
+        file.sync_all()?;
+    }
+
+    Ok(Some(Recovery {
+        path: path.to_path_buf(),
+        original_len,
+        recovered_len: end,
+        damaged_at,
+    }))
+}
+
+/// Recovers every uncompressed log in a directory, returning the logs that
+/// had to be cut back or were found damaged
+pub fn recover_directory(directory: impl AsRef<Path>)
+        -> Result<Vec<Recovery>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| {
+            [LogFormat::Json, LogFormat::Binary].iter()
+                .any(|format| extension == format.extension())
+        }))
+        .collect();
+    paths.sort();
+
+    let mut recovered = vec![];
+    for path in paths {
+        recovered.extend(recover_log(&path)?);
+    }
+    Ok(recovered)
+}
+
+/// Checks whether the header of a log was written in full, which for JSON
+/// is its first line and for a binary log its length-prefixed JSON
+fn header_complete(file: &mut File, len: u64, binary: bool) -> Result<bool> {
+    let mut reader = BufReader::new(file);
+    if binary {
+        let mut prefix = [0u8; 12];
+        if read_full(&mut reader, &mut prefix)? < prefix.len() {
+            return Ok(false);
+        }
+        let header_len = u32::from_le_bytes(prefix[8..].try_into().unwrap());
+        Ok(header_len as usize <= MAX_HEADER_LEN
+            && prefix.len() as u64 + header_len as u64 <= len)
+    }
+    else {
+        let mut line = vec![];
+        reader.take(MAX_HEADER_LEN as u64).read_until(b'\n', &mut line)?;
+        Ok(line.last() == Some(&b'\n'))
+    }
+}
+
+/// Length of a JSON log up to its last complete line
+///
+/// Only the tail is read, as every complete record ends with a newline and
+/// a crash can only cut off the last one.
+fn json_end(file: &mut File, data_start: u64, len: u64) -> Result<u64> {
+    const CHUNK: u64 = 64 * 1024;
+    let mut end = len;
+    let mut buffer = vec![0u8; CHUNK as usize];
+    while end > data_start {
+        let start = end.saturating_sub(CHUNK).max(data_start);
+        let chunk = &mut buffer[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        if let Some(newline) = chunk.iter().rposition(|byte| *byte == b'\n') {
+            return Ok(start + newline as u64 + 1);
+        }
+        end = start;
+    }
+
+    Ok(data_start)
+}
+
+/// Length of a binary log up to its last complete record, along with the
+/// offset of a damaged record found before the end
+///
+/// Records are skipped by their length prefix, and only the last complete
+/// one is decoded and checked, so the log is not read in full. A record
+/// running past the end, or zeros up to the end as the filesystem may leave
+/// after a crash, is cut. A length no writer produces with other data after
+/// it is damage in the middle of the log, which is reported without cutting.
+fn binary_end(file: &mut File, data_start: u64, len: u64)
+        -> Result<(u64, Option<u64>)> {
+    let mut reader = BufReader::new(file);
+    reader.seek(SeekFrom::Start(data_start))?;
+
+    let mut position = data_start;
+    let mut last = None;
+    while position < len {
+        let mut record = [0u8; 8];
+        if read_full(&mut reader, &mut record)? < record.len() {
+            break;
+        }
+        let payload_len = u32::from_le_bytes(record[..4].try_into().unwrap());
+        let next = position + record.len() as u64 + payload_len as u64;
+        if payload_len == 0 || payload_len as usize > MAX_RECORD_LEN {
+            if record.iter().all(|byte| *byte == 0)
+                    && zeros_to_end(&mut reader)? {
+                break;
+            }
+            return Ok((len, Some(position)));
+        }
+        if next > len {
+            break;
+        }
+        last = Some((position, record));
+        reader.seek_relative(payload_len as i64)?;
+        position = next;
+    }
+
+    let (start, record) = match last {
+        Some(last) => last,
+        None => return Ok((position, None)),
+    };
+    let payload_len = u32::from_le_bytes(record[..4].try_into().unwrap());
+    let checksum = u32::from_le_bytes(record[4..].try_into().unwrap());
+    let mut payload = vec![0u8; payload_len as usize];
+    reader.seek(SeekFrom::Start(start + record.len() as u64))?;
+    reader.read_exact(&mut payload)?;
+    let valid = crc32fast::hash(&payload) == checksum
+        && rmp_serde::from_slice::<(Timestamp, EventKind)>(&payload).is_ok();
+
+    Ok((if valid { position } else { start }, None))
+}
+
+/// Checks whether nothing but zeros is left up to the end
+fn zeros_to_end(reader: &mut impl Read) -> Result<bool> {
+    let mut buffer = [0u8; 4096];
+    loop {
+        let read = read_full(reader, &mut buffer)?;
+        if buffer[..read].iter().any(|byte| *byte != 0) {
+            return Ok(false);
+        }
+        if read < buffer.len() {
+            return Ok(true);
+        }
+    }
+}
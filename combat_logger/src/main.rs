@@ -1,9 +1,678 @@
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+use clap::{Parser, Subcommand};
+use combat_logger::analysis::{
+    BuffTracker, BuildComparison, DefenseTracker, DpsMeter, RotationTracker,
+    SessionMetrics,
+};
+use combat_logger::index::{
+    format_date, parse_date, parse_date_end, SessionEntry, SessionIndex,
+    SessionQuery,
+};
+use combat_logger::logger::{
+    CombatEvent, LogHeader, LogReader, Playback, Replay, Timestamp,
+};
+use combat_logger::memory::{
+    DiffMode, MemoryReader, MemoryRegion, Result, Snapshot, ValueType,
+};
+use combat_logger::server::{Server, DEFAULT_ADDRESS};
 
-use combat_logger::memory::{MemoryReader, Result};
+/// Name of the game process
+const GAME_PROCESS: &str = "Diablo IV.exe";
 
-fn main() -> Result<()> {
-    let game_reader = MemoryReader::new("Diablo IV.exe")?;
-    println!("{}", game_reader.process_id);
+/// Diablo 4 combat logger
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Dump memory of the game to a compressed snapshot
+    Snapshot {
+        /// Output snapshot file
+        output: PathBuf,
+
+        /// Process identifier, instead of looking up the process by name
+        #[arg(long)]
+        pid: Option<i32>,
+
+        /// Name of the process to look up
+        #[arg(long, default_value = GAME_PROCESS)]
+        process: String,
+
+        /// Only capture regions backed by this module
+        #[arg(long)]
+        module: Option<String>,
+
+        /// Only capture writable regions
+        #[arg(long)]
+        writable: bool,
+    },
+
+    /// Compare two snapshots and list the values that satisfy a condition
+    Diff {
+        /// Older snapshot file
+        old: PathBuf,
+
+        /// Newer snapshot file
+        new: PathBuf,
+
+        /// Value type to compare (u8, u16, u32, u64, i32, i64, f32, f64)
+        #[arg(long = "type", default_value = "i32")]
+        value_type: ValueType,
+
+        /// Condition to match (changed, unchanged, increased, decreased)
+        #[arg(long, default_value = "changed")]
+        mode: DiffMode,
+
+        /// Maximum number of addresses to print
+        #[arg(long, default_value_t = 100)]
+        limit: usize,
+    },
+
+    /// Play back a recorded combat log, printing every event as JSON
+    Replay {
+        /// Log file to play back
+        log: PathBuf,
+
+        /// Pace of the playback (realtime, instant or a factor such as 10x)
+        #[arg(long, default_value = "instant")]
+        speed: Playback,
+
+        /// Only print events of these kinds, separated by commas
+        #[arg(long, value_delimiter = ',')]
+        kinds: Vec<String>,
+
+        /// Start this many seconds into the session
+        #[arg(long)]
+        from: Option<f64>,
+    },
+
+    /// Report DPS and per-skill damage of recorded combat logs
+    Dps {
+        /// Log files of one session, in order
+        #[arg(required = true)]
+        logs: Vec<PathBuf>,
+
+        /// Seconds rolling DPS is averaged over
+        #[arg(long, default_value_t = 5.0)]
+        window: f64,
+
+        /// Seconds without a hit that end an encounter
+        #[arg(long, default_value_t = 5.0)]
+        idle_gap: f64,
+
+        /// Actor identifier of the player, instead of the one in the header
+        #[arg(long)]
+        player: Option<u64>,
+
+        /// Print the report as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Report buff and debuff uptimes of recorded combat logs
+    Buffs {
+        /// Log files of one session, in order
+        #[arg(required = true)]
+        logs: Vec<PathBuf>,
+
+        /// Also report the time these buffs are all active, separated by
+        /// plus signs (e.g. Vulnerable+Berserking); may be repeated
+        #[arg(long)]
+        combo: Vec<String>,
+
+        /// Seconds without a hit that end an encounter
+        #[arg(long, default_value_t = 5.0)]
+        idle_gap: f64,
+
+        /// Actor identifier of the player, instead of the one in the header
+        #[arg(long)]
+        player: Option<u64>,
+
+        /// Print the report as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Report skill usage, wasted cooldowns and cast sequences of recorded
+    /// combat logs
+    Rotation {
+        /// Log files of one session, in order
+        #[arg(required = true)]
+        logs: Vec<PathBuf>,
+
+        /// Length of the cast sequences counted
+        #[arg(long, default_value_t = 3)]
+        sequence: usize,
+
+        /// Number of cast sequences reported per encounter
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+
+        /// Write the cast timeline to this file as CSV
+        #[arg(long)]
+        timeline: Option<PathBuf>,
+
+        /// Seconds without a hit that end an encounter
+        #[arg(long, default_value_t = 5.0)]
+        idle_gap: f64,
+
+        /// Actor identifier of the player, instead of the one in the header
+        #[arg(long)]
+        player: Option<u64>,
+
+        /// Print the report as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Report damage taken, mitigation, healing and death recaps of
+    /// recorded combat logs
+    Defense {
+        /// Log files of one session, in order
+        #[arg(required = true)]
+        logs: Vec<PathBuf>,
+
+        /// Seconds before a death covered by its recap
+        #[arg(long, default_value_t = 10.0)]
+        recap: f64,
+
+        /// Fraction of maximum life below which the player nearly died
+        #[arg(long, default_value_t = 0.2)]
+        near_death: f64,
+
+        /// Actor identifier of the player, instead of the one in the header
+        #[arg(long)]
+        player: Option<u64>,
+
+        /// Print the report as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Index the sessions recorded in a log directory and list those
+    /// matching every filter
+    Sessions {
+        /// Directory of the logs, where the index is also kept
+        #[arg(long, default_value = "logs")]
+        dir: PathBuf,
+
+        /// Only list sessions played with this character
+        #[arg(long)]
+        character: Option<String>,
+
+        /// Only list sessions played with this class
+        #[arg(long)]
+        class: Option<String>,
+
+        /// Only list sessions played at this level
+        #[arg(long)]
+        level: Option<u32>,
+
+        /// Only list sessions played on this world tier
+        #[arg(long)]
+        world_tier: Option<u32>,
+
+        /// Only list sessions that visited this zone
+        #[arg(long)]
+        zone: Option<String>,
+
+        /// Only list sessions started on or after this day (YYYY-MM-DD, UTC)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only list sessions started on or before this day (YYYY-MM-DD,
+        /// UTC)
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Only list sessions played with the gear of this hash
+        #[arg(long)]
+        gear: Option<String>,
+
+        /// Print the sessions as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Compare DPS, skill damage, buff uptimes and damage taken between two
+    /// groups of indexed sessions, such as before and after a gear change
+    Compare {
+        /// Filters selecting the first group from the session index, as
+        /// key=value pairs separated by commas (e.g.
+        /// class=Sorcerer,until=2026-10-05)
+        #[arg(long)]
+        a: String,
+
+        /// Filters selecting the second group from the session index
+        #[arg(long)]
+        b: String,
+
+        /// Directory of the logs, where the index is also kept
+        #[arg(long, default_value = "logs")]
+        dir: PathBuf,
+
+        /// Name of the first group in the report
+        #[arg(long, default_value = "A")]
+        a_label: String,
+
+        /// Name of the second group in the report
+        #[arg(long, default_value = "B")]
+        b_label: String,
+
+        /// Number of bootstrap resamples
+        #[arg(long, default_value_t = 2000)]
+        resamples: usize,
+
+        /// Confidence level of the intervals
+        #[arg(long, default_value_t = 0.95)]
+        confidence: f64,
+
+        /// Seconds without a hit that end an encounter
+        #[arg(long, default_value_t = 5.0)]
+        idle_gap: f64,
+
+        /// Write the report as HTML instead of Markdown
+        #[arg(long)]
+        html: bool,
+
+        /// Write the report to this file instead of standard output
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// Print the report as JSON instead of Markdown
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Serve combat events and meter snapshots to browser overlays over
+    /// WebSocket and HTTP, playing back a recorded log as the source (no live
+    /// source exists yet)
+    Serve {
+        /// Log file to play back
+        log: PathBuf,
+
+        /// Pace of the playback (realtime, instant or a factor such as 10x)
+        #[arg(long, default_value = "realtime")]
+        speed: Playback,
+
+        /// Address to listen on, which only accepts connections from this
+        /// machine by default
+        #[arg(long, default_value = DEFAULT_ADDRESS)]
+        bind: String,
+
+        /// Seconds between meter snapshots sent to WebSocket clients
+        #[arg(long, default_value_t = 1.0)]
+        snapshot_interval: f64,
+
+        /// Seconds without a hit that end an encounter
+        #[arg(long, default_value_t = 5.0)]
+        idle_gap: f64,
+
+        /// Actor identifier of the player, instead of the one in the header
+        #[arg(long)]
+        player: Option<u64>,
+
+        /// Origin of a web page allowed to read from the server besides
+        /// local files (e.g. http://localhost:8080); may be repeated
+        #[arg(long)]
+        allow_origin: Vec<String>,
+    },
+}
+
+/// Opens a reader for a process identifier or name
+fn open_reader(pid: Option<i32>, process: &str) -> Result<MemoryReader> {
+    match pid {
+        Some(pid) => Ok(MemoryReader::from_pid(pid)),
+        None => MemoryReader::new(process),
+    }
+}
+
+/// Reads every event of the logs of a session, returning along with them
+/// the header of the first log that names the character, or else of the
+/// first log
+///
+/// A lenient read treats the logs the way the session index does: logs
+/// whose header cannot be read are skipped, and a damaged record ends its
+/// log instead of failing the whole read.
+fn read_session(logs: &[PathBuf], lenient: bool)
+        -> std::result::Result<(LogHeader, Vec<CombatEvent>), Box<dyn Error>> {
+    let mut header: Option<LogHeader> = None;
+    let mut events = vec![];
+    for log in logs {
+        let reader = match LogReader::open(log) {
+            Ok(reader) => reader,
+            Err(_) if lenient => continue,
+            Err(e) => return Err(e.into()),
+        };
+        // Later segments may name the character the first one did not
+        let better = match &header {
+            Some(header) => header.game.character.is_none()
+                && reader.header().game.character.is_some(),
+            None => true,
+        };
+        if better {
+            header = Some(reader.header().clone());
+        }
+        for event in reader {
+            match event {
+                Ok(event) => events.push(event),
+                Err(_) if lenient => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    Ok((header.ok_or("No readable log files given")?, events))
+}
+
+/// Actor identifier of the player, from the option or else the log header
+fn player_id(header: &LogHeader, player: Option<u64>)
+        -> std::result::Result<u64, Box<dyn Error>> {
+    player
+        .or_else(|| header.game.character.as_ref()
+            .map(|character| character.id))
+        .ok_or_else(|| "Log header has no character, pass --player".into())
+}
+
+/// Computes the metrics of every indexed session matching a query
+fn group_metrics(index: &SessionIndex, query: &SessionQuery,
+        idle_gap: Duration)
+        -> std::result::Result<Vec<SessionMetrics>, Box<dyn Error>> {
+    let mut metrics = vec![];
+    for entry in index.query(query) {
+        let logs: Vec<PathBuf> = entry.files.iter()
+            .map(|file| index.directory().join(&file.name))
+            .collect();
+        let (header, events) = read_session(&logs, true)?;
+        let player = header.game.character
+            .map(|character| character.id)
+            .ok_or_else(|| format!("Session {} has no character in its log \
+                headers", entry.session_id))?;
+        metrics.push(SessionMetrics::from_events(&entry.session_id, player,
+            idle_gap, &events));
+    }
+
+    if metrics.is_empty() {
+        return Err("No indexed session matches the filters".into());
+    }
+    Ok(metrics)
+}
+
+/// Prints indexed sessions as a terminal table
+fn print_sessions(sessions: &[&SessionEntry]) {
+    println!("{:<16} {:<16} {:<12} {:>5} {:>4} {:>8} {:>10} {:>6} {:>6}  Zones",
+        "Started", "Character", "Class", "Level", "Tier", "Duration",
+        "Damage", "Kills", "Deaths");
+    for session in sessions {
+        let number = |value: Option<u32>| value
+            .map_or_else(|| "-".to_string(), |value| value.to_string());
+        let minutes = session.duration as u64 / 60;
+        println!(
+            "{:<16} {:<16} {:<12} {:>5} {:>4} {:>8} {:>10.0} {:>6} {:>6}  {}",
+            format_date(session.started_at),
+            session.character.as_deref().unwrap_or("-"),
+            session.class.as_deref().unwrap_or("-"),
+            number(session.level), number(session.world_tier),
+            format!("{}:{:02}:{:02}", minutes / 60, minutes % 60,
+                session.duration as u64 % 60),
+            session.totals.damage_dealt, session.totals.kills,
+            session.totals.deaths, session.zones.join(", "));
+    }
+}
+
+/// Converts seconds given on the command line
+fn seconds(seconds: f64) -> Duration {
+    Duration::from_secs_f64(seconds.max(0.0))
+}
+
+fn main() -> std::result::Result<(), Box<dyn Error>> {
+    match Cli::parse().command {
+        None => {
+            let game_reader = MemoryReader::new(GAME_PROCESS)?;
+            game_reader.check_access()?;
+            println!("{}", game_reader.process_id());
+        }
+        Some(Command::Snapshot { output, pid, process, module, writable }) => {
+            let reader = open_reader(pid, &process)?;
+            reader.check_access()?;
+
+            // Select the regions to capture
+            let regions: Vec<MemoryRegion> = match &module {
+                Some(module) => reader.module_regions(module)?,
+                None => reader.regions()?,
+            };
+            let regions: Vec<MemoryRegion> = regions.into_iter()
+                .filter(|region| region.is_readable())
+                .filter(|region| !writable || region.is_writable())
+                .collect();
+
+            let captured = reader.capture_snapshot(&regions, &output)?;
+            println!("Captured {} bytes from {} regions to {}",
+                captured, regions.len(), output.display());
+        }
+        Some(Command::Diff { old, new, value_type, mode, limit }) => {
+            let old = Snapshot::open(old)?;
+            let new = Snapshot::open(new)?;
+            // Only the printed matches are kept, the rest are just counted
+            let mut printed = 0;
+            let count = old.diff_each(&new, value_type, mode, |change| {
+                if printed < limit {
+                    println!("0x{:x}  {} -> {}",
+                        change.address, change.old, change.new);
+                    printed += 1;
+                }
+            })?;
+            println!("{} matching {} values", count, value_type);
+        }
+        Some(Command::Replay { log, speed, kinds, from }) => {
+            let mut replay = Replay::open(log)?.playback(speed);
+            if !kinds.is_empty() {
+                let kinds: Vec<&str> = kinds.iter().map(String::as_str)
+                    .collect();
+                replay = replay.kinds(&kinds)?;
+            }
+            if let Some(from) = from {
+                replay.seek(Timestamp::from_duration(seconds(from)))?;
+            }
+
+            for event in replay {
+                println!("{}", serde_json::to_string(&event?)?);
+            }
+        }
+        Some(Command::Dps { logs, window, idle_gap, player, json }) => {
+            let (header, events) = read_session(&logs, false)?;
+            let mut meter = DpsMeter::new(player_id(&header, player)?)
+                .window(seconds(window).max(Duration::from_millis(1)))
+                .idle_gap(seconds(idle_gap));
+            for event in &events {
+                meter.push(event);
+            }
+
+            let report = meter.report();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+            else {
+                print!("{}", report);
+            }
+        }
+        Some(Command::Buffs { logs, combo, idle_gap, player, json }) => {
+            let (header, events) = read_session(&logs, false)?;
+            let mut tracker = BuffTracker::new(player_id(&header, player)?)
+                .idle_gap(seconds(idle_gap));
+            for combination in &combo {
+                let buffs: Vec<&str> = combination.split('+')
+                    .map(str::trim)
+                    .collect();
+                tracker = tracker.combination(&buffs);
+            }
+            for event in &events {
+                tracker.push(event);
+            }
+
+            let report = tracker.report();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+            else {
+                print!("{}", report);
+            }
+        }
+        Some(Command::Rotation {
+            logs, sequence, top, timeline, idle_gap, player, json
+        }) => {
+            let (header, events) = read_session(&logs, false)?;
+            let mut tracker = RotationTracker::new(player_id(&header, player)?)
+                .sequence_length(sequence.max(1))
+                .top_sequences(top)
+                .idle_gap(seconds(idle_gap));
+            for event in &events {
+                tracker.push(event);
+            }
+
+            let report = tracker.report();
+            if let Some(timeline) = timeline {
+                let file = std::fs::File::create(&timeline)?;
+                report.write_timeline(std::io::BufWriter::new(file))?;
+            }
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+            else {
+                print!("{}", report);
+            }
+        }
+        Some(Command::Defense { logs, recap, near_death, player, json }) => {
+            let (header, events) = read_session(&logs, false)?;
+            let mut tracker = DefenseTracker::new(player_id(&header, player)?)
+                .recap(seconds(recap))
+                .near_death(near_death);
+            for event in &events {
+                tracker.push(event);
+            }
+
+            let report = tracker.report();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+            else {
+                print!("{}", report);
+            }
+        }
+        Some(Command::Sessions {
+            dir, character, class, level, world_tier, zone, since, until,
+            gear, json
+        }) => {
+            let mut index = SessionIndex::open(&dir)?;
+            let summary = index.scan()?;
+            if summary.skipped > 0 {
+                eprintln!("Skipped {} unreadable log files", summary.skipped);
+            }
+
+            let mut query = SessionQuery::new();
+            if let Some(character) = &character {
+                query = query.character(character);
+            }
+            if let Some(class) = &class {
+                query = query.class(class);
+            }
+            if let Some(level) = level {
+                query = query.level(level);
+            }
+            if let Some(world_tier) = world_tier {
+                query = query.world_tier(world_tier);
+            }
+            if let Some(zone) = &zone {
+                query = query.zone(zone);
+            }
+            if let Some(since) = &since {
+                query = query.since(parse_date(since)?);
+            }
+            if let Some(until) = &until {
+                // Sessions started at any time of the last day are included
+                query = query.until(parse_date_end(until)?);
+            }
+            if let Some(gear) = &gear {
+                query = query.gear_hash(gear);
+            }
+
+            let sessions = index.query(&query);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&sessions)?);
+            }
+            else {
+                print_sessions(&sessions);
+            }
+        }
+        Some(Command::Compare {
+            a, b, dir, a_label, b_label, resamples, confidence, idle_gap, html,
+            output, json
+        }) => {
+            if resamples == 0 {
+                return Err("At least one bootstrap resample is needed".into());
+            }
+            if !(confidence > 0.0 && confidence < 1.0) {
+                return Err(format!("Confidence level {} is not between 0 \
+                    and 1, such as 0.95", confidence).into());
+            }
+            let (a, b): (SessionQuery, SessionQuery) = (a.parse()?, b.parse()?);
+            let mut index = SessionIndex::open(&dir)?;
+            index.scan()?;
+            let report = BuildComparison::new(
+                    group_metrics(&index, &a, seconds(idle_gap))?,
+                    group_metrics(&index, &b, seconds(idle_gap))?)
+                .labels(&a_label, &b_label)
+                .resamples(resamples)
+                .confidence(confidence)
+                .report();
+
+            let mut writer: Box<dyn std::io::Write> = match &output {
+                Some(output) => Box::new(std::io::BufWriter::new(
+                    std::fs::File::create(output)?)),
+                None => Box::new(std::io::stdout().lock()),
+            };
+            if json {
+                serde_json::to_writer_pretty(&mut writer, &report)?;
+                writeln!(writer)?;
+            }
+            else if html {
+                report.write_html(writer)?;
+            }
+            else {
+                report.write_markdown(writer)?;
+            }
+        }
+        Some(Command::Serve {
+            log, speed, bind, snapshot_interval, idle_gap, player, allow_origin
+        }) => {
+            let replay = Replay::open(&log)?.playback(speed);
+            let player = player_id(replay.header(), player)?;
+            let (replay, events) = replay.start();
+            let server = allow_origin.iter()
+                .fold(Server::new(player), |server, origin| {
+                    server.allow_origin(origin)
+                })
+                .address(&bind)
+                .snapshot_interval(seconds(snapshot_interval)
+                    .max(Duration::from_millis(10)))
+                .idle_gap(seconds(idle_gap))
+                .start(events)?;
+            println!("Serving on http://{}", server.local_addr());
+
+            // Overlays keep the final numbers until the server is killed
+            let sent = replay.wait()?;
+            println!("Replayed {} events, still serving", sent);
+            loop {
+                std::thread::park();
+            }
+        }
+    }
 
     Ok(())
 }
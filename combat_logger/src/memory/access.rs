@@ -0,0 +1,200 @@
+use std::io::ErrorKind;
+use super::{read_maps, Error, MemoryReader, MemoryRegion, ProcessInfo, Result};
+
+/// Path of the Yama setting restricting which processes may be accessed
+const PTRACE_SCOPE_PATH: &str = "/proc/sys/kernel/yama/ptrace_scope";
+
+/// Bit of `CAP_SYS_PTRACE` in the capability sets of `/proc/<pid>/status`
+const CAP_SYS_PTRACE: u32 = 19;
+
+/// Where a failed address was found in the memory maps
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressRegion {
+    /// Address falls inside this region
+    Mapped(MemoryRegion),
+
+    /// Address is not mapped at all
+    Unmapped,
+
+    /// Memory maps could not be read
+    Unknown,
+}
+
+/// Details of a memory access the kernel refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessFailure {
+    /// `errno` of the failed syscall, such as `EFAULT` or `EPERM`
+    pub errno: i32,
+
+    /// Region the address was in right after the failure
+    pub region: AddressRegion,
+}
+
+/// Settings of this process deciding whether it may access the memory of
+/// another one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PtraceAccess {
+    /// Value of `kernel.yama.ptrace_scope`, or `None` without Yama
+    pub ptrace_scope: Option<u32>,
+
+    /// Whether `CAP_SYS_PTRACE` is in the effective capability set
+    pub cap_sys_ptrace: bool,
+
+    /// Effective user identifier
+    pub uid: u32,
+}
+
+impl AddressRegion {
+    /// Looks up an address in memory maps that may not have been readable
+    pub fn find(regions: Option<&[MemoryRegion]>, address: usize) -> Self {
+        match regions {
+            Some(regions) => regions.iter()
+                .find(|region| region.contains(address))
+                .map_or(AddressRegion::Unmapped,
+                    |region| AddressRegion::Mapped(region.clone())),
+            None => AddressRegion::Unknown,
+        }
+    }
+}
+
+impl AccessFailure {
+    /// Error the failed syscall reported
+    pub fn os_error(&self) -> std::io::Error {
+        std::io::Error::from_raw_os_error(self.errno)
+    }
+
+    /// Whether the kernel denied access to the process as a whole, rather
+    /// than failing at this address
+    pub fn is_permission_denied(&self) -> bool {
+        self.errno == libc::EPERM || self.errno == libc::EACCES
+    }
+}
+
+impl std::fmt::Display for AccessFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.os_error())?;
+        match &self.region {
+            AddressRegion::Mapped(region) => {
+                write!(f, ", in region 0x{:x}-0x{:x} {}", region.range.start,
+                    region.range.end, region.permissions)?;
+                if let Some(pathname) = &region.pathname {
+                    write!(f, " {}", pathname)?;
+                }
+            }
+            AddressRegion::Unmapped => write!(f, ", address is not mapped")?,
+            AddressRegion::Unknown => {}
+        }
+        if self.is_permission_denied() {
+            write!(f, " (check_access explains why)")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PtraceAccess {
+    /// Reads the settings of the current process
+    pub fn current() -> Result<Self> {
+        // Kernels without Yama apply the classic ptrace permission checks
+        let ptrace_scope = match std::fs::read_to_string(PTRACE_SCOPE_PATH) {
+            Ok(scope) => Some(scope.trim().parse()?),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        let status = std::fs::read_to_string("/proc/self/status")?;
+        let cap_eff = status.lines()
+            .find_map(|line| line.strip_prefix("CapEff:"))
+            .ok_or_else(|| Error::ParseStr(
+                "No CapEff line in /proc/self/status".to_string()))?;
+        let cap_eff = u64::from_str_radix(cap_eff.trim(), 16)?;
+
+        Ok(Self {
+            ptrace_scope,
+            cap_sys_ptrace: cap_eff & (1 << CAP_SYS_PTRACE) != 0,
+            uid: unsafe { libc::geteuid() },
+        })
+    }
+
+    /// Explains why access to a process owned by the given user is denied,
+    /// along with what to change, or `None` when nothing here forbids it
+    pub fn explain(&self, target_uid: u32) -> Option<String> {
+        const SETCAP: &str = "grant CAP_SYS_PTRACE with \
+            `sudo setcap cap_sys_ptrace=eip <path to combat_logger>`";
+        let mut reasons = vec![];
+
+        match self.ptrace_scope {
+            Some(3) => reasons.push("kernel.yama.ptrace_scope is 3, which \
+                blocks access to other processes until the next reboot"
+                .to_string()),
+            Some(2) if !self.cap_sys_ptrace => reasons.push(format!(
+                "kernel.yama.ptrace_scope is 2, which requires \
+                CAP_SYS_PTRACE: run as root or {}", SETCAP)),
+            Some(1) if !self.cap_sys_ptrace => reasons.push(format!(
+                "kernel.yama.ptrace_scope is 1, which only allows access to \
+                child processes: launch the game from the logger, {}, or \
+                allow it for all processes of the same user with \
+                `sudo sysctl kernel.yama.ptrace_scope=0`", SETCAP)),
+            _ => {}
+        }
+
+        if target_uid != self.uid && !self.cap_sys_ptrace {
+            reasons.push(format!("the process belongs to uid {} but the \
+                logger runs as uid {}: run the logger as the same user as \
+                the game or {}", target_uid, self.uid, SETCAP));
+        }
+
+        (!reasons.is_empty()).then(|| reasons.join("; "))
+    }
+}
+
+impl MemoryReader {
+    /// Checks that the memory of the process can be read, explaining what
+    /// to change when it cannot
+    ///
+    /// Meant to run once before attaching for real, as failing reads only
+    /// report `EPERM` without saying which setting caused it.
+    pub fn check_access(&self) -> Result<()> {
+        let pid = self.process_id();
+        if !self.is_alive() {
+            return Err(Error::ProcessExited(pid));
+        }
+
+        // Reading the maps needs the same permission as reading memory
+        let regions = match read_maps(pid) {
+            Ok(regions) => regions,
+            Err(Error::IOError(e))
+                    if e.kind() == ErrorKind::PermissionDenied => {
+                return Err(self.access_denied(e.to_string()));
+            }
+            Err(e) => return Err(e),
+        };
+
+        // Probe a single byte of the first readable region
+        let region = regions.iter()
+            .find(|region| region.is_readable() && !region.is_empty())
+            .ok_or_else(|| Error::AccessDenied(pid,
+                "process has no readable memory mapped".to_string()))?;
+        match self.read_bytes(region.range.start as usize, 1) {
+            Ok(_) => Ok(()),
+            Err(Error::ReadMemoryFailed(_, Some(failure)))
+                    if failure.is_permission_denied() => {
+                Err(self.access_denied(failure.os_error().to_string()))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Builds the error for a denied access, with the best explanation
+    /// available
+    fn access_denied(&self, os_error: String) -> Error {
+        let pid = self.process_id();
+        let explanation = ProcessInfo::read(pid).ok()
+            .zip(PtraceAccess::current().ok())
+            .and_then(|(process, access)| access.explain(process.uid));
+
+        Error::AccessDenied(pid, explanation.unwrap_or_else(|| format!(
+            "{}; the game may be marked non-dumpable or be confined by a \
+            security module such as SELinux or AppArmor", os_error)))
+    }
+}
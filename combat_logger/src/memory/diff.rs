@@ -0,0 +1,209 @@
+use std::path::Path;
+use std::str::FromStr;
+use super::{
+    Error, MemoryReader, MemoryRegion, MemorySource, Result, Snapshot,
+    SnapshotWriter, Value, ValueType, PAGE_SIZE, READ_CHUNK_SIZE,
+};
+
+/// How values must relate between two snapshots to be reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffMode {
+    /// Value differs
+    Changed,
+
+    /// Value is identical
+    Unchanged,
+
+    /// Value grew
+    Increased,
+
+    /// Value shrank
+    Decreased,
+}
+
+/// A value that satisfied a diff between two snapshots
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SnapshotChange {
+    /// Address of the value
+    pub address: usize,
+
+    /// Value in the older snapshot
+    pub old: Value,
+
+    /// Value in the newer snapshot
+    pub new: Value,
+}
+
+impl FromStr for DiffMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "changed" => DiffMode::Changed,
+            "unchanged" => DiffMode::Unchanged,
+            "increased" => DiffMode::Increased,
+            "decreased" => DiffMode::Decreased,
+            _ => return Err(Error::ParseStr(
+                format!("Unknown diff mode '{}'", s))),
+        })
+    }
+}
+
+impl DiffMode {
+    /// Checks whether a pair of raw values satisfies this mode
+    fn matches(self, value_type: ValueType, old: &[u8], new: &[u8]) -> bool {
+        // Compare bits first, so that floats such as NaN still count as
+        // unchanged and numeric comparisons only run on differing values
+        let identical = old == new;
+        match self {
+            DiffMode::Changed => !identical,
+            DiffMode::Unchanged => identical,
+            DiffMode::Increased | DiffMode::Decreased if identical => false,
+            DiffMode::Increased =>
+                value_type.decode(new) > value_type.decode(old),
+            DiffMode::Decreased =>
+                value_type.decode(new) < value_type.decode(old),
+        }
+    }
+}
+
+impl<S: MemorySource> MemoryReader<S> {
+    /// Captures the given regions into a compressed snapshot file and
+    /// returns the number of bytes stored
+    ///
+    /// Regions are read and written out in chunks and any page that cannot
+    /// be read is left out, splitting the region into separately indexed
+    /// pieces.
+    pub fn capture_snapshot(&self, regions: &[MemoryRegion],
+            path: impl AsRef<Path>) -> Result<u64> {
+        self.verify_identity()?;
+        let mut writer = SnapshotWriter::create(path)?;
+        let mut captured = 0;
+
+        for region in regions {
+            let end = region.range.end as usize;
+            let mut start = region.range.start as usize;
+            writer.begin_region(region.clone())?;
+
+            while start < end {
+                let len = READ_CHUNK_SIZE.min(end - start);
+                if let Ok(chunk) = self.probe_bytes(start, len) {
+                    writer.append_chunk(&chunk)?;
+                    start += len;
+                    continue;
+                }
+
+                // Retry the chunk page by page to keep its readable part
+                for page in (start..start + len).step_by(PAGE_SIZE) {
+                    let page_len = PAGE_SIZE.min(start + len - page);
+                    match self.probe_bytes(page, page_len) {
+                        Ok(data) => writer.append_chunk(&data)?,
+                        Err(_) => {
+                            // Store what we have so far and resume after
+                            // the gap
+                            captured += writer.end_region()?;
+                            writer.begin_region(
+                                Self::piece(region, page + page_len))?;
+                        }
+                    }
+                }
+                start += len;
+            }
+            captured += writer.end_region()?;
+        }
+
+        writer.finish()?;
+        Ok(captured)
+    }
+
+    /// Part of a region from the given address to its end
+    fn piece(region: &MemoryRegion, start: usize) -> MemoryRegion {
+        let skipped = start as u64 - region.range.start;
+        MemoryRegion {
+            range: start as u64..region.range.end,
+            offset: region.offset + skipped,
+            ..region.clone()
+        }
+    }
+}
+
+impl Snapshot {
+    /// Compares this snapshot with a newer one and lists every aligned value
+    /// of the given type that satisfies the diff mode
+    ///
+    /// Every match is kept in memory, which is a lot for `Unchanged` over
+    /// large snapshots. Use `diff_each` to only look at some of them.
+    pub fn diff(&self, newer: &Snapshot, value_type: ValueType,
+            mode: DiffMode) -> Result<Vec<SnapshotChange>> {
+        let mut changes = vec![];
+        self.diff_each(newer, value_type, mode,
+            |change| changes.push(change))?;
+        Ok(changes)
+    }
+
+    /// Compares this snapshot with a newer one, calling `visit` with every
+    /// aligned value of the given type that satisfies the diff mode in
+    /// address order, and returns the number of matches
+    pub fn diff_each(&self, newer: &Snapshot, value_type: ValueType,
+            mode: DiffMode, mut visit: impl FnMut(SnapshotChange))
+            -> Result<u64> {
+        let size = value_type.size();
+        let mut count = 0;
+
+        // Both indexes are ordered by address, so walk them side by side and
+        // keep the current region of the newer snapshot decompressed
+        let mut newer_index = 0;
+        let mut newer_data: Option<(usize, Vec<u8>)> = None;
+
+        for (older_index, older) in self.entries.iter().enumerate() {
+            let older_range = &older.region.range;
+            let mut older_data = None;
+
+            while newer_index < newer.entries.len()
+                    && newer.entries[newer_index].region.range.end
+                        <= older_range.start {
+                newer_index += 1;
+            }
+
+            for index in newer_index..newer.entries.len() {
+                let newer_range = &newer.entries[index].region.range;
+                if newer_range.start >= older_range.end {
+                    break;
+                }
+
+                // Only decompress regions that actually overlap
+                let old = match &older_data {
+                    Some(data) => data,
+                    None => older_data.insert(self.read_region(older_index)?),
+                };
+                if newer_data.as_ref().is_none_or(|(i, _)| *i != index) {
+                    newer_data = Some((index, newer.read_region(index)?));
+                }
+                let new = &newer_data.as_ref().unwrap().1;
+
+                // Compare every aligned value inside the overlap
+                let start = older_range.start.max(newer_range.start) as usize;
+                let end = older_range.end.min(newer_range.end) as usize;
+                let mut address = start.next_multiple_of(size);
+                while address + size <= end {
+                    let old_offset = address - older_range.start as usize;
+                    let new_offset = address - newer_range.start as usize;
+                    let old_bytes = &old[old_offset..old_offset + size];
+                    let new_bytes = &new[new_offset..new_offset + size];
+
+                    if mode.matches(value_type, old_bytes, new_bytes) {
+                        count += 1;
+                        visit(SnapshotChange {
+                            address,
+                            old: value_type.decode(old_bytes).unwrap(),
+                            new: value_type.decode(new_bytes).unwrap(),
+                        });
+                    }
+                    address += size;
+                }
+            }
+        }
+
+        Ok(count)
+    }
+}
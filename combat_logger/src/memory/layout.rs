@@ -0,0 +1,458 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use super::{
+    Error, MemoryReader, MemorySource, Result, Value, ValueType, POINTER_SIZE,
+};
+
+/// Pointer levels followed when a layout file does not say otherwise
+const DEFAULT_POINTER_DEPTH: usize = 4;
+
+/// Type of a field in a struct layout
+///
+/// Written in layout files as a primitive (`u32`, `f32`, `bool`, ...), the
+/// name of another struct, `*T` for a 64-bit pointer to `T`, or `[T; N]` for
+/// an inline array of `N` elements.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub enum FieldType {
+    /// Primitive value
+    Value(ValueType),
+
+    /// Single byte, true when non-zero
+    Bool,
+
+    /// Struct stored inline
+    Struct(String),
+
+    /// Pointer to a value stored elsewhere
+    Pointer(Box<FieldType>),
+
+    /// Inline array with a fixed number of elements
+    Array(Box<FieldType>, usize),
+}
+
+/// A named field at a fixed offset inside a struct
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct FieldLayout {
+    /// Name of the field
+    pub name: String,
+
+    /// Offset from the start of the struct, as a number or a hex string in
+    /// formats without hex literals
+    #[serde(deserialize_with = "deserialize_offset")]
+    pub offset: usize,
+
+    /// Type of the field
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+}
+
+/// Layout of a struct in game memory
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct StructLayout {
+    /// Size of the struct, defaulting to the end of its last field
+    #[serde(default)]
+    pub size: Option<usize>,
+
+    /// Fields of the struct
+    pub fields: Vec<FieldLayout>,
+}
+
+/// Set of struct layouts describing one game build
+///
+/// Layouts are loaded from TOML or JSON:
+///
+/// ```toml
+/// build = "1.0.0"
+///
+/// [[structs.Player.fields]]
+/// name = "health"
+/// offset = 0x40
+/// type = "f32"
+///
+/// [[structs.Player.fields]]
+/// name = "target"
+/// offset = 0x48
+/// type = "*Actor"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct LayoutSet {
+    /// Game build the offsets belong to
+    #[serde(default)]
+    pub build: Option<String>,
+
+    /// Number of pointer levels followed when reading a struct, which keeps
+    /// cyclic structures such as actors targeting each other finite
+    #[serde(default = "default_pointer_depth")]
+    pub max_pointer_depth: usize,
+
+    /// Struct layouts by name
+    pub structs: BTreeMap<String, StructLayout>,
+}
+
+/// A struct read from memory through its layout
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayoutValue {
+    /// Primitive value
+    Value(Value),
+
+    /// Boolean value
+    Bool(bool),
+
+    /// Struct fields in layout order
+    Struct(Vec<(String, LayoutValue)>),
+
+    /// Array elements
+    Array(Vec<LayoutValue>),
+
+    /// Pointer with the value it points to, if it was followed
+    Pointer {
+        /// Address stored in the pointer
+        address: usize,
+
+        /// Value pointed to, or `None` when the pointer is null, cannot be
+        /// read or lies beyond the maximum pointer depth
+        target: Option<Box<LayoutValue>>,
+    },
+}
+
+/// Default for `LayoutSet::max_pointer_depth`
+fn default_pointer_depth() -> usize {
+    DEFAULT_POINTER_DEPTH
+}
+
+/// Reads an offset given either as a number or as a decimal or hex string
+fn deserialize_offset<'de, D: Deserializer<'de>>(deserializer: D)
+        -> std::result::Result<usize, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Offset {
+        Number(usize),
+        Text(String),
+    }
+
+    match Offset::deserialize(deserializer)? {
+        Offset::Number(offset) => Ok(offset),
+        Offset::Text(text) => {
+            let parsed = match text.strip_prefix("0x") {
+                Some(hex) => usize::from_str_radix(hex, 16),
+                None => text.parse(),
+            };
+            parsed.map_err(|_| serde::de::Error::custom(
+                format!("invalid offset '{}'", text)))
+        }
+    }
+}
+
+impl FromStr for FieldType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(target) = s.strip_prefix('*') {
+            return Ok(FieldType::Pointer(Box::new(target.parse()?)));
+        }
+
+        if let Some(inner) = s.strip_prefix('[')
+                .and_then(|s| s.strip_suffix(']')) {
+            let (element, count) = inner.rsplit_once(';')
+                .ok_or_else(|| Error::Layout(
+                    format!("Array type '{}' has no length", s)))?;
+            return Ok(FieldType::Array(Box::new(element.parse()?),
+                count.trim().parse()?));
+        }
+
+        if s == "bool" {
+            return Ok(FieldType::Bool);
+        }
+        if let Ok(value_type) = s.parse() {
+            return Ok(FieldType::Value(value_type));
+        }
+
+        // Anything else names a struct, which must be a plain identifier
+        let identifier = s.chars().next().is_some_and(|c| !c.is_ascii_digit())
+            && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !identifier {
+            return Err(Error::Layout(format!("Invalid field type '{}'", s)));
+        }
+
+        Ok(FieldType::Struct(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for FieldType {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl std::fmt::Display for FieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FieldType::Value(value_type) => write!(f, "{}", value_type),
+            FieldType::Bool => write!(f, "bool"),
+            FieldType::Struct(name) => write!(f, "{}", name),
+            FieldType::Pointer(target) => write!(f, "*{}", target),
+            FieldType::Array(element, count) =>
+                write!(f, "[{}; {}]", element, count),
+        }
+    }
+}
+
+impl LayoutSet {
+    /// Parses and validates layouts written in TOML
+    pub fn from_toml(s: &str) -> Result<Self> {
+        let layouts: Self = toml::from_str(s)
+            .map_err(|e| Error::Layout(e.to_string()))?;
+        layouts.validate()?;
+        Ok(layouts)
+    }
+
+    /// Parses and validates layouts written in JSON
+    pub fn from_json(s: &str) -> Result<Self> {
+        let layouts: Self = serde_json::from_str(s)
+            .map_err(|e| Error::Layout(e.to_string()))?;
+        layouts.validate()?;
+        Ok(layouts)
+    }
+
+    /// Loads layouts from a `.toml` or `.json` file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&contents),
+            _ => Self::from_toml(&contents),
+        }
+    }
+
+    /// Looks up a struct layout by name
+    pub fn get(&self, name: &str) -> Result<&StructLayout> {
+        self.structs.get(name)
+            .ok_or_else(|| Error::Layout(format!("Unknown struct '{}'", name)))
+    }
+
+    /// Size in bytes of a value of the given type
+    pub fn size_of(&self, field_type: &FieldType) -> Result<usize> {
+        self.size_with(field_type, &mut vec![])
+    }
+
+    /// Computes a size while tracking the structs being measured, so that
+    /// a struct containing itself inline is reported instead of recursing
+    fn size_with<'a>(&'a self, field_type: &'a FieldType,
+            stack: &mut Vec<&'a str>) -> Result<usize> {
+        Ok(match field_type {
+            FieldType::Value(value_type) => value_type.size(),
+            FieldType::Bool => 1,
+            FieldType::Pointer(_) => POINTER_SIZE,
+            FieldType::Array(element, count) =>
+                self.size_with(element, stack)?.checked_mul(*count)
+                    .ok_or_else(|| Error::Layout(format!(
+                        "Array {} is too large", field_type)))?,
+            FieldType::Struct(name) => {
+                if stack.contains(&name.as_str()) {
+                    return Err(Error::Layout(
+                        format!("Struct '{}' contains itself", name)));
+                }
+                let layout = self.get(name)?;
+                if let Some(size) = layout.size {
+                    return Ok(size);
+                }
+
+                stack.push(name);
+                let mut end = 0;
+                for field in &layout.fields {
+                    let size = self.size_with(&field.field_type, stack)?;
+                    end = end.max(field_end(name, field, size)?);
+                }
+                stack.pop();
+                end
+            }
+        })
+    }
+
+    /// Checks that every referenced struct exists and every field fits
+    /// inside its struct
+    pub fn validate(&self) -> Result<()> {
+        for (name, layout) in &self.structs {
+            let size = self.size_with(&FieldType::Struct(name.clone()),
+                &mut vec![])?;
+            for field in &layout.fields {
+                // Pointer targets are only sized when they are followed
+                let mut target = &field.field_type;
+                while let FieldType::Pointer(inner)
+                        | FieldType::Array(inner, _) = target {
+                    target = inner;
+                }
+                self.size_of(target)?;
+
+                let end = field_end(name, field,
+                    self.size_of(&field.field_type)?)?;
+                if end > size {
+                    return Err(Error::Layout(format!(
+                        "Field '{}.{}' ends at 0x{:x}, past the struct size \
+                        0x{:x}", name, field.name, end, size)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl LayoutValue {
+    /// Looks up a struct field by name, following pointers on the way
+    pub fn get(&self, name: &str) -> Option<&LayoutValue> {
+        match self {
+            LayoutValue::Struct(fields) => fields.iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            LayoutValue::Pointer { target: Some(target), .. } =>
+                target.get(name),
+            _ => None,
+        }
+    }
+
+    /// Primitive value, following pointers on the way
+    pub fn as_value(&self) -> Option<Value> {
+        match self {
+            LayoutValue::Value(value) => Some(*value),
+            LayoutValue::Pointer { target: Some(target), .. } =>
+                target.as_value(),
+            _ => None,
+        }
+    }
+
+    /// Converts the value to JSON, with structs as objects and pointers
+    /// replaced by their target, or null when it was not followed
+    pub fn to_json(&self) -> serde_json::Value {
+        use serde_json::Value as Json;
+
+        match self {
+            LayoutValue::Value(value) => match *value {
+                Value::U8(v) => Json::from(v),
+                Value::U16(v) => Json::from(v),
+                Value::U32(v) => Json::from(v),
+                Value::U64(v) => Json::from(v),
+                Value::I32(v) => Json::from(v),
+                Value::I64(v) => Json::from(v),
+                Value::F32(v) => Json::from(v as f64),
+                Value::F64(v) => Json::from(v),
+            },
+            LayoutValue::Bool(v) => Json::from(*v),
+            LayoutValue::Struct(fields) => Json::Object(fields.iter()
+                .map(|(name, value)| (name.clone(), value.to_json()))
+                .collect()),
+            LayoutValue::Array(elements) => Json::Array(elements.iter()
+                .map(LayoutValue::to_json)
+                .collect()),
+            LayoutValue::Pointer { target, .. } => target.as_ref()
+                .map_or(Json::Null, |target| target.to_json()),
+        }
+    }
+}
+
+/// Offset right past a field of the given size, failing instead of
+/// overflowing on offsets read from a layout file
+fn field_end(struct_name: &str, field: &FieldLayout, size: usize)
+        -> Result<usize> {
+    field.offset.checked_add(size)
+        .ok_or_else(|| Error::Layout(format!(
+            "Field '{}.{}' at offset 0x{:x} is too large",
+            struct_name, field.name, field.offset)))
+}
+
+impl<S: MemorySource> MemoryReader<S> {
+    /// Reads a whole struct at the given address into a dynamic value
+    ///
+    /// The struct is read with a single read, pointers are followed up to
+    /// the maximum pointer depth of the layouts.
+    pub fn read_layout(&self, layouts: &LayoutSet, name: &str,
+            address: usize) -> Result<LayoutValue> {
+        self.read_typed(layouts, &FieldType::Struct(name.to_string()),
+            address, 0)
+    }
+
+    /// Reads a struct into any type implementing `Deserialize`, matching
+    /// its fields to layout fields by name
+    pub fn read_struct<T: DeserializeOwned>(&self, layouts: &LayoutSet,
+            name: &str, address: usize) -> Result<T> {
+        let value = self.read_layout(layouts, name, address)?;
+        serde_json::from_value(value.to_json())
+            .map_err(|e| Error::Layout(
+                format!("Cannot convert struct '{}': {}", name, e)))
+    }
+
+    /// Reads a value of any layout type
+    fn read_typed(&self, layouts: &LayoutSet, field_type: &FieldType,
+            address: usize, depth: usize) -> Result<LayoutValue> {
+        let bytes = self.read_bytes(address, layouts.size_of(field_type)?)?;
+        self.decode_typed(layouts, field_type, &bytes, depth)
+    }
+
+    /// Decodes a value of any layout type from bytes already read
+    ///
+    /// Layouts that were never validated may place fields past the bytes
+    /// read, which fails instead of panicking.
+    fn decode_typed(&self, layouts: &LayoutSet, field_type: &FieldType,
+            bytes: &[u8], depth: usize) -> Result<LayoutValue> {
+        let slice = |start: usize, len: usize| start.checked_add(len)
+            .and_then(|end| bytes.get(start..end))
+            .ok_or_else(|| Error::Layout(format!(
+                "Field at offset 0x{:x} runs past the {} bytes read",
+                start, bytes.len())));
+
+        Ok(match field_type {
+            FieldType::Value(value_type) => LayoutValue::Value(
+                value_type.decode(bytes)
+                    .ok_or_else(|| Error::Layout(
+                        format!("Missing bytes for a {}", value_type)))?),
+            FieldType::Bool => LayoutValue::Bool(slice(0, 1)?[0] != 0),
+            FieldType::Struct(name) => {
+                let mut fields = vec![];
+                for field in &layouts.get(name)?.fields {
+                    let size = layouts.size_of(&field.field_type)?;
+                    let bytes = slice(field.offset, size)?;
+                    fields.push((field.name.clone(), self.decode_typed(
+                        layouts, &field.field_type, bytes, depth)?));
+                }
+                LayoutValue::Struct(fields)
+            }
+            FieldType::Array(element, count) => {
+                let size = layouts.size_of(element)?;
+                let mut elements = vec![];
+                for i in 0..*count {
+                    let start = i.checked_mul(size)
+                        .ok_or_else(|| Error::Layout(format!(
+                            "Array {} is too large", field_type)))?;
+                    let bytes = slice(start, size)?;
+                    elements.push(
+                        self.decode_typed(layouts, element, bytes, depth)?);
+                }
+                LayoutValue::Array(elements)
+            }
+            FieldType::Pointer(target_type) => {
+                let address = u64::from_le_bytes(
+                    slice(0, POINTER_SIZE)?.try_into().unwrap()) as usize;
+
+                // Dangling pointers are common in game memory, so a target
+                // that cannot be read is left out instead of failing
+                let target = if address == 0
+                        || depth >= layouts.max_pointer_depth {
+                    None
+                }
+                else {
+                    self.read_typed(layouts, target_type, address, depth + 1)
+                        .ok()
+                        .map(Box::new)
+                };
+                LayoutValue::Pointer { address, target }
+            }
+        })
+    }
+}
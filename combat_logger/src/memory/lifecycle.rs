@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use super::{
+    find_processes, Error, MemoryReader, Pattern, ProcessIdentity,
+    ProcessMatcher, Result,
+};
+
+/// Interval between checks when a pidfd cannot be used to wait for an exit
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time between attach attempts while the game is not running yet
+const DEFAULT_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Byte pattern located in a module every time the game is attached
+#[derive(Debug, Clone)]
+pub struct Signature {
+    /// Name the resolved address is stored under
+    pub name: String,
+
+    /// Module the pattern is searched in
+    pub module: String,
+
+    /// Pattern to search for
+    pub pattern: Pattern,
+}
+
+/// A process attached to along with everything resolved for it
+pub struct Attachment {
+    /// Process attached to
+    pub identity: ProcessIdentity,
+
+    /// Reader for the process
+    pub reader: MemoryReader,
+
+    /// Base address of every requested module
+    pub module_bases: HashMap<String, usize>,
+
+    /// Address of the first match of every signature
+    pub signatures: HashMap<String, usize>,
+
+    /// Number of earlier attachments by the same attacher, which numbers the
+    /// log segment recorded for this process
+    pub generation: u64,
+}
+
+/// Change in the attachment state reported by a running attacher
+pub enum AttachEvent {
+    /// A process was attached to, starting a new log segment
+    Attached(Box<Attachment>),
+
+    /// An attach attempt failed with a different error than the previous
+    /// one, such as a signature that no longer matches after a patch
+    Failed(Error),
+
+    /// The attached process exited or crashed
+    Exited(ProcessIdentity),
+}
+
+/// Attaches to the game and attaches again whenever it restarts
+///
+/// Every attach picks the most recently started live process matching the
+/// matcher, then resolves module bases and signatures again, as both change
+/// between runs.
+#[derive(Debug, Clone)]
+pub struct Attacher {
+    /// Selects the process to attach to
+    matcher: ProcessMatcher,
+
+    /// Modules whose base addresses are resolved
+    modules: Vec<String>,
+
+    /// Signatures resolved on every attach
+    signatures: Vec<Signature>,
+
+    /// Time between attach attempts
+    retry_interval: Duration,
+}
+
+/// Handle to a running attacher, which stops the thread when dropped
+pub struct AttacherHandle {
+    /// Channel used to wake up and stop the thread
+    stop: Option<mpsc::Sender<()>>,
+
+    /// Attach thread
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Opens a pidfd for the process, if the kernel supports it
+fn open_pidfd(pid: i32) -> Option<OwnedFd> {
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) };
+    if fd < 0 {
+        return None;
+    }
+
+    Some(unsafe { OwnedFd::from_raw_fd(fd as i32) })
+}
+
+/// Time left until an optional deadline, or `None` once it has passed
+fn remaining(deadline: Option<Instant>) -> Option<Option<Duration>> {
+    match deadline {
+        Some(deadline) => {
+            let now = Instant::now();
+            (now < deadline).then(|| Some(deadline - now))
+        }
+        None => Some(None),
+    }
+}
+
+impl ProcessIdentity {
+    /// Waits until the process exits or the timeout passes, returning
+    /// whether it exited
+    ///
+    /// Uses a pidfd where the kernel supports it and falls back to polling
+    /// `/proc` otherwise.
+    pub fn wait_for_exit(&self, timeout: Option<Duration>) -> bool {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let pidfd = open_pidfd(self.pid);
+
+        // The pid may already belong to another process by the time the
+        // pidfd was opened
+        if !self.is_alive() {
+            return true;
+        }
+
+        if let Some(pidfd) = pidfd {
+            // A pidfd becomes readable once the process exits
+            loop {
+                let timeout_ms = match remaining(deadline) {
+                    Some(Some(left)) =>
+                        left.as_millis().clamp(1, i32::MAX as u128) as i32,
+                    Some(None) => -1,
+                    None => return false,
+                };
+                let mut pollfd = libc::pollfd {
+                    fd: pidfd.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                let ready = unsafe { libc::poll(&mut pollfd, 1, timeout_ms) };
+                if ready > 0 {
+                    return true;
+                }
+                if ready < 0 && std::io::Error::last_os_error().kind()
+                        != std::io::ErrorKind::Interrupted {
+                    break;
+                }
+            }
+        }
+
+        // Poll the process state instead
+        loop {
+            if !self.is_alive() {
+                return true;
+            }
+            match remaining(deadline) {
+                Some(Some(left)) =>
+                    std::thread::sleep(left.min(EXIT_POLL_INTERVAL)),
+                Some(None) => std::thread::sleep(EXIT_POLL_INTERVAL),
+                None => return false,
+            }
+        }
+    }
+}
+
+impl Attacher {
+    /// Create a new attacher for processes matching the matcher
+    pub fn new(matcher: ProcessMatcher) -> Self {
+        Self {
+            matcher,
+            modules: vec![],
+            signatures: vec![],
+            retry_interval: DEFAULT_RETRY_INTERVAL,
+        }
+    }
+
+    /// Resolves the base address of a module on every attach
+    pub fn module(mut self, module: &str) -> Self {
+        self.modules.push(module.to_string());
+        self
+    }
+
+    /// Resolves a signature on every attach
+    pub fn signature(mut self, name: &str, module: &str, pattern: Pattern)
+            -> Self {
+        self.signatures.push(Signature {
+            name: name.to_string(),
+            module: module.to_string(),
+            pattern,
+        });
+        self
+    }
+
+    /// Sets the time between attach attempts and exit checks
+    pub fn retry_interval(mut self, retry_interval: Duration) -> Self {
+        self.retry_interval = retry_interval;
+        self
+    }
+
+    /// Makes a single attempt to attach to the newest matching process
+    pub fn attach(&self) -> Result<Attachment> {
+        let process = find_processes(&self.matcher)?
+            .into_iter()
+            .filter(|process| process.identity().is_alive())
+            .max_by_key(|process| process.start_time)
+            .ok_or_else(|| Error::ProcessNotFound(self.matcher.to_string()))?;
+        let identity = process.identity();
+        let reader = MemoryReader::attach(identity);
+
+        let mut module_bases = HashMap::new();
+        for module in &self.modules {
+            module_bases.insert(module.clone(), reader.module_base(module)?);
+        }
+
+        let mut signatures = HashMap::new();
+        for signature in &self.signatures {
+            let address = reader.scan_module(&signature.module,
+                    &signature.pattern)?
+                .first()
+                .copied()
+                .ok_or_else(|| Error::SignatureNotFound(
+                    signature.name.clone()))?;
+            signatures.insert(signature.name.clone(), address);
+        }
+
+        Ok(Attachment {
+            identity,
+            reader,
+            module_bases,
+            signatures,
+            generation: 0,
+        })
+    }
+
+    /// Keeps attaching on a background thread, returning a handle to stop it
+    /// and the channel attach events arrive on
+    pub fn start(self) -> (AttacherHandle, mpsc::Receiver<AttachEvent>) {
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let (event_tx, event_rx) = mpsc::channel();
+        let thread = std::thread::spawn(move || self.run(stop_rx, event_tx));
+
+        let handle = AttacherHandle {
+            stop: Some(stop_tx),
+            thread: Some(thread),
+        };
+        (handle, event_rx)
+    }
+
+    /// Attach loop of the background thread
+    fn run(self, stop: mpsc::Receiver<()>, events: mpsc::Sender<AttachEvent>) {
+        let stopped = || !matches!(stop.try_recv(),
+            Err(mpsc::TryRecvError::Empty));
+        let mut generation = 0;
+        let mut last_error = None;
+
+        loop {
+            // Attach, retrying until the game is up and fully loaded
+            let mut attachment = loop {
+                match self.attach() {
+                    Ok(attachment) => break attachment,
+                    Err(e) => {
+                        // Only report errors that changed, as the same one
+                        // repeats every retry while the game is not running
+                        let message = e.to_string();
+                        if last_error.as_ref() != Some(&message) {
+                            last_error = Some(message);
+                            if events.send(AttachEvent::Failed(e)).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                }
+
+                let woken = stop.recv_timeout(self.retry_interval);
+                if !matches!(woken, Err(mpsc::RecvTimeoutError::Timeout)) {
+                    return;
+                }
+            };
+            last_error = None;
+
+            let identity = attachment.identity;
+            attachment.generation = generation;
+            generation += 1;
+            if events.send(AttachEvent::Attached(Box::new(attachment)))
+                    .is_err() {
+                return;
+            }
+
+            // Wait for the process to exit, checking for a stop in between
+            while !identity.wait_for_exit(Some(self.retry_interval)) {
+                if stopped() {
+                    return;
+                }
+            }
+            if events.send(AttachEvent::Exited(identity)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl AttacherHandle {
+    /// Stops the attacher and waits for its thread to exit
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    /// Signals the thread to stop and joins it
+    fn shutdown(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for AttacherHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
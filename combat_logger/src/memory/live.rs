@@ -0,0 +1,299 @@
+use std::cell::OnceCell;
+use super::{
+    read_maps, AccessFailure, AddressRegion, Error, MemoryRegion, MemorySource,
+    ProcessIdentity, Result,
+};
+
+/// Most iovecs the kernel accepts in a single `process_vm_readv` call
+const IOV_MAX: usize = libc::UIO_MAXIOV as usize;
+
+/// Memory of a running process, accessed through `process_vm_readv` and
+/// `process_vm_writev` without attaching with ptrace
+///
+/// When the start time is known, every write and every batch first checks
+/// that the pid still belongs to the same process. Single reads skip the
+/// check to stay cheap, so pollers call `verify_identity` once per poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiveMemory {
+    /// Process identifier
+    pub process_id: i32,
+
+    /// Start time of the process when known, which tells a reused pid apart
+    /// from the process we attached to
+    pub start_time: Option<u64>,
+}
+
+impl LiveMemory {
+    /// Identity of the process, when its start time is known
+    pub fn identity(&self) -> Option<ProcessIdentity> {
+        self.start_time.map(|start_time| ProcessIdentity {
+            pid: self.process_id,
+            start_time,
+        })
+    }
+
+    /// Checks whether the process is still running
+    pub fn is_alive(&self) -> bool {
+        match self.identity() {
+            Some(identity) => identity.is_alive(),
+            None => ProcessIdentity::of(self.process_id)
+                .is_ok_and(|identity| identity.is_alive()),
+        }
+    }
+
+    /// Reports a failed syscall as an exited process when that is the cause,
+    /// or as a failed read or write with its `errno` otherwise. Must be
+    /// called right after the syscall, before `errno` is overwritten.
+    fn failure(&self, address: usize, write: bool) -> Error {
+        let errno = std::io::Error::last_os_error().raw_os_error()
+            .unwrap_or(0);
+        if errno == libc::ESRCH || !self.is_alive() {
+            return Error::ProcessExited(self.process_id);
+        }
+
+        let failure = Some(self.access_failure(errno, address,
+            &OnceCell::new()));
+        if write {
+            Error::WriteMemoryFailed(address, failure)
+        }
+        else {
+            Error::ReadMemoryFailed(address, failure)
+        }
+    }
+
+    /// Details of a failed access, looking the address up in memory maps
+    /// that are read at most once per call
+    fn access_failure(&self, errno: i32, address: usize,
+            regions: &OnceCell<Option<Vec<MemoryRegion>>>)
+            -> Box<AccessFailure> {
+        let regions = regions.get_or_init(|| read_maps(self.process_id).ok());
+        Box::new(AccessFailure {
+            errno,
+            region: AddressRegion::find(regions.as_deref(), address),
+        })
+    }
+
+    /// Reads many requests with as few syscalls as possible
+    ///
+    /// Requests are packed into `process_vm_readv` calls of up to `IOV_MAX`
+    /// iovecs each. The kernel stops a call at the first remote iovec it
+    /// cannot read, so a failing request only costs an extra syscall to
+    /// resume after it, rather than failing the whole batch. Results are
+    /// returned in request order.
+    ///
+    /// With `diagnose`, failures carry their `errno` and region, and any
+    /// error but a bad address checks whether the process exited. Without
+    /// it nothing but the syscalls runs, for callers that expect failures.
+    fn read_vectored(&self, requests: &[(usize, usize)], diagnose: bool)
+            -> Vec<Result<Vec<u8>>> {
+        let mut buffers: Vec<Vec<u8>> = requests.iter()
+            .map(|&(_, len)| vec![0u8; len])
+            .collect();
+        let mut statuses: Vec<Result<()>> = Vec::with_capacity(requests.len());
+        let regions = OnceCell::new();
+
+        while statuses.len() < requests.len() {
+            let next = statuses.len();
+            let batch_end = (next + IOV_MAX).min(requests.len());
+
+            // Setup local/remote IO vectors for every request in the batch
+            let local_iovecs: Vec<libc::iovec> = buffers[next..batch_end]
+                .iter_mut()
+                .map(|buffer| libc::iovec {
+                    iov_base: buffer.as_mut_ptr() as *mut libc::c_void,
+                    iov_len: buffer.len(),
+                })
+                .collect();
+            let remote_iovecs: Vec<libc::iovec> = requests[next..batch_end]
+                .iter()
+                .map(|&(address, len)| libc::iovec {
+                    iov_base: address as *mut libc::c_void,
+                    iov_len: len,
+                })
+                .collect();
+
+            // Perform the read operation using PROCESS_VM_READV syscall
+            let bytes_read = unsafe {
+                libc::process_vm_readv(
+                    self.process_id as libc::pid_t,
+                    local_iovecs.as_ptr(),
+                    local_iovecs.len() as libc::c_ulong,
+                    remote_iovecs.as_ptr(),
+                    remote_iovecs.len() as libc::c_ulong,
+                    0,
+                )
+            };
+
+            let bytes_read = if bytes_read == -1 {
+                let errno = std::io::Error::last_os_error().raw_os_error()
+                    .unwrap_or(0);
+
+                // Anything but a bad address (process gone, no permission)
+                // fails every remaining request the same way
+                if errno != libc::EFAULT {
+                    let exited = errno == libc::ESRCH
+                        || (diagnose && !self.is_alive());
+                    statuses.extend(requests[next..].iter()
+                        .map(|&(address, _)| Err(if exited {
+                            Error::ProcessExited(self.process_id)
+                        }
+                        else {
+                            Error::ReadMemoryFailed(address, diagnose.then(||
+                                self.access_failure(errno, address, &regions)))
+                        })));
+                    break;
+                }
+
+                // Otherwise the first non-empty request could not be read
+                0
+            }
+            else {
+                bytes_read as usize
+            };
+
+            // Mark every request that was read completely
+            let mut remaining = bytes_read;
+            for &(_, len) in &requests[next..batch_end] {
+                if remaining < len {
+                    break;
+                }
+                remaining -= len;
+                statuses.push(Ok(()));
+            }
+
+            // The call stopped inside the next request, which failed on a bad
+            // address
+            let failed = statuses.len();
+            if failed < batch_end {
+                let address = requests[failed].0;
+                statuses.push(Err(if remaining > 0 {
+                    Error::ReadMemoryPartial(address, remaining)
+                }
+                else {
+                    Error::ReadMemoryFailed(address, diagnose.then(||
+                        self.access_failure(libc::EFAULT, address, &regions)))
+                }));
+            }
+        }
+
+        statuses.into_iter()
+            .zip(buffers)
+            .map(|(status, buffer)| status.map(|_| buffer))
+            .collect()
+    }
+}
+
+impl MemorySource for LiveMemory {
+    /// Reads bytes using a single PROCESS_VM_READV call
+    fn read_bytes(&self, address: usize, len: usize) -> Result<Vec<u8>> {
+        // Setup local/remote IO vectors for our buffer and memory that we 
+        // are reading
+        let mut buffer = vec![0u8; len];
+        let local_iovec = libc::iovec {
+            iov_base: buffer.as_mut_ptr() as *mut libc::c_void,
+            iov_len: len,
+        };
+        let remote_iovec = libc::iovec {
+            iov_base: address as *mut libc::c_void,
+            iov_len: len,
+        };
+
+        // Perform the read operation using PROCESS_VM_READV syscall
+        let bytes_read = unsafe {
+            libc::process_vm_readv(
+                self.process_id as libc::pid_t,
+                &local_iovec as *const libc::iovec,
+                1,
+                &remote_iovec as *const libc::iovec,
+                1,
+                0,
+            )
+        };
+
+        // Check the result of the read operation
+        if bytes_read == -1 {
+            Err(self.failure(address, false))
+        }
+        else if bytes_read != len as isize {
+            Err(Error::ReadMemoryPartial(address, bytes_read as usize))
+        }
+        else {
+            Ok(buffer)
+        }
+    }
+
+    /// Writes bytes using a single PROCESS_VM_WRITEV call, once the process
+    /// is known to be the one we attached to
+    fn write_bytes(&self, address: usize, data: &[u8]) -> Result<()> {
+        self.verify_identity()?;
+
+        // Setup local/remote IO vectors for our buffer and memory that we 
+        // are writing to
+        let local_iovec = libc::iovec {
+            iov_base: data.as_ptr() as *mut libc::c_void,
+            iov_len: data.len(),
+        };
+        let remote_iovec = libc::iovec {
+            iov_base: address as *mut libc::c_void,
+            iov_len: data.len(),
+        };
+
+        // Perform the write operation using PROCESS_VM_WRITEV syscall
+        let bytes_written = unsafe {
+            libc::process_vm_writev(
+                self.process_id as libc::pid_t,
+                &local_iovec as *const libc::iovec,
+                1,
+                &remote_iovec as *const libc::iovec,
+                1,
+                0,
+            )
+        };
+
+        // Check the result of the write operation
+        if bytes_written == -1 {
+            Err(self.failure(address, true))
+        }
+        else if bytes_written != data.len() as isize {
+            Err(Error::WriteMemoryPartial(address, bytes_written as usize))
+        }
+        else {
+            Ok(())
+        }
+    }
+
+    /// Reads the mappings from `/proc/<pid>/maps`
+    fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        read_maps(self.process_id)
+    }
+
+    /// Fails with `ProcessExited` once the process we attached to is gone,
+    /// even if its pid now belongs to another process
+    fn verify_identity(&self) -> Result<()> {
+        match self.identity() {
+            Some(identity) if !identity.is_alive() =>
+                Err(Error::ProcessExited(self.process_id)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads many requests with as few syscalls as possible, once the
+    /// process is known to be the one we attached to
+    fn read_batch(&self, requests: &[(usize, usize)])
+            -> Vec<Result<Vec<u8>>> {
+        if !requests.is_empty() && self.verify_identity().is_err() {
+            return requests.iter()
+                .map(|_| Err(Error::ProcessExited(self.process_id)))
+                .collect();
+        }
+
+        self.read_vectored(requests, true)
+    }
+
+    /// Reads many requests like `read_batch`, without checking the identity
+    /// of the process or looking up why a request failed
+    fn probe_batch(&self, requests: &[(usize, usize)])
+            -> Vec<Result<Vec<u8>>> {
+        self.read_vectored(requests, false)
+    }
+}
@@ -0,0 +1,177 @@
+use std::path::Path;
+use std::str::FromStr;
+use super::{Error, MemoryRange, Result};
+
+/// Access permissions of a mapped region
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Permissions {
+    /// Region can be read
+    pub read: bool,
+
+    /// Region can be written
+    pub write: bool,
+
+    /// Region can be executed
+    pub execute: bool,
+
+    /// Region is shared rather than copy-on-write private
+    pub shared: bool,
+}
+
+/// A single mapping from `/proc/<pid>/maps`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryRegion {
+    /// Virtual address range of the mapping
+    pub range: MemoryRange,
+
+    /// Access permissions
+    pub permissions: Permissions,
+
+    /// Offset into the backing file
+    pub offset: u64,
+
+    /// Major and minor number of the backing device
+    pub device: (u32, u32),
+
+    /// Inode of the backing file, zero for anonymous mappings
+    pub inode: u64,
+
+    /// Backing file or pseudo-path such as `[heap]`, if any
+    pub pathname: Option<String>,
+}
+
+impl FromStr for Permissions {
+    type Err = Error;
+
+    /// Parses permissions such as `rw-p`
+    fn from_str(s: &str) -> Result<Self> {
+        let flags = s.as_bytes();
+        if flags.len() != 4 {
+            return Err(Error::ParseStr(format!("Bad permissions '{}'", s)));
+        }
+
+        Ok(Self {
+            read: flags[0] == b'r',
+            write: flags[1] == b'w',
+            execute: flags[2] == b'x',
+            shared: flags[3] == b's',
+        })
+    }
+}
+
+impl std::fmt::Display for Permissions {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}{}{}{}",
+            if self.read { 'r' } else { '-' },
+            if self.write { 'w' } else { '-' },
+            if self.execute { 'x' } else { '-' },
+            if self.shared { 's' } else { 'p' })
+    }
+}
+
+impl FromStr for MemoryRegion {
+    type Err = Error;
+
+    /// Parses a line of `/proc/<pid>/maps`, for example
+    /// `7f12a000-7f12b000 r-xp 00001000 08:02 131 /usr/lib/libc.so.6`
+    fn from_str(line: &str) -> Result<Self> {
+        // The pathname may contain spaces, so split off the fixed columns
+        // one at a time and keep whatever remains
+        let mut rest = line.trim_start();
+        let mut columns = [""; 5];
+        for column in columns.iter_mut() {
+            let (value, remaining) = rest.split_once(' ')
+                .unwrap_or((rest, ""));
+            *column = value;
+            rest = remaining.trim_start();
+        }
+        let [range, permissions, offset, device, inode] = columns;
+
+        let (start, end) = range.split_once('-').ok_or_else(|| {
+            Error::ParseStr(format!("Bad range '{}'", range))
+        })?;
+        let (major, minor) = device.split_once(':').ok_or_else(|| {
+            Error::ParseStr(format!("Bad device '{}'", device))
+        })?;
+        let pathname = rest.trim_end();
+
+        Ok(Self {
+            range: u64::from_str_radix(start, 16)?
+                ..u64::from_str_radix(end, 16)?,
+            permissions: permissions.parse()?,
+            offset: u64::from_str_radix(offset, 16)?,
+            device: (
+                u32::from_str_radix(major, 16)?,
+                u32::from_str_radix(minor, 16)?,
+            ),
+            inode: inode.parse()?,
+            pathname: (!pathname.is_empty()).then(|| pathname.to_string()),
+        })
+    }
+}
+
+impl MemoryRegion {
+    /// Size of the region in bytes
+    pub fn len(&self) -> u64 {
+        self.range.end - self.range.start
+    }
+
+    /// Whether the region is empty
+    pub fn is_empty(&self) -> bool {
+        self.range.start >= self.range.end
+    }
+
+    /// Whether the given address falls inside this region
+    pub fn contains(&self, address: usize) -> bool {
+        self.range.contains(&(address as u64))
+    }
+
+    /// Whether the region can be read
+    pub fn is_readable(&self) -> bool {
+        self.permissions.read
+    }
+
+    /// Whether the region can be written
+    pub fn is_writable(&self) -> bool {
+        self.permissions.write
+    }
+
+    /// Whether the region has no backing file, which includes pseudo-paths
+    /// such as `[heap]` and `[stack]`
+    pub fn is_anonymous(&self) -> bool {
+        self.inode == 0 && self.pathname.as_deref()
+            .is_none_or(|path| path.starts_with('['))
+    }
+
+    /// Whether the region is backed by the given file, matched either by
+    /// its full path or by its file name
+    pub fn is_backed_by(&self, file: &str) -> bool {
+        let path = match self.file_path() {
+            Some(path) => path,
+            None => return false,
+        };
+
+        path == file || Path::new(path).file_name()
+            .is_some_and(|name| name == file)
+    }
+
+    /// Path of the backing file, without the ` (deleted)` marker the kernel
+    /// appends once the file is unlinked
+    pub fn file_path(&self) -> Option<&str> {
+        if self.is_anonymous() {
+            return None;
+        }
+
+        self.pathname.as_deref()
+            .map(|path| path.strip_suffix(" (deleted)").unwrap_or(path))
+    }
+}
+
+/// Reads every mapping of the given process from `/proc/<pid>/maps`
+pub fn read_maps(process_id: i32) -> Result<Vec<MemoryRegion>> {
+    std::fs::read_to_string(format!("/proc/{}/maps", process_id))?
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::parse)
+        .collect()
+}
@@ -0,0 +1,178 @@
+use std::sync::RwLock;
+use super::{
+    Error, MemoryReader, MemoryRegion, MemorySource, Permissions, Result,
+};
+
+/// A buffer mapped into the mock address space
+#[derive(Debug)]
+struct MockRegion {
+    /// Mapping the buffer is exposed as
+    region: MemoryRegion,
+
+    /// Contents of the mapping, exactly as long as its range
+    data: Vec<u8>,
+}
+
+/// In-memory address space built from byte buffers at given addresses
+///
+/// Reads and writes behave like they do against a live process: they may
+/// run across adjacent regions, stop with a partial result at a gap and
+/// respect the read/write permissions of each region.
+#[derive(Debug, Default)]
+pub struct MockMemory {
+    /// Mapped buffers, ordered by address
+    regions: RwLock<Vec<MockRegion>>,
+}
+
+impl MockMemory {
+    /// Create an empty address space
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps an anonymous read/write buffer at the given address
+    pub fn with_buffer(self, address: usize, data: Vec<u8>) -> Self {
+        let permissions = Permissions {
+            read: true,
+            write: true,
+            ..Default::default()
+        };
+        self.with_region(address, data, permissions, None)
+    }
+
+    /// Maps a read-only buffer backed by the given module file
+    pub fn with_module(self, address: usize, data: Vec<u8>, pathname: &str)
+            -> Self {
+        let permissions = Permissions {
+            read: true,
+            ..Default::default()
+        };
+        self.with_region(address, data, permissions, Some(pathname))
+    }
+
+    /// Maps a buffer with the given permissions and backing file
+    pub fn with_region(self, address: usize, data: Vec<u8>,
+            permissions: Permissions, pathname: Option<&str>) -> Self {
+        let region = MemoryRegion {
+            range: address as u64..(address + data.len()) as u64,
+            permissions,
+            offset: 0,
+            device: (0, 0),
+            inode: if pathname.is_some() { 1 } else { 0 },
+            pathname: pathname.map(str::to_string),
+        };
+        self.insert(region, data);
+        self
+    }
+
+    /// Maps a buffer as the given region, as read back from a snapshot
+    pub fn insert(&self, region: MemoryRegion, data: Vec<u8>) {
+        let mut regions = self.regions.write().unwrap();
+        let index = regions
+            .partition_point(|r| r.region.range.start < region.range.start);
+        regions.insert(index, MockRegion { region, data });
+    }
+
+    /// Copies from the mapped buffers into `buffer`, starting at the given
+    /// address and continuing into adjacent regions. Returns the number of
+    /// bytes copied before reaching a gap or a region without permission.
+    fn copy_from(&self, address: usize, buffer: &mut [u8]) -> usize {
+        let regions = self.regions.read().unwrap();
+        let mut copied = 0;
+        for (index, offset, len) in spans(&regions, address, buffer.len(),
+                false) {
+            buffer[copied..copied + len]
+                .copy_from_slice(&regions[index].data[offset..offset + len]);
+            copied += len;
+        }
+
+        copied
+    }
+
+    /// Copies `data` into the mapped buffers, the same way `copy_from` reads
+    /// them, and returns the number of bytes copied
+    fn copy_to(&self, address: usize, data: &[u8]) -> usize {
+        let mut regions = self.regions.write().unwrap();
+        let mut copied = 0;
+        for (index, offset, len) in spans(&regions, address, data.len(),
+                true) {
+            regions[index].data[offset..offset + len]
+                .copy_from_slice(&data[copied..copied + len]);
+            copied += len;
+        }
+
+        copied
+    }
+}
+
+/// Splits an access of `len` bytes at the given address into the part each
+/// region holds, as its index, the offset into its buffer and the length,
+/// stopping at a gap or a region without the permission the access needs
+fn spans(regions: &[MockRegion], address: usize, len: usize, write: bool)
+        -> Vec<(usize, usize, usize)> {
+    let mut spans = vec![];
+    let mut covered = 0;
+
+    while covered < len {
+        let cursor = address + covered;
+        let index = match regions.iter()
+                .position(|r| r.region.contains(cursor)) {
+            Some(index) => index,
+            None => break,
+        };
+
+        let region = &regions[index];
+        let permitted = if write {
+            region.region.is_writable()
+        }
+        else {
+            region.region.is_readable()
+        };
+        if !permitted {
+            break;
+        }
+
+        let offset = cursor - region.region.range.start as usize;
+        let span_len = (len - covered).min(region.data.len() - offset);
+        spans.push((index, offset, span_len));
+        covered += span_len;
+    }
+
+    spans
+}
+
+impl MemoryReader<MockMemory> {
+    /// Maps a buffer as the given region of the mock address space
+    ///
+    /// This sets up what is mapped rather than writing to it, so the write
+    /// policy does not apply.
+    pub fn insert_region(&self, region: MemoryRegion, data: Vec<u8>) {
+        self.source.insert(region, data);
+    }
+}
+
+impl MemorySource for MockMemory {
+    fn read_bytes(&self, address: usize, len: usize) -> Result<Vec<u8>> {
+        let mut buffer = vec![0u8; len];
+        match self.copy_from(address, &mut buffer) {
+            read if read == len => Ok(buffer),
+            0 => Err(Error::ReadMemoryFailed(address, None)),
+            read => Err(Error::ReadMemoryPartial(address, read)),
+        }
+    }
+
+    fn write_bytes(&self, address: usize, data: &[u8]) -> Result<()> {
+        match self.copy_to(address, data) {
+            written if written == data.len() => Ok(()),
+            0 => Err(Error::WriteMemoryFailed(address, None)),
+            written => Err(Error::WriteMemoryPartial(address, written)),
+        }
+    }
+
+    fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        Ok(self.regions.read().unwrap()
+            .iter()
+            .map(|r| r.region.clone())
+            .collect())
+    }
+}
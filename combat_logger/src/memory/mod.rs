@@ -0,0 +1,348 @@
+use std::sync::Mutex;
+
+mod access;
+mod diff;
+mod layout;
+mod lifecycle;
+mod live;
+mod maps;
+mod mock;
+mod pointer;
+mod process;
+mod remote;
+mod scan;
+mod snapshot;
+mod source;
+mod string;
+mod value;
+mod value_scan;
+mod watcher;
+mod write;
+
+pub use access::{AccessFailure, AddressRegion, PtraceAccess};
+pub use combat_logger_derive::RemoteStruct;
+pub use diff::{DiffMode, SnapshotChange};
+pub use layout::{
+    FieldLayout, FieldType, LayoutSet, LayoutValue, StructLayout,
+};
+pub use lifecycle::{
+    AttachEvent, Attacher, AttacherHandle, Attachment, Signature,
+};
+pub use live::LiveMemory;
+pub use maps::{read_maps, MemoryRegion, Permissions};
+pub use mock::MockMemory;
+pub use pointer::PointerChain;
+pub use process::{
+    find_processes, processes, ProcessIdentity, ProcessInfo, ProcessMatcher,
+};
+pub use remote::{RemoteField, RemotePtr};
+pub use scan::Pattern;
+pub use snapshot::{Snapshot, SnapshotEntry, SnapshotMemory, SnapshotWriter};
+pub use source::MemorySource;
+pub use string::StringEncoding;
+pub use value::{Value, ValueType};
+pub use value_scan::{Scannable, ScanCondition, ScanFilter, ValueScanner};
+pub use watcher::{
+    Watch, WatchEvent, WatchTarget, Watcher, WatcherHandle, WatcherStats,
+};
+pub use write::{
+    AllowedRegion, WriteMode, WritePolicy, WriteRecord, WriteTransaction,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    /// Process was not found
+    ProcessNotFound(String),
+
+    /// Process we were attached to has exited
+    ProcessExited(i32),
+
+    /// Module is not mapped in the process
+    ModuleNotFound(String),
+
+    /// Signature was not found in its module
+    SignatureNotFound(String),
+
+    /// Failed to read memory, with the reason when the kernel reported one
+    ReadMemoryFailed(usize, Option<Box<AccessFailure>>),
+
+    /// Read memory but was incomplete
+    ReadMemoryPartial(usize, usize),
+
+    /// Failed to write memory, with the reason when the kernel reported one
+    WriteMemoryFailed(usize, Option<Box<AccessFailure>>),
+
+    /// Wrote memory but was incomplete
+    WriteMemoryPartial(usize, usize),
+
+    /// Write was refused by the write policy
+    WriteDenied(usize, String),
+
+    /// Memory of the process cannot be accessed, with what to change
+    AccessDenied(i32, String),
+
+    /// Pointer chain reached a null pointer at the given level
+    NullPointer { level: usize, address: usize },
+
+    /// Pointer chain could not be read at the given level
+    ChainFailed { level: usize, address: usize, source: Box<Error> },
+
+    /// IO error
+    IOError(std::io::Error),
+
+    /// UTF8 conversion error
+    UTF8Conversion(std::str::Utf8Error),
+
+    /// UTF16 conversion error
+    UTF16Conversion(std::string::FromUtf16Error),
+
+    /// Parse int error
+    ParseInt(std::num::ParseIntError),
+
+    /// Parse str error
+    ParseStr(String),
+
+    /// Invalid or unknown struct layout
+    Layout(String),
+}
+
+/// Implement the formatter for our custom error type
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::ProcessNotFound(e) =>
+                write!(f, "Process '{}' not found", e),
+            Error::ProcessExited(pid) =>
+                write!(f, "Process {} has exited", pid),
+            Error::ModuleNotFound(e) =>
+                write!(f, "Module '{}' not found", e),
+            Error::SignatureNotFound(e) =>
+                write!(f, "Signature '{}' not found", e),
+            Error::ReadMemoryFailed(addr, None) =>
+                write!(f, "Failed to read memory from address 0x{:x}", addr),
+            Error::ReadMemoryFailed(addr, Some(failure)) =>
+                write!(f, "Failed to read memory from address 0x{:x}: {}",
+                    addr, failure),
+            Error::ReadMemoryPartial(addr, bytes) =>
+                write!(f, 
+                    "Partial read: only read {} bytes from address 0x{:x}",
+                    bytes, addr),
+            Error::WriteMemoryFailed(addr, None) =>
+                write!(f, "Failed to write memory at address 0x{:x}", addr),
+            Error::WriteMemoryFailed(addr, Some(failure)) =>
+                write!(f, "Failed to write memory at address 0x{:x}: {}",
+                    addr, failure),
+            Error::WriteMemoryPartial(addr, bytes) =>
+                write!(f,
+                    "Partial write: only wrote {} bytes at address 0x{:x}",
+                    bytes, addr),
+            Error::WriteDenied(addr, reason) =>
+                write!(f, "Write at address 0x{:x} denied: {}", addr, reason),
+            Error::AccessDenied(pid, reason) =>
+                write!(f, "Cannot access memory of process {}: {}", pid,
+                    reason),
+            Error::NullPointer { level, address } =>
+                write!(f,
+                    "Null pointer at level {} read from address 0x{:x}",
+                    level, address),
+            Error::ChainFailed { level, address, source } =>
+                write!(f,
+                    "Pointer chain failed at level {} reading 0x{:x}: {}",
+                    level, address, source),
+            Error::IOError(e) =>
+                write!(f, "IO error: {}", e),
+            Error::UTF8Conversion(e) =>
+                write!(f, "UTF8 conversion error: {}", e),
+            Error::UTF16Conversion(e) =>
+                write!(f, "UTF16 conversion error: {}", e),
+            Error::ParseInt(e) =>
+                write!(f, "Parse int error: {}", e),
+            Error::ParseStr(e) =>
+                write!(f, "Parse str error: {}", e),
+            Error::Layout(e) =>
+                write!(f, "Layout error: {}", e),
+        }
+    }
+}
+
+/// Implement standard error trait and conversion from other error types
+impl std::error::Error for Error {}
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::IOError(err)
+    }
+}
+impl From<std::str::Utf8Error> for Error {
+    fn from(err: std::str::Utf8Error) -> Self {
+        Error::UTF8Conversion(err)
+    }
+}
+impl From<std::string::FromUtf16Error> for Error {
+    fn from(err: std::string::FromUtf16Error) -> Self {
+        Error::UTF16Conversion(err)
+    }
+}
+impl From<std::num::ParseIntError> for Error {
+    fn from(err: std::num::ParseIntError) -> Self {
+        Error::ParseInt(err)
+    }
+}
+impl From<&str> for Error {
+    fn from(err: &str) -> Self {
+        Error::ParseStr(err.to_string())
+    }
+}
+
+/// Custom Result type alias
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Custom memory range type
+pub type MemoryRange = core::ops::Range<u64>;
+
+/// Size of a pointer in the game process
+pub const POINTER_SIZE: usize = 8;
+
+/// Number of bytes read from the process at a time while walking whole
+/// regions, as scans and snapshot captures do
+pub(crate) const READ_CHUNK_SIZE: usize = 1 << 20;
+
+/// Granularity memory is mapped at, and so the smallest unit a read can
+/// fail on
+pub(crate) const PAGE_SIZE: usize = 4096;
+
+pub struct MemoryReader<S: MemorySource = LiveMemory> {
+    /// Backend the memory is accessed through, kept private so that every
+    /// write goes through the write policy
+    source: S,
+
+    /// Rules every write through the reader must pass
+    write_policy: WritePolicy,
+
+    /// Writes recorded in dry-run mode
+    write_log: Mutex<Vec<WriteRecord>>,
+}
+
+impl MemoryReader {
+    /// Create a new memory reader for given process name
+    pub fn new(process_name: &str) -> Result<Self> {
+        Ok(Self::attach(Self::find_newest(process_name)?.identity()))
+    }
+
+    /// Create a new memory reader for a known process identifier, bound to
+    /// the process currently running under it
+    pub fn from_pid(process_id: i32) -> Self {
+        let start_time = ProcessIdentity::of(process_id)
+            .ok()
+            .map(|identity| identity.start_time);
+        Self::with_source(LiveMemory { process_id, start_time })
+    }
+
+    /// Create a new memory reader for an exact process
+    pub fn attach(identity: ProcessIdentity) -> Self {
+        Self::with_source(LiveMemory {
+            process_id: identity.pid,
+            start_time: Some(identity.start_time),
+        })
+    }
+
+    /// Process identifier of the live process
+    pub fn process_id(&self) -> i32 {
+        self.source.process_id
+    }
+
+    /// Identity of the live process, when its start time is known
+    pub fn identity(&self) -> Option<ProcessIdentity> {
+        self.source.identity()
+    }
+
+    /// Checks whether the live process is still running
+    pub fn is_alive(&self) -> bool {
+        self.source.is_alive()
+    }
+
+    /// Find a process by name, preferring the most recently started one
+    /// when several processes share the name
+    pub fn find_process(name: &str) -> Result<i32> {
+        Ok(Self::find_newest(name)?.pid)
+    }
+
+    /// Finds the most recently started process with the given name
+    fn find_newest(name: &str) -> Result<ProcessInfo> {
+        find_processes(&ProcessMatcher::Name(name.to_string()))?
+            .into_iter()
+            .max_by_key(|process| process.start_time)
+            .ok_or_else(|| Error::ProcessNotFound(name.to_string()))
+    }
+}
+
+impl<S: MemorySource> MemoryReader<S> {
+    /// Create a new memory reader over any memory backend
+    ///
+    /// Readers are read-only until a write policy allowing writes is set.
+    pub fn with_source(source: S) -> Self {
+        Self {
+            source,
+            write_policy: WritePolicy::default(),
+            write_log: Mutex::new(vec![]),
+        }
+    }
+
+    /// Checks that the reader still reaches the process it was opened for,
+    /// failing with `ProcessExited` once its pid belongs to another process
+    pub fn verify_identity(&self) -> Result<()> {
+        self.source.verify_identity()
+    }
+
+    /// Lists every mapped region
+    pub fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        self.source.regions()
+    }
+
+    /// Lists the regions backed by the given module, ordered by address
+    pub fn module_regions(&self, module: &str) -> Result<Vec<MemoryRegion>> {
+        let regions: Vec<MemoryRegion> = self.regions()?
+            .into_iter()
+            .filter(|region| region.is_backed_by(module))
+            .collect();
+
+        if regions.is_empty() {
+            return Err(Error::ModuleNotFound(module.to_string()));
+        }
+
+        Ok(regions)
+    }
+
+    /// Finds the base address of a module, such as `Diablo IV.exe` when it
+    /// is mapped under Wine
+    pub fn module_base(&self, module: &str) -> Result<usize> {
+        // The kernel lists mappings in address order, so the first region
+        // of the module is its base
+        Ok(self.module_regions(module)?[0].range.start as usize)
+    }
+
+    /// Reads bytes at the given address for the given length
+    pub fn read_bytes(&self, address: usize, len: usize) -> Result<Vec<u8>> {
+        self.source.read_bytes(address, len)
+    }
+
+    /// Reads many `(address, len)` requests with as few syscalls as the
+    /// backend allows, reporting success for each request separately
+    pub fn read_batch(&self, requests: &[(usize, usize)])
+            -> Vec<Result<Vec<u8>>> {
+        self.source.read_batch(requests)
+    }
+
+    /// Reads bytes where failures are expected, such as while scanning,
+    /// without looking up why a read failed
+    pub fn probe_bytes(&self, address: usize, len: usize) -> Result<Vec<u8>> {
+        self.probe_batch(&[(address, len)]).pop()
+            .unwrap_or(Err(Error::ReadMemoryFailed(address, None)))
+    }
+
+    /// Reads many requests where failures are expected, without checking
+    /// the process identity or looking up why a request failed
+    pub fn probe_batch(&self, requests: &[(usize, usize)])
+            -> Vec<Result<Vec<u8>>> {
+        self.source.probe_batch(requests)
+    }
+}
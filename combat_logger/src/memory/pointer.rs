@@ -0,0 +1,115 @@
+use bytemuck::Pod;
+use super::{Error, MemoryReader, MemorySource, Result};
+
+/// Chain of offsets followed from the base of a module
+///
+/// The value `[[["Diablo IV.exe"+0x1A0]+0x18]+0x40]` is reached with module
+/// `Diablo IV.exe` and offsets `[0x1A0, 0x18, 0x40]`. Every offset but the
+/// last is added to an address that is then dereferenced, the last offset
+/// gives the address of the value itself.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PointerChain {
+    /// Module whose base address starts the chain
+    pub module: String,
+
+    /// Offsets applied at each level
+    pub offsets: Vec<usize>,
+}
+
+impl PointerChain {
+    /// Create a new pointer chain from a module and its offsets
+    pub fn new(module: &str, offsets: &[usize]) -> Self {
+        Self {
+            module: module.to_string(),
+            offsets: offsets.to_vec(),
+        }
+    }
+}
+
+impl std::fmt::Display for PointerChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let levels = self.offsets.len().saturating_sub(1);
+        write!(f, "{}\"{}\"", "[".repeat(levels), self.module)?;
+        for (level, offset) in self.offsets.iter().enumerate() {
+            write!(f, "+0x{:X}", offset)?;
+            if level < levels {
+                write!(f, "]")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: MemorySource> MemoryReader<S> {
+    /// Reads a plain-old-data value from the given address
+    pub fn read<T: Pod>(&self, address: usize) -> Result<T> {
+        let bytes = self.read_bytes(address, std::mem::size_of::<T>())?;
+        Ok(bytemuck::pod_read_unaligned(&bytes))
+    }
+
+    /// Reads a little-endian `u32` from the given address
+    pub fn read_u32(&self, address: usize) -> Result<u32> {
+        self.read(address)
+    }
+
+    /// Reads a little-endian `u64` from the given address
+    pub fn read_u64(&self, address: usize) -> Result<u64> {
+        self.read(address)
+    }
+
+    /// Reads a little-endian `f32` from the given address
+    pub fn read_f32(&self, address: usize) -> Result<f32> {
+        self.read(address)
+    }
+
+    /// Reads a little-endian `f64` from the given address
+    pub fn read_f64(&self, address: usize) -> Result<f64> {
+        self.read(address)
+    }
+
+    /// Reads a 64-bit pointer from the given address
+    pub fn read_ptr(&self, address: usize) -> Result<usize> {
+        Ok(self.read_u64(address)? as usize)
+    }
+
+    /// Follows a pointer chain and returns the address of its final value
+    pub fn resolve_chain(&self, chain: &PointerChain) -> Result<usize> {
+        self.resolve_offsets(self.module_base(&chain.module)?, &chain.offsets)
+    }
+
+    /// Follows offsets from an already known base address, as done by
+    /// `resolve_chain` once the module base has been looked up
+    pub fn resolve_offsets(&self, base: usize, offsets: &[usize])
+            -> Result<usize> {
+        let mut address = base;
+
+        if let Some((last, levels)) = offsets.split_last() {
+            for (level, offset) in levels.iter().enumerate() {
+                // Dereference this level and stop at the first null or
+                // unreadable pointer, while an exited process stays an exit
+                let pointer = address.wrapping_add(*offset);
+                address = match self.read_ptr(pointer) {
+                    Ok(address) => address,
+                    Err(e @ Error::ProcessExited(_)) => return Err(e),
+                    Err(e) => return Err(Error::ChainFailed {
+                        level,
+                        address: pointer,
+                        source: Box::new(e),
+                    }),
+                };
+                if address == 0 {
+                    return Err(Error::NullPointer { level, address: pointer });
+                }
+            }
+            address = address.wrapping_add(*last);
+        }
+
+        Ok(address)
+    }
+
+    /// Follows a pointer chain and reads the value at its end
+    pub fn read_chain<T: Pod>(&self, chain: &PointerChain) -> Result<T> {
+        self.read(self.resolve_chain(chain)?)
+    }
+}
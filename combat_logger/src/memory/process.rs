@@ -0,0 +1,226 @@
+use std::path::{Path, PathBuf};
+use regex::Regex;
+use super::Result;
+
+/// Longest name the kernel keeps in `/proc/<pid>/comm` (TASK_COMM_LEN - 1)
+const COMM_MAX_LEN: usize = 15;
+
+/// Criteria used to select processes while walking `/proc`
+#[derive(Debug, Clone)]
+pub enum ProcessMatcher {
+    /// Exact executable name, compared against `comm`, the `exe` link and
+    /// the first command line argument (Windows paths included for Wine)
+    Name(String),
+
+    /// Regular expression tested against the same names as `Name`
+    Regex(Regex),
+
+    /// Substring of the space-joined command line
+    Cmdline(String),
+}
+
+/// Identity of a process that stays unique when its pid is reused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProcessIdentity {
+    /// Process identifier
+    pub pid: i32,
+
+    /// Start time in clock ticks since boot
+    pub start_time: u64,
+}
+
+/// A process found in `/proc`
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    /// Process identifier
+    pub pid: i32,
+
+    /// Real user identifier of the process owner
+    pub uid: u32,
+
+    /// Start time in clock ticks since boot
+    pub start_time: u64,
+
+    /// Kernel command name, truncated to 15 bytes
+    pub comm: String,
+
+    /// Command line arguments
+    pub cmdline: Vec<String>,
+
+    /// Resolved executable path, if we are allowed to read it
+    pub exe: Option<PathBuf>,
+}
+
+impl ProcessMatcher {
+    /// Checks whether a process satisfies this matcher
+    pub fn matches(&self, process: &ProcessInfo) -> bool {
+        match self {
+            ProcessMatcher::Name(name) => {
+                // `comm` is truncated by the kernel, so a matching prefix
+                // of a long name only counts once the `exe` link or the
+                // first argument confirms the full name
+                if process.comm.len() == COMM_MAX_LEN
+                    && name.len() > COMM_MAX_LEN
+                    && name.starts_with(&process.comm) {
+                    return process.names().skip(1).any(|n| n == name);
+                }
+                process.names().any(|n| n == name)
+            }
+            ProcessMatcher::Regex(re) =>
+                process.names().any(|n| re.is_match(n)),
+            ProcessMatcher::Cmdline(needle) =>
+                process.cmdline.join(" ").contains(needle.as_str()),
+        }
+    }
+}
+
+impl std::fmt::Display for ProcessMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ProcessMatcher::Name(name) => write!(f, "{}", name),
+            ProcessMatcher::Regex(re) => write!(f, "/{}/", re),
+            ProcessMatcher::Cmdline(needle) =>
+                write!(f, "command line containing '{}'", needle),
+        }
+    }
+}
+
+impl ProcessIdentity {
+    /// Reads the identity of a running process
+    pub fn of(pid: i32) -> Result<Self> {
+        let (_, start_time) = read_stat(pid)?;
+        Ok(Self { pid, start_time })
+    }
+
+    /// Checks whether this exact process is still running, which is false
+    /// once it exited (even before its parent reaped it) or when its pid
+    /// now belongs to another process
+    pub fn is_alive(&self) -> bool {
+        match read_stat(self.pid) {
+            Ok((state, start_time)) =>
+                start_time == self.start_time && !matches!(state, 'Z' | 'X'),
+            Err(_) => false,
+        }
+    }
+}
+
+impl std::fmt::Display for ProcessIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}@{}", self.pid, self.start_time)
+    }
+}
+
+impl ProcessInfo {
+    /// Reads process details from `/proc/<pid>`
+    pub fn read(pid: i32) -> Result<Self> {
+        let proc_dir = PathBuf::from(format!("/proc/{}", pid));
+
+        // The command name is newline terminated
+        let comm = std::fs::read_to_string(proc_dir.join("comm"))?
+            .trim_end_matches('\n')
+            .to_string();
+
+        // Arguments are separated and terminated by NUL bytes
+        let cmdline = std::fs::read(proc_dir.join("cmdline"))?
+            .split(|&b| b == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect();
+
+        // The executable link is only readable with ptrace access
+        let exe = std::fs::read_link(proc_dir.join("exe")).ok();
+
+        Ok(Self {
+            pid,
+            uid: Self::read_uid(&proc_dir)?,
+            start_time: read_stat(pid)?.1,
+            comm,
+            cmdline,
+            exe,
+        })
+    }
+
+    /// Identity of the process
+    pub fn identity(&self) -> ProcessIdentity {
+        ProcessIdentity { pid: self.pid, start_time: self.start_time }
+    }
+
+    /// Names this process may be known by: `comm`, the file name of the
+    /// `exe` link and the file name of the first argument
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        let exe_name = self.exe.as_deref()
+            .and_then(Path::file_name)
+            .and_then(|n| n.to_str());
+
+        // Wine passes Windows paths such as `C:\Games\Diablo IV.exe`, so
+        // split on both separators
+        let arg_name = self.cmdline.first()
+            .and_then(|arg| arg.rsplit(['/', '\\']).next());
+
+        std::iter::once(self.comm.as_str())
+            .chain(exe_name)
+            .chain(arg_name)
+    }
+
+    /// Parses the real user id from `/proc/<pid>/status`
+    fn read_uid(proc_dir: &Path) -> Result<u32> {
+        let status = std::fs::read_to_string(proc_dir.join("status"))?;
+        let uid = status.lines()
+            .find_map(|line| line.strip_prefix("Uid:"))
+            .and_then(|ids| ids.split_whitespace().next())
+            .ok_or("Missing Uid in process status")?;
+
+        Ok(uid.parse()?)
+    }
+}
+
+/// Parses the state and start time from `/proc/<pid>/stat`
+fn read_stat(pid: i32) -> Result<(char, u64)> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid))?;
+
+    // The command name may contain spaces and parentheses, so only parse the
+    // fields after the last closing parenthesis, starting at field 3
+    let fields: Vec<&str> = stat.rsplit_once(')')
+        .map(|(_, rest)| rest)
+        .ok_or("Malformed process stat")?
+        .split_whitespace()
+        .collect();
+    let state = fields.first()
+        .and_then(|state| state.chars().next())
+        .ok_or("Missing state in process stat")?;
+    let start_time = fields.get(22 - 3)
+        .ok_or("Missing start time in process stat")?;
+
+    Ok((state, start_time.parse()?))
+}
+
+/// Lists every process currently visible in `/proc`
+pub fn processes() -> Result<Vec<ProcessInfo>> {
+    let mut processes = vec![];
+
+    for entry in std::fs::read_dir("/proc")? {
+        // Only numeric directories are processes
+        let pid = match entry?.file_name().to_str()
+                .and_then(|name| name.parse::<i32>().ok()) {
+            Some(pid) => pid,
+            None => continue,
+        };
+
+        // Processes can exit while we are walking the directory, and one
+        // with unexpected status fields says nothing about the others, so
+        // skip any we cannot read
+        if let Ok(process) = ProcessInfo::read(pid) {
+            processes.push(process);
+        }
+    }
+
+    Ok(processes)
+}
+
+/// Lists every process satisfying the given matcher
+pub fn find_processes(matcher: &ProcessMatcher) -> Result<Vec<ProcessInfo>> {
+    Ok(processes()?
+        .into_iter()
+        .filter(|process| matcher.matches(process))
+        .collect())
+}
@@ -0,0 +1,140 @@
+use std::marker::PhantomData;
+use super::{MemoryReader, MemorySource, Result, POINTER_SIZE};
+
+/// A value with a fixed size and layout in the memory of another process
+///
+/// Implemented for primitives and arrays, and derived for structs with
+/// `#[derive(RemoteStruct)]`, which decodes every field from a single read
+/// of the whole struct.
+pub trait RemoteField: Sized {
+    /// Size of the value in bytes
+    const SIZE: usize;
+
+    /// Decodes the value from exactly `SIZE` little-endian bytes
+    fn decode(bytes: &[u8]) -> Self;
+}
+
+/// Pointer field of a remote struct, followed only when read
+pub struct RemotePtr<T> {
+    /// Address the pointer holds
+    address: usize,
+
+    /// Type of the value pointed to
+    target: PhantomData<fn() -> T>,
+}
+
+/// Implements `RemoteField` for little-endian primitives
+macro_rules! remote_primitive {
+    ($($ty:ty),*) => {
+        $(
+            impl RemoteField for $ty {
+                const SIZE: usize = std::mem::size_of::<$ty>();
+
+                fn decode(bytes: &[u8]) -> Self {
+                    <$ty>::from_le_bytes(bytes.try_into().unwrap())
+                }
+            }
+        )*
+    };
+}
+
+remote_primitive!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl RemoteField for bool {
+    const SIZE: usize = 1;
+
+    fn decode(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+}
+
+impl<T: RemoteField, const N: usize> RemoteField for [T; N] {
+    const SIZE: usize = T::SIZE * N;
+
+    fn decode(bytes: &[u8]) -> Self {
+        std::array::from_fn(|i| T::decode(&bytes[i * T::SIZE..][..T::SIZE]))
+    }
+}
+
+impl<T> RemotePtr<T> {
+    /// Create a pointer to the given address
+    pub fn new(address: usize) -> Self {
+        Self { address, target: PhantomData }
+    }
+
+    /// Decodes a pointer from its 64-bit little-endian bytes
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let address = u64::from_le_bytes(
+            bytes[..POINTER_SIZE].try_into().unwrap());
+        Self::new(address as usize)
+    }
+
+    /// Address the pointer holds
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    /// Whether the pointer is null
+    pub fn is_null(&self) -> bool {
+        self.address == 0
+    }
+}
+
+impl<T: RemoteField> RemotePtr<T> {
+    /// Reads the value pointed to, or `None` for a null pointer
+    pub fn read<S: MemorySource>(&self, reader: &MemoryReader<S>)
+            -> Result<Option<T>> {
+        if self.is_null() {
+            return Ok(None);
+        }
+
+        reader.read_remote(self.address).map(Some)
+    }
+}
+
+// Implemented by hand so that pointers do not require anything of the type
+// they point to
+impl<T> Clone for RemotePtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RemotePtr<T> {}
+
+impl<T> PartialEq for RemotePtr<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.address == other.address
+    }
+}
+
+impl<T> Eq for RemotePtr<T> {}
+
+impl<T> std::fmt::Debug for RemotePtr<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "RemotePtr(0x{:x})", self.address)
+    }
+}
+
+impl<S: MemorySource> MemoryReader<S> {
+    /// Reads a remote value, such as a `#[derive(RemoteStruct)]` struct,
+    /// with a single read of its whole span
+    pub fn read_remote<T: RemoteField>(&self, address: usize) -> Result<T> {
+        let bytes = self.read_bytes(address, T::SIZE)?;
+        Ok(T::decode(&bytes))
+    }
+
+    /// Reads remote values at many addresses in one batch, reporting
+    /// success for each address separately
+    pub fn read_remote_batch<T: RemoteField>(&self, addresses: &[usize])
+            -> Vec<Result<T>> {
+        let requests: Vec<(usize, usize)> = addresses.iter()
+            .map(|&address| (address, T::SIZE))
+            .collect();
+
+        self.read_batch(&requests)
+            .into_iter()
+            .map(|result| result.map(|bytes| T::decode(&bytes)))
+            .collect()
+    }
+}
@@ -0,0 +1,150 @@
+use std::str::FromStr;
+use super::{
+    Error, MemoryRange, MemoryReader, MemoryRegion, MemorySource, Result,
+    READ_CHUNK_SIZE,
+};
+
+/// Byte signature with wildcards, parsed from IDA-style strings such as
+/// `48 8B 05 ?? ?? ?? ?? 48 85 C0`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    /// Expected bytes, `None` matches any byte
+    bytes: Vec<Option<u8>>,
+}
+
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let bytes = s.split_whitespace()
+            .map(|token| match token {
+                "?" | "??" => Ok(None),
+                _ if token.len() == 2 =>
+                    Ok(Some(u8::from_str_radix(token, 16)?)),
+                _ => Err(Error::ParseStr(
+                    format!("Bad pattern byte '{}'", token))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // A pattern of only wildcards would match every address
+        if bytes.iter().all(Option::is_none) {
+            return Err(Error::ParseStr(
+                format!("Pattern '{}' has no fixed bytes", s)));
+        }
+
+        Ok(Self { bytes })
+    }
+}
+
+impl Pattern {
+    /// Number of bytes covered by the pattern
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Whether the pattern is empty
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Checks whether the pattern matches the start of the given data
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.len() >= self.bytes.len() && self.bytes.iter()
+            .zip(data)
+            .all(|(expected, &actual)| expected.is_none_or(|b| b == actual))
+    }
+
+    /// Finds the offset of every match in the given data
+    pub fn find_all<'a>(&'a self, data: &'a [u8])
+            -> impl Iterator<Item = usize> + 'a {
+        // Use the first fixed byte as an anchor to skip most offsets cheaply
+        let (anchor_offset, anchor) = self.bytes.iter()
+            .enumerate()
+            .find_map(|(i, b)| b.map(|b| (i, b)))
+            .unwrap_or((0, 0));
+        let last_start = (data.len() + 1).saturating_sub(self.bytes.len());
+
+        (0..last_start)
+            .filter(move |&i| data[i + anchor_offset] == anchor)
+            .filter(move |&i| self.matches(&data[i..]))
+    }
+}
+
+impl<S: MemorySource> MemoryReader<S> {
+    /// Scans every readable region of the process for a pattern
+    pub fn scan(&self, pattern: &Pattern) -> Result<Vec<usize>> {
+        let regions: Vec<MemoryRegion> = self.regions()?
+            .into_iter()
+            .filter(MemoryRegion::is_readable)
+            .collect();
+
+        self.scan_regions(&regions, pattern)
+    }
+
+    /// Scans the regions of a single module for a pattern
+    pub fn scan_module(&self, module: &str, pattern: &Pattern)
+            -> Result<Vec<usize>> {
+        let regions: Vec<MemoryRegion> = self.module_regions(module)?
+            .into_iter()
+            .filter(MemoryRegion::is_readable)
+            .collect();
+
+        self.scan_regions(&regions, pattern)
+    }
+
+    /// Scans the given regions for a pattern
+    pub fn scan_regions(&self, regions: &[MemoryRegion], pattern: &Pattern)
+            -> Result<Vec<usize>> {
+        let mut matches = vec![];
+        for region in regions {
+            matches.extend(self.scan_range(region.range.clone(), pattern)?);
+        }
+
+        Ok(matches)
+    }
+
+    /// Scans an address range for a pattern, reading it in large chunks
+    pub fn scan_range(&self, range: MemoryRange, pattern: &Pattern)
+            -> Result<Vec<usize>> {
+        self.verify_identity()?;
+        let mut matches = vec![];
+        let end = range.end as usize;
+        let mut start = range.start as usize;
+
+        while start < end {
+            // Read past the chunk by one pattern length so that matches
+            // straddling two chunks are still found
+            let chunk_len = READ_CHUNK_SIZE.min(end - start);
+            let read_len = (chunk_len + pattern.len() - 1).min(end - start);
+
+            // Some readable mappings (guard pages, device memory) still fail
+            // to read, so skip the chunk rather than abort the whole scan
+            if let Ok(chunk) = self.probe_bytes(start, read_len) {
+                // Only report matches starting inside this chunk, the
+                // overlap is scanned again with the next one
+                matches.extend(pattern.find_all(&chunk)
+                    .take_while(|&offset| offset < chunk_len)
+                    .map(|offset| start + offset));
+            }
+
+            start += chunk_len;
+        }
+
+        Ok(matches)
+    }
+
+    /// Resolves a RIP-relative operand to an absolute address
+    ///
+    /// `address` is the start of the instruction, `displacement_offset` the
+    /// position of its 32-bit displacement and `instruction_len` its total
+    /// length, since the displacement is relative to the next instruction.
+    /// For `48 8B 05 ?? ?? ?? ??` these are 3 and 7.
+    pub fn resolve_rip_relative(&self, address: usize,
+            displacement_offset: usize, instruction_len: usize)
+            -> Result<usize> {
+        let displacement: i32 = self.read(address + displacement_offset)?;
+
+        Ok((address + instruction_len)
+            .wrapping_add_signed(displacement as isize))
+    }
+}
@@ -0,0 +1,411 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use super::{
+    Error, MemoryRegion, MemorySource, MockMemory, Permissions, Result,
+};
+
+/// Magic bytes at the start of every snapshot file
+const MAGIC: &[u8; 8] = b"D4SNAP\0\0";
+
+/// Current snapshot format version
+const VERSION: u32 = 1;
+
+/// Size of the header, from the magic bytes to the capture time
+const HEADER_LEN: u64 = MAGIC.len() as u64 + 4 + 8;
+
+/// Smallest size of a region in the index, with an empty pathname
+const INDEX_ENTRY_LEN: u64 = 8 + 8 + 1 + 8 + 4 + 4 + 8 + 4 + 8 + 8;
+
+/// Most memory set aside before decompressing a region, which then grows
+/// as the data actually arrives
+const MAX_PREALLOCATION: u64 = 64 * 1024 * 1024;
+
+// Snapshot files are laid out as
+//
+//   header  magic, version, capture time in unix milliseconds
+//   blobs   zlib-compressed contents of each region, back to back
+//   index   region count, then for each region its mapping and blob extent
+//   footer  file offset of the index
+//
+// so regions can be streamed to disk while capturing and read back one at a
+// time without decompressing the whole file.
+
+/// A region stored in a snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotEntry {
+    /// Mapping the data was captured from
+    pub region: MemoryRegion,
+
+    /// File offset of the compressed data
+    data_offset: u64,
+
+    /// Length of the compressed data
+    data_len: u64,
+}
+
+/// Streams captured regions into a compressed snapshot file
+///
+/// A region is written with `begin_region`, any number of `append_chunk`
+/// calls and `end_region`, and its data is compressed and written out as it
+/// arrives, so a region never has to be held in memory whole.
+pub struct SnapshotWriter {
+    /// Output file
+    file: BufWriter<File>,
+
+    /// Regions written so far
+    entries: Vec<SnapshotEntry>,
+
+    /// Region being written, if any
+    current: Option<OpenRegion>,
+
+    /// Current write position
+    position: u64,
+}
+
+/// A region whose data is still being appended
+struct OpenRegion {
+    /// Mapping the data is captured from, up to its end at most
+    region: MemoryRegion,
+
+    /// File offset of the compressed data
+    data_offset: u64,
+
+    /// Number of bytes appended so far
+    len: u64,
+
+    /// Compresses the appended data, drained to the file after every chunk
+    encoder: ZlibEncoder<Vec<u8>>,
+}
+
+/// An opened snapshot file with its region index
+#[derive(Debug)]
+pub struct Snapshot {
+    /// Capture time in milliseconds since the unix epoch
+    pub captured_at: u64,
+
+    /// Stored regions, ordered by address
+    pub entries: Vec<SnapshotEntry>,
+
+    /// Input file, shared by region reads
+    file: Mutex<BufReader<File>>,
+}
+
+/// Read-only memory backend over the contents of a snapshot
+#[derive(Debug)]
+pub struct SnapshotMemory {
+    /// Capture time in milliseconds since the unix epoch
+    pub captured_at: u64,
+
+    /// Decompressed regions
+    memory: MockMemory,
+}
+
+impl SnapshotWriter {
+    /// Create a new snapshot file, stamped with the current time
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let captured_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&VERSION.to_le_bytes())?;
+        file.write_all(&captured_at.to_le_bytes())?;
+
+        Ok(Self {
+            file,
+            entries: vec![],
+            current: None,
+            position: HEADER_LEN,
+        })
+    }
+
+    /// Compresses and appends the contents of a region
+    pub fn add_region(&mut self, region: &MemoryRegion, data: &[u8])
+            -> Result<()> {
+        if data.len() as u64 != region.len() {
+            return Err(Error::ParseStr(format!(
+                "Region 0x{:x} is {} bytes but got {} bytes of data",
+                region.range.start, region.len(), data.len())));
+        }
+
+        self.begin_region(region.clone())?;
+        self.append_chunk(data)?;
+        self.end_region()?;
+        Ok(())
+    }
+
+    /// Starts a region whose data follows in chunks, ending any region
+    /// still open
+    ///
+    /// The data may stop short of the end of the range, which is then cut
+    /// back to what was appended once the region ends.
+    pub fn begin_region(&mut self, region: MemoryRegion) -> Result<()> {
+        self.end_region()?;
+        self.current = Some(OpenRegion {
+            region,
+            data_offset: self.position,
+            len: 0,
+            encoder: ZlibEncoder::new(vec![], Compression::fast()),
+        });
+        Ok(())
+    }
+
+    /// Compresses the next chunk of the open region and writes it out
+    pub fn append_chunk(&mut self, data: &[u8]) -> Result<()> {
+        let current = self.current.as_mut()
+            .ok_or("No snapshot region to append to")?;
+        if current.len + data.len() as u64 > current.region.len() {
+            return Err(Error::ParseStr(format!(
+                "Region 0x{:x} is {} bytes but got more data",
+                current.region.range.start, current.region.len())));
+        }
+
+        current.encoder.write_all(data)?;
+        current.len += data.len() as u64;
+        let compressed = std::mem::take(current.encoder.get_mut());
+        self.file.write_all(&compressed)?;
+        self.position += compressed.len() as u64;
+        Ok(())
+    }
+
+    /// Ends the open region and returns the number of bytes it holds
+    ///
+    /// A region without any data is left out of the snapshot.
+    pub fn end_region(&mut self) -> Result<u64> {
+        let current = match self.current.take() {
+            Some(current) => current,
+            None => return Ok(0),
+        };
+        if current.len == 0 {
+            return Ok(0);
+        }
+
+        let compressed = current.encoder.finish()?;
+        self.file.write_all(&compressed)?;
+        self.position += compressed.len() as u64;
+
+        let start = current.region.range.start;
+        self.entries.push(SnapshotEntry {
+            region: MemoryRegion {
+                range: start..start + current.len,
+                ..current.region
+            },
+            data_offset: current.data_offset,
+            data_len: self.position - current.data_offset,
+        });
+        Ok(current.len)
+    }
+
+    /// Writes the region index and flushes the file to disk
+    pub fn finish(mut self) -> Result<()> {
+        self.end_region()?;
+        let index_offset = self.position;
+        self.entries.sort_by_key(|entry| entry.region.range.start);
+
+        let file = &mut self.file;
+        file.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        for entry in &self.entries {
+            let region = &entry.region;
+            let pathname = region.pathname.as_deref().unwrap_or("");
+
+            file.write_all(&region.range.start.to_le_bytes())?;
+            file.write_all(&region.range.end.to_le_bytes())?;
+            file.write_all(&[encode_permissions(region.permissions)])?;
+            file.write_all(&region.offset.to_le_bytes())?;
+            file.write_all(&region.device.0.to_le_bytes())?;
+            file.write_all(&region.device.1.to_le_bytes())?;
+            file.write_all(&region.inode.to_le_bytes())?;
+            file.write_all(&(pathname.len() as u32).to_le_bytes())?;
+            file.write_all(pathname.as_bytes())?;
+            file.write_all(&entry.data_offset.to_le_bytes())?;
+            file.write_all(&entry.data_len.to_le_bytes())?;
+        }
+        file.write_all(&index_offset.to_le_bytes())?;
+
+        file.flush()?;
+        file.get_ref().sync_all()?;
+        Ok(())
+    }
+}
+
+impl Snapshot {
+    /// Opens a snapshot file and reads its region index
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+
+        // Check the header
+        let mut magic = [0u8; 8];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err("Not a snapshot file".into());
+        }
+        let version = read_u32(&mut file)?;
+        if version != VERSION {
+            return Err(Error::ParseStr(
+                format!("Unsupported snapshot version {}", version)));
+        }
+        let captured_at = read_u64(&mut file)?;
+
+        // Find the index through the footer. Every size read from here on
+        // is checked against the file, so a corrupt snapshot fails instead
+        // of allocating whatever it claims.
+        let footer = file.seek(SeekFrom::End(-8))?;
+        let index_offset = read_u64(&mut file)?;
+        if index_offset < HEADER_LEN || index_offset > footer {
+            return Err("Corrupt snapshot: index out of bounds".into());
+        }
+        file.seek(SeekFrom::Start(index_offset))?;
+        let mut index_left = footer - index_offset;
+
+        let count = read_u32(&mut file)? as u64;
+        index_left = index_left.checked_sub(4)
+            .filter(|left| count <= left / INDEX_ENTRY_LEN)
+            .ok_or("Corrupt snapshot: too many regions")?;
+        let mut entries = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let start = read_u64(&mut file)?;
+            let end = read_u64(&mut file)?;
+            let mut permissions = [0u8];
+            file.read_exact(&mut permissions)?;
+            let offset = read_u64(&mut file)?;
+            let device = (read_u32(&mut file)?, read_u32(&mut file)?);
+            let inode = read_u64(&mut file)?;
+            let pathname_len = read_u32(&mut file)? as u64;
+            index_left = index_left.checked_sub(INDEX_ENTRY_LEN)
+                .filter(|left| pathname_len <= *left)
+                .ok_or("Corrupt snapshot: pathname out of bounds")?;
+            index_left -= pathname_len;
+            let mut pathname = vec![0u8; pathname_len as usize];
+            file.read_exact(&mut pathname)?;
+            let pathname = String::from_utf8(pathname)
+                .map_err(|e| e.utf8_error())?;
+
+            let data_offset = read_u64(&mut file)?;
+            let data_len = read_u64(&mut file)?;
+            if start > end || data_offset < HEADER_LEN
+                    || data_offset.checked_add(data_len)
+                        .is_none_or(|data_end| data_end > index_offset) {
+                return Err(Error::ParseStr(format!(
+                    "Corrupt snapshot: region 0x{:x} out of bounds", start)));
+            }
+
+            entries.push(SnapshotEntry {
+                region: MemoryRegion {
+                    range: start..end,
+                    permissions: decode_permissions(permissions[0]),
+                    offset,
+                    device,
+                    inode,
+                    pathname: (!pathname.is_empty()).then_some(pathname),
+                },
+                data_offset,
+                data_len,
+            });
+        }
+
+        Ok(Self {
+            captured_at,
+            entries,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Decompresses the contents of the region at the given index
+    pub fn read_region(&self, index: usize) -> Result<Vec<u8>> {
+        let entry = self.entries.get(index)
+            .ok_or("Snapshot region index out of bounds")?;
+
+        let mut compressed = vec![0u8; entry.data_len as usize];
+        {
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start(entry.data_offset))?;
+            file.read_exact(&mut compressed)?;
+        }
+
+        // Never decompress more than the region holds
+        let len = entry.region.len();
+        let mut data = Vec::with_capacity(len.min(MAX_PREALLOCATION) as usize);
+        ZlibDecoder::new(compressed.as_slice())
+            .take(len + 1)
+            .read_to_end(&mut data)?;
+        if data.len() as u64 != entry.region.len() {
+            return Err(Error::ParseStr(format!(
+                "Snapshot region 0x{:x} is truncated",
+                entry.region.range.start)));
+        }
+
+        Ok(data)
+    }
+}
+
+impl SnapshotMemory {
+    /// Loads every region of a snapshot file into memory
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_snapshot(&Snapshot::open(path)?)
+    }
+
+    /// Loads every region of an opened snapshot into memory
+    pub fn from_snapshot(snapshot: &Snapshot) -> Result<Self> {
+        let memory = MockMemory::new();
+        for (index, entry) in snapshot.entries.iter().enumerate() {
+            memory.insert(entry.region.clone(), snapshot.read_region(index)?);
+        }
+
+        Ok(Self {
+            captured_at: snapshot.captured_at,
+            memory,
+        })
+    }
+}
+
+impl MemorySource for SnapshotMemory {
+    fn read_bytes(&self, address: usize, len: usize) -> Result<Vec<u8>> {
+        self.memory.read_bytes(address, len)
+    }
+
+    /// Snapshots are a record of the past, so writing always fails
+    fn write_bytes(&self, address: usize, _data: &[u8]) -> Result<()> {
+        Err(Error::WriteMemoryFailed(address, None))
+    }
+
+    fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        self.memory.regions()
+    }
+}
+
+/// Packs permissions into a single byte
+fn encode_permissions(permissions: Permissions) -> u8 {
+    permissions.read as u8
+        | (permissions.write as u8) << 1
+        | (permissions.execute as u8) << 2
+        | (permissions.shared as u8) << 3
+}
+
+/// Unpacks permissions from a single byte
+fn decode_permissions(bits: u8) -> Permissions {
+    Permissions {
+        read: bits & 1 != 0,
+        write: bits & 2 != 0,
+        execute: bits & 4 != 0,
+        shared: bits & 8 != 0,
+    }
+}
+
+/// Reads a little-endian `u32` from a stream
+fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Reads a little-endian `u64` from a stream
+fn read_u64(reader: &mut impl Read) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
@@ -0,0 +1,40 @@
+use super::{MemoryRegion, Result};
+
+/// Backend providing access to an address space
+///
+/// `MemoryReader` builds every higher level operation (typed reads, pointer
+/// chains, scanning) on top of these primitives, so the same code runs
+/// against a live process, a recorded snapshot or an in-memory mock.
+pub trait MemorySource {
+    /// Reads bytes at the given address for the given length
+    fn read_bytes(&self, address: usize, len: usize) -> Result<Vec<u8>>;
+
+    /// Writes bytes at the given address
+    fn write_bytes(&self, address: usize, data: &[u8]) -> Result<()>;
+
+    /// Lists every mapped region, ordered by address
+    fn regions(&self) -> Result<Vec<MemoryRegion>>;
+
+    /// Checks that the backend still reaches the process it was opened for,
+    /// which always holds for backends that are not a live process
+    fn verify_identity(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Reads many `(address, len)` requests, returning results in request
+    /// order. Backends that can batch reads should override this.
+    fn read_batch(&self, requests: &[(usize, usize)])
+            -> Vec<Result<Vec<u8>>> {
+        requests.iter()
+            .map(|&(address, len)| self.read_bytes(address, len))
+            .collect()
+    }
+
+    /// Reads many requests like `read_batch`, for callers that expect some
+    /// of them to fail and only care whether they did. Backends that spend
+    /// time explaining failures should skip it here.
+    fn probe_batch(&self, requests: &[(usize, usize)])
+            -> Vec<Result<Vec<u8>>> {
+        self.read_batch(requests)
+    }
+}
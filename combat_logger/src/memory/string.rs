@@ -0,0 +1,156 @@
+use super::{
+    Error, MemoryRange, MemoryReader, MemorySource, Result, PAGE_SIZE,
+};
+
+/// Number of bytes read at a time while looking for a terminator
+const STRING_CHUNK_SIZE: usize = 256;
+
+/// Longest sized string that will be read, in bytes, so that a garbage
+/// length does not turn into a huge allocation
+const MAX_SIZED_STRING_LEN: usize = 1 << 20;
+
+/// Encoding of a string stored in process memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StringEncoding {
+    /// UTF-8, one byte per code unit
+    Utf8,
+
+    /// UTF-16 little-endian, as used by Windows, two bytes per code unit
+    Utf16,
+}
+
+impl StringEncoding {
+    /// Size of a code unit in bytes
+    pub fn unit_size(self) -> usize {
+        match self {
+            StringEncoding::Utf8 => 1,
+            StringEncoding::Utf16 => 2,
+        }
+    }
+
+    /// Decodes raw bytes, replacing invalid sequences with U+FFFD when
+    /// `lossy` is set and failing on them otherwise
+    pub fn decode(self, bytes: &[u8], lossy: bool) -> Result<String> {
+        match self {
+            StringEncoding::Utf8 if lossy =>
+                Ok(String::from_utf8_lossy(bytes).into_owned()),
+            StringEncoding::Utf8 =>
+                Ok(std::str::from_utf8(bytes)?.to_string()),
+            StringEncoding::Utf16 => {
+                let units: Vec<u16> = bytes.chunks_exact(2)
+                    .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                    .collect();
+                if lossy {
+                    Ok(String::from_utf16_lossy(&units))
+                }
+                else {
+                    Ok(String::from_utf16(&units)?)
+                }
+            }
+        }
+    }
+}
+
+impl<S: MemorySource> MemoryReader<S> {
+    /// Reads a NUL-terminated UTF-8 string from a process within the given
+    /// range
+    pub fn read_string(&self, range: MemoryRange) -> Result<String> {
+        self.read_terminated(range, StringEncoding::Utf8, false)
+    }
+
+    /// Reads a NUL-terminated UTF-8 string, replacing invalid sequences
+    pub fn read_string_lossy(&self, range: MemoryRange) -> Result<String> {
+        self.read_terminated(range, StringEncoding::Utf8, true)
+    }
+
+    /// Reads a NUL-terminated UTF-16LE string from a process within the given
+    /// range
+    pub fn read_utf16(&self, range: MemoryRange) -> Result<String> {
+        self.read_terminated(range, StringEncoding::Utf16, false)
+    }
+
+    /// Reads a NUL-terminated UTF-16LE string, replacing invalid sequences
+    pub fn read_utf16_lossy(&self, range: MemoryRange) -> Result<String> {
+        self.read_terminated(range, StringEncoding::Utf16, true)
+    }
+
+    /// Reads a string stored as a `(pointer, length)` pair at the given
+    /// address, with a 64-bit pointer followed by a 64-bit length counted in
+    /// code units of the encoding
+    pub fn read_sized_string(&self, address: usize, encoding: StringEncoding)
+            -> Result<String> {
+        let bytes = self.read_sized_bytes(address, encoding)?;
+        encoding.decode(&bytes, false)
+    }
+
+    /// Reads a `(pointer, length)` string, replacing invalid sequences
+    pub fn read_sized_string_lossy(&self, address: usize,
+            encoding: StringEncoding) -> Result<String> {
+        let bytes = self.read_sized_bytes(address, encoding)?;
+        encoding.decode(&bytes, true)
+    }
+
+    /// Reads the raw bytes of a `(pointer, length)` string
+    fn read_sized_bytes(&self, address: usize, encoding: StringEncoding)
+            -> Result<Vec<u8>> {
+        let pointer = self.read_ptr(address)?;
+        let len = self.read_u64(address + 8)? as usize;
+        if len == 0 {
+            return Ok(vec![]);
+        }
+
+        let byte_len = len.checked_mul(encoding.unit_size())
+            .filter(|&byte_len| byte_len <= MAX_SIZED_STRING_LEN)
+            .ok_or_else(|| Error::ParseStr(format!(
+                "String length {} at address 0x{:x} is too large",
+                len, address)))?;
+        if pointer == 0 {
+            return Err(Error::NullPointer { level: 0, address });
+        }
+
+        self.read_bytes(pointer, byte_len)
+    }
+
+    /// Reads a string up to its NUL terminator and decodes it
+    ///
+    /// Chunks never cross a page boundary, so a string ending right before
+    /// an unmapped page is still read.
+    fn read_terminated(&self, range: MemoryRange, encoding: StringEncoding,
+            lossy: bool) -> Result<String> {
+        let unit = encoding.unit_size();
+        let mut buffer = vec![];
+        let mut start = range.start as usize;
+
+        // Only whole code units are part of the string
+        let end = start
+            + (range.end as usize).saturating_sub(start) / unit * unit;
+
+        while start < end {
+            // Read up to the next page boundary at most, in whole code
+            // units, unless a single code unit straddles the boundary
+            let to_page = PAGE_SIZE - start % PAGE_SIZE;
+            let length_to_read = STRING_CHUNK_SIZE.min(end - start)
+                .min(to_page / unit * unit)
+                .max(unit);
+
+            // Read a chunk of memory
+            let chunk = self.read_bytes(start, length_to_read)?;
+
+            // Check if there is a null terminator in the chunk, which must
+            // be a whole zero code unit
+            let terminator = chunk.chunks_exact(unit)
+                .position(|code_unit| code_unit.iter().all(|&b| b == 0));
+            if let Some(end) = terminator {
+                // Null terminator found
+                buffer.extend_from_slice(&chunk[..end * unit]);
+                break;
+            }
+            buffer.extend(chunk);
+
+            // Move to the next block of memory
+            start += length_to_read;
+        }
+
+        encoding.decode(&buffer, lossy)
+    }
+}
@@ -0,0 +1,174 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+use super::{Error, Result};
+
+/// Primitive type of a value stored in process memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+/// A primitive value decoded from process memory
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+impl ValueType {
+    /// Size of the value in bytes
+    pub fn size(self) -> usize {
+        match self {
+            ValueType::U8 => 1,
+            ValueType::U16 => 2,
+            ValueType::U32 | ValueType::I32 | ValueType::F32 => 4,
+            ValueType::U64 | ValueType::I64 | ValueType::F64 => 8,
+        }
+    }
+
+    /// Decodes a little-endian value from the start of the given bytes
+    pub fn decode(self, bytes: &[u8]) -> Option<Value> {
+        let bytes = bytes.get(..self.size())?;
+        Some(match self {
+            ValueType::U8 => Value::U8(bytes[0]),
+            ValueType::U16 => Value::U16(u16::from_le_bytes(
+                bytes.try_into().ok()?)),
+            ValueType::U32 => Value::U32(u32::from_le_bytes(
+                bytes.try_into().ok()?)),
+            ValueType::U64 => Value::U64(u64::from_le_bytes(
+                bytes.try_into().ok()?)),
+            ValueType::I32 => Value::I32(i32::from_le_bytes(
+                bytes.try_into().ok()?)),
+            ValueType::I64 => Value::I64(i64::from_le_bytes(
+                bytes.try_into().ok()?)),
+            ValueType::F32 => Value::F32(f32::from_le_bytes(
+                bytes.try_into().ok()?)),
+            ValueType::F64 => Value::F64(f64::from_le_bytes(
+                bytes.try_into().ok()?)),
+        })
+    }
+}
+
+impl FromStr for ValueType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "u8" => ValueType::U8,
+            "u16" => ValueType::U16,
+            "u32" => ValueType::U32,
+            "u64" => ValueType::U64,
+            "i32" => ValueType::I32,
+            "i64" => ValueType::I64,
+            "f32" => ValueType::F32,
+            "f64" => ValueType::F64,
+            _ => return Err(Error::ParseStr(
+                format!("Unknown value type '{}'", s))),
+        })
+    }
+}
+
+impl std::fmt::Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            ValueType::U8 => "u8",
+            ValueType::U16 => "u16",
+            ValueType::U32 => "u32",
+            ValueType::U64 => "u64",
+            ValueType::I32 => "i32",
+            ValueType::I64 => "i64",
+            ValueType::F32 => "f32",
+            ValueType::F64 => "f64",
+        })
+    }
+}
+
+impl Value {
+    /// Type of the value
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Value::U8(_) => ValueType::U8,
+            Value::U16(_) => ValueType::U16,
+            Value::U32(_) => ValueType::U32,
+            Value::U64(_) => ValueType::U64,
+            Value::I32(_) => ValueType::I32,
+            Value::I64(_) => ValueType::I64,
+            Value::F32(_) => ValueType::F32,
+            Value::F64(_) => ValueType::F64,
+        }
+    }
+
+    /// Little-endian bytes of the value
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Value::U8(v) => vec![*v],
+            Value::U16(v) => v.to_le_bytes().to_vec(),
+            Value::U32(v) => v.to_le_bytes().to_vec(),
+            Value::U64(v) => v.to_le_bytes().to_vec(),
+            Value::I32(v) => v.to_le_bytes().to_vec(),
+            Value::I64(v) => v.to_le_bytes().to_vec(),
+            Value::F32(v) => v.to_le_bytes().to_vec(),
+            Value::F64(v) => v.to_le_bytes().to_vec(),
+        }
+    }
+
+    /// The value widened to a float, for arithmetic across types
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Value::U8(v) => v as f64,
+            Value::U16(v) => v as f64,
+            Value::U32(v) => v as f64,
+            Value::U64(v) => v as f64,
+            Value::I32(v) => v as f64,
+            Value::I64(v) => v as f64,
+            Value::F32(v) => v as f64,
+            Value::F64(v) => v,
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    /// Values of the same type compare numerically, different types do not
+    /// compare at all
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Value::U8(a), Value::U8(b)) => a.partial_cmp(b),
+            (Value::U16(a), Value::U16(b)) => a.partial_cmp(b),
+            (Value::U32(a), Value::U32(b)) => a.partial_cmp(b),
+            (Value::U64(a), Value::U64(b)) => a.partial_cmp(b),
+            (Value::I32(a), Value::I32(b)) => a.partial_cmp(b),
+            (Value::I64(a), Value::I64(b)) => a.partial_cmp(b),
+            (Value::F32(a), Value::F32(b)) => a.partial_cmp(b),
+            (Value::F64(a), Value::F64(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Value::U8(v) => write!(f, "{}", v),
+            Value::U16(v) => write!(f, "{}", v),
+            Value::U32(v) => write!(f, "{}", v),
+            Value::U64(v) => write!(f, "{}", v),
+            Value::I32(v) => write!(f, "{}", v),
+            Value::I64(v) => write!(f, "{}", v),
+            Value::F32(v) => write!(f, "{}", v),
+            Value::F64(v) => write!(f, "{}", v),
+        }
+    }
+}
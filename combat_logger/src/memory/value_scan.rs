@@ -0,0 +1,400 @@
+use bytemuck::Pod;
+use super::{
+    MemoryReader, MemoryRegion, MemorySource, Result, READ_CHUNK_SIZE,
+};
+
+/// Types the value scanner can search for
+pub trait Scannable: Pod + PartialOrd + std::fmt::Debug {
+    /// Whether the type is a floating point type, which is compared with the
+    /// scanner tolerance instead of exactly
+    const FLOAT: bool;
+
+    /// The value widened to a float
+    fn to_f64(self) -> f64;
+}
+
+impl Scannable for i32 {
+    const FLOAT: bool = false;
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Scannable for f32 {
+    const FLOAT: bool = true;
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Scannable for f64 {
+    const FLOAT: bool = true;
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+/// Condition every value must satisfy on the first scan
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScanCondition<T> {
+    /// Value equals the given value
+    Exact(T),
+
+    /// Value lies within the inclusive range
+    Range(T, T),
+}
+
+/// Predicate used to narrow down the candidates on later scans
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScanFilter<T> {
+    /// Value differs from the previous scan
+    Changed,
+
+    /// Value is identical to the previous scan
+    Unchanged,
+
+    /// Value is greater than on the previous scan
+    Increased,
+
+    /// Value is less than on the previous scan
+    Decreased,
+
+    /// Value grew by exactly the given amount
+    IncreasedBy(T),
+
+    /// Value shrank by exactly the given amount
+    DecreasedBy(T),
+
+    /// Value now equals the given value
+    Equals(T),
+
+    /// Value now lies within the inclusive range
+    InRange(T, T),
+}
+
+/// Candidate slots of a region, in whichever form takes less memory
+#[derive(Debug, Clone)]
+enum Slots {
+    /// One bit per aligned slot of the region
+    Dense(Vec<u64>),
+
+    /// Sorted indices of candidate slots
+    Sparse(Vec<usize>),
+}
+
+/// Candidates found inside a single region
+#[derive(Debug, Clone)]
+struct CandidateRegion<T> {
+    /// Address of slot zero
+    base: usize,
+
+    /// End address of the scanned region
+    end: usize,
+
+    /// Candidate slots
+    slots: Slots,
+
+    /// Value of each candidate at the last scan, in slot order
+    values: Vec<T>,
+}
+
+/// Cheat Engine style value scanner that narrows down a set of candidate
+/// addresses over repeated scans
+///
+/// Candidates are kept per region as a bitmap of aligned slots, or as a list
+/// of slot indices once they become sparse, next to the last value seen at
+/// each one. Scanning gigabytes of memory therefore never holds a `Vec` of
+/// every address.
+#[derive(Debug, Clone)]
+pub struct ValueScanner<T: Scannable> {
+    /// Largest difference at which floats still compare equal
+    tolerance: f64,
+
+    /// Distance between scanned addresses
+    alignment: usize,
+
+    /// Regions that still hold candidates
+    regions: Vec<CandidateRegion<T>>,
+}
+
+impl Slots {
+    /// Builds the most compact representation of a candidate bitmap
+    fn compact(bitmap: Vec<u64>) -> Self {
+        let count: usize = bitmap.iter()
+            .map(|word| word.count_ones() as usize)
+            .sum();
+        if count * std::mem::size_of::<usize>() < bitmap.len() * 8 {
+            Slots::Sparse(Self::iter_bitmap(&bitmap).collect())
+        }
+        else {
+            Slots::Dense(bitmap)
+        }
+    }
+
+    /// Iterates the indices of set bits in a bitmap
+    fn iter_bitmap(bitmap: &[u64]) -> impl Iterator<Item = usize> + '_ {
+        bitmap.iter().enumerate().flat_map(|(word_index, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                (word != 0).then(|| {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    word_index * 64 + bit
+                })
+            })
+        })
+    }
+
+    /// Iterates the candidate slot indices in order
+    fn iter(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        match self {
+            Slots::Dense(bitmap) => Box::new(Self::iter_bitmap(bitmap)),
+            Slots::Sparse(indices) => Box::new(indices.iter().copied()),
+        }
+    }
+}
+
+impl<T: Scannable> Default for ValueScanner<T> {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.0,
+            alignment: std::mem::size_of::<T>(),
+            regions: vec![],
+        }
+    }
+}
+
+impl<T: Scannable> ValueScanner<T> {
+    /// Create a new scanner with no candidates, aligned to the value size
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the largest difference at which floats still compare equal
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sets the distance between scanned addresses
+    pub fn alignment(mut self, alignment: usize) -> Self {
+        self.alignment = alignment.max(1);
+        self
+    }
+
+    /// Number of remaining candidates
+    pub fn len(&self) -> usize {
+        self.regions.iter().map(|region| region.values.len()).sum()
+    }
+
+    /// Whether no candidates remain
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Iterates the remaining candidates with their last seen values
+    pub fn candidates(&self) -> impl Iterator<Item = (usize, T)> + '_ {
+        self.regions.iter().flat_map(move |region| {
+            region.slots.iter()
+                .zip(region.values.iter().copied())
+                .map(move |(slot, value)| {
+                    (region.base + slot * self.alignment, value)
+                })
+        })
+    }
+
+    /// Scans the given regions for values satisfying the condition, replacing
+    /// any previous candidates, and returns the number of candidates found
+    pub fn first_scan<S: MemorySource>(&mut self, reader: &MemoryReader<S>,
+            regions: &[MemoryRegion], condition: ScanCondition<T>)
+            -> Result<usize> {
+        let size = std::mem::size_of::<T>();
+        reader.verify_identity()?;
+        self.regions.clear();
+
+        for region in regions {
+            let base = region.range.start as usize;
+            let end = region.range.end as usize;
+            if end - base < size {
+                continue;
+            }
+
+            let slot_count = (end - base - size) / self.alignment + 1;
+            let mut bitmap = vec![0u64; slot_count.div_ceil(64)];
+            let mut values = vec![];
+
+            // Read the region in chunks of whole slots, plus enough bytes to
+            // decode the value in the last slot of each chunk
+            let chunk_slots = (READ_CHUNK_SIZE / self.alignment).max(1);
+            let mut slot = 0;
+            while slot < slot_count {
+                let slots = chunk_slots.min(slot_count - slot);
+                let start = base + slot * self.alignment;
+                let len = (slots - 1) * self.alignment + size;
+
+                // Unreadable chunks simply hold no candidates
+                if let Ok(chunk) = reader.probe_bytes(start, len) {
+                    for i in 0..slots {
+                        let offset = i * self.alignment;
+                        let value: T = bytemuck::pod_read_unaligned(
+                            &chunk[offset..offset + size]);
+                        if self.satisfies(&condition, value) {
+                            let index = slot + i;
+                            bitmap[index / 64] |= 1 << (index % 64);
+                            values.push(value);
+                        }
+                    }
+                }
+                slot += slots;
+            }
+
+            if !values.is_empty() {
+                self.regions.push(CandidateRegion {
+                    base,
+                    end,
+                    slots: Slots::compact(bitmap),
+                    values,
+                });
+            }
+        }
+
+        Ok(self.len())
+    }
+
+    /// Re-reads every candidate and keeps those satisfying the filter,
+    /// returning the number of candidates left
+    pub fn next_scan<S: MemorySource>(&mut self, reader: &MemoryReader<S>,
+            filter: ScanFilter<T>) -> Result<usize> {
+        let alignment = self.alignment;
+        reader.verify_identity()?;
+        let regions = std::mem::take(&mut self.regions);
+
+        for region in regions {
+            let addresses = region.slots.iter()
+                .map(|slot| region.base + slot * alignment);
+            let current = match &region.slots {
+                Slots::Dense(_) =>
+                    Self::read_chunked(reader, addresses, region.end),
+                Slots::Sparse(_) => Self::read_batched(reader, addresses),
+            };
+
+            // Keep the candidates that still satisfy the filter
+            let slot_count = region.slots.iter().last().unwrap_or(0) + 1;
+            let mut bitmap = vec![0u64; slot_count.div_ceil(64)];
+            let mut values = vec![];
+            for ((slot, previous), value) in region.slots.iter()
+                    .zip(region.values.iter().copied())
+                    .zip(current) {
+                let value = match value {
+                    Some(value) => value,
+                    None => continue,
+                };
+                if self.keeps(&filter, previous, value) {
+                    bitmap[slot / 64] |= 1 << (slot % 64);
+                    values.push(value);
+                }
+            }
+
+            if !values.is_empty() {
+                self.regions.push(CandidateRegion {
+                    base: region.base,
+                    end: region.end,
+                    slots: Slots::compact(bitmap),
+                    values,
+                });
+            }
+        }
+
+        Ok(self.len())
+    }
+
+    /// Reads values at ascending addresses below `end` through large
+    /// chunked reads
+    fn read_chunked<S: MemorySource>(reader: &MemoryReader<S>,
+            addresses: impl Iterator<Item = usize>, end: usize)
+            -> Vec<Option<T>> {
+        let size = std::mem::size_of::<T>();
+        let mut chunk: Option<(usize, usize, Option<Vec<u8>>)> = None;
+        let mut values = vec![];
+
+        for address in addresses {
+            // Load the next chunk once the value runs past the current one.
+            // A chunk that failed to read is remembered as such, so its
+            // other candidates are not read again.
+            let in_chunk = chunk.as_ref().is_some_and(|(start, len, _)| {
+                address + size <= start + len
+            });
+            if !in_chunk {
+                let len = READ_CHUNK_SIZE.min(end - address);
+                chunk = Some((address, len,
+                    reader.probe_bytes(address, len).ok()));
+            }
+
+            values.push(chunk.as_ref().and_then(|(start, _, data)| {
+                let offset = address.checked_sub(*start)?;
+                data.as_ref()?.get(offset..offset + size)
+                    .map(bytemuck::pod_read_unaligned)
+            }));
+        }
+
+        values
+    }
+
+    /// Reads values at scattered addresses through batched reads
+    fn read_batched<S: MemorySource>(reader: &MemoryReader<S>,
+            addresses: impl Iterator<Item = usize>) -> Vec<Option<T>> {
+        let size = std::mem::size_of::<T>();
+        let requests: Vec<(usize, usize)> = addresses
+            .map(|address| (address, size))
+            .collect();
+
+        reader.probe_batch(&requests)
+            .into_iter()
+            .map(|result| result.ok()
+                .map(|bytes| bytemuck::pod_read_unaligned(&bytes)))
+            .collect()
+    }
+
+    /// Compares two values, within the tolerance for floats
+    fn approx_eq(&self, a: f64, b: f64) -> bool {
+        if T::FLOAT {
+            (a - b).abs() <= self.tolerance
+        }
+        else {
+            a == b
+        }
+    }
+
+    /// Checks a value against a first scan condition
+    fn satisfies(&self, condition: &ScanCondition<T>, value: T) -> bool {
+        match *condition {
+            ScanCondition::Exact(target) =>
+                self.approx_eq(value.to_f64(), target.to_f64()),
+            ScanCondition::Range(low, high) => low <= value && value <= high,
+        }
+    }
+
+    /// Checks a re-read value against a narrowing filter
+    fn keeps(&self, filter: &ScanFilter<T>, previous: T, value: T) -> bool {
+        let identical = bytemuck::bytes_of(&previous)
+            == bytemuck::bytes_of(&value);
+        let delta = value.to_f64() - previous.to_f64();
+
+        match *filter {
+            ScanFilter::Changed => !identical,
+            ScanFilter::Unchanged => identical,
+            ScanFilter::Increased => value > previous,
+            ScanFilter::Decreased => value < previous,
+            ScanFilter::IncreasedBy(amount) =>
+                self.approx_eq(delta, amount.to_f64()),
+            ScanFilter::DecreasedBy(amount) =>
+                self.approx_eq(-delta, amount.to_f64()),
+            ScanFilter::Equals(target) =>
+                self.approx_eq(value.to_f64(), target.to_f64()),
+            ScanFilter::InRange(low, high) => low <= value && value <= high,
+        }
+    }
+}
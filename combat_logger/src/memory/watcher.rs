@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use super::{MemoryReader, MemorySource, PointerChain, Value, ValueType};
+
+/// Location of a watched value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchTarget {
+    /// Fixed address
+    Address(usize),
+
+    /// Pointer chain, resolved again on every poll
+    Chain(PointerChain),
+}
+
+/// A value registered with a watcher
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watch {
+    /// Name reported in change events
+    pub name: String,
+
+    /// Where the value lives
+    pub target: WatchTarget,
+
+    /// Type the value is decoded as
+    pub value_type: ValueType,
+
+    /// Time between polls
+    pub interval: Duration,
+}
+
+/// A watched value that changed between two polls
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchEvent {
+    /// Time of the poll that saw the change
+    pub timestamp: Instant,
+
+    /// Name of the watch
+    pub name: String,
+
+    /// Previous value, or `None` for the first value read
+    pub old: Option<Value>,
+
+    /// Current value
+    pub new: Value,
+}
+
+/// Counters describing how well the watcher keeps up with its schedule
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WatcherStats {
+    /// Number of polls of individual watches
+    pub polls: u64,
+
+    /// Number of scheduled polls skipped because the watcher fell behind
+    pub missed_ticks: u64,
+
+    /// Number of polls whose value could not be read
+    pub read_errors: u64,
+
+    /// Number of change events sent
+    pub events: u64,
+
+    /// Largest delay between a scheduled tick and its poll
+    pub max_lateness: Duration,
+}
+
+/// Polls named values on a background thread and reports their changes
+///
+/// Every watch is polled on its own fixed schedule of `start + n * interval`,
+/// so timing does not drift with the time spent reading. Ticks that have
+/// already passed when the watcher catches up are skipped and counted in
+/// `WatcherStats::missed_ticks` instead of being polled in a burst.
+pub struct Watcher<S: MemorySource = super::LiveMemory> {
+    /// Reader the values are polled through
+    reader: MemoryReader<S>,
+
+    /// Registered watches
+    watches: Vec<Watch>,
+}
+
+/// Handle to a running watcher, which stops the thread when dropped
+pub struct WatcherHandle {
+    /// Channel used to wake up and stop the thread
+    stop: Option<mpsc::Sender<()>>,
+
+    /// Polling thread
+    thread: Option<JoinHandle<()>>,
+
+    /// Counters shared with the thread
+    stats: Arc<Mutex<WatcherStats>>,
+}
+
+/// Schedule and last value of a watch while running
+struct WatchState {
+    /// Registered watch
+    watch: Watch,
+
+    /// Index of the next tick on the schedule
+    tick: u64,
+
+    /// Time of the next tick
+    next: Instant,
+
+    /// Last value read
+    value: Option<Value>,
+}
+
+impl<S: MemorySource + Send + 'static> Watcher<S> {
+    /// Create a new watcher with no watches
+    pub fn new(reader: MemoryReader<S>) -> Self {
+        Self { reader, watches: vec![] }
+    }
+
+    /// Registers a value to poll
+    ///
+    /// Panics if the interval is zero.
+    pub fn watch(&mut self, name: &str, target: WatchTarget,
+            value_type: ValueType, interval: Duration) -> &mut Self {
+        assert!(!interval.is_zero(), "Watch interval must not be zero");
+        self.watches.push(Watch {
+            name: name.to_string(),
+            target,
+            value_type,
+            interval,
+        });
+        self
+    }
+
+    /// Registers a value at a fixed address
+    pub fn watch_address(&mut self, name: &str, address: usize,
+            value_type: ValueType, interval: Duration) -> &mut Self {
+        self.watch(name, WatchTarget::Address(address), value_type, interval)
+    }
+
+    /// Registers a value at the end of a pointer chain
+    pub fn watch_chain(&mut self, name: &str, chain: PointerChain,
+            value_type: ValueType, interval: Duration) -> &mut Self {
+        self.watch(name, WatchTarget::Chain(chain), value_type, interval)
+    }
+
+    /// Registered watches
+    pub fn watches(&self) -> &[Watch] {
+        &self.watches
+    }
+
+    /// Starts polling on a background thread, returning a handle to stop it
+    /// and the channel change events arrive on
+    pub fn start(self) -> (WatcherHandle, mpsc::Receiver<WatchEvent>) {
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let (event_tx, event_rx) = mpsc::channel();
+        let stats = Arc::new(Mutex::new(WatcherStats::default()));
+
+        let thread_stats = Arc::clone(&stats);
+        let thread = std::thread::spawn(move || {
+            self.run(stop_rx, event_tx, thread_stats);
+        });
+
+        let handle = WatcherHandle {
+            stop: Some(stop_tx),
+            thread: Some(thread),
+            stats,
+        };
+        (handle, event_rx)
+    }
+
+    /// Polling loop of the background thread
+    fn run(self, stop: mpsc::Receiver<()>, events: mpsc::Sender<WatchEvent>,
+            stats: Arc<Mutex<WatcherStats>>) {
+        let origin = Instant::now();
+        let mut states: Vec<WatchState> = self.watches.into_iter()
+            .map(|watch| WatchState {
+                watch,
+                tick: 0,
+                next: origin,
+                value: None,
+            })
+            .collect();
+        let reader = self.reader;
+
+        // Module bases are only looked up again after a chain fails
+        let mut bases: HashMap<String, usize> = HashMap::new();
+
+        loop {
+            // Sleep until the earliest tick, waking up early to stop
+            let next = states.iter().map(|state| state.next).min();
+            let woken = match next {
+                Some(next) => stop.recv_timeout(
+                    next.saturating_duration_since(Instant::now())),
+                None => stop.recv().map_err(|_| {
+                    mpsc::RecvTimeoutError::Disconnected
+                }),
+            };
+            if !matches!(woken, Err(mpsc::RecvTimeoutError::Timeout)) {
+                return;
+            }
+
+            // Resolve the address of every watch that is due, unless the
+            // pid no longer belongs to the process being watched
+            let now = Instant::now();
+            let due: Vec<usize> = (0..states.len())
+                .filter(|&i| states[i].next <= now)
+                .collect();
+            let attached = due.is_empty() || reader.verify_identity().is_ok();
+            let addresses: Vec<Option<usize>> = due.iter()
+                .map(|&i| attached.then(|| Self::resolve(&reader, &mut bases,
+                    &states[i].watch.target)).flatten())
+                .collect();
+
+            // Read all of them in one batch
+            let requests: Vec<(usize, usize)> = due.iter()
+                .zip(&addresses)
+                .filter_map(|(&i, address)| {
+                    address.map(|address| {
+                        (address, states[i].watch.value_type.size())
+                    })
+                })
+                .collect();
+            let mut results = reader.probe_batch(&requests).into_iter();
+
+            let mut stats = stats.lock().unwrap();
+            for (&i, address) in due.iter().zip(&addresses) {
+                let state = &mut states[i];
+                let value = address
+                    .and_then(|_| results.next())
+                    .and_then(|result| result.ok())
+                    .and_then(|bytes| state.watch.value_type.decode(&bytes));
+
+                stats.polls += 1;
+                stats.max_lateness = stats.max_lateness
+                    .max(now - state.next);
+
+                // Compare bits, so that a NaN float does not count as a
+                // change on every poll
+                match value {
+                    None => stats.read_errors += 1,
+                    Some(new) if state.value.is_none_or(|old| {
+                        old.to_bytes() != new.to_bytes()
+                    }) => {
+                        let event = WatchEvent {
+                            timestamp: now,
+                            name: state.watch.name.clone(),
+                            old: state.value,
+                            new,
+                        };
+                        if events.send(event).is_err() {
+                            return;
+                        }
+                        stats.events += 1;
+                        state.value = Some(new);
+                    }
+                    Some(_) => {}
+                }
+
+                // Move to the next tick that is still ahead, skipping the
+                // ones that passed while polling
+                let interval = state.watch.interval;
+                let elapsed = Instant::now() - origin;
+                let passed = (elapsed.as_nanos() / interval.as_nanos()) as u64;
+                let tick = (state.tick + 1).max(passed + 1);
+                stats.missed_ticks += tick - state.tick - 1;
+                state.tick = tick;
+                state.next = origin + Duration::from_nanos(
+                    (interval.as_nanos() * tick as u128) as u64);
+            }
+        }
+    }
+
+    /// Resolves the address of a watch target, forgetting the cached module
+    /// base when the chain cannot be followed
+    fn resolve(reader: &MemoryReader<S>, bases: &mut HashMap<String, usize>,
+            target: &WatchTarget) -> Option<usize> {
+        match target {
+            WatchTarget::Address(address) => Some(*address),
+            WatchTarget::Chain(chain) => {
+                let base = match bases.get(&chain.module) {
+                    Some(base) => *base,
+                    None => {
+                        let base = reader.module_base(&chain.module).ok()?;
+                        *bases.entry(chain.module.clone()).or_insert(base)
+                    }
+                };
+
+                let address = reader.resolve_offsets(base, &chain.offsets);
+                if address.is_err() {
+                    bases.remove(&chain.module);
+                }
+                address.ok()
+            }
+        }
+    }
+}
+
+impl WatcherHandle {
+    /// Snapshot of the watcher counters
+    pub fn stats(&self) -> WatcherStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// Stops the watcher and waits for its thread to exit
+    pub fn stop(mut self) -> WatcherStats {
+        self.shutdown();
+        self.stats()
+    }
+
+    /// Signals the thread to stop and joins it
+    fn shutdown(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for WatcherHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
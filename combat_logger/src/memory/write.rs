@@ -0,0 +1,238 @@
+use super::{Error, MemoryRange, MemoryReader, MemorySource, Result};
+
+/// Whether writes reach the process
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WriteMode {
+    /// Every write is denied
+    #[default]
+    ReadOnly,
+
+    /// Writes are checked and recorded with the bytes they would replace,
+    /// but never performed
+    DryRun,
+
+    /// Writes that pass the checks are performed
+    Enabled,
+}
+
+/// Region writes may target when an allowlist is set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AllowedRegion {
+    /// Fixed address range
+    Range(MemoryRange),
+
+    /// Every region backed by a module, such as `Diablo IV.exe`
+    Module(String),
+}
+
+/// Rules `MemoryReader::write_bytes` enforces before touching memory
+///
+/// Readers start out read-only. Writes are only meant for testing, and a
+/// single write to the wrong address can crash the game.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WritePolicy {
+    /// Whether writes reach the process
+    pub mode: WriteMode,
+
+    /// Regions writes are restricted to, checked against the current memory
+    /// maps, or `None` to allow writing anywhere
+    pub allowlist: Option<Vec<AllowedRegion>>,
+}
+
+/// A write recorded in dry-run mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteRecord {
+    /// Address of the write
+    pub address: usize,
+
+    /// Bytes that would have been written
+    pub data: Vec<u8>,
+
+    /// Bytes currently at the address, or `None` if they cannot be read
+    pub original: Option<Vec<u8>>,
+}
+
+/// Group of writes that are undone when dropped unless committed
+///
+/// Original bytes are read before every write and restored in reverse
+/// order, so overlapping writes unwind correctly.
+pub struct WriteTransaction<'a, S: MemorySource> {
+    /// Reader the writes go through
+    reader: &'a MemoryReader<S>,
+
+    /// Address and original bytes of every performed write
+    undo: Vec<(usize, Vec<u8>)>,
+}
+
+impl WritePolicy {
+    /// Policy denying every write
+    pub fn read_only() -> Self {
+        Self::default()
+    }
+
+    /// Policy recording writes without performing them
+    pub fn dry_run() -> Self {
+        Self { mode: WriteMode::DryRun, allowlist: None }
+    }
+
+    /// Policy allowing writes anywhere
+    pub fn enabled() -> Self {
+        Self { mode: WriteMode::Enabled, allowlist: None }
+    }
+
+    /// Adds a region to the allowlist, restricting writes to the allowlist
+    pub fn allow(mut self, region: AllowedRegion) -> Self {
+        self.allowlist.get_or_insert_with(Vec::new).push(region);
+        self
+    }
+}
+
+impl<S: MemorySource> MemoryReader<S> {
+    /// Replaces the write policy
+    pub fn with_write_policy(mut self, policy: WritePolicy) -> Self {
+        self.write_policy = policy;
+        self
+    }
+
+    /// Replaces the write policy
+    pub fn set_write_policy(&mut self, policy: WritePolicy) {
+        self.write_policy = policy;
+    }
+
+    /// Current write policy
+    pub fn write_policy(&self) -> &WritePolicy {
+        &self.write_policy
+    }
+
+    /// Writes bytes at the given address, if the write policy allows it
+    pub fn write_bytes(&self, address: usize, data: &[u8]) -> Result<()> {
+        // A read-only reader refuses outright, without reading the maps
+        // the allowlist is checked against
+        let mode = self.write_policy.mode;
+        if mode == WriteMode::ReadOnly {
+            return Err(Error::WriteDenied(address,
+                "reader is read-only".to_string()));
+        }
+        self.check_write(address, data.len())?;
+
+        if mode == WriteMode::DryRun {
+            let original = self.read_bytes(address, data.len()).ok();
+            self.write_log.lock().unwrap().push(WriteRecord {
+                address,
+                data: data.to_vec(),
+                original,
+            });
+            Ok(())
+        }
+        else {
+            self.source.write_bytes(address, data)
+        }
+    }
+
+    /// Takes the writes recorded in dry-run mode so far
+    pub fn take_write_log(&self) -> Vec<WriteRecord> {
+        std::mem::take(&mut self.write_log.lock().unwrap())
+    }
+
+    /// Starts a group of writes that are undone when it is dropped
+    pub fn transaction(&self) -> WriteTransaction<'_, S> {
+        WriteTransaction { reader: self, undo: vec![] }
+    }
+
+    /// Checks a write against the allowlist and the memory maps
+    fn check_write(&self, address: usize, len: usize) -> Result<()> {
+        let allowlist = match &self.write_policy.allowlist {
+            Some(allowlist) => allowlist,
+            None => return Ok(()),
+        };
+        let end = address.checked_add(len)
+            .ok_or_else(|| Error::WriteDenied(address,
+                "write wraps around the address space".to_string()))?;
+        let range = address as u64..end as u64;
+
+        let regions = self.regions()?;
+        let allowed = allowlist.iter().any(|allowed| match allowed {
+            AllowedRegion::Range(allowed) =>
+                allowed.start <= range.start && range.end <= allowed.end,
+            AllowedRegion::Module(module) => covers(regions.iter()
+                .filter(|region| region.is_backed_by(module))
+                .map(|region| &region.range), &range),
+        });
+        if !allowed {
+            return Err(Error::WriteDenied(address,
+                "outside of the allowed regions".to_string()));
+        }
+
+        // The allowlist may be stale, so also require the current maps to
+        // have the whole range mapped writable
+        let writable = covers(regions.iter()
+            .filter(|region| region.is_writable())
+            .map(|region| &region.range), &range);
+        if !writable {
+            return Err(Error::WriteDenied(address,
+                "not mapped writable".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks whether address-ordered ranges cover the whole target range
+fn covers<'a>(ranges: impl Iterator<Item = &'a MemoryRange>,
+        target: &MemoryRange) -> bool {
+    let mut covered = target.start;
+    for range in ranges {
+        if range.start <= covered && covered < range.end {
+            covered = range.end;
+        }
+        if covered >= target.end {
+            return true;
+        }
+    }
+
+    target.is_empty()
+}
+
+impl<S: MemorySource> WriteTransaction<'_, S> {
+    /// Writes bytes after saving the bytes they replace
+    pub fn write_bytes(&mut self, address: usize, data: &[u8]) -> Result<()> {
+        let original = self.reader.read_bytes(address, data.len())?;
+        self.reader.write_bytes(address, data)?;
+
+        // Dry runs change nothing, so there is nothing to restore
+        if self.reader.write_policy.mode == WriteMode::Enabled {
+            self.undo.push((address, original));
+        }
+
+        Ok(())
+    }
+
+    /// Keeps every write made so far
+    pub fn commit(mut self) {
+        self.undo.clear();
+    }
+
+    /// Restores the original bytes now, reporting the first failure
+    pub fn rollback(mut self) -> Result<()> {
+        self.restore()
+    }
+
+    /// Restores the original bytes in reverse order
+    fn restore(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        while let Some((address, original)) = self.undo.pop() {
+            let restored = self.reader.write_bytes(address, &original);
+            if result.is_ok() {
+                result = restored;
+            }
+        }
+
+        result
+    }
+}
+
+impl<S: MemorySource> Drop for WriteTransaction<'_, S> {
+    fn drop(&mut self) {
+        let _ = self.restore();
+    }
+}
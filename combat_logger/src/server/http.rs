@@ -0,0 +1,112 @@
+use std::io::{BufRead, Write};
+use std::net::TcpStream;
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::WebSocket;
+use super::{Error, Result};
+
+/// Longest request head accepted, which is far more than overlays send
+const MAX_HEAD: u64 = 16 * 1024;
+
+/// Request line and headers of an HTTP request
+#[derive(Debug, Clone)]
+pub(super) struct Request {
+    /// Method, such as `GET`
+    pub method: String,
+
+    /// Path without the query string
+    pub path: String,
+
+    /// Header names in lower case along with their values
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Reads a request head, leaving any body unread
+    pub fn read<R: BufRead>(reader: R) -> Result<Self> {
+        let mut lines = std::io::Read::take(reader, MAX_HEAD).lines();
+        let bad_request = || Error::BadRequest("Incomplete request".into());
+
+        let line = lines.next().ok_or_else(bad_request)??;
+        let mut parts = line.split_whitespace();
+        let (method, target) = match (parts.next(), parts.next()) {
+            (Some(method), Some(target)) => (method, target),
+            _ => return Err(Error::BadRequest(line.clone())),
+        };
+        let path = target.split('?').next().unwrap_or(target).to_string();
+
+        let mut headers = vec![];
+        loop {
+            let line = lines.next().ok_or_else(bad_request)??;
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_ascii_lowercase(),
+                    value.trim().to_string()));
+            }
+        }
+
+        Ok(Self { method: method.to_string(), path, headers })
+    }
+
+    /// Value of a header, looked up ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Origin of the page that sent the request, which browsers always send
+    /// along with cross-origin and WebSocket requests
+    pub fn origin(&self) -> Option<&str> {
+        self.header("origin")
+    }
+
+    /// Whether the request asks to switch to the WebSocket protocol
+    pub fn is_websocket(&self) -> bool {
+        self.header("upgrade")
+            .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+    }
+}
+
+/// Writes a complete response and closes the connection
+///
+/// Only the origin given, which was checked against the allowed ones, may
+/// read the response from a browser.
+pub(super) fn write_response<W: Write>(mut writer: W, status: u16,
+        origin: Option<&str>, content_type: &str, body: &[u8])
+        -> std::io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    write!(writer, "HTTP/1.1 {} {}\r\n\
+        Content-Type: {}\r\n\
+        Content-Length: {}\r\n", status, reason, content_type, body.len())?;
+    if let Some(origin) = origin {
+        write!(writer, "Access-Control-Allow-Origin: {}\r\n\
+            Vary: Origin\r\n", origin)?;
+    }
+    write!(writer, "Cache-Control: no-store\r\n\
+        Connection: close\r\n\r\n")?;
+    writer.write_all(body)?;
+    writer.flush()
+}
+
+/// Completes the WebSocket handshake of an upgrade request
+pub(super) fn accept_websocket(mut stream: TcpStream, request: &Request)
+        -> Result<WebSocket<TcpStream>> {
+    let key = request.header("sec-websocket-key")
+        .ok_or_else(|| Error::BadRequest("Missing WebSocket key".into()))?;
+    write!(stream, "HTTP/1.1 101 Switching Protocols\r\n\
+        Upgrade: websocket\r\n\
+        Connection: Upgrade\r\n\
+        Sec-WebSocket-Accept: {}\r\n\r\n", derive_accept_key(key.as_bytes()))?;
+    stream.flush()?;
+    Ok(WebSocket::from_raw_socket(stream, Role::Server, None))
+}
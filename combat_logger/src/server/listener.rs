@@ -0,0 +1,418 @@
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use tungstenite::Message;
+use crate::logger::CombatEvent;
+use super::http::{accept_websocket, write_response, Request};
+use super::{Error, LiveMeter, MeterSnapshot, Result};
+
+/// Address served by default, which only accepts connections from this
+/// machine
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+
+/// Time between meter snapshots sent to WebSocket clients by default
+pub const DEFAULT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Longest wait for new connections and events before checking for a stop
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Longest wait for a client to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest wait for a WebSocket client to take a message off the socket
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Most messages queued for a WebSocket client, past which it is dropped
+/// as too slow rather than queueing every event in memory
+const CLIENT_QUEUE: usize = 4096;
+
+/// Most connections served at once, past which new ones are closed rather
+/// than each getting a thread
+pub const MAX_CONNECTIONS: usize = 64;
+
+/// Message sent to WebSocket clients, as JSON tagged with its `type`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamMessage {
+    /// Combat event, as soon as it arrives
+    Event(CombatEvent),
+
+    /// State of the meters, at every snapshot interval
+    Snapshot(MeterSnapshot),
+}
+
+/// Counters describing a running server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ServerStats {
+    /// Number of events received from the source
+    pub events: u64,
+
+    /// Number of snapshots sent out
+    pub snapshots: u64,
+
+    /// Number of well-formed HTTP requests, including WebSocket upgrades
+    pub requests: u64,
+
+    /// Number of connections currently being served, including WebSocket
+    /// clients
+    pub connections: usize,
+
+    /// Number of WebSocket clients currently connected
+    pub clients: usize,
+
+    /// Number of WebSocket clients dropped for falling behind the stream
+    pub dropped: u64,
+}
+
+/// Serves combat events and meter snapshots to browser overlays
+///
+/// `GET /events` upgrades to a WebSocket that streams every event and a
+/// snapshot of the meters at a fixed interval, starting with a snapshot of
+/// the current state. `GET /stats` returns the latest snapshot, and
+/// `GET /dps`, `GET /buffs` and `GET /defense` the full reports of the
+/// session so far.
+///
+/// Browsers send the origin of the page making a request, and only pages
+/// opened from a file, such as an OBS browser source, and the origins
+/// allowed with [`Server::allow_origin`] are answered. Other programs send
+/// no origin and are always answered.
+pub struct Server {
+    /// Address to listen on
+    address: String,
+
+    /// Meters fed with the events
+    meter: LiveMeter,
+
+    /// Time between snapshots sent to WebSocket clients
+    snapshot_interval: Duration,
+
+    /// Origins of the web pages allowed besides local files
+    origins: Vec<String>,
+}
+
+/// Handle to a running server, which stops it when dropped
+pub struct ServerHandle {
+    /// Address the server listens on
+    address: SocketAddr,
+
+    /// Channel used to wake up and stop the thread
+    stop: Option<mpsc::Sender<()>>,
+
+    /// Server thread
+    thread: Option<JoinHandle<()>>,
+
+    /// State shared with the threads
+    shared: Arc<Shared>,
+}
+
+/// State shared between the server thread and the connection threads
+struct Shared {
+    /// Meters fed with the events
+    meter: Mutex<LiveMeter>,
+
+    /// Bounded channels to the threads of the connected WebSocket clients
+    clients: Mutex<Vec<mpsc::SyncSender<Arc<str>>>>,
+
+    /// Counters of the server
+    stats: Mutex<ServerStats>,
+
+    /// Origins of the web pages allowed besides local files
+    origins: Vec<String>,
+}
+
+impl Server {
+    /// Create a new server for the player with the given actor identifier,
+    /// listening on localhost
+    pub fn new(player: u64) -> Self {
+        Self {
+            address: DEFAULT_ADDRESS.to_string(),
+            meter: LiveMeter::new(player),
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+            origins: vec![],
+        }
+    }
+
+    /// Sets the address to listen on, where port 0 picks a free port
+    pub fn address(mut self, address: &str) -> Self {
+        self.address = address.to_string();
+        self
+    }
+
+    /// Sets the idle time that ends an encounter
+    pub fn idle_gap(mut self, idle_gap: Duration) -> Self {
+        self.meter = self.meter.idle_gap(idle_gap);
+        self
+    }
+
+    /// Sets the time between snapshots sent to WebSocket clients
+    pub fn snapshot_interval(mut self, snapshot_interval: Duration) -> Self {
+        self.snapshot_interval = snapshot_interval;
+        self
+    }
+
+    /// Allows web pages of an origin such as `http://localhost:8080` to
+    /// read from the server
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        self.origins.push(origin.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Starts listening and serving the events of the channel on a
+    /// background thread
+    ///
+    /// The server keeps answering requests after the channel closes, until
+    /// it is stopped.
+    pub fn start(self, events: mpsc::Receiver<CombatEvent>)
+            -> Result<ServerHandle> {
+        let listener = TcpListener::bind(&self.address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+
+        let shared = Arc::new(Shared {
+            meter: Mutex::new(self.meter),
+            clients: Mutex::new(vec![]),
+            stats: Mutex::new(ServerStats::default()),
+            origins: self.origins,
+        });
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let thread = {
+            let shared = shared.clone();
+            let interval = self.snapshot_interval;
+            std::thread::spawn(move || {
+                run(listener, events, stop_rx, shared, interval)
+            })
+        };
+
+        Ok(ServerHandle {
+            address,
+            stop: Some(stop_tx),
+            thread: Some(thread),
+            shared,
+        })
+    }
+}
+
+/// Main loop of the server thread, accepting connections and fanning out
+/// events until stopped
+fn run(listener: TcpListener, events: mpsc::Receiver<CombatEvent>,
+        stop: mpsc::Receiver<()>, shared: Arc<Shared>, interval: Duration) {
+    let mut source_open = true;
+    let mut last_snapshot = Instant::now();
+    loop {
+        while let Ok((stream, _)) = listener.accept() {
+            // Closing the connection right away, as answering it would mean
+            // waiting for its request
+            let mut stats = shared.stats.lock().unwrap();
+            if stats.connections >= MAX_CONNECTIONS {
+                continue;
+            }
+            stats.connections += 1;
+            drop(stats);
+
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                handle_connection(stream, &shared);
+                shared.stats.lock().unwrap().connections -= 1;
+            });
+        }
+
+        // Wait on the events while they flow, then only on the stop signal
+        let stopped = if source_open {
+            match events.recv_timeout(POLL_INTERVAL) {
+                Ok(event) => {
+                    // Holding the meter while broadcasting keeps clients
+                    // from registering in between and counting it twice
+                    let mut meter = shared.meter.lock().unwrap();
+                    meter.push(&event);
+                    shared.stats.lock().unwrap().events += 1;
+                    broadcast(&shared, &StreamMessage::Event(event));
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    source_open = false;
+                }
+            }
+            !matches!(stop.try_recv(), Err(mpsc::TryRecvError::Empty))
+        }
+        else {
+            !matches!(stop.recv_timeout(POLL_INTERVAL),
+                Err(mpsc::RecvTimeoutError::Timeout))
+        };
+        if stopped {
+            break;
+        }
+
+        if last_snapshot.elapsed() >= interval {
+            let meter = shared.meter.lock().unwrap();
+            shared.stats.lock().unwrap().snapshots += 1;
+            broadcast(&shared, &StreamMessage::Snapshot(meter.snapshot()));
+            last_snapshot = Instant::now();
+        }
+    }
+
+    // Closing the channels ends the threads of the WebSocket clients
+    shared.clients.lock().unwrap().clear();
+}
+
+/// Sends a message to every WebSocket client, forgetting the ones that are
+/// gone or whose queue is full
+fn broadcast(shared: &Shared, message: &StreamMessage) {
+    let mut clients = shared.clients.lock().unwrap();
+    if clients.is_empty() {
+        return;
+    }
+
+    let text: Arc<str> = match serde_json::to_string(message) {
+        Ok(text) => text.into(),
+        Err(_) => return,
+    };
+    let mut dropped = 0;
+    clients.retain(|client| match client.try_send(text.clone()) {
+        Ok(()) => true,
+        Err(mpsc::TrySendError::Full(_)) => {
+            dropped += 1;
+            false
+        }
+        Err(mpsc::TrySendError::Disconnected(_)) => false,
+    });
+    if dropped > 0 {
+        shared.stats.lock().unwrap().dropped += dropped;
+    }
+}
+
+/// Answers one connection, replying to bad requests with an error
+fn handle_connection(stream: TcpStream, shared: &Shared) {
+    // Clients that left early or timed out only affect themselves
+    if let Err(Error::BadRequest(message)) = answer(&stream, shared) {
+        let _ = respond_error(&stream, None, 400, &message);
+    }
+}
+
+/// Answers the request of a connection, with a single response or by
+/// turning it into a WebSocket stream
+fn answer(stream: &TcpStream, shared: &Shared) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let request = Request::read(BufReader::new(stream))?;
+    shared.stats.lock().unwrap().requests += 1;
+
+    let origin = request.origin();
+    if origin.is_some_and(|origin| !origin_allowed(origin, &shared.origins)) {
+        return respond_error(stream, None, 403, "Origin not allowed");
+    }
+    if request.method != "GET" {
+        return respond_error(stream, origin, 405, "Only GET is supported");
+    }
+    if request.path == "/events" && request.is_websocket() {
+        return stream_events(stream.try_clone()?, &request, shared);
+    }
+
+    let meter = shared.meter.lock().unwrap();
+    let body = match request.path.as_str() {
+        "/stats" => serde_json::to_vec(&meter.snapshot())?,
+        "/dps" => serde_json::to_vec(&meter.dps_report())?,
+        "/buffs" => serde_json::to_vec(&meter.buff_report())?,
+        "/defense" => serde_json::to_vec(&meter.defense_report())?,
+        _ => {
+            drop(meter);
+            return respond_error(stream, origin, 404, "Not found");
+        }
+    };
+    drop(meter);
+    write_response(stream, 200, origin, "application/json", &body)?;
+    Ok(())
+}
+
+/// Whether a web page of the origin may read from the server, which pages
+/// opened from a file always may
+///
+/// Browsers send `null` as the origin of local files, although some send
+/// `file://` instead.
+fn origin_allowed(origin: &str, allowed: &[String]) -> bool {
+    origin == "null"
+        || origin.starts_with("file://")
+        || allowed.iter().any(|allowed| allowed.eq_ignore_ascii_case(origin))
+}
+
+/// Sends an error response with a JSON body, readable by the origin given
+fn respond_error(stream: &TcpStream, origin: Option<&str>, status: u16,
+        message: &str) -> Result<()> {
+    let body = serde_json::to_vec(&serde_json::json!({ "error": message }))?;
+    write_response(stream, status, origin, "application/json", &body)?;
+    Ok(())
+}
+
+/// Streams messages to a WebSocket client until it leaves, falls behind or
+/// the server stops
+///
+/// A client that stops reading is dropped once its queue fills up, and its
+/// thread gives up once a write times out.
+fn stream_events(stream: TcpStream, request: &Request, shared: &Shared)
+        -> Result<()> {
+    stream.set_read_timeout(None)?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut socket = accept_websocket(stream, request)?;
+
+    // Events are pushed and broadcast under the meter lock, so registering
+    // under it too means every event is either in the first snapshot or
+    // streamed afterwards, never both
+    let (client_tx, client_rx) = mpsc::sync_channel::<Arc<str>>(CLIENT_QUEUE);
+    let snapshot = {
+        let meter = shared.meter.lock().unwrap();
+        shared.clients.lock().unwrap().push(client_tx);
+        meter.snapshot()
+    };
+    shared.stats.lock().unwrap().clients += 1;
+
+    let result = (|| -> Result<()> {
+        let text = serde_json::to_string(&StreamMessage::Snapshot(snapshot))?;
+        socket.send(Message::text(text))?;
+        for text in client_rx {
+            socket.send(Message::text(&*text))?;
+        }
+        socket.close(None)?;
+        socket.flush()?;
+        Ok(())
+    })();
+
+    shared.stats.lock().unwrap().clients -= 1;
+    result
+}
+
+impl ServerHandle {
+    /// Address the server listens on, with the port it picked
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Current counters of the server
+    pub fn stats(&self) -> ServerStats {
+        *self.shared.stats.lock().unwrap()
+    }
+
+    /// Stops the server and waits for its thread to exit, returning the
+    /// final counters
+    pub fn stop(mut self) -> ServerStats {
+        self.shutdown();
+        self.stats()
+    }
+
+    /// Signals the thread to stop and joins it
+    fn shutdown(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
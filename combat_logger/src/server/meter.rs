@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use serde::Serialize;
+use crate::analysis::{
+    BuffReport, BuffTracker, DefenseReport, DefenseTracker, DpsMeter,
+    DpsReport, EncounterDetector,
+};
+use crate::logger::{CombatEvent, EventKind, Timestamp};
+
+/// Buff on the player with the time it has left
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BuffTimer {
+    /// Name of the buff
+    pub buff: String,
+
+    /// Stack count
+    pub stacks: u32,
+
+    /// Seconds until the buff runs out, or `None` if it lasts until removed
+    pub remaining: Option<f64>,
+}
+
+/// State of the meters at one point of the session, as sent to overlays
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MeterSnapshot {
+    /// Time of the latest event
+    pub timestamp: Timestamp,
+
+    /// Number of events received
+    pub events: u64,
+
+    /// Zone the player is in, when known
+    pub zone: Option<String>,
+
+    /// Damage dealt by the player, including what barriers absorbed
+    pub damage: f64,
+
+    /// Damage per second spent in encounters
+    pub dps: f64,
+
+    /// DPS over the rolling window of the current encounter
+    pub rolling_dps: f64,
+
+    /// Number of encounters so far
+    pub encounters: usize,
+
+    /// Damage taken by the player, including what barriers absorbed
+    pub damage_taken: f64,
+
+    /// Number of times the player died
+    pub deaths: u64,
+
+    /// Buffs active on the player, soonest to run out first
+    pub buffs: Vec<BuffTimer>,
+}
+
+/// Runs every meter of a session on events as they arrive
+///
+/// Snapshots only use running totals, so they stay cheap however long the
+/// session gets. The full reports are built on request.
+#[derive(Debug, Clone)]
+pub struct LiveMeter {
+    /// Actor identifier of the player
+    player: u64,
+
+    /// Splits the session into encounters
+    detector: EncounterDetector,
+
+    /// DPS and per-skill damage
+    dps: DpsMeter,
+
+    /// Buff and debuff uptimes
+    buffs: BuffTracker,
+
+    /// Damage taken, healing and deaths
+    defense: DefenseTracker,
+
+    /// Buffs active on the player, with their stacks and expiry time
+    timers: BTreeMap<String, (u32, Option<Timestamp>)>,
+
+    /// Time of the latest event
+    now: Timestamp,
+
+    /// Number of events received
+    events: u64,
+
+    /// Zone the player is in, when known
+    zone: Option<String>,
+
+    /// Damage dealt by the player
+    damage: f64,
+
+    /// Damage taken by the player
+    damage_taken: f64,
+
+    /// Number of times the player died
+    deaths: u64,
+}
+
+impl LiveMeter {
+    /// Create a new meter for the player with the given actor identifier
+    pub fn new(player: u64) -> Self {
+        Self {
+            player,
+            detector: EncounterDetector::new(player),
+            dps: DpsMeter::new(player),
+            buffs: BuffTracker::new(player),
+            defense: DefenseTracker::new(player),
+            timers: BTreeMap::new(),
+            now: Timestamp(0),
+            events: 0,
+            zone: None,
+            damage: 0.0,
+            damage_taken: 0.0,
+            deaths: 0,
+        }
+    }
+
+    /// Sets the idle time that ends an encounter
+    pub fn idle_gap(mut self, idle_gap: Duration) -> Self {
+        self.detector = self.detector.idle_gap(idle_gap);
+        self.dps = self.dps.idle_gap(idle_gap);
+        self.buffs = self.buffs.idle_gap(idle_gap);
+        self
+    }
+
+    /// Feeds the next event of the session
+    pub fn push(&mut self, event: &CombatEvent) {
+        self.detector.push(event);
+        self.dps.push(event);
+        self.buffs.push(event);
+        self.defense.push(event);
+        self.now = self.now.max(event.timestamp);
+        self.events += 1;
+
+        let player = self.player;
+        match &event.kind {
+            EventKind::ZoneChange(change) => {
+                self.zone = Some(change.zone.clone());
+            }
+            EventKind::Damage(damage) => {
+                let amount = damage.amount + damage.absorbed;
+                if damage.source.id == player {
+                    self.damage += amount;
+                }
+                if damage.target.id == player {
+                    self.damage_taken += amount;
+                }
+            }
+            EventKind::BuffApplied(applied) if applied.target.id == player => {
+                let expires = applied.duration.map(|duration| Timestamp(
+                    event.timestamp.0 + (duration.max(0.0) * 1e6) as u64));
+                self.timers.insert(applied.buff.clone(),
+                    (applied.stacks, expires));
+            }
+            EventKind::BuffExpired(expired) if expired.target.id == player => {
+                self.timers.remove(&expired.buff);
+            }
+            EventKind::Kill(kill) if kill.victim.id == player => {
+                self.deaths += 1;
+                self.timers.clear();
+            }
+            _ => {}
+        }
+    }
+
+    /// State of the meters as of the latest event
+    pub fn snapshot(&self) -> MeterSnapshot {
+        let duration: f64 = self.detector.encounters().iter()
+            .map(|encounter| encounter.active_secs())
+            .sum();
+
+        let mut buffs: Vec<BuffTimer> = self.timers.iter()
+            .filter(|(_, (_, expires))| expires.is_none_or(|at| at > self.now))
+            .map(|(buff, &(stacks, expires))| BuffTimer {
+                buff: buff.clone(),
+                stacks,
+                remaining: expires
+                    .map(|at| Timestamp(at.0 - self.now.0).as_secs_f64()),
+            })
+            .collect();
+        buffs.sort_by(|a, b| match (a.remaining, b.remaining) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        });
+
+        MeterSnapshot {
+            timestamp: self.now,
+            events: self.events,
+            zone: self.zone.clone(),
+            damage: self.damage,
+            dps: if duration > 0.0 { self.damage / duration } else { 0.0 },
+            rolling_dps: self.dps.rolling_dps(self.now),
+            encounters: self.detector.encounters().len(),
+            damage_taken: self.damage_taken,
+            deaths: self.deaths,
+            buffs,
+        }
+    }
+
+    /// Full DPS report over every event so far
+    pub fn dps_report(&self) -> DpsReport {
+        self.dps.report()
+    }
+
+    /// Full buff uptime report over every event so far
+    pub fn buff_report(&self) -> BuffReport {
+        self.buffs.report()
+    }
+
+    /// Full damage taken report over every event so far
+    pub fn defense_report(&self) -> DefenseReport {
+        self.defense.report()
+    }
+}
@@ -0,0 +1,61 @@
+mod http;
+mod listener;
+mod meter;
+
+pub use listener::{
+    Server, ServerHandle, ServerStats, StreamMessage, DEFAULT_ADDRESS,
+    DEFAULT_SNAPSHOT_INTERVAL, MAX_CONNECTIONS,
+};
+pub use meter::{BuffTimer, LiveMeter, MeterSnapshot};
+
+#[derive(Debug)]
+pub enum Error {
+    /// Client sent a request that could not be understood
+    BadRequest(String),
+
+    /// WebSocket error, boxed as it is far larger than the others
+    WebSocket(Box<tungstenite::Error>),
+
+    /// JSON error
+    Json(serde_json::Error),
+
+    /// IO error
+    IOError(std::io::Error),
+}
+
+/// Implement the formatter for our custom error type
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::BadRequest(message) =>
+                write!(f, "Bad request: {}", message),
+            Error::WebSocket(e) =>
+                write!(f, "WebSocket error: {}", e),
+            Error::Json(e) =>
+                write!(f, "JSON error: {}", e),
+            Error::IOError(e) =>
+                write!(f, "IO error: {}", e),
+        }
+    }
+}
+
+/// Implement standard error trait and conversion from other error types
+impl std::error::Error for Error {}
+impl From<tungstenite::Error> for Error {
+    fn from(err: tungstenite::Error) -> Self {
+        Error::WebSocket(Box::new(err))
+    }
+}
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::IOError(err)
+    }
+}
+
+/// Custom Result type alias
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,175 @@
+#![allow(dead_code)]
+
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use combat_logger::logger::{
+    Actor, CombatEvent, Damage, DamageType, EventKind, LogFormat, LogHeader,
+    LogWriter, Timestamp,
+};
+
+/// A running instance of `tests/common/test_binary.rs`
+pub struct TestProcess {
+    /// Handle to the child, killed when dropped
+    pub child: std::process::Child,
+
+    /// Process identifier
+    pub process_id: i32,
+
+    /// Start address of the region mapped by the binary
+    pub start_addr: usize,
+
+    /// Size of the mapped region
+    pub size: usize,
+}
+
+impl Drop for TestProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// A child process that is killed when dropped
+pub struct KillOnDrop(pub std::process::Child);
+
+impl KillOnDrop {
+    /// Process identifier
+    pub fn pid(&self) -> i32 {
+        self.0.id() as i32
+    }
+}
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Spawns a `sleep` child with a distinctive duration argument, returning
+/// once it has replaced the forked test image
+pub fn spawn_sleep(duration: &str) -> KillOnDrop {
+    let child = std::process::Command::new("sleep")
+        .arg(duration)
+        .spawn()
+        .expect("Failed to spawn sleep");
+    let cmdline_path = format!("/proc/{}/cmdline", child.id());
+    let expected = format!("sleep\0{}\0", duration);
+
+    // The command line is only filled in once `exec` has completed
+    for _ in 0..100 {
+        match std::fs::read_to_string(&cmdline_path) {
+            Ok(cmdline) if cmdline == expected => break,
+            _ => std::thread::sleep(std::time::Duration::from_millis(10)),
+        }
+    }
+
+    KillOnDrop(child)
+}
+
+/// Compiles the test binary once per test executable
+fn test_binary_path() -> &'static PathBuf {
+    static BINARY: OnceLock<PathBuf> = OnceLock::new();
+    BINARY.get_or_init(|| {
+        let source_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests").join("common").join("test_binary.rs");
+
+        // Each test executable gets its own copy so concurrently running
+        // test files do not race on the same output path
+        let binary_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+            .join(format!("test_binary_{}", std::process::id()));
+        let status = std::process::Command::new("rustc")
+            .arg(&source_path)
+            .arg("-o")
+            .arg(&binary_path)
+            .status()
+            .expect("Failed to compile the test binary");
+        assert!(status.success(), "Failed to compile the test binary");
+
+        binary_path
+    })
+}
+
+/// Runs the test binary and waits for it to report its mapped region
+pub fn run_test_binary() -> TestProcess {
+    run_test_binary_with_args(&[])
+}
+
+/// Runs the test binary with arguments, such as `counter` to make it
+/// increment the first `u32` of its region
+pub fn run_test_binary_with_args(args: &[&str]) -> TestProcess {
+    let mut child = std::process::Command::new(test_binary_path())
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to run the test binary");
+    let process_id = child.id() as i32;
+
+    let output = std::io::BufReader::new(child.stdout.take().unwrap());
+    let mut memory_range = (0, 0);
+    for line in output.lines() {
+        let line = line.expect("Failed to read line from binary stdout");
+        let parts: Vec<&str> = line.split('-')
+            .map(|s| s.trim_start_matches("0x"))
+            .collect();
+        if parts.len() == 2 {
+            if let Ok(start) = usize::from_str_radix(parts[0], 16) {
+                if let Ok(end) = usize::from_str_radix(parts[1], 16) {
+                    memory_range = (start, end);
+                    break;
+                }
+            }
+        }
+    }
+
+    if memory_range.0 == 0 && memory_range.1 == 0 {
+        panic!("Failed to parse memory range from test binary stdout");
+    }
+
+    TestProcess {
+        child,
+        process_id,
+        start_addr: memory_range.0,
+        size: memory_range.1 - memory_range.0,
+    }
+}
+
+/// Converts seconds to a timestamp
+pub fn at(second: f64) -> Timestamp {
+    Timestamp((second * 1e6) as u64)
+}
+
+/// Physical damage of a skill between two actors, with nothing mitigated or
+/// absorbed and none of the hit flags set
+pub fn damage(source: Actor, target: Actor, skill: Option<&str>, amount: f64)
+        -> Damage {
+    Damage {
+        source,
+        target,
+        skill: skill.map(str::to_string),
+        damage_type: DamageType::Physical,
+        amount,
+        mitigated: None,
+        absorbed: 0.0,
+        critical: false,
+        overpower: false,
+        vulnerable: false,
+    }
+}
+
+/// A hit at the given second
+pub fn hit(second: f64, damage: Damage) -> CombatEvent {
+    CombatEvent::new(at(second), EventKind::Damage(damage))
+}
+
+/// Writes a log of the given events
+pub fn write_log(path: &Path, format: LogFormat, header: LogHeader,
+        events: &[CombatEvent]) {
+    let file = std::fs::File::create(path).unwrap();
+    let mut writer = LogWriter::new(file, format, header).unwrap();
+    for event in events {
+        writer.write(event).unwrap();
+    }
+}
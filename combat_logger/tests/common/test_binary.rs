@@ -0,0 +1,63 @@
+use std::ffi::c_void;
+use std::io::{Read, Write};
+
+const MEMORY_SIZE: usize = 4096;
+
+// Linux mmap constants, declared here so the binary builds with plain `rustc`
+// and no external crates
+const PROT_READ: i32 = 0x1;
+const PROT_WRITE: i32 = 0x2;
+const MAP_PRIVATE: i32 = 0x02;
+const MAP_ANONYMOUS: i32 = 0x20;
+const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+extern "C" {
+    fn mmap(addr: *mut c_void, len: usize, prot: i32, flags: i32, fd: i32,
+        offset: i64) -> *mut c_void;
+    fn munmap(addr: *mut c_void, len: usize) -> i32;
+}
+
+fn main() {
+    // Allocate a region of memory using `mmap`
+    let addr = unsafe {
+        mmap(
+            std::ptr::null_mut(),
+            MEMORY_SIZE,
+            PROT_READ | PROT_WRITE,
+            MAP_PRIVATE | MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+
+    // Check for `mmap` failures
+    if addr == MAP_FAILED {
+        eprintln!("mmap failed to allocate memory");
+        std::process::exit(1);
+    }
+
+    // Print the memory address range to stdout
+    println!("{:p}-{:p}", addr, unsafe { addr.add(MEMORY_SIZE) });
+    std::io::stdout().flush().expect("Failed to flush stdout");
+
+    // In counter mode, keep incrementing the first `u32` of the region so
+    // tests can observe memory changing
+    if std::env::args().any(|arg| arg == "counter") {
+        let counter = addr as usize;
+        std::thread::spawn(move || loop {
+            unsafe {
+                let counter = counter as *mut u32;
+                counter.write_volatile(counter.read_volatile() + 1);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        });
+    }
+
+    // Stay alive until the test harness closes our stdin
+    let _ = std::io::stdin().read_to_end(&mut vec![]);
+
+    // Deallocate the memory region before exiting
+    unsafe {
+        munmap(addr, MEMORY_SIZE);
+    }
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use combat_logger::memory::{
+    AddressRegion, Error, MemoryReader, MockMemory, PtraceAccess, WritePolicy,
+};
+use common::{run_test_binary, spawn_sleep};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_failure_unmapped() {
+        let test_process = run_test_binary();
+        let memory_reader = MemoryReader::from_pid(test_process.process_id);
+
+        let result = memory_reader.read_bytes(8, 4);
+        match &result {
+            Err(Error::ReadMemoryFailed(8, Some(failure))) => {
+                assert_eq!(failure.errno, libc::EFAULT);
+                assert_eq!(failure.region, AddressRegion::Unmapped);
+                assert!(!failure.is_permission_denied());
+            }
+            _ => panic!("Unexpected result {:?}", result),
+        }
+        assert!(result.unwrap_err().to_string().contains("not mapped"));
+
+        // Probing reads for scanners skip looking up the region
+        let start = test_process.start_addr;
+        let probed = memory_reader.probe_batch(&[(8, 4), (start, 4)]);
+        assert!(matches!(probed[0], Err(Error::ReadMemoryFailed(8, None))));
+        assert!(probed[1].is_ok());
+    }
+
+    #[test]
+    fn test_write_failure_reports_region() {
+        let test_process = run_test_binary();
+        let memory_reader = MemoryReader::from_pid(test_process.process_id)
+            .with_write_policy(WritePolicy::enabled());
+
+        // The executable itself is mapped without write access
+        let region = memory_reader.regions().unwrap()
+            .into_iter()
+            .find(|region| region.is_readable() && !region.is_writable()
+                && region.pathname.is_some())
+            .unwrap();
+        let address = region.range.start as usize + 0x10;
+
+        let result = memory_reader.write_bytes(address, &[0; 4]);
+        match &result {
+            Err(Error::WriteMemoryFailed(failed, Some(failure))) => {
+                assert_eq!(*failed, address);
+                assert_eq!(failure.errno, libc::EFAULT);
+                assert_eq!(failure.region,
+                    AddressRegion::Mapped(region.clone()));
+            }
+            _ => panic!("Unexpected result {:?}", result),
+        }
+        assert!(result.unwrap_err().to_string()
+            .contains(region.pathname.as_deref().unwrap()));
+    }
+
+    #[test]
+    fn test_batch_failure_reports_errno() {
+        let test_process = run_test_binary();
+        let start = test_process.start_addr;
+        let memory_reader = MemoryReader::from_pid(test_process.process_id);
+
+        let results = memory_reader.read_batch(&[(start, 4), (8, 4)]);
+        assert!(results[0].is_ok());
+        assert!(matches!(&results[1],
+            Err(Error::ReadMemoryFailed(8, Some(failure)))
+                if failure.errno == libc::EFAULT
+                    && failure.region == AddressRegion::Unmapped),
+            "Unexpected result {:?}", results[1]);
+    }
+
+    #[test]
+    fn test_mock_failure_has_no_errno() {
+        let memory_reader = MemoryReader::with_source(MockMemory::new());
+
+        let result = memory_reader.read_bytes(0x1000, 4);
+        assert!(matches!(result, Err(Error::ReadMemoryFailed(0x1000, None))),
+            "Unexpected result {:?}", result);
+    }
+
+    #[test]
+    fn test_check_access() {
+        let test_process = run_test_binary();
+        let memory_reader = MemoryReader::from_pid(test_process.process_id);
+
+        memory_reader.check_access().unwrap();
+    }
+
+    #[test]
+    fn test_check_access_exited() {
+        let mut sleep = spawn_sleep("30.15");
+        let memory_reader = MemoryReader::from_pid(sleep.pid());
+        sleep.0.kill().unwrap();
+        sleep.0.wait().unwrap();
+
+        let result = memory_reader.check_access();
+        assert!(matches!(result, Err(Error::ProcessExited(_))),
+            "Unexpected result {:?}", result);
+    }
+
+    #[test]
+    fn test_ptrace_access_current() {
+        let access = PtraceAccess::current().unwrap();
+        assert_eq!(access.uid, unsafe { libc::geteuid() });
+
+        // The current user can always access its own processes unless Yama
+        // restricts it
+        if access.ptrace_scope.is_none_or(|scope| scope == 0) {
+            assert_eq!(access.explain(access.uid), None);
+        }
+    }
+
+    #[test]
+    fn test_explain() {
+        let classic = PtraceAccess {
+            ptrace_scope: Some(0),
+            cap_sys_ptrace: false,
+            uid: 1000,
+        };
+        assert_eq!(classic.explain(1000), None);
+        let explanation = classic.explain(0).unwrap();
+        assert!(explanation.contains("uid 0"));
+        assert!(explanation.contains("setcap cap_sys_ptrace=eip"));
+
+        // Restricted scope needs the capability, which also allows access
+        // to processes of other users
+        let restricted = PtraceAccess { ptrace_scope: Some(1), ..classic };
+        let explanation = restricted.explain(1000).unwrap();
+        assert!(explanation.contains("ptrace_scope is 1"));
+        assert!(explanation.contains("kernel.yama.ptrace_scope=0"));
+        let privileged = PtraceAccess { cap_sys_ptrace: true, ..restricted };
+        assert_eq!(privileged.explain(0), None);
+
+        let admin_only = PtraceAccess { ptrace_scope: Some(2), ..classic };
+        assert!(admin_only.explain(1000).unwrap()
+            .contains("requires CAP_SYS_PTRACE"));
+
+        // Nothing allows access once attaching is disabled entirely
+        let disabled = PtraceAccess {
+            ptrace_scope: Some(3),
+            cap_sys_ptrace: true,
+            uid: 0,
+        };
+        assert!(disabled.explain(0).unwrap().contains("reboot"));
+
+        let no_yama = PtraceAccess { ptrace_scope: None, ..classic };
+        assert_eq!(no_yama.explain(1000), None);
+    }
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use combat_logger::memory::{Error, MemoryReader, WritePolicy};
+use common::run_test_binary;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_batch() {
+        let test_process = run_test_binary();
+        let start = test_process.start_addr;
+        let end = start + test_process.size;
+        let memory_reader = MemoryReader::from_pid(test_process.process_id)
+            .with_write_policy(WritePolicy::enabled());
+
+        let data: Vec<u8> = (0..=255).cycle().take(test_process.size)
+            .collect();
+        memory_reader.write_bytes(start, &data).unwrap();
+
+        // Mix good requests with ones that are unmapped or run off the end
+        // of the region
+        let requests = [
+            (start, 16),
+            (8, 4),
+            (start + 0x20, 0),
+            (start + 0x40, 8),
+            (end - 4, 8),
+            (start + 0x100, 4),
+        ];
+        let results = memory_reader.read_batch(&requests);
+        assert_eq!(results.len(), requests.len());
+
+        assert_eq!(results[0].as_ref().unwrap(), &data[..16]);
+        assert!(matches!(results[1], Err(Error::ReadMemoryFailed(8, Some(_)))));
+        assert!(results[2].as_ref().unwrap().is_empty());
+        assert_eq!(results[3].as_ref().unwrap(), &data[0x40..0x48]);
+        assert!(matches!(results[4],
+            Err(Error::ReadMemoryPartial(address, 4)) if address == end - 4));
+        assert_eq!(results[5].as_ref().unwrap(), &data[0x100..0x104]);
+    }
+
+    #[test]
+    fn test_read_batch_many() {
+        let test_process = run_test_binary();
+        let start = test_process.start_addr;
+        let memory_reader = MemoryReader::from_pid(test_process.process_id)
+            .with_write_policy(WritePolicy::enabled());
+
+        let data: Vec<u8> = (0..=255).cycle().take(test_process.size)
+            .collect();
+        memory_reader.write_bytes(start, &data).unwrap();
+
+        // More requests than fit in a single syscall
+        let requests: Vec<(usize, usize)> = (0..3000)
+            .map(|i| (start + i % test_process.size, 1))
+            .collect();
+        let results = memory_reader.read_batch(&requests);
+        for (i, result) in results.into_iter().enumerate() {
+            assert_eq!(result.unwrap(), [data[i % test_process.size]]);
+        }
+    }
+
+    #[test]
+    fn test_read_batch_missing_process() {
+        let memory_reader = MemoryReader::from_pid(i32::MAX);
+
+        let results = memory_reader.read_batch(&[(0x1000, 4), (0x2000, 4)]);
+        assert!(results.iter().all(|result| result.is_err()));
+    }
+}
@@ -0,0 +1,199 @@
+mod common;
+
+use combat_logger::analysis::{BuffInterval, BuffTracker};
+use combat_logger::logger::{
+    Actor, BuffApplied, BuffExpired, CombatEvent, EventKind,
+};
+use common::{at, damage};
+
+/// Actor identifier of the player
+const PLAYER: u64 = 1;
+
+/// A hit by the player at the given second
+fn hit(second: f64) -> CombatEvent {
+    common::hit(second, damage(Actor::new(PLAYER, "Player"),
+        Actor::new(2, "Target"), None, 100.0))
+}
+
+/// A buff applied or refreshed at the given second
+fn apply(second: f64, target: u64, buff: &str, stacks: u32,
+        duration: Option<f64>) -> CombatEvent {
+    CombatEvent::new(at(second), EventKind::BuffApplied(BuffApplied {
+        target: Actor::new(target, "Target"),
+        source: Some(Actor::new(PLAYER, "Player")),
+        buff: buff.to_string(),
+        debuff: target != PLAYER,
+        stacks,
+        duration,
+    }))
+}
+
+/// A buff expiring at the given second
+fn expire(second: f64, target: u64, buff: &str) -> CombatEvent {
+    CombatEvent::new(at(second), EventKind::BuffExpired(BuffExpired {
+        target: Actor::new(target, "Target"),
+        buff: buff.to_string(),
+    }))
+}
+
+/// A ten second encounter with buffs on the player and two monsters,
+/// followed by a short encounter
+fn session() -> Vec<CombatEvent> {
+    let mut events = vec![
+        // Runs out after four seconds, but is refreshed before that
+        apply(0.0, PLAYER, "Berserking", 1, Some(4.0)),
+        apply(1.0, 2, "Vulnerable", 1, None),
+        apply(2.0, PLAYER, "Berserking", 1, Some(4.0)),
+        apply(3.0, 2, "Vulnerable", 3, None),
+        apply(4.0, 3, "Vulnerable", 1, None),
+        expire(5.0, 2, "Vulnerable"),
+        expire(8.0, 3, "Vulnerable"),
+        // Carries over into the second encounter
+        apply(12.0, PLAYER, "Unstoppable", 1, None),
+    ];
+    events.extend((0..=10).map(|second| hit(second as f64)));
+    events.extend([hit(20.0), hit(22.0)]);
+    events.sort_by_key(|event| event.timestamp);
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intervals() {
+        let mut tracker = BuffTracker::new(PLAYER);
+        for event in session() {
+            tracker.push(&event);
+        }
+
+        // A refresh splits the interval, and the duration ends the last one
+        assert_eq!(tracker.intervals("Berserking"), [
+            BuffInterval { target: PLAYER, start: at(0.0), end: at(2.0),
+                stacks: 1 },
+            BuffInterval { target: PLAYER, start: at(2.0), end: at(6.0),
+                stacks: 1 },
+        ]);
+
+        let vulnerable = tracker.intervals("Vulnerable");
+        assert_eq!(vulnerable.len(), 3);
+        assert_eq!(vulnerable[1].stacks, 3);
+        assert_eq!(vulnerable[2].target, 3);
+
+        // Buffs still active end at the latest event
+        let unstoppable = tracker.intervals("Unstoppable");
+        assert_eq!(unstoppable[0].end, at(22.0));
+        assert!(tracker.intervals("Shrine").is_empty());
+    }
+
+    #[test]
+    fn test_uptime_report() {
+        let mut tracker = BuffTracker::new(PLAYER)
+            .combination(&["Vulnerable", "Berserking"])
+            .combination(&["Vulnerable", "Unstoppable"]);
+        for event in session() {
+            tracker.push(&event);
+        }
+        let report = tracker.report();
+        assert_eq!(report.encounters.len(), 2);
+
+        let first = &report.encounters[0];
+        let buffs: Vec<&str> = first.buffs.iter()
+            .map(|buff| buff.buff.as_str())
+            .collect();
+        assert_eq!(buffs, ["Berserking", "Vulnerable"]);
+
+        let berserking = &first.buffs[0];
+        assert!(!berserking.debuff);
+        assert_eq!(berserking.uptime, 0.6);
+        assert_eq!(berserking.target_uptime, 0.6);
+        assert_eq!(berserking.average_stacks, 1.0);
+        assert_eq!(berserking.longest_gap, 4.0);
+        assert_eq!(berserking.applications, 2);
+
+        // Uptime counts time on any target, stacks are averaged over all
+        let vulnerable = &first.buffs[1];
+        assert!(vulnerable.debuff);
+        assert_eq!(vulnerable.targets, 2);
+        assert_eq!(vulnerable.uptime, 0.7);
+        assert_eq!(vulnerable.target_uptime, 0.4);
+        assert_eq!(vulnerable.average_stacks, 1.5);
+        assert_eq!(vulnerable.longest_gap, 2.0);
+        assert_eq!(vulnerable.applications, 3);
+
+        assert_eq!(first.combinations[0].buffs, ["Vulnerable", "Berserking"]);
+        assert_eq!(first.combinations[0].uptime, 0.5);
+        assert_eq!(first.combinations[1].uptime, 0.0);
+
+        // A buff applied between encounters covers the whole second one
+        let second = &report.encounters[1];
+        assert_eq!(second.buffs.len(), 1);
+        assert_eq!(second.buffs[0].buff, "Unstoppable");
+        assert_eq!(second.buffs[0].uptime, 1.0);
+        assert_eq!(second.buffs[0].target_uptime, 1.0);
+        assert_eq!(second.buffs[0].applications, 0);
+        assert_eq!(second.buffs[0].longest_gap, 0.0);
+    }
+
+    #[test]
+    fn test_combinations_per_target() {
+        // Each debuff is on a different monster the whole time, while a
+        // single monster out of four is Frozen
+        let mut events = vec![
+            apply(0.0, 2, "Vulnerable", 1, None),
+            apply(0.0, 3, "Chilled", 1, None),
+            apply(0.0, 2, "Frozen", 1, None),
+            apply(0.0, PLAYER, "Berserking", 1, None),
+        ];
+        for target in 2..=5 {
+            events.extend((0..=10).map(|second| common::hit(second as f64,
+                damage(Actor::new(PLAYER, "Player"),
+                    Actor::new(target, "Target"), None, 100.0))));
+        }
+        events.sort_by_key(|event| event.timestamp);
+
+        let mut tracker = BuffTracker::new(PLAYER)
+            .combination(&["Vulnerable", "Chilled"])
+            .combination(&["Vulnerable", "Frozen"])
+            .combination(&["Chilled", "Berserking"]);
+        for event in events {
+            tracker.push(&event);
+        }
+        let report = tracker.report();
+        let encounter = &report.encounters[0];
+
+        let uptimes: Vec<f64> = encounter.combinations.iter()
+            .map(|combination| combination.uptime)
+            .collect();
+        assert_eq!(uptimes, [0.0, 1.0, 1.0]);
+
+        let frozen = encounter.buffs.iter()
+            .find(|buff| buff.buff == "Frozen")
+            .unwrap();
+        assert_eq!(frozen.uptime, 1.0);
+        assert_eq!(frozen.target_uptime, 0.25);
+    }
+
+    #[test]
+    fn test_report_output() {
+        let mut tracker = BuffTracker::new(PLAYER)
+            .combination(&["Vulnerable", "Berserking"]);
+        for event in session() {
+            tracker.push(&event);
+        }
+        let report = tracker.report();
+
+        let table = report.to_string();
+        assert!(table.contains("Encounter 2  0:20.0 - 0:22.0"));
+        assert!(table.lines().any(|line| line.starts_with("Vulnerable ")
+            && line.contains("70.0%") && line.contains("1.5")));
+        assert!(table.lines().any(|line|
+            line.starts_with("Vulnerable + Berserking")
+            && line.contains("50.0%")));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["encounters"][0]["buffs"][1]["buff"], "Vulnerable");
+        assert_eq!(json["encounters"][0]["combinations"][0]["uptime"], 0.5);
+    }
+}
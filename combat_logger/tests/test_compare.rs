@@ -0,0 +1,245 @@
+mod common;
+
+use std::collections::BTreeMap;
+use combat_logger::analysis::{
+    BuildComparison, MetricKind, SessionMetrics, DEFAULT_IDLE_GAP,
+};
+use combat_logger::logger::{
+    Actor, BuffApplied, BuffExpired, Character, CombatEvent, EventKind,
+    GameInfo, LogFormat, LogHeader, Timestamp,
+};
+use common::damage;
+
+/// Actor identifier of the player
+const PLAYER: u64 = 1;
+
+/// A hit of a skill between two actors at the given second
+fn hit(second: u64, source: u64, target: u64, skill: &str, amount: f64)
+        -> CombatEvent {
+    common::hit(second as f64, damage(Actor::new(source, "Source"),
+        Actor::new(target, "Target"), Some(skill), amount))
+}
+
+/// Metrics of a session with the given DPS and one skill and buff
+fn metrics(session_id: &str, dps: f64, uptime: f64) -> SessionMetrics {
+    SessionMetrics {
+        session_id: session_id.to_string(),
+        dps,
+        skill_dps: BTreeMap::from([("<Blizzard>".to_string(), dps)]),
+        buff_uptime: BTreeMap::from([("Berserking".to_string(), uptime)]),
+        damage_taken: 50.0,
+    }
+}
+
+/// A group of sessions with the given DPS values and steady buff uptime
+fn group(prefix: &str, dps: &[f64]) -> Vec<SessionMetrics> {
+    dps.iter()
+        .enumerate()
+        .map(|(i, &dps)| metrics(&format!("{}{}", prefix, i), dps, 0.5))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_metrics() {
+        // Ten seconds of alternating skills, hit twice by a monster, with a
+        // buff for the first half
+        let mut events = vec![
+            CombatEvent::new(Timestamp(0), EventKind::BuffApplied(BuffApplied {
+                target: Actor::new(PLAYER, "Player"),
+                source: None,
+                buff: "Berserking".to_string(),
+                debuff: false,
+                stacks: 1,
+                duration: None,
+            })),
+            CombatEvent::new(Timestamp(5_000_000),
+                EventKind::BuffExpired(BuffExpired {
+                    target: Actor::new(PLAYER, "Player"),
+                    buff: "Berserking".to_string(),
+                })),
+            hit(3, 2, PLAYER, "Claw", 50.0),
+            hit(7, 2, PLAYER, "Claw", 50.0),
+        ];
+        for second in 0..=10 {
+            let skill = ["Frost Bolt", "Ice Shards"][second as usize % 2];
+            events.push(hit(second, PLAYER, 2, skill, 100.0));
+        }
+        events.sort_by_key(|event| event.timestamp);
+
+        let metrics = SessionMetrics::from_events("s", PLAYER, DEFAULT_IDLE_GAP,
+            &events);
+        assert_eq!(metrics.dps, 110.0);
+        assert_eq!(metrics.skill_dps["Frost Bolt"], 60.0);
+        assert_eq!(metrics.skill_dps["Ice Shards"], 50.0);
+        assert_eq!(metrics.buff_uptime["Berserking"], 0.5);
+        assert_eq!(metrics.damage_taken, 10.0);
+    }
+
+    #[test]
+    fn test_significance() {
+        let before = group("a", &[100.0, 102.0, 98.0, 101.0, 99.0]);
+        let after = group("b", &[120.0, 118.0, 122.0, 121.0, 119.0]);
+        let report = BuildComparison::new(before.clone(), after).report();
+
+        // DPS first, then per skill and buff, then damage taken
+        let kinds: Vec<MetricKind> = report.metrics.iter()
+            .map(|metric| metric.kind)
+            .collect();
+        assert_eq!(kinds, [MetricKind::Dps, MetricKind::SkillDps,
+            MetricKind::BuffUptime, MetricKind::DamageTaken]);
+        let dps = &report.metrics[0];
+        assert_eq!((dps.a.mean, dps.b.mean, dps.difference.mean),
+            (100.0, 120.0, 20.0));
+        assert!(dps.difference.low > 0.0 && dps.difference.high < 40.0);
+        assert!(dps.a.low <= 100.0 && dps.a.high >= 100.0);
+        assert_eq!(dps.change, Some(0.2));
+        assert!(dps.significant);
+        assert!(!report.metrics[2].significant);
+
+        // Differences within the spread of each group are noise
+        let noisy = group("b", &[101.0, 112.0, 89.0, 104.0, 97.0]);
+        let report = BuildComparison::new(before.clone(), noisy.clone())
+            .report();
+        assert!(!report.metrics[0].significant, "{:?}", report.metrics[0]);
+        assert_eq!(report, BuildComparison::new(before, noisy).report());
+
+        // One session per group says nothing about the spread
+        let report = BuildComparison::new(group("a", &[100.0]),
+            group("b", &[200.0])).report();
+        assert!(!report.metrics[0].significant);
+    }
+
+    #[test]
+    fn test_multiple_comparisons() {
+        // Sessions with the given DPS and a number of steady buffs
+        let sessions = |prefix: &str, dps: &[f64], buffs: usize| {
+            let mut group = group(prefix, dps);
+            for session in &mut group {
+                session.skill_dps.clear();
+                session.buff_uptime = (0..buffs)
+                    .map(|i| (format!("Buff {}", i), 0.5))
+                    .collect();
+            }
+            group
+        };
+        let before = [100.0, 102.0, 98.0, 101.0, 99.0];
+        let after = [103.0, 101.0, 105.0, 100.0, 104.0];
+
+        // Compared with damage taken alone, the difference holds
+        let report = BuildComparison::new(sessions("a", &before, 0),
+            sessions("b", &after, 0)).report();
+        let dps = &report.metrics[0];
+        assert!(dps.difference.low > 0.0);
+        assert!(dps.p_value.unwrap() < 0.025, "{:?}", dps);
+        assert!(dps.significant);
+
+        // Among twenty buffs as well, it no longer passes the correction
+        let report = BuildComparison::new(sessions("a", &before, 20),
+            sessions("b", &after, 20)).report();
+        let dps = &report.metrics[0];
+        assert!(dps.difference.low > 0.0);
+        assert!(!dps.significant);
+        assert!(report.metrics.iter().all(|metric| !metric.significant));
+    }
+
+    #[test]
+    #[should_panic(expected = "resamples must not be zero")]
+    fn test_zero_resamples() {
+        BuildComparison::new(vec![], vec![]).resamples(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be between 0 and 1")]
+    fn test_confidence_as_percentage() {
+        BuildComparison::new(vec![], vec![]).confidence(95.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be between 0 and 1")]
+    fn test_confidence_nan() {
+        BuildComparison::new(vec![], vec![]).confidence(f64::NAN);
+    }
+
+    #[test]
+    fn test_cli_compare_tolerates_damage() {
+        // Each session names its character only in its second segment, and
+        // the first one has a damaged record before more hits
+        let directory = tempfile::tempdir().unwrap();
+        for (session, class, amount) in [("s1", "Sorcerer", 100.0),
+                ("s2", "Barbarian", 200.0)] {
+            let character = Character {
+                id: PLAYER,
+                name: "Lilith".to_string(),
+                class: class.to_string(),
+                level: 60,
+            };
+            for segment in 0..2 {
+                let game = GameInfo {
+                    character: (segment == 1).then(|| character.clone()),
+                    ..Default::default()
+                };
+                let path = directory.path().join(
+                    format!("{}-{:03}.jsonl", session, segment));
+                let events: Vec<CombatEvent> = (0..5)
+                    .map(|second| hit(segment * 10 + second, PLAYER, 2,
+                        "Blizzard", amount))
+                    .collect();
+                common::write_log(&path, LogFormat::Json,
+                    LogHeader::new(session, segment, 0, game), &events[..3]);
+                let mut log = std::fs::read(&path).unwrap();
+                log.extend_from_slice(b"{\"damaged\"\n");
+                for event in &events[3..] {
+                    log.extend(serde_json::to_vec(event).unwrap());
+                    log.push(b'\n');
+                }
+                std::fs::write(&path, log).unwrap();
+            }
+        }
+
+        let output = std::process::Command::new(
+                env!("CARGO_BIN_EXE_combat_logger"))
+            .args(["compare", "--a", "class=Sorcerer", "--b",
+                "class=Barbarian", "--resamples", "10", "--json", "--dir"])
+            .arg(directory.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}",
+            String::from_utf8_lossy(&output.stderr));
+        let report: serde_json::Value =
+            serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(report["a"]["sessions"][0], "s1");
+        assert!(report["metrics"][0]["b"]["mean"].as_f64().unwrap()
+            > report["metrics"][0]["a"]["mean"].as_f64().unwrap());
+    }
+
+    #[test]
+    fn test_rendering() {
+        let report = BuildComparison::new(
+                group("a", &[100.0, 102.0, 98.0]),
+                group("b", &[150.0, 152.0, 148.0]))
+            .labels("Before", "After")
+            .resamples(500)
+            .report();
+
+        let mut markdown = vec![];
+        report.write_markdown(&mut markdown).unwrap();
+        let markdown = String::from_utf8(markdown).unwrap();
+        assert!(markdown.starts_with("# Build comparison\n"));
+        assert!(markdown.contains("Before: 3 sessions (a0, a1, a2)"));
+        assert!(markdown.contains(
+            "| Metric | Before | After | Difference | Change | Significant |"));
+        assert!(markdown.contains("| DPS | 100 ("), "{}", markdown);
+        assert!(markdown.contains("| +50.0% | yes |"), "{}", markdown);
+
+        let mut html = vec![];
+        report.write_html(&mut html).unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.contains("<h2>Buff uptime</h2>"));
+        assert!(html.contains("<td>&lt;Blizzard&gt;</td>"), "{}", html);
+        assert_eq!(html.matches("<tr class=\"significant\">").count(), 2);
+    }
+}
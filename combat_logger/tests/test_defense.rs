@@ -0,0 +1,181 @@
+mod common;
+
+use combat_logger::analysis::DefenseTracker;
+use combat_logger::logger::{
+    Actor, Cast, CombatEvent, Damage, DamageType, EventKind, Heal, Kill,
+    ResourceChange, ResourceKind,
+};
+use common::{at, damage, hit};
+
+/// Actor identifier of the player
+const PLAYER: u64 = 1;
+
+/// The player
+fn player() -> Actor {
+    Actor::new(PLAYER, "Lilith")
+}
+
+/// A hit taken by the player
+fn taken(second: f64, source: Actor, damage_type: DamageType, amount: f64,
+        absorbed: f64, mitigated: Option<f64>) -> CombatEvent {
+    hit(second, Damage {
+        damage_type,
+        mitigated,
+        absorbed,
+        ..damage(source, player(), None, amount)
+    })
+}
+
+/// A new value of a resource of the player
+fn resource(second: f64, resource: ResourceKind, value: f64) -> CombatEvent {
+    CombatEvent::new(at(second), EventKind::Resource(ResourceChange {
+        actor: player(),
+        resource,
+        value,
+        max: 1000.0,
+    }))
+}
+
+/// A heal of the player
+fn heal(second: f64, skill: Option<&str>, amount: f64, overheal: f64)
+        -> CombatEvent {
+    CombatEvent::new(at(second), EventKind::Heal(Heal {
+        source: player(),
+        target: player(),
+        skill: skill.map(str::to_string),
+        amount,
+        overheal,
+    }))
+}
+
+/// A fight with a close call early on and a death twenty seconds in
+fn session() -> Vec<CombatEvent> {
+    let fallen = |id| Actor::new(id, "Fallen");
+    let ghoul = Actor::new(12, "Ghoul");
+    vec![
+        resource(0.0, ResourceKind::Life, 1000.0),
+        taken(1.0, fallen(10), DamageType::Physical, 100.0, 0.0, Some(100.0)),
+        taken(2.0, fallen(11), DamageType::Fire, 150.0, 50.0, Some(200.0)),
+        resource(2.5, ResourceKind::Life, 700.0),
+        resource(2.5, ResourceKind::Fortify, 900.0),
+        taken(3.0, ghoul.clone(), DamageType::Cold, 300.0, 0.0, None),
+        resource(3.0, ResourceKind::Life, 400.0),
+        taken(4.0, ghoul.clone(), DamageType::Cold, 250.0, 0.0, None),
+        resource(4.0, ResourceKind::Life, 150.0),
+        heal(5.0, Some("Healing Potion"), 500.0, 0.0),
+        resource(5.0, ResourceKind::Life, 650.0),
+        heal(6.0, None, 100.0, 50.0),
+        resource(10.0, ResourceKind::Fortify, 0.0),
+        CombatEvent::new(at(15.0), EventKind::Cast(Cast {
+            source: player(),
+            skill: "Teleport".to_string(),
+            cooldown: Some(11.0),
+        })),
+        taken(20.0, ghoul.clone(), DamageType::Physical, 900.0, 0.0,
+            Some(100.0)),
+        resource(20.0, ResourceKind::Life, 0.0),
+        CombatEvent::new(at(20.5), EventKind::Kill(Kill {
+            killer: Some(ghoul),
+            victim: player(),
+        })),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_damage_taken() {
+        let mut tracker = DefenseTracker::new(PLAYER);
+        for event in session() {
+            tracker.push(&event);
+        }
+        let report = tracker.report();
+
+        assert_eq!(report.damage, 1750.0);
+        assert_eq!(report.hits, 5);
+        assert_eq!(report.absorbed, 50.0);
+        assert_eq!(report.reduction, Some(0.25));
+        // Only the two ghoul hits landed while fortify exceeded life
+        assert_eq!(report.fortified_share, 550.0 / 1750.0);
+
+        let sources: Vec<(&str, u64)> = report.sources.iter()
+            .map(|source| (source.source.as_str(), source.hits))
+            .collect();
+        assert_eq!(sources, [("Ghoul", 3), ("Fallen", 2)]);
+        assert_eq!(report.sources[0].max_hit, 900.0);
+
+        let types: Vec<(DamageType, Option<f64>)> = report.damage_types
+            .iter()
+            .map(|damage_type| (damage_type.damage_type, damage_type.reduction))
+            .collect();
+        assert_eq!(types, [
+            (DamageType::Physical, Some(200.0 / 1200.0)),
+            (DamageType::Cold, None),
+            (DamageType::Fire, Some(0.5)),
+        ]);
+    }
+
+    #[test]
+    fn test_healing_and_near_deaths() {
+        let mut tracker = DefenseTracker::new(PLAYER);
+        for event in session() {
+            tracker.push(&event);
+        }
+        let report = tracker.report();
+
+        assert_eq!(report.healing, 600.0);
+        assert_eq!(report.overheal, 50.0);
+        assert_eq!(report.healing_sources[0].source, "Healing Potion");
+        assert_eq!(report.healing_sources[1].source, "unknown");
+        assert_eq!(report.healing_sources[1].overheal, 50.0);
+
+        // The dip into the death does not count as a near death
+        assert_eq!(report.near_deaths.len(), 1);
+        let near_death = &report.near_deaths[0];
+        assert_eq!(near_death.timestamp, at(4.0));
+        assert_eq!(near_death.lowest, 0.15);
+        assert_eq!(near_death.recovered, Some(at(5.0)));
+
+        // A higher threshold catches the hit before as well
+        let mut tracker = DefenseTracker::new(PLAYER).near_death(0.5);
+        for event in session() {
+            tracker.push(&event);
+        }
+        let report = tracker.report();
+        assert_eq!(report.near_deaths[0].timestamp, at(3.0));
+    }
+
+    #[test]
+    fn test_death_recap() {
+        let mut tracker = DefenseTracker::new(PLAYER);
+        for event in session() {
+            tracker.push(&event);
+        }
+        let report = tracker.report();
+        assert_eq!(report.deaths.len(), 1);
+
+        let death = &report.deaths[0];
+        assert_eq!(death.timestamp, at(20.5));
+        assert_eq!(death.killer.as_ref().unwrap().name, "Ghoul");
+        assert_eq!(death.damage, 900.0);
+        assert_eq!(death.healing, 0.0);
+
+        // Events more than ten seconds before the death are left out
+        let kinds: Vec<&str> = death.events.iter()
+            .map(|entry| entry.kind.name())
+            .collect();
+        assert_eq!(kinds, ["cast", "damage", "resource"]);
+        assert_eq!(death.events[0].before, 5.5);
+
+        let text = report.to_string();
+        assert!(text.contains("Death at 0:20.5 to Ghoul"));
+        assert!(text.contains("-5.5s  Cast Teleport"));
+        assert!(text.contains("Near death at 0:04.0, down to 15.0% life"));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["deaths"][0]["events"][1]["kind"], "damage");
+        assert_eq!(json["damage_types"][0]["damage_type"], "physical");
+    }
+}
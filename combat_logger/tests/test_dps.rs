@@ -0,0 +1,138 @@
+mod common;
+
+use std::time::Duration;
+use combat_logger::analysis::{DpsMeter, EncounterDetector};
+use combat_logger::logger::{
+    Actor, CombatEvent, Damage, EventKind, Timestamp, ZoneChange,
+};
+use common::damage;
+
+/// Actor identifier of the player
+const PLAYER: u64 = 1;
+
+/// A hit at the given second
+fn hit(second: f64, source: u64, target: u64, skill: Option<&str>,
+        amount: f64, critical: bool, overpower: bool) -> CombatEvent {
+    common::hit(second, Damage {
+        critical,
+        overpower,
+        ..damage(Actor::new(source, "Source"), Actor::new(target, "Target"),
+            skill, amount)
+    })
+}
+
+/// Two encounters separated by a ten second gap
+fn session() -> Vec<CombatEvent> {
+    vec![
+        hit(0.0, PLAYER, 2, Some("Fireball"), 1000.0, true, false),
+        hit(1.0, PLAYER, 2, Some("Spark"), 200.0, false, false),
+        hit(2.0, PLAYER, 2, Some("Fireball"), 3000.0, false, true),
+        hit(3.0, PLAYER, 2, Some("Spark"), 200.0, true, false),
+        // Taking damage keeps the encounter going
+        hit(7.0, 2, PLAYER, None, 500.0, false, false),
+        hit(10.0, PLAYER, 2, None, 600.0, false, false),
+        CombatEvent::new(Timestamp(12_000_000),
+            EventKind::ZoneChange(ZoneChange { zone: "Cerrigar".to_string() })),
+        hit(25.0, PLAYER, 3, Some("Spark"), 400.0, false, false),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encounters() {
+        let encounters = EncounterDetector::new(PLAYER)
+            .detect(&session());
+        assert_eq!(encounters.len(), 2);
+        assert_eq!(encounters[0].start, Timestamp(0));
+        assert_eq!(encounters[0].end, Timestamp(10_000_000));
+        assert_eq!(encounters[1].start, Timestamp(25_000_000));
+
+        // A shorter idle gap splits the first encounter at the pause
+        let encounters = EncounterDetector::new(PLAYER)
+            .idle_gap(Duration::from_secs(3))
+            .detect(&session());
+        assert_eq!(encounters.len(), 3);
+        assert_eq!(encounters[1].start, Timestamp(7_000_000));
+    }
+
+    #[test]
+    fn test_dps_report() {
+        let mut meter = DpsMeter::new(PLAYER)
+            .window(Duration::from_secs(2));
+        for event in session() {
+            meter.push(&event);
+        }
+        let report = meter.report();
+
+        assert_eq!(report.encounters.len(), 2);
+        assert_eq!(report.damage, 5400.0);
+        assert_eq!(report.duration, 11.0);
+
+        let first = &report.encounters[0];
+        assert_eq!(first.damage, 5000.0);
+        assert_eq!(first.dps, 500.0);
+        assert_eq!(first.hits, 5);
+        assert_eq!(first.crit_rate, 0.4);
+        assert_eq!(first.overpower_rate, 0.2);
+        assert_eq!(first.average_hit, 1000.0);
+        assert_eq!(first.max_hit, 3000.0);
+
+        // Skills are sorted by damage, with unknown skills labelled
+        let skills: Vec<&str> = first.skills.iter()
+            .map(|skill| skill.skill.as_str())
+            .collect();
+        assert_eq!(skills, ["Fireball", "unknown", "Spark"]);
+        let fireball = &first.skills[0];
+        assert_eq!(fireball.share, 0.8);
+        assert_eq!(fireball.hits, 2);
+        assert_eq!(fireball.crit_rate, 0.5);
+        assert_eq!(fireball.average_hit, 2000.0);
+        let spark = &first.skills[2];
+        assert_eq!(spark.crit_rate, 0.5);
+        assert_eq!(spark.max_hit, 200.0);
+
+        // The peak two second window holds the 200 and 3000 hits
+        assert_eq!(first.peak_dps, 1600.0);
+        assert_eq!(first.rolling.len(), 11);
+        assert_eq!(first.rolling[0].dps, 500.0);
+        assert_eq!(first.rolling[5].dps, 0.0);
+
+        // A single hit counts as a second of combat
+        let second = &report.encounters[1];
+        assert_eq!(second.dps, 400.0);
+        assert_eq!(second.skills.len(), 1);
+    }
+
+    #[test]
+    fn test_live_rolling_dps() {
+        let mut meter = DpsMeter::new(PLAYER);
+        assert_eq!(meter.rolling_dps(Timestamp(0)), 0.0);
+
+        for event in &session()[..4] {
+            meter.push(event);
+        }
+        assert_eq!(meter.rolling_dps(Timestamp(3_000_000)), 880.0);
+        assert_eq!(meter.rolling_dps(Timestamp(60_000_000)), 0.0);
+    }
+
+    #[test]
+    fn test_report_output() {
+        let mut meter = DpsMeter::new(PLAYER);
+        for event in session() {
+            meter.push(&event);
+        }
+        let report = meter.report();
+
+        let table = report.to_string();
+        assert!(table.contains("Encounter 1  0:00.0 - 0:10.0"));
+        assert!(table.lines().any(|line| line.starts_with("Fireball")
+            && line.contains("4.0k") && line.contains("80.0%")));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["encounters"][0]["skills"][0]["skill"], "Fireball");
+        assert_eq!(json["encounters"][1]["encounter"]["start"], 25_000_000);
+    }
+}
@@ -0,0 +1,212 @@
+mod common;
+
+use std::path::Path;
+use combat_logger::index::{
+    format_date, gear_hash, parse_date, parse_date_end, Error, SessionIndex,
+    SessionQuery, INDEX_FILE,
+};
+use combat_logger::logger::{
+    Actor, Cast, Character, CombatEvent, EventKind, GameInfo, Kill, LogFormat,
+    LogHeader, Timestamp, ZoneChange,
+};
+use common::{damage, write_log};
+
+/// Start of 2026-10-01 in milliseconds since the Unix epoch
+const OCTOBER: u64 = 1_790_812_800_000;
+
+/// Game details of a level 60 character
+fn game(name: &str, class: &str, world_tier: u32) -> GameInfo {
+    GameInfo {
+        build: "1.0.0".to_string(),
+        character: Some(Character {
+            id: 1,
+            name: name.to_string(),
+            class: class.to_string(),
+            level: 60,
+        }),
+        world_tier: Some(world_tier),
+        gear: vec![
+            "Harlequin Crest".to_string(),
+            "Godslayer Crown".to_string(),
+        ],
+    }
+}
+
+/// A zone change, then a cast and hit on a monster per second, then the kill
+fn events(zone: &str, start: u64, hits: u64) -> Vec<CombatEvent> {
+    let player = Actor::new(1, "Lilith");
+    let monster = Actor::new(77, "Fallen Shaman");
+    let at = |second: u64| Timestamp((start + second) * 1_000_000);
+
+    let mut events = vec![CombatEvent::new(at(0), EventKind::ZoneChange(
+        ZoneChange { zone: zone.to_string() }))];
+    for second in 1..=hits {
+        events.push(CombatEvent::new(at(second), EventKind::Cast(Cast {
+            source: player.clone(),
+            skill: "Fireball".to_string(),
+            cooldown: None,
+        })));
+        events.push(CombatEvent::new(at(second), EventKind::Damage(damage(
+            player.clone(), monster.clone(), Some("Fireball"), 1000.0))));
+    }
+    events.push(CombatEvent::new(at(hits), EventKind::Kill(Kill {
+        killer: Some(player),
+        victim: monster,
+    })));
+    events
+}
+
+/// Writes one segment of a session to the directory
+fn write_segment(directory: &Path, header: LogHeader, format: LogFormat,
+        events: &[CombatEvent]) {
+    let name = format!("{}-{:03}.{}", header.session_id, header.segment,
+        format.extension());
+    write_log(&directory.join(name), format, header, events);
+}
+
+/// Writes a two segment Sorcerer session in October and a Barbarian session
+/// in September
+fn write_sessions(directory: &Path) {
+    let sorcerer = game("Lilith", "Sorcerer", 4);
+    write_segment(directory,
+        LogHeader::new("sorcerer", 0, OCTOBER + 3_600_000, sorcerer.clone()),
+        LogFormat::Json, &events("Kyovashad", 0, 10));
+    write_segment(directory,
+        LogHeader::new("sorcerer", 1, OCTOBER + 3_600_000, sorcerer),
+        LogFormat::Binary, &events("Zarbinzet", 60, 5));
+
+    write_segment(directory,
+        LogHeader::new("barbarian", 0, OCTOBER - 86_400_000,
+            game("Inarius", "Barbarian", 3)),
+        LogFormat::Json, &events("Kyovashad", 0, 3));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_and_query() {
+        let directory = tempfile::tempdir().unwrap();
+        write_sessions(directory.path());
+        let mut index = SessionIndex::open(directory.path()).unwrap();
+        let summary = index.scan().unwrap();
+        assert_eq!((summary.sessions, summary.updated), (2, 2));
+
+        // Sessions are listed in the order they started
+        let ids: Vec<&str> = index.entries().iter()
+            .map(|entry| entry.session_id.as_str())
+            .collect();
+        assert_eq!(ids, ["barbarian", "sorcerer"]);
+
+        // Both segments count towards the session
+        let sorcerer = &index.entries()[1];
+        assert_eq!(sorcerer.class.as_deref(), Some("Sorcerer"));
+        assert_eq!((sorcerer.level, sorcerer.world_tier), (Some(60), Some(4)));
+        assert_eq!(sorcerer.duration, 65.0);
+        assert_eq!(sorcerer.zones, ["Kyovashad", "Zarbinzet"]);
+        assert_eq!(sorcerer.totals.damage_dealt, 15_000.0);
+        assert_eq!((sorcerer.totals.casts, sorcerer.totals.kills), (15, 2));
+        assert_eq!(sorcerer.files.len(), 2);
+        assert_eq!(sorcerer.gear_hash, gear_hash(&game("", "", 0).gear));
+
+        let matching = |query: SessionQuery| -> Vec<String> {
+            index.query(&query).iter()
+                .map(|entry| entry.session_id.clone())
+                .collect()
+        };
+        assert_eq!(matching(SessionQuery::new()
+            .class("sorcerer")
+            .since(parse_date("2026-10-01").unwrap())), ["sorcerer"]);
+        assert_eq!(matching(SessionQuery::new().zone("kyovashad")),
+            ["barbarian", "sorcerer"]);
+        assert_eq!(matching(SessionQuery::new().until(OCTOBER)),
+            ["barbarian"]);
+        assert!(matching(SessionQuery::new().world_tier(4).character("Inarius"))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_query_filters() {
+        let directory = tempfile::tempdir().unwrap();
+        write_sessions(directory.path());
+        let mut index = SessionIndex::open(directory.path()).unwrap();
+        index.scan().unwrap();
+
+        // The last day of the range is included
+        let count = |filters: &str| index.query(&filters.parse().unwrap())
+            .len();
+        assert_eq!(count("class=Sorcerer, since=2026-10-01"), 1);
+        assert_eq!(count("until=2026-09-30"), 1);
+        assert_eq!(count("until=2026-10-01,world_tier=4,level=60"), 1);
+        assert_eq!(count("zone=Kyovashad"), 2);
+        assert_eq!(count(""), 2);
+
+        for bad in ["class", "tier=4", "level=high"] {
+            let result = bad.parse::<SessionQuery>();
+            assert!(matches!(result, Err(Error::BadQuery(_))),
+                "Unexpected result {:?}", result);
+        }
+    }
+
+    #[test]
+    fn test_incremental_scan() {
+        let directory = tempfile::tempdir().unwrap();
+        write_sessions(directory.path());
+        SessionIndex::open(directory.path()).unwrap().scan().unwrap();
+        assert!(directory.path().join(INDEX_FILE).exists());
+
+        // A reopened index only reads logs that changed
+        let mut index = SessionIndex::open(directory.path()).unwrap();
+        assert_eq!(index.entries().len(), 2);
+        assert_eq!(index.scan().unwrap().updated, 0);
+
+        write_segment(directory.path(),
+            LogHeader::new("sorcerer", 2, OCTOBER + 3_600_000,
+                game("Lilith", "Sorcerer", 4)),
+            LogFormat::Json, &events("Cerrigar", 120, 1));
+        std::fs::write(directory.path().join("garbage.jsonl"), b"not a log")
+            .unwrap();
+        std::fs::remove_file(directory.path().join("barbarian-000.jsonl"))
+            .unwrap();
+        let summary = index.scan().unwrap();
+        assert_eq!(summary.sessions, 1);
+        assert_eq!((summary.updated, summary.removed, summary.skipped),
+            (1, 1, 1));
+
+        let sorcerer = &index.entries()[0];
+        assert_eq!(sorcerer.files.len(), 3);
+        assert_eq!(sorcerer.zones, ["Kyovashad", "Zarbinzet", "Cerrigar"]);
+        assert_eq!(SessionIndex::open(directory.path()).unwrap().entries(),
+            index.entries());
+    }
+
+    #[test]
+    fn test_newer_index() {
+        let directory = tempfile::tempdir().unwrap();
+        write_sessions(directory.path());
+
+        // Entries of a newer build need not parse, the index is rebuilt
+        std::fs::write(directory.path().join(INDEX_FILE),
+            r#"{"version":2,"sessions":[{"id":7,"files":"all"}]}"#).unwrap();
+        let mut index = SessionIndex::open(directory.path()).unwrap();
+        assert!(index.entries().is_empty());
+        assert_eq!(index.scan().unwrap().updated, 2);
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(parse_date("2026-10-01").unwrap(), OCTOBER);
+        assert_eq!(parse_date("2024-02-29").unwrap(), 1_709_164_800_000);
+        assert_eq!(parse_date_end("2026-09-30").unwrap(), OCTOBER);
+        for bad in ["2026-02-29", "2026-13-01", "2026-10", "yesterday",
+                "1969-12-31", "10000-01-01", "99999999999999-01-01"] {
+            let result = parse_date(bad);
+            assert!(matches!(result, Err(Error::BadDate(_))),
+                "Unexpected result {:?}", result);
+        }
+
+        assert_eq!(format_date(1_789_913_100_000), "2026-09-20 14:05");
+        assert_eq!(format_date(0), "1970-01-01 00:00");
+    }
+}
@@ -0,0 +1,239 @@
+use serde::Deserialize;
+use combat_logger::game_data;
+use combat_logger::memory::{
+    Error, FieldType, LayoutSet, LayoutValue, MemoryReader, MockMemory, Value,
+    ValueType,
+};
+
+/// Heap address used by the mock game objects
+const HEAP_BASE: usize = 0x7f00_0000_0000;
+
+/// Layouts of a player pointing at an actor with buffs
+const LAYOUTS: &str = r#"
+build = "test"
+max_pointer_depth = 2
+
+[structs.Position]
+fields = [
+    { name = "x", offset = 0x0, type = "f32" },
+    { name = "y", offset = 0x4, type = "f32" },
+]
+
+[structs.Buff]
+size = 0x10
+fields = [
+    { name = "id", offset = 0x0, type = "u32" },
+    { name = "stacks", offset = 0x4, type = "u8" },
+]
+
+[structs.Actor]
+fields = [
+    { name = "health", offset = 0x0, type = "f32" },
+    { name = "position", offset = 0x8, type = "Position" },
+    { name = "buffs", offset = 0x10, type = "[Buff; 2]" },
+    { name = "elite", offset = 0x30, type = "bool" },
+    { name = "target", offset = 0x38, type = "*Actor" },
+]
+
+[structs.Player]
+size = 0x20
+fields = [
+    { name = "level", offset = 0x0, type = "u32" },
+    { name = "actor", offset = 0x8, type = "*Actor" },
+    { name = "pet", offset = 0x10, type = "*Actor" },
+]
+"#;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Player {
+    level: u32,
+    actor: Option<Actor>,
+    pet: Option<Actor>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Actor {
+    health: f32,
+    position: Position,
+    buffs: Vec<Buff>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Position {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Buff {
+    id: u32,
+    stacks: u8,
+}
+
+/// Builds a player at the heap base whose actor targets itself
+fn mock_player() -> MockMemory {
+    let actor = 0x100;
+    let mut heap = vec![0u8; 0x1000];
+    heap[0x0..0x4].copy_from_slice(&60u32.to_le_bytes());
+    heap[0x8..0x10].copy_from_slice(&((HEAP_BASE + actor) as u64)
+        .to_le_bytes());
+
+    heap[actor..actor + 4].copy_from_slice(&812.5f32.to_le_bytes());
+    heap[actor + 0x8..actor + 0xC].copy_from_slice(&1.5f32.to_le_bytes());
+    heap[actor + 0xC..actor + 0x10].copy_from_slice(&(-2.0f32).to_le_bytes());
+    heap[actor + 0x10..actor + 0x14].copy_from_slice(&1001u32.to_le_bytes());
+    heap[actor + 0x14] = 3;
+    heap[actor + 0x20..actor + 0x24].copy_from_slice(&1002u32.to_le_bytes());
+    heap[actor + 0x30] = 1;
+    heap[actor + 0x38..actor + 0x40].copy_from_slice(
+        &((HEAP_BASE + actor) as u64).to_le_bytes());
+
+    MockMemory::new().with_buffer(HEAP_BASE, heap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_layouts() {
+        let layouts = LayoutSet::from_toml(LAYOUTS).unwrap();
+        assert_eq!(layouts.build.as_deref(), Some("test"));
+        assert_eq!(layouts.size_of(&"Actor".parse().unwrap()).unwrap(), 0x40);
+        assert_eq!(layouts.size_of(&"[Buff; 3]".parse().unwrap()).unwrap(),
+            0x30);
+
+        let field_type: FieldType = "*[ *Actor; 4]".parse().unwrap();
+        assert_eq!(field_type, FieldType::Pointer(Box::new(FieldType::Array(
+            Box::new(FieldType::Pointer(Box::new(
+                FieldType::Struct("Actor".to_string())))), 4))));
+        assert_eq!(field_type.to_string(), "*[*Actor; 4]");
+        assert_eq!("f64".parse::<FieldType>().unwrap(),
+            FieldType::Value(ValueType::F64));
+        assert!("[u8]".parse::<FieldType>().is_err());
+        assert!("9lives".parse::<FieldType>().is_err());
+
+        // JSON has no hex literals, so offsets may be hex strings
+        let json = LayoutSet::from_json(r#"{"structs": {"Buff": {"fields": [
+            {"name": "id", "offset": 0, "type": "u32"},
+            {"name": "stacks", "offset": "0x4", "type": "u8"}
+        ]}}}"#).unwrap();
+        assert_eq!(json.get("Buff").unwrap().fields[1].offset, 4);
+        assert_eq!(json.max_pointer_depth, 4);
+    }
+
+    #[test]
+    fn test_invalid_layouts() {
+        let invalid = [
+            // Unknown struct
+            "[structs.A]\nfields = [{ name = \"b\", offset = 0, type = \"B\" }]",
+            // Struct containing itself inline
+            "[structs.A]\nfields = [{ name = \"a\", offset = 8, type = \"A\" }]",
+            // Field past the declared size
+            "[structs.A]\nsize = 4\n\
+                fields = [{ name = \"a\", offset = 2, type = \"u32\" }]",
+            // Unknown pointer target
+            "[structs.A]\nfields = [{ name = \"b\", offset = 0, type = \"*B\" }]",
+            // Array too large to size
+            "[structs.A]\nfields = [{ name = \"a\", offset = 0, \
+                type = \"[u64; 4611686018427387904]\" }]",
+            // Offset too large to end
+            "[structs.A]\nfields = [{ name = \"a\", \
+                offset = \"0xFFFFFFFFFFFFFFFF\", type = \"u32\" }]",
+        ];
+        for layouts in invalid {
+            let result = LayoutSet::from_toml(layouts);
+            assert!(matches!(result, Err(Error::Layout(_))),
+                "Unexpected result {:?} for {}", result, layouts);
+        }
+
+        // Pointers to the struct itself are fine
+        LayoutSet::from_toml(
+            "[structs.A]\nfields = [{ name = \"a\", offset = 8, type = \"*A\" }]")
+            .unwrap();
+
+        // Layouts changed after validation fail to read instead of panicking
+        let mut layouts = LayoutSet::from_toml(LAYOUTS).unwrap();
+        layouts.structs.get_mut("Buff").unwrap().size = Some(2);
+        let memory_reader = MemoryReader::with_source(mock_player());
+        let result = memory_reader.read_layout(&layouts, "Buff", HEAP_BASE);
+        assert!(matches!(result, Err(Error::Layout(_))));
+    }
+
+    #[test]
+    fn test_read_layout() {
+        let layouts = LayoutSet::from_toml(LAYOUTS).unwrap();
+        let memory_reader = MemoryReader::with_source(mock_player());
+        let player = memory_reader.read_layout(&layouts, "Player", HEAP_BASE)
+            .unwrap();
+
+        assert_eq!(player.get("level").unwrap().as_value(),
+            Some(Value::U32(60)));
+        assert_eq!(player.get("pet"), Some(&LayoutValue::Pointer {
+            address: 0,
+            target: None,
+        }));
+
+        let actor = player.get("actor").unwrap();
+        assert_eq!(actor.get("health").unwrap().as_value(),
+            Some(Value::F32(812.5)));
+        assert_eq!(actor.get("elite"), Some(&LayoutValue::Bool(true)));
+        assert_eq!(actor.get("position").unwrap().get("y").unwrap()
+            .as_value(), Some(Value::F32(-2.0)));
+
+        // The self-targeting actor is only followed up to the pointer depth
+        let target = actor.get("target").unwrap();
+        assert!(target.get("health").is_some());
+        assert_eq!(target.get("target"), Some(&LayoutValue::Pointer {
+            address: HEAP_BASE + 0x100,
+            target: None,
+        }));
+
+        // Unknown structs and unreadable addresses are errors
+        assert!(matches!(memory_reader.read_layout(&layouts, "Pet", HEAP_BASE),
+            Err(Error::Layout(_))));
+        assert!(memory_reader.read_layout(&layouts, "Player", 0x1000)
+            .is_err());
+    }
+
+    #[test]
+    fn test_read_struct() {
+        let layouts = LayoutSet::from_toml(LAYOUTS).unwrap();
+        let memory_reader = MemoryReader::with_source(mock_player());
+        let player: Player = memory_reader.read_struct(&layouts, "Player",
+            HEAP_BASE).unwrap();
+
+        assert_eq!(player, Player {
+            level: 60,
+            actor: Some(Actor {
+                health: 812.5,
+                position: Position { x: 1.5, y: -2.0 },
+                buffs: vec![
+                    Buff { id: 1001, stacks: 3 },
+                    Buff { id: 1002, stacks: 0 },
+                ],
+            }),
+            pet: None,
+        });
+
+        // Fields that are not in the layout fail the conversion
+        let result = memory_reader.read_struct::<Position>(&layouts, "Buff",
+            HEAP_BASE);
+        assert!(matches!(result, Err(Error::Layout(_))));
+    }
+
+    #[test]
+    fn test_shipped_layouts() {
+        for build in game_data::builds() {
+            let layouts = game_data::layouts(build).unwrap();
+            assert_eq!(layouts.build.as_deref(), Some(build));
+            layouts.get("Player").unwrap();
+        }
+        assert!(game_data::layouts("0.0.0").is_err());
+
+        // The template is not tied to any build
+        let template = game_data::template_layouts().unwrap();
+        assert_eq!(template.build, None);
+        template.get("Player").unwrap();
+    }
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+use combat_logger::memory::{
+    AttachEvent, Attacher, Attachment, Error, LiveMemory, MemoryReader,
+    Pattern, ProcessIdentity, ProcessInfo, ProcessMatcher, WritePolicy,
+};
+use common::{run_test_binary, spawn_sleep};
+
+/// Generous timeout for events, so slow machines do not fail the tests
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Waits for the next attach or exit, skipping failed attach attempts
+fn next_event(events: &Receiver<AttachEvent>) -> AttachEvent {
+    loop {
+        match events.recv_timeout(TIMEOUT).expect("No attach event") {
+            AttachEvent::Failed(_) => continue,
+            event => return event,
+        }
+    }
+}
+
+/// Waits for the next attachment
+fn next_attachment(events: &Receiver<AttachEvent>) -> Box<Attachment> {
+    match next_event(events) {
+        AttachEvent::Attached(attachment) => attachment,
+        _ => panic!("Expected an attachment"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_identity() {
+        let mut sleep = spawn_sleep("31.5");
+        let identity = ProcessIdentity::of(sleep.pid()).unwrap();
+        assert_eq!(identity, ProcessInfo::read(sleep.pid()).unwrap()
+            .identity());
+        assert!(identity.is_alive());
+        assert!(!identity.wait_for_exit(Some(Duration::from_millis(50))));
+
+        // Another process under the same pid is not the same process
+        let reused = ProcessIdentity {
+            start_time: identity.start_time + 1,
+            ..identity
+        };
+        assert!(!reused.is_alive());
+
+        // An exited process counts as dead before it is reaped
+        sleep.0.kill().unwrap();
+        assert!(identity.wait_for_exit(Some(TIMEOUT)));
+        assert!(!identity.is_alive());
+    }
+
+    #[test]
+    fn test_reads_after_exit() {
+        let mut test_process = run_test_binary();
+        let start = test_process.start_addr;
+        let memory_reader = MemoryReader::from_pid(test_process.process_id);
+        assert_eq!(memory_reader.identity().unwrap().pid,
+            test_process.process_id);
+        assert!(memory_reader.is_alive());
+        memory_reader.read_bytes(start, 8).unwrap();
+
+        test_process.child.kill().unwrap();
+        test_process.child.wait().unwrap();
+        assert!(!memory_reader.is_alive());
+
+        let result = memory_reader.read_bytes(start, 8);
+        assert!(matches!(result, Err(Error::ProcessExited(pid))
+                if pid == test_process.process_id),
+            "Unexpected result {:?}", result);
+        for result in memory_reader.read_batch(&[(start, 8), (start + 8, 8)]) {
+            assert!(matches!(result, Err(Error::ProcessExited(_))));
+        }
+    }
+
+    #[test]
+    fn test_reused_pid() {
+        let test_process = run_test_binary();
+        let start = test_process.start_addr;
+        let identity = ProcessIdentity::of(test_process.process_id).unwrap();
+
+        // A reader bound to an earlier process that had the same pid
+        let memory_reader = MemoryReader::with_source(LiveMemory {
+            process_id: identity.pid,
+            start_time: Some(identity.start_time - 1),
+        }).with_write_policy(WritePolicy::enabled());
+
+        let exited = |result: &Result<_, Error>| matches!(result,
+            Err(Error::ProcessExited(pid)) if *pid == identity.pid);
+        assert!(exited(&memory_reader.verify_identity()));
+        assert!(exited(&memory_reader.write_bytes(start, &[1, 2, 3, 4])));
+        for result in memory_reader.read_batch(&[(start, 4), (start + 4, 4)]) {
+            assert!(exited(&result.map(|_| ())));
+        }
+
+        // Nothing was written to the process that took over the pid
+        let current = MemoryReader::attach(identity);
+        current.verify_identity().unwrap();
+        assert_ne!(current.read_bytes(start, 4).unwrap(), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_reattach() {
+        let module = format!("test_binary_{}", std::process::id());
+        let pattern: Pattern = "6D 6D 61 70 20 66 61 69 6C 65 64".parse()
+            .unwrap();
+        let attacher = Attacher::new(ProcessMatcher::Name(module.clone()))
+            .module(&module)
+            .signature("mmap_failed", &module, pattern)
+            .retry_interval(Duration::from_millis(20));
+
+        // Nothing to attach to yet
+        assert!(matches!(attacher.attach(), Err(Error::ProcessNotFound(_))));
+        let (handle, events) = attacher.start();
+
+        let first = run_test_binary();
+        let attachment = next_attachment(&events);
+        assert_eq!(attachment.identity.pid, first.process_id);
+        assert_eq!(attachment.generation, 0);
+        assert_eq!(attachment.module_bases[&module],
+            attachment.reader.module_base(&module).unwrap());
+        let signature = attachment.signatures["mmap_failed"];
+        assert_eq!(attachment.reader.read_bytes(signature, 11).unwrap(),
+            b"mmap failed");
+
+        // A crash is reported, then the next run is attached to
+        let identity = attachment.identity;
+        drop(first);
+        assert!(matches!(next_event(&events),
+            AttachEvent::Exited(exited) if exited == identity));
+
+        let second = run_test_binary();
+        let attachment = next_attachment(&events);
+        assert_eq!(attachment.identity.pid, second.process_id);
+        assert_eq!(attachment.generation, 1);
+        assert!(attachment.reader.is_alive());
+
+        handle.stop();
+    }
+}
@@ -0,0 +1,313 @@
+mod common;
+
+use std::io::Cursor;
+use std::sync::mpsc;
+use std::time::Duration;
+use combat_logger::logger::{
+    Actor, BuffApplied, BuffExpired, Cast, Character, CombatEvent, Damage,
+    DamageType, Error, EventKind, EventSource, GameInfo, Heal, Kill, LogFormat,
+    LogHeader, LogReader, LogWriter, Logger, ResourceChange, ResourceKind,
+    SessionWriter, Timestamp, ZoneChange,
+};
+use combat_logger::memory::{
+    self, AttachEvent, Attachment, MemoryReader, ProcessIdentity,
+};
+use common::run_test_binary;
+
+/// Header of a single-segment test session
+fn header() -> LogHeader {
+    LogHeader::new("1791000000000-42", 0, 1_791_000_000_000, GameInfo {
+        build: "1.0.0".to_string(),
+        character: Some(Character {
+            id: 1,
+            name: "Lilith".to_string(),
+            class: "Sorcerer".to_string(),
+            level: 60,
+        }),
+        world_tier: Some(4),
+        gear: vec!["Harlequin Crest".to_string()],
+    })
+}
+
+/// One event of every kind
+fn events() -> Vec<CombatEvent> {
+    let player = Actor::new(1, "Lilith");
+    let monster = Actor::new(77, "Fallen Shaman");
+    let kinds = vec![
+        EventKind::ZoneChange(ZoneChange { zone: "Kyovashad".to_string() }),
+        EventKind::Cast(Cast {
+            source: player.clone(),
+            skill: "Fireball".to_string(),
+            cooldown: None,
+        }),
+        EventKind::Damage(Damage {
+            source: player.clone(),
+            target: monster.clone(),
+            skill: Some("Fireball".to_string()),
+            damage_type: DamageType::Fire,
+            amount: 1520.5,
+            mitigated: None,
+            absorbed: 0.0,
+            critical: true,
+            overpower: false,
+            vulnerable: true,
+        }),
+        EventKind::BuffApplied(BuffApplied {
+            target: monster.clone(),
+            source: Some(player.clone()),
+            buff: "Vulnerable".to_string(),
+            debuff: true,
+            stacks: 1,
+            duration: Some(3.0),
+        }),
+        EventKind::Damage(Damage {
+            source: monster.clone(),
+            target: player.clone(),
+            skill: None,
+            damage_type: DamageType::Shadow,
+            amount: 210.0,
+            mitigated: Some(90.0),
+            absorbed: 40.0,
+            critical: false,
+            overpower: false,
+            vulnerable: false,
+        }),
+        EventKind::Resource(ResourceChange {
+            actor: player.clone(),
+            resource: ResourceKind::Mana,
+            value: 80.0,
+            max: 100.0,
+        }),
+        EventKind::Heal(Heal {
+            source: player.clone(),
+            target: player.clone(),
+            skill: Some("Healing Potion".to_string()),
+            amount: 500.0,
+            overheal: 120.0,
+        }),
+        EventKind::BuffExpired(BuffExpired {
+            target: monster.clone(),
+            buff: "Vulnerable".to_string(),
+        }),
+        EventKind::Kill(Kill { killer: Some(player), victim: monster }),
+    ];
+
+    kinds.into_iter()
+        .enumerate()
+        .map(|(i, kind)| CombatEvent::new(Timestamp(i as u64 * 250_000), kind))
+        .collect()
+}
+
+/// Writes the test events to an in-memory log
+fn write_log(format: LogFormat) -> Vec<u8> {
+    let mut writer = LogWriter::new(Vec::new(), format, header()).unwrap();
+    for event in events() {
+        writer.write(&event).unwrap();
+    }
+    writer.into_inner().unwrap()
+}
+
+/// Emits one hit per poll, taking the damage from the memory of the test
+/// binary
+struct TestSource {
+    /// Address of the value used as damage
+    address: usize,
+}
+
+impl EventSource for TestSource {
+    fn attach(&mut self, attachment: &Attachment) -> memory::Result<GameInfo> {
+        attachment.reader.read_bytes(self.address, 4)?;
+        Ok(GameInfo { build: "1.0.0".to_string(), ..Default::default() })
+    }
+
+    fn poll(&mut self, attachment: &Attachment)
+            -> memory::Result<Vec<EventKind>> {
+        let bytes = attachment.reader.read_bytes(self.address, 4)?;
+        let amount = u32::from_le_bytes(bytes.try_into().unwrap());
+        Ok(vec![EventKind::Damage(Damage {
+            source: Actor::new(1, "Lilith"),
+            target: Actor::new(2, "Dummy"),
+            skill: Some("Spark".to_string()),
+            damage_type: DamageType::Lightning,
+            amount: amount as f64,
+            mitigated: None,
+            absorbed: 0.0,
+            critical: false,
+            overpower: false,
+            vulnerable: false,
+        })])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_roundtrip() {
+        let log = write_log(LogFormat::Json);
+
+        // Header first, then one tagged object per line
+        let text = String::from_utf8(log.clone()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), events().len() + 1);
+        assert!(lines[0].contains("\"version\":1"));
+        assert!(lines[0].contains("\"class\":\"Sorcerer\""));
+        assert!(lines[3]
+            .starts_with("{\"timestamp\":500000,\"kind\":\"damage\""));
+
+        let reader = LogReader::new(Cursor::new(log)).unwrap();
+        assert_eq!(reader.format(), LogFormat::Json);
+        assert_eq!(reader.header(), &header());
+        let read: Vec<CombatEvent> = reader.map(Result::unwrap).collect();
+        assert_eq!(read, events());
+    }
+
+    #[test]
+    fn test_binary_roundtrip() {
+        let log = write_log(LogFormat::Binary);
+        assert!(log.starts_with(b"D4CL"));
+        assert!(log.len() < write_log(LogFormat::Json).len());
+
+        let reader = LogReader::new(Cursor::new(log)).unwrap();
+        assert_eq!(reader.format(), LogFormat::Binary);
+        assert_eq!(reader.header(), &header());
+        let read: Vec<CombatEvent> = reader.map(Result::unwrap).collect();
+        assert_eq!(read, events());
+    }
+
+    #[test]
+    fn test_truncated_and_corrupt() {
+        for format in [LogFormat::Json, LogFormat::Binary] {
+            let log = write_log(format);
+
+            // A crash mid-write leaves only part of the last record
+            let mut reader = LogReader::new(
+                Cursor::new(&log[..log.len() - 3])).unwrap();
+            for _ in 0..events().len() - 1 {
+                reader.read_event().unwrap().unwrap();
+            }
+            let result = reader.read_event();
+            assert!(matches!(result, Err(Error::Truncated(_))),
+                "Unexpected result {:?}", result);
+            assert!(reader.read_event().unwrap().is_none());
+        }
+
+        // Flip a byte in the payload of the last binary record
+        let mut log = write_log(LogFormat::Binary);
+        let last = log.len() - 2;
+        log[last] ^= 0xFF;
+        let result: Result<Vec<CombatEvent>, Error> =
+            LogReader::new(Cursor::new(log)).unwrap().collect();
+        assert!(matches!(result, Err(Error::Corrupt(..))),
+            "Unexpected result {:?}", result);
+
+        // Huge lengths are corrupt before anything is allocated for them
+        let mut log = write_log(LogFormat::Binary);
+        let header_len = u32::from_le_bytes(log[8..12].try_into().unwrap());
+        let first = 12 + header_len as usize;
+        log[first..first + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let result = LogReader::new(Cursor::new(&log)).unwrap().read_event();
+        assert!(matches!(result, Err(Error::Corrupt(offset, _))
+                if offset == first as u64),
+            "Unexpected result {:?}", result);
+        log[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        let result = LogReader::new(Cursor::new(&log));
+        assert!(matches!(result, Err(Error::Corrupt(..))),
+            "Unexpected result {:?}", result.err());
+    }
+
+    #[test]
+    fn test_newer_version() {
+        for format in [LogFormat::Json, LogFormat::Binary] {
+            let mut newer = header();
+            newer.version = 99;
+            let log = LogWriter::new(Vec::new(), format, newer).unwrap()
+                .into_inner().unwrap();
+
+            let result = LogReader::new(Cursor::new(log));
+            assert!(matches!(result, Err(Error::UnsupportedVersion(99))),
+                "Unexpected result {:?}", result.err());
+        }
+    }
+
+    #[test]
+    fn test_session_segments() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut writer = SessionWriter::new(directory.path(), LogFormat::Json)
+            .unwrap();
+        assert!(matches!(writer.write(&events()[0]), Err(Error::NoSegment)));
+
+        let first = writer.start_segment(0, GameInfo::default()).unwrap();
+        writer.write(&events()[0]).unwrap();
+        let second = writer.start_segment(1, GameInfo::default()).unwrap();
+        writer.write(&events()[1]).unwrap();
+        writer.finish_segment().unwrap();
+        assert_eq!(writer.segments(), [first.clone(), second.clone()]);
+
+        let reader = LogReader::open(&second).unwrap();
+        assert_eq!(reader.header().session_id, writer.session_id());
+        assert_eq!(reader.header().segment, 1);
+        assert_eq!(reader.count(), 1);
+        assert_eq!(LogReader::open(&first).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_logger_segments() {
+        let test_process = run_test_binary();
+        let start = test_process.start_addr;
+        let identity = ProcessIdentity::of(test_process.process_id).unwrap();
+        let attachment = |generation| Box::new(Attachment {
+            identity,
+            reader: MemoryReader::attach(identity),
+            module_bases: Default::default(),
+            signatures: Default::default(),
+            generation,
+        });
+
+        let directory = tempfile::tempdir().unwrap();
+        let writer = SessionWriter::new(directory.path(), LogFormat::Binary)
+            .unwrap();
+        let (attach_tx, attach_rx) = mpsc::channel();
+        let (handle, events) = Logger::new(TestSource { address: start },
+                attach_rx)
+            .record(writer)
+            .poll_interval(Duration::from_millis(5))
+            .start();
+
+        // Two runs of the game, each logged to its own segment
+        let mut timestamps = vec![];
+        for generation in 0..2 {
+            attach_tx.send(AttachEvent::Attached(attachment(generation)))
+                .unwrap();
+            for _ in 0..3 {
+                let event = events.recv_timeout(Duration::from_secs(5))
+                    .unwrap();
+                assert!(matches!(event.kind, EventKind::Damage(_)));
+                timestamps.push(event.timestamp);
+            }
+            attach_tx.send(AttachEvent::Exited(identity)).unwrap();
+            std::thread::sleep(Duration::from_millis(50));
+            while events.try_recv().is_ok() {}
+        }
+
+        let stats = handle.stop();
+        assert_eq!(stats.segments, 2);
+        assert_eq!(stats.write_errors, 0);
+        assert!(timestamps.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        let mut paths: Vec<_> = std::fs::read_dir(directory.path()).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        paths.sort();
+        assert_eq!(paths.len(), 2);
+        let mut logged = 0;
+        for (segment, path) in paths.iter().enumerate() {
+            let reader = LogReader::open(path).unwrap();
+            assert_eq!(reader.header().segment, segment as u64);
+            assert_eq!(reader.header().game.build, "1.0.0");
+            logged += reader.map(Result::unwrap).count() as u64;
+        }
+        assert_eq!(logged, stats.events);
+    }
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use combat_logger::memory::{MemoryReader, MemoryRegion};
+use common::run_test_binary;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wine_module() {
+        let line = "140000000-140001000 r--p 00000000 fd:01 2883614      \
+            /home/user/Games/drive_c/Diablo IV/Diablo IV.exe";
+        let region: MemoryRegion = line.parse().unwrap();
+
+        assert_eq!(region.range, 0x140000000..0x140001000);
+        assert_eq!(region.permissions.to_string(), "r--p");
+        assert_eq!(region.offset, 0);
+        assert_eq!(region.device, (0xfd, 0x01));
+        assert_eq!(region.inode, 2883614);
+        assert!(region.is_readable());
+        assert!(!region.is_writable());
+        assert!(!region.is_anonymous());
+        assert!(region.is_backed_by("Diablo IV.exe"));
+        assert!(!region.is_backed_by("Diablo IV"));
+    }
+
+    #[test]
+    fn test_parse_anonymous() {
+        let anonymous: MemoryRegion =
+            "7f0000000000-7f0000001000 rw-p 00000000 00:00 0".parse().unwrap();
+        assert!(anonymous.is_anonymous());
+        assert_eq!(anonymous.pathname, None);
+        assert_eq!(anonymous.len(), 0x1000);
+
+        let heap: MemoryRegion =
+            "55d0c000-55d2d000 rw-p 00000000 00:00 0    [heap]".parse().unwrap();
+        assert!(heap.is_anonymous());
+        assert_eq!(heap.pathname.as_deref(), Some("[heap]"));
+        assert!(!heap.is_backed_by("[heap]"));
+
+        assert!("not a maps line".parse::<MemoryRegion>().is_err());
+    }
+
+    #[test]
+    fn test_regions() {
+        let test_process = run_test_binary();
+        let memory_reader = MemoryReader::from_pid(test_process.process_id);
+
+        // The page mapped by the test binary should be listed as an
+        // anonymous read/write region
+        let regions = memory_reader.regions().unwrap();
+        let start = test_process.start_addr;
+        let region = regions.iter()
+            .find(|region| region.contains(start))
+            .expect("Mapped region not listed");
+        assert!(region.is_readable());
+        assert!(region.is_writable());
+        assert!(region.is_anonymous());
+        assert!(region.range.end >= (start + test_process.size) as u64);
+    }
+
+    #[test]
+    fn test_module_base() {
+        let test_process = run_test_binary();
+        let memory_reader = MemoryReader::from_pid(test_process.process_id);
+
+        // The executable is mapped starting at its ELF header
+        let module = format!("test_binary_{}", std::process::id());
+        let base = memory_reader.module_base(&module).unwrap();
+        assert_eq!(memory_reader.read_bytes(base, 4).unwrap(), b"\x7fELF");
+
+        assert!(memory_reader.module_base("no_such_module.exe").is_err());
+    }
+}
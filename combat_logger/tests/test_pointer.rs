@@ -0,0 +1,99 @@
+mod common;
+
+use combat_logger::memory::{Error, MemoryReader, PointerChain, WritePolicy};
+use common::run_test_binary;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_reads() {
+        let test_process = run_test_binary();
+        let start = test_process.start_addr;
+        let memory_reader = MemoryReader::from_pid(test_process.process_id)
+            .with_write_policy(WritePolicy::enabled());
+
+        memory_reader.write_bytes(start, &0xDEADBEEFu32.to_le_bytes())
+            .unwrap();
+        memory_reader.write_bytes(start + 8, &1.5f32.to_le_bytes()).unwrap();
+        memory_reader.write_bytes(start + 12, &(-2.25f64).to_le_bytes())
+            .unwrap();
+
+        assert_eq!(memory_reader.read_u32(start).unwrap(), 0xDEADBEEF);
+        assert_eq!(memory_reader.read_u64(start).unwrap(), 0xDEADBEEF);
+        assert_eq!(memory_reader.read::<u16>(start + 2).unwrap(), 0xDEAD);
+        assert_eq!(memory_reader.read_f32(start + 8).unwrap(), 1.5);
+        assert_eq!(memory_reader.read_f64(start + 12).unwrap(), -2.25);
+        assert_eq!(memory_reader.read::<[u8; 2]>(start).unwrap(),
+            [0xEF, 0xBE]);
+    }
+
+    #[test]
+    fn test_pointer_chain() {
+        let test_process = run_test_binary();
+        let start = test_process.start_addr;
+        let memory_reader = MemoryReader::from_pid(test_process.process_id)
+            .with_write_policy(WritePolicy::enabled());
+
+        // Use the unused tail of the binary's last writable mapping as the
+        // static root of the chain
+        let module = format!("test_binary_{}", std::process::id());
+        let base = memory_reader.module_base(&module).unwrap();
+        let root = memory_reader.module_regions(&module).unwrap()
+            .iter()
+            .rfind(|region| region.is_writable())
+            .map(|region| region.range.end as usize - 8)
+            .expect("Module has no writable region");
+
+        // root -> start, [start+0x10] -> start+0x100, value at +0x40
+        memory_reader.write_bytes(root, &(start as u64).to_le_bytes())
+            .unwrap();
+        memory_reader.write_bytes(start + 0x10,
+            &(start as u64 + 0x100).to_le_bytes()).unwrap();
+        memory_reader.write_bytes(start + 0x140, &42.0f32.to_le_bytes())
+            .unwrap();
+
+        let chain = PointerChain::new(&module, &[root - base, 0x10, 0x40]);
+        assert_eq!(memory_reader.read_ptr(root).unwrap(), start);
+        assert_eq!(memory_reader.resolve_chain(&chain).unwrap(),
+            start + 0x140);
+        assert_eq!(memory_reader.read_chain::<f32>(&chain).unwrap(), 42.0);
+        assert_eq!(chain.to_string(),
+            format!("[[\"{}\"+0x{:X}]+0x10]+0x40", module, root - base));
+
+        // A null pointer reports the level and the address it was read from
+        memory_reader.write_bytes(start + 0x10, &0u64.to_le_bytes()).unwrap();
+        match memory_reader.resolve_chain(&chain) {
+            Err(Error::NullPointer { level, address }) => {
+                assert_eq!(level, 1);
+                assert_eq!(address, start + 0x10);
+            }
+            other => panic!("Expected a null pointer error, got {:?}", other),
+        }
+
+        // An unmapped pointer in the middle reports the level it broke at
+        memory_reader.write_bytes(root, &0x1000u64.to_le_bytes()).unwrap();
+        match memory_reader.resolve_chain(&chain) {
+            Err(Error::ChainFailed { level, address, source }) => {
+                assert_eq!(level, 1);
+                assert_eq!(address, 0x1010);
+                assert!(matches!(*source, Error::ReadMemoryFailed(..)));
+            }
+            other => panic!("Expected a chain failure, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_chain_without_levels() {
+        let test_process = run_test_binary();
+        let memory_reader = MemoryReader::from_pid(test_process.process_id);
+
+        // A single offset is relative to the module base without any
+        // dereference
+        let module = format!("test_binary_{}", std::process::id());
+        let chain = PointerChain::new(&module, &[1]);
+        assert_eq!(memory_reader.read_chain::<[u8; 3]>(&chain).unwrap(),
+            *b"ELF");
+    }
+}
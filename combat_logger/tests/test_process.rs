@@ -0,0 +1,104 @@
+mod common;
+
+use combat_logger::memory::{
+    find_processes, MemoryReader, ProcessInfo, ProcessMatcher,
+};
+use common::{run_test_binary, spawn_sleep};
+use regex::Regex;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_info() {
+        let child = spawn_sleep("301.5");
+        let process = ProcessInfo::read(child.pid()).unwrap();
+
+        assert_eq!(process.pid, child.pid());
+        assert_eq!(process.comm, "sleep");
+        assert_eq!(process.cmdline, vec!["sleep", "301.5"]);
+        assert_eq!(process.uid, unsafe { libc::getuid() });
+        assert!(process.start_time > 0);
+    }
+
+    #[test]
+    fn test_find_by_name() {
+        let test_process = run_test_binary();
+        let name = format!("test_binary_{}", std::process::id());
+
+        let found = find_processes(&ProcessMatcher::Name(name.clone()))
+            .unwrap();
+        assert!(found.iter().any(|p| p.pid == test_process.process_id));
+
+        // The kernel truncates `comm`, but long names should still match
+        assert_eq!(
+            MemoryReader::find_process(&name).unwrap(),
+            test_process.process_id
+        );
+
+        // Partial names must not match
+        let partial = ProcessMatcher::Name("test_binary".to_string());
+        assert!(!find_processes(&partial).unwrap()
+            .iter().any(|p| p.pid == test_process.process_id));
+    }
+
+    #[test]
+    fn test_find_by_regex_and_cmdline() {
+        let child = spawn_sleep("302.25");
+        let pid = child.pid();
+
+        let by_regex = ProcessMatcher::Regex(Regex::new("^sl(e)+p$").unwrap());
+        assert!(find_processes(&by_regex).unwrap()
+            .iter().any(|p| p.pid == pid));
+
+        let by_cmdline = ProcessMatcher::Cmdline("sleep 302.25".to_string());
+        let found = find_processes(&by_cmdline).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].pid, pid);
+    }
+
+    #[test]
+    fn test_wine_style_argument() {
+        let process = ProcessInfo {
+            pid: 1,
+            uid: 1000,
+            start_time: 1,
+            comm: "wine64-preload".to_string(),
+            cmdline: vec![r"C:\Program Files\Diablo IV\Diablo IV.exe".into()],
+            exe: Some("/usr/bin/wine64-preloader".into()),
+        };
+
+        let name = ProcessMatcher::Name("Diablo IV.exe".to_string());
+        assert!(name.matches(&process));
+        let partial = ProcessMatcher::Name("Diablo IV".to_string());
+        assert!(!partial.matches(&process));
+    }
+
+    #[test]
+    fn test_truncated_comm_needs_confirmation() {
+        let mut process = ProcessInfo {
+            pid: 1,
+            uid: 1000,
+            start_time: 1,
+            comm: "DiabloIVLaunch_".to_string(),
+            cmdline: vec!["/opt/other/DiabloIVLaunch_helper".into()],
+            exe: None,
+        };
+
+        // Another process whose name shares the truncated prefix
+        let name = ProcessMatcher::Name("DiabloIVLaunch_game".to_string());
+        assert!(!name.matches(&process));
+
+        process.cmdline = vec!["/opt/d4/DiabloIVLaunch_game".into()];
+        assert!(name.matches(&process));
+        process.cmdline = vec![];
+        process.exe = Some("/opt/d4/DiabloIVLaunch_game".into());
+        assert!(name.matches(&process));
+    }
+
+    #[test]
+    fn test_process_not_found() {
+        assert!(MemoryReader::find_process("no such process name").is_err());
+    }
+}
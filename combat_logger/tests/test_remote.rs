@@ -0,0 +1,169 @@
+mod common;
+
+use combat_logger::game_data;
+use combat_logger::memory::{
+    MemoryReader, MockMemory, RemoteField, RemotePtr, RemoteStruct, Value,
+    WritePolicy,
+};
+use common::run_test_binary;
+
+/// Heap address used by the mock game objects
+const HEAP_BASE: usize = 0x7f00_0000_0000;
+
+#[derive(Debug, PartialEq, RemoteStruct)]
+struct Position {
+    #[offset(0x0)]
+    x: f32,
+
+    #[offset(0x4)]
+    y: f32,
+}
+
+#[derive(Debug, PartialEq, RemoteStruct)]
+struct Monster {
+    #[offset(0x0)]
+    id: u32,
+
+    #[offset(0x8)]
+    position: Position,
+
+    #[offset(0x10)]
+    resistances: [i16; 3],
+
+    #[offset(0x16)]
+    elite: bool,
+
+    #[offset(0x18)]
+    #[pointer]
+    target: RemotePtr<Monster>,
+
+    #[offset(0x20)]
+    #[pointer]
+    name: RemotePtr<[u8; 4]>,
+}
+
+#[derive(Debug, PartialEq, RemoteStruct)]
+#[remote(size = 0x100)]
+struct Padded {
+    #[offset(0x10)]
+    value: u64,
+}
+
+/// Writes a monster at the given heap offset
+fn write_monster(heap: &mut [u8], offset: usize, id: u32, target: usize) {
+    let monster = &mut heap[offset..offset + 0x28];
+    monster[0x0..0x4].copy_from_slice(&id.to_le_bytes());
+    monster[0x8..0xC].copy_from_slice(&1.5f32.to_le_bytes());
+    monster[0xC..0x10].copy_from_slice(&(-4.0f32).to_le_bytes());
+    monster[0x10..0x12].copy_from_slice(&25i16.to_le_bytes());
+    monster[0x14..0x16].copy_from_slice(&(-10i16).to_le_bytes());
+    monster[0x16] = 1;
+    monster[0x18..0x20].copy_from_slice(&(target as u64).to_le_bytes());
+    monster[0x20..0x28].copy_from_slice(
+        &(HEAP_BASE as u64 + 0x800).to_le_bytes());
+}
+
+/// Builds two monsters, the first one targeting the second
+fn mock_monsters() -> MockMemory {
+    let mut heap = vec![0u8; 0x1000];
+    write_monster(&mut heap, 0x0, 7, HEAP_BASE + 0x100);
+    write_monster(&mut heap, 0x100, 8, 0);
+    heap[0x800..0x804].copy_from_slice(b"Imp\0");
+
+    MockMemory::new().with_buffer(HEAP_BASE, heap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_struct_size() {
+        assert_eq!(Position::SIZE, 8);
+        assert_eq!(Monster::SIZE, 0x28);
+        assert_eq!(Padded::SIZE, 0x100);
+        assert_eq!(<[Position; 3]>::SIZE, 24);
+    }
+
+    #[test]
+    fn test_read_remote() {
+        let memory_reader = MemoryReader::with_source(mock_monsters());
+        let monster: Monster = memory_reader.read_remote(HEAP_BASE).unwrap();
+
+        assert_eq!(monster.id, 7);
+        assert_eq!(monster.position, Position { x: 1.5, y: -4.0 });
+        assert_eq!(monster.resistances, [25, 0, -10]);
+        assert!(monster.elite);
+        assert_eq!(monster.name.read(&memory_reader).unwrap(),
+            Some(*b"Imp\0"));
+
+        // Pointers are only followed when read
+        assert_eq!(monster.target.address(), HEAP_BASE + 0x100);
+        let target = monster.target.read(&memory_reader).unwrap().unwrap();
+        assert_eq!(target.id, 8);
+        assert!(target.target.is_null());
+        assert_eq!(target.target.read(&memory_reader).unwrap(), None);
+
+        let dangling = RemotePtr::<Monster>::new(0x1000);
+        assert!(dangling.read(&memory_reader).is_err());
+    }
+
+    #[test]
+    fn test_read_remote_batch() {
+        let memory_reader = MemoryReader::with_source(mock_monsters());
+        let monsters = memory_reader.read_remote_batch::<Monster>(
+            &[HEAP_BASE + 0x100, 0x1000, HEAP_BASE]);
+
+        assert_eq!(monsters[0].as_ref().unwrap().id, 8);
+        assert!(monsters[1].is_err());
+        assert_eq!(monsters[2].as_ref().unwrap().id, 7);
+    }
+
+    #[test]
+    fn test_game_data_matches_layouts() {
+        // The typed structs mirror the template layouts
+        let layouts = game_data::template_layouts().unwrap();
+        let size = |name: &str| layouts.size_of(&name.parse().unwrap())
+            .unwrap();
+        assert_eq!(game_data::Player::SIZE, size("Player"));
+        assert_eq!(game_data::Actor::SIZE, size("Actor"));
+        assert_eq!(game_data::Buff::SIZE, size("Buff"));
+        assert_eq!(game_data::Vector3::SIZE, size("Vector3"));
+
+        // And both read the same values from memory
+        let mut heap = vec![0u8; 0x1000];
+        heap[0x10..0x18].copy_from_slice(&(HEAP_BASE as u64 + 0x400)
+            .to_le_bytes());
+        heap[0x40..0x44].copy_from_slice(&60u32.to_le_bytes());
+        heap[0x80..0x88].copy_from_slice(&123456u64.to_le_bytes());
+        heap[0x5E0..0x5E4].copy_from_slice(&950.0f32.to_le_bytes());
+        let memory_reader = MemoryReader::with_source(
+            MockMemory::new().with_buffer(HEAP_BASE, heap));
+
+        let player: game_data::Player = memory_reader.read_remote(HEAP_BASE)
+            .unwrap();
+        let actor = player.actor.read(&memory_reader).unwrap().unwrap();
+        let dynamic = memory_reader.read_layout(&layouts, "Player", HEAP_BASE)
+            .unwrap();
+        assert_eq!(player.level, 60);
+        assert_eq!(player.gold, 123456);
+        assert_eq!(actor.health, 950.0);
+        assert_eq!(dynamic.get("gold").unwrap().as_value(),
+            Some(Value::U64(player.gold)));
+        assert_eq!(dynamic.get("actor").unwrap().get("health").unwrap()
+            .as_value(), Some(Value::F32(actor.health)));
+    }
+
+    #[test]
+    fn test_live_read() {
+        let test_process = run_test_binary();
+        let start = test_process.start_addr;
+        let memory_reader = MemoryReader::from_pid(test_process.process_id)
+            .with_write_policy(WritePolicy::enabled());
+
+        memory_reader.write_bytes(start + 0x10, &0x00D1_AB10u64.to_le_bytes())
+            .unwrap();
+        let padded: Padded = memory_reader.read_remote(start).unwrap();
+        assert_eq!(padded, Padded { value: 0x00D1_AB10 });
+    }
+}
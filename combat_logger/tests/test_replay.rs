@@ -0,0 +1,155 @@
+mod common;
+
+use std::io::Cursor;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use combat_logger::logger::{
+    Actor, Cast, CombatEvent, Error, EventKind, GameInfo, LogFormat, LogHeader,
+    LogReader, LogWriter, Playback, Replay, Timestamp, ZoneChange,
+};
+
+/// Alternating casts and zone changes, 100ms apart
+fn events() -> Vec<CombatEvent> {
+    (0..10u64)
+        .map(|i| {
+            let kind = if i % 2 == 0 {
+                EventKind::Cast(Cast {
+                    source: Actor::new(1, "Lilith"),
+                    skill: format!("Skill {}", i),
+                    cooldown: None,
+                })
+            }
+            else {
+                EventKind::ZoneChange(ZoneChange { zone: format!("Zone {}", i) })
+            };
+            CombatEvent::new(Timestamp(i * 100_000), kind)
+        })
+        .collect()
+}
+
+/// Writes the test events to a log file
+fn write_log(path: &Path, format: LogFormat) {
+    common::write_log(path, format,
+        LogHeader::new("replay", 0, 0, GameInfo::default()), &events());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instant_replay() {
+        let directory = tempfile::tempdir().unwrap();
+        for format in [LogFormat::Json, LogFormat::Binary] {
+            let path = directory.path().join(format.extension());
+            write_log(&path, format);
+
+            let replay = Replay::open(&path).unwrap();
+            assert_eq!(replay.header().session_id, "replay");
+            let played: Vec<CombatEvent> = replay.map(Result::unwrap)
+                .collect();
+            assert_eq!(played, events());
+        }
+    }
+
+    #[test]
+    fn test_kind_filter() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("log.jsonl");
+        write_log(&path, LogFormat::Json);
+
+        let casts: Vec<CombatEvent> = Replay::open(&path).unwrap()
+            .kinds(&["cast"]).unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(casts.len(), 5);
+        assert!(casts.iter().all(|event| event.kind.name() == "cast"));
+
+        let result = Replay::open(&path).unwrap().kinds(&["explosion"]);
+        assert!(matches!(result, Err(Error::BadArgument(_))));
+    }
+
+    #[test]
+    fn test_seek() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("log.d4log");
+        write_log(&path, LogFormat::Binary);
+        let mut replay = Replay::open(&path).unwrap();
+
+        // Seek past the current position, then back before it
+        replay.seek(Timestamp(650_000)).unwrap();
+        let event = replay.next().unwrap().unwrap();
+        assert_eq!(event.timestamp, Timestamp(700_000));
+
+        replay.seek(Timestamp(200_000)).unwrap();
+        let rest: Vec<Timestamp> = replay
+            .map(|event| event.unwrap().timestamp)
+            .collect();
+        assert_eq!(rest.first(), Some(&Timestamp(200_000)));
+        assert_eq!(rest.len(), 8);
+    }
+
+    #[test]
+    fn test_paced_replay() {
+        let log = {
+            let header = LogHeader::new("paced", 0, 0, GameInfo::default());
+            let mut writer = LogWriter::new(Vec::new(), LogFormat::Json,
+                header).unwrap();
+            for event in events() {
+                writer.write(&event).unwrap();
+            }
+            writer.into_inner().unwrap()
+        };
+
+        // 900ms of recorded events at ten times the speed
+        let start = Instant::now();
+        let replay = Replay::new(LogReader::new(Cursor::new(log.clone()))
+                .unwrap())
+            .playback(Playback::Speed(10.0));
+        assert_eq!(replay.count(), 10);
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(85), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(600), "{:?}", elapsed);
+
+        // Three zone changes 200ms apart in real time
+        let start = Instant::now();
+        let replay = Replay::new(LogReader::new(Cursor::new(log)).unwrap())
+            .playback(Playback::RealTime)
+            .kinds(&["zone_change"]).unwrap();
+        assert_eq!(replay.take(3).count(), 3);
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(395), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn test_replay_channel() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("log.jsonl");
+        write_log(&path, LogFormat::Json);
+
+        let (handle, events_rx) = Replay::open(&path).unwrap().start();
+        let played: Vec<CombatEvent> = events_rx.iter().collect();
+        assert_eq!(played, events());
+        assert_eq!(handle.wait().unwrap(), 10);
+
+        // Stopping a slow replay does not wait for the rest of the log
+        let (handle, events_rx) = Replay::open(&path).unwrap()
+            .playback(Playback::Speed(0.01))
+            .start();
+        events_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        let start = Instant::now();
+        assert_eq!(handle.stop().unwrap(), 1);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_parse_playback() {
+        assert_eq!("realtime".parse::<Playback>().unwrap(),
+            Playback::RealTime);
+        assert_eq!("instant".parse::<Playback>().unwrap(), Playback::Instant);
+        assert_eq!("4x".parse::<Playback>().unwrap(), Playback::Speed(4.0));
+        assert_eq!("0.5".parse::<Playback>().unwrap(), Playback::Speed(0.5));
+        assert!("0x".parse::<Playback>().is_err());
+        assert!("fast".parse::<Playback>().is_err());
+    }
+}
@@ -0,0 +1,229 @@
+mod common;
+
+use combat_logger::analysis::RotationTracker;
+use combat_logger::logger::{Actor, Cast, CombatEvent, EventKind, Timestamp};
+use common::damage;
+
+/// Actor identifier of the player
+const PLAYER: u64 = 1;
+
+/// A hit by the player at the given second
+fn hit(second: u64) -> CombatEvent {
+    common::hit(second as f64, damage(Actor::new(PLAYER, "Player"),
+        Actor::new(2, "Target"), None, 100.0))
+}
+
+/// A cast at the given second
+fn cast(second: u64, source: u64, skill: &str, cooldown: Option<f64>)
+        -> CombatEvent {
+    CombatEvent::new(Timestamp(second * 1_000_000), EventKind::Cast(Cast {
+        source: Actor::new(source, "Source"),
+        skill: skill.to_string(),
+        cooldown,
+    }))
+}
+
+/// A twenty second encounter of a basic rotation, and a single hit later on
+fn session() -> Vec<CombatEvent> {
+    let mut events = vec![
+        cast(0, PLAYER, "Frost Bolt", None),
+        cast(1, PLAYER, "Ice Shards", None),
+        cast(2, PLAYER, "Frost Nova", Some(10.0)),
+        cast(3, PLAYER, "Frost Bolt", None),
+        cast(4, PLAYER, "Ice Shards", None),
+        cast(5, PLAYER, "Frost Bolt", None),
+        cast(6, PLAYER, "Ice Shards", None),
+        cast(7, PLAYER, "Frost Bolt", None),
+        cast(8, PLAYER, "Ice Shards", None),
+        // Ready again at 12 seconds
+        cast(15, PLAYER, "Frost Nova", Some(10.0)),
+        cast(16, PLAYER, "Frost Bolt", None),
+        cast(17, PLAYER, "Ice Shards", None),
+        // Casts by monsters are ignored
+        cast(18, 2, "Leap", Some(4.0)),
+        cast(40, PLAYER, "Frost Bolt", None),
+    ];
+    events.extend((0..=20).map(hit));
+    events.push(hit(40));
+    events.sort_by_key(|event| event.timestamp);
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeline() {
+        let mut tracker = RotationTracker::new(PLAYER);
+        for event in session() {
+            tracker.push(&event);
+        }
+
+        let timeline = tracker.timeline();
+        assert_eq!(timeline.len(), 13);
+        assert!(timeline.iter().all(|cast| cast.skill != "Leap"));
+
+        // The first cast of a skill has no known cooldown state
+        let novas: Vec<_> = timeline.iter()
+            .filter(|cast| cast.skill == "Frost Nova")
+            .collect();
+        assert_eq!(novas[0].ready, None);
+        assert_eq!(novas[1].ready, Some(Timestamp(12_000_000)));
+        assert_eq!(novas[1].delay, Some(3.0));
+        assert_eq!(timeline[0].delay, None);
+    }
+
+    #[test]
+    fn test_rotation_report() {
+        let mut tracker = RotationTracker::new(PLAYER)
+            .sequence_length(2)
+            .top_sequences(4);
+        for event in session() {
+            tracker.push(&event);
+        }
+        let report = tracker.report();
+        assert_eq!(report.encounters.len(), 2);
+
+        let first = &report.encounters[0];
+        assert_eq!(first.casts, 12);
+        assert_eq!(first.apm, 36.0);
+
+        let skills: Vec<(&str, u64)> = first.skills.iter()
+            .map(|skill| (skill.skill.as_str(), skill.casts))
+            .collect();
+        assert_eq!(skills,
+            [("Frost Bolt", 5), ("Ice Shards", 5), ("Frost Nova", 2)]);
+        assert_eq!(first.skills[0].casts_per_minute, 15.0);
+        assert_eq!(first.skills[0].wasted, None);
+
+        // Ready again from 12 to 15 seconds
+        let nova = &first.skills[2];
+        assert_eq!(nova.cooldown, Some(10.0));
+        assert_eq!(nova.wasted, Some(3.0));
+        assert_eq!(nova.wasted_share, Some(0.15));
+
+        let sequences: Vec<(String, u64)> = first.sequences.iter()
+            .map(|sequence| (sequence.skills.join(" > "), sequence.count))
+            .collect();
+        // Ties are broken by skill names
+        assert_eq!(sequences, [
+            ("Frost Bolt > Ice Shards".to_string(), 5),
+            ("Frost Nova > Frost Bolt".to_string(), 2),
+            ("Ice Shards > Frost Bolt".to_string(), 2),
+            ("Ice Shards > Frost Nova".to_string(), 2),
+        ]);
+
+        // Skills with a cooldown show up even when left unused
+        let second = &report.encounters[1];
+        let skills: Vec<(&str, u64)> = second.skills.iter()
+            .map(|skill| (skill.skill.as_str(), skill.casts))
+            .collect();
+        assert_eq!(skills, [("Frost Bolt", 1), ("Frost Nova", 0)]);
+        assert!(second.sequences.is_empty());
+    }
+
+    #[test]
+    fn test_skill_added_mid_session() {
+        // Frost Nova only goes on the bar in the second encounter, and sits
+        // ready for the last two seconds of it
+        let mut events = vec![
+            cast(0, PLAYER, "Frost Bolt", None),
+            cast(30, PLAYER, "Frost Nova", Some(3.0)),
+        ];
+        events.extend([hit(0), hit(5), hit(30), hit(35)]);
+        events.sort_by_key(|event| event.timestamp);
+
+        let mut tracker = RotationTracker::new(PLAYER);
+        for event in events {
+            tracker.push(&event);
+        }
+        let report = tracker.report();
+        assert_eq!(report.encounters.len(), 2);
+
+        let skills = |index: usize| -> Vec<&str> {
+            report.encounters[index].skills.iter()
+                .map(|skill| skill.skill.as_str())
+                .collect()
+        };
+        assert_eq!(skills(0), ["Frost Bolt"]);
+        assert_eq!(skills(1), ["Frost Nova"]);
+        assert_eq!(report.encounters[1].skills[0].wasted, Some(2.0));
+    }
+
+    #[test]
+    fn test_report_output() {
+        let mut tracker = RotationTracker::new(PLAYER);
+        for event in session() {
+            tracker.push(&event);
+        }
+        let report = tracker.report();
+
+        let mut csv = vec![];
+        report.write_timeline(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 14);
+        assert_eq!(lines[0], "time,skill,cooldown,ready,delay");
+        assert_eq!(lines[1], "0.000,Frost Bolt,,,");
+        assert_eq!(lines[10], "15.000,Frost Nova,10.000,12.000,3.000");
+
+        let table = report.to_string();
+        assert!(table.contains("Encounter 1  0:00.0 - 0:20.0  12 casts"));
+        assert!(table.lines().any(|line| line.starts_with("Frost Nova")
+            && line.contains("3.0s") && line.contains("15.0%")));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["timeline"][9]["delay"], 3.0);
+        assert_eq!(json["encounters"][0]["apm"], 36.0);
+    }
+
+    #[test]
+    fn test_opening_cast() {
+        // The cast lands 200ms before the hit it starts the fight with, and
+        // a cast further back than the idle gap is not part of it
+        let mut events = vec![
+            cast(0, PLAYER, "Teleport", None),
+            cast(10, PLAYER, "Frost Nova", Some(10.0)),
+        ];
+        events[1].timestamp = Timestamp(9_800_000);
+        events.extend([hit(10), hit(11), hit(12)]);
+
+        let mut tracker = RotationTracker::new(PLAYER);
+        for event in events {
+            tracker.push(&event);
+        }
+        let report = tracker.report();
+        assert_eq!(report.encounters.len(), 1);
+        assert_eq!(report.encounters[0].encounter.start, Timestamp(10_000_000));
+        assert_eq!(report.encounters[0].casts, 1);
+        assert_eq!(report.encounters[0].skills[0].skill, "Frost Nova");
+        assert_eq!(report.encounters[0].active_secs, 2.2);
+        assert_eq!(report.apm, 1.0 / (2.2 / 60.0));
+    }
+
+    #[test]
+    fn test_opener_counts_towards_rates() {
+        // Three casts set up the fight before the first of two hits a
+        // second apart
+        let mut events = vec![
+            cast(7, PLAYER, "Ice Blades", None),
+            cast(8, PLAYER, "Frost Nova", None),
+            cast(9, PLAYER, "Blizzard", None),
+            cast(10, PLAYER, "Frost Bolt", None),
+        ];
+        events.extend([hit(10), hit(11)]);
+
+        let mut tracker = RotationTracker::new(PLAYER);
+        for event in events {
+            tracker.push(&event);
+        }
+        let report = tracker.report();
+        let encounter = &report.encounters[0];
+        assert_eq!(encounter.casts, 4);
+        assert_eq!(encounter.active_secs, 4.0);
+        assert_eq!(encounter.apm, 60.0);
+        assert_eq!(encounter.skills[0].casts_per_minute, 15.0);
+        assert_eq!(report.apm, 60.0);
+    }
+}
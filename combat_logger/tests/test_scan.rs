@@ -0,0 +1,81 @@
+mod common;
+
+use combat_logger::memory::{MemoryReader, Pattern, WritePolicy};
+use common::run_test_binary;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pattern() {
+        let pattern: Pattern = "48 8B 05 ?? ? ?? ?? 48 85 C0".parse().unwrap();
+        assert_eq!(pattern.len(), 10);
+        assert!(pattern.matches(
+            &[0x48, 0x8B, 0x05, 1, 2, 3, 4, 0x48, 0x85, 0xC0, 0xFF]));
+        assert!(!pattern.matches(&[0x48, 0x8B, 0x05, 1, 2, 3, 4, 0x48, 0x85]));
+
+        assert!("48 8G".parse::<Pattern>().is_err());
+        assert!("488B".parse::<Pattern>().is_err());
+        assert!("?? ??".parse::<Pattern>().is_err());
+    }
+
+    #[test]
+    fn test_find_all() {
+        let pattern: Pattern = "?? AA ?? AA".parse().unwrap();
+        let data = [0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0x00, 0xAA];
+        let offsets: Vec<usize> = pattern.find_all(&data).collect();
+        assert_eq!(offsets, vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn test_scan_process() {
+        let test_process = run_test_binary();
+        let memory_reader = MemoryReader::from_pid(test_process.process_id)
+            .with_write_policy(WritePolicy::enabled());
+
+        // Plant a `mov rax, [rip+0x20]` with a distinctive suffix, and a
+        // truncated copy at the very end of the region
+        let signature = [
+            0x48, 0x8B, 0x05, 0x20, 0x00, 0x00, 0x00,
+            0xD4, 0x7E, 0x0F, 0x13, 0x37, 0x5C, 0xA1,
+        ];
+        let first = test_process.start_addr + 0x100;
+        let second = test_process.start_addr + test_process.size - 9;
+        memory_reader.write_bytes(first, &signature).unwrap();
+        memory_reader.write_bytes(second, &signature[..9]).unwrap();
+        memory_reader.write_bytes(first + 7 + 0x20, &[0xC0, 0xFF, 0xEE])
+            .unwrap();
+
+        let pattern: Pattern = "48 8B 05 ?? ?? ?? ?? D4 7E 0F 13 37"
+            .parse().unwrap();
+        let matches = memory_reader.scan(&pattern).unwrap();
+        assert_eq!(matches, vec![first]);
+
+        // The truncated copy only matches a shorter pattern
+        let prefix: Pattern = "48 8B 05 20 00 00 00 D4 7E".parse().unwrap();
+        let region = test_process.start_addr as u64
+            ..(test_process.start_addr + test_process.size) as u64;
+        let matches = memory_reader.scan_range(region, &prefix).unwrap();
+        assert_eq!(matches, vec![first, second]);
+
+        // The displacement is relative to the end of the instruction
+        let target = memory_reader.resolve_rip_relative(first, 3, 7).unwrap();
+        assert_eq!(target, first + 7 + 0x20);
+        assert_eq!(memory_reader.read_bytes(target, 3).unwrap(),
+            [0xC0, 0xFF, 0xEE]);
+    }
+
+    #[test]
+    fn test_scan_module() {
+        let test_process = run_test_binary();
+        let memory_reader = MemoryReader::from_pid(test_process.process_id);
+
+        // Every ELF executable starts with its magic and class bytes
+        let module = format!("test_binary_{}", std::process::id());
+        let pattern: Pattern = "7F 45 4C 46 02".parse().unwrap();
+        let matches = memory_reader.scan_module(&module, &pattern).unwrap();
+        assert_eq!(matches.first().copied(),
+            Some(memory_reader.module_base(&module).unwrap()));
+    }
+}
@@ -0,0 +1,365 @@
+mod common;
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use combat_logger::logger::{
+    Actor, BuffApplied, CombatEvent, EventKind, GameInfo, LogFormat, LogHeader,
+    Replay, Timestamp, ZoneChange,
+};
+use common::{damage, hit};
+use combat_logger::server::{
+    Server, ServerHandle, DEFAULT_ADDRESS, MAX_CONNECTIONS,
+};
+use serde_json::Value;
+use tungstenite::{Message, WebSocket};
+
+/// Actor identifier of the player
+const PLAYER: u64 = 1;
+
+/// A zone change, a buff on the player and a hit every second
+fn events() -> Vec<CombatEvent> {
+    let player = Actor::new(PLAYER, "Lilith");
+    let mut events = vec![
+        CombatEvent::new(Timestamp(0), EventKind::ZoneChange(ZoneChange {
+            zone: "Kyovashad".to_string(),
+        })),
+        CombatEvent::new(Timestamp(0), EventKind::BuffApplied(BuffApplied {
+            target: player.clone(),
+            source: None,
+            buff: "Berserking".to_string(),
+            debuff: false,
+            stacks: 1,
+            duration: Some(10.0),
+        })),
+    ];
+    events.extend((0..5).map(|second| hit(second as f64, damage(
+        player.clone(), Actor::new(2, "Fallen Shaman"), Some("Spark"), 250.0))));
+    events
+}
+
+/// Writes the test events to a log
+fn write_log(path: &Path) {
+    common::write_log(path, LogFormat::Json,
+        LogHeader::new("serve", 0, 0, GameInfo::default()), &events());
+}
+
+/// Starts a server on a free port with the replayed log as its source,
+/// returning the channel the replay is fed through
+fn start_server() -> (ServerHandle, mpsc::Sender<CombatEvent>) {
+    let (event_tx, event_rx) = mpsc::channel();
+    let server = Server::new(PLAYER)
+        .address("127.0.0.1:0")
+        .snapshot_interval(Duration::from_millis(20))
+        .start(event_rx)
+        .unwrap();
+    (server, event_tx)
+}
+
+/// Plays the log back into the channel of a server
+fn replay(path: &Path, events: mpsc::Sender<CombatEvent>) {
+    for event in Replay::open(path).unwrap() {
+        events.send(event.unwrap()).unwrap();
+    }
+}
+
+/// Sends a raw request, returning the head and body of the response
+fn response(address: SocketAddr, request: &str) -> (String, String) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    (head.to_string(), body.to_string())
+}
+
+/// Sends a raw request, returning the status code and body of the response
+fn request(address: SocketAddr, request: &str) -> (u16, String) {
+    let (head, body) = response(address, request);
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, body)
+}
+
+/// Fetches a JSON endpoint
+fn get(address: SocketAddr, path: &str) -> (u16, Value) {
+    let (status, body) = request(address,
+        &format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path));
+    (status, serde_json::from_str(&body).unwrap())
+}
+
+/// Reads the next JSON message of a WebSocket
+fn next_message(socket: &mut WebSocket<TcpStream>) -> Value {
+    loop {
+        match socket.read().unwrap() {
+            Message::Text(text) => return serde_json::from_str(&text).unwrap(),
+            Message::Ping(_) | Message::Pong(_) => continue,
+            message => panic!("Unexpected message {:?}", message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_websocket_stream() {
+        let directory = tempfile::tempdir().unwrap();
+        let log = directory.path().join("serve.jsonl");
+        write_log(&log);
+        let (server, event_tx) = start_server();
+
+        let stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let (mut socket, _) = tungstenite::client(
+            format!("ws://{}/events", server.local_addr()), stream).unwrap();
+
+        // The current state comes first, before anything happened
+        let first = next_message(&mut socket);
+        assert_eq!(first["type"], "snapshot");
+        assert_eq!(first["events"], 0);
+        assert_eq!(server.stats().clients, 1);
+
+        // Every event arrives in order, along with snapshots in between
+        replay(&log, event_tx);
+        let mut streamed: Vec<CombatEvent> = vec![];
+        let mut last_snapshot = None;
+        while last_snapshot.as_ref().is_none_or(|snapshot: &Value| {
+            snapshot["events"] != events().len()
+        }) {
+            let mut message = next_message(&mut socket);
+            match message["type"].as_str() {
+                Some("event") => {
+                    message.as_object_mut().unwrap().remove("type");
+                    streamed.push(serde_json::from_value(message).unwrap());
+                }
+                Some("snapshot") => last_snapshot = Some(message),
+                _ => panic!("Unexpected message {}", message),
+            }
+        }
+        assert_eq!(streamed, events());
+        let snapshot = last_snapshot.unwrap();
+        assert_eq!(snapshot["damage"], 1250.0);
+        assert_eq!(snapshot["zone"], "Kyovashad");
+
+        // Stopping the server closes the stream
+        let stats = server.stop();
+        assert_eq!(stats.events, events().len() as u64);
+        loop {
+            match socket.read() {
+                Ok(Message::Close(_)) | Err(_) => break,
+                Ok(_) => continue,
+            }
+        }
+    }
+
+    #[test]
+    fn test_join_mid_stream() {
+        let (server, event_tx) = start_server();
+        let total = 5000;
+        let sender = std::thread::spawn(move || {
+            for batch in 0..total / 100 {
+                for index in 0..100 {
+                    let second = (batch * 100 + index) as f64 * 0.001;
+                    event_tx.send(hit(second, damage(
+                        Actor::new(PLAYER, "Lilith"), Actor::new(2, "Fallen"),
+                        Some("Spark"), 1.0))).unwrap();
+                }
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        });
+
+        // Join several times once the events are flowing
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while server.stats().events == 0 {
+            assert!(Instant::now() < deadline, "Events never arrived");
+            std::thread::yield_now();
+        }
+        let clients: Vec<_> = (0..4).map(|_| {
+            let stream = TcpStream::connect(server.local_addr()).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let (mut socket, _) = tungstenite::client(
+                format!("ws://{}/events", server.local_addr()), stream).unwrap();
+            let first = next_message(&mut socket);
+            assert_eq!(first["type"], "snapshot");
+            (socket, first["events"].as_u64().unwrap())
+        }).collect();
+
+        // Every event is either in the first snapshot or streamed after it
+        for (mut socket, mut counted) in clients {
+            loop {
+                let message = next_message(&mut socket);
+                match message["type"].as_str() {
+                    Some("event") => counted += 1,
+                    Some("snapshot") => {
+                        assert_eq!(message["events"], counted);
+                        if counted == total as u64 {
+                            break;
+                        }
+                    }
+                    _ => panic!("Unexpected message {}", message),
+                }
+            }
+        }
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_rest_endpoints() {
+        let directory = tempfile::tempdir().unwrap();
+        let log = directory.path().join("serve.jsonl");
+        write_log(&log);
+        let (server, event_tx) = start_server();
+        let address = server.local_addr();
+        replay(&log, event_tx);
+
+        // Events are applied on the server thread, so wait for the last one
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let stats = loop {
+            let (status, stats) = get(address, "/stats");
+            assert_eq!(status, 200);
+            if stats["events"] == events().len() {
+                break stats;
+            }
+            assert!(Instant::now() < deadline, "Events never arrived");
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(stats["damage"], 1250.0);
+        assert_eq!(stats["dps"], 312.5);
+        assert_eq!(stats["buffs"][0]["buff"], "Berserking");
+        assert_eq!(stats["buffs"][0]["remaining"], 6.0);
+
+        let (status, dps) = get(address, "/dps");
+        assert_eq!(status, 200);
+        assert_eq!(dps["encounters"][0]["skills"][0]["skill"], "Spark");
+        let (status, buffs) = get(address, "/buffs");
+        assert_eq!(status, 200);
+        assert_eq!(buffs["encounters"][0]["buffs"][0]["uptime"], 1.0);
+        assert_eq!(get(address, "/defense").0, 200);
+        assert_eq!(get(address, "/missing").0, 404);
+        assert_eq!(request(address, "POST /stats HTTP/1.1\r\n\r\n").0, 405);
+    }
+
+    #[test]
+    fn test_stalled_client() {
+        let (server, event_tx) = start_server();
+        let stream = TcpStream::connect(server.local_addr()).unwrap();
+        let (mut socket, _) = tungstenite::client(
+            format!("ws://{}/events", server.local_addr()), stream).unwrap();
+        assert_eq!(next_message(&mut socket)["type"], "snapshot");
+
+        // A client that stops reading is dropped once the socket buffers and
+        // its queue fill up, rather than queueing every event
+        let skill = "Spark".repeat(200);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut second = 0.0;
+        while server.stats().dropped == 0 {
+            assert!(Instant::now() < deadline, "Client was never dropped");
+            for _ in 0..1000 {
+                second += 0.001;
+                event_tx.send(hit(second, damage(Actor::new(PLAYER, "Lilith"),
+                    Actor::new(2, "Fallen"), Some(&skill), 1.0))).unwrap();
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        // Other clients are still served
+        let (status, stats) = get(server.local_addr(), "/stats");
+        assert_eq!(status, 200);
+        assert!(stats["events"].as_u64().unwrap() > 0);
+        drop(socket);
+    }
+
+    #[test]
+    fn test_localhost_and_bad_requests() {
+        let address: SocketAddr = DEFAULT_ADDRESS.parse().unwrap();
+        assert!(address.ip().is_loopback());
+
+        let (server, event_tx) = start_server();
+        assert!(server.local_addr().ip().is_loopback());
+        let (status, body) = request(server.local_addr(), "nonsense\r\n\r\n");
+        assert_eq!(status, 400);
+        assert!(body.contains("error"));
+
+        // A closed source leaves the server answering with the final state
+        drop(event_tx);
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(get(server.local_addr(), "/stats").1["events"], 0);
+        assert_eq!(server.stop().requests, 1);
+    }
+
+    #[test]
+    fn test_origins() {
+        let (event_tx, event_rx) = mpsc::channel::<CombatEvent>();
+        let server = Server::new(PLAYER)
+            .address("127.0.0.1:0")
+            .allow_origin("http://localhost:8080/")
+            .start(event_rx)
+            .unwrap();
+        let address = server.local_addr();
+        let from = |origin: &str| response(address, &format!(
+            "GET /stats HTTP/1.1\r\nOrigin: {}\r\n\r\n", origin)).0;
+
+        // Programs other than browsers send no origin and get no CORS header
+        let (head, _) = response(address, "GET /stats HTTP/1.1\r\n\r\n");
+        assert!(head.starts_with("HTTP/1.1 200"));
+        assert!(!head.contains("Access-Control-Allow-Origin"));
+
+        // Local files and allowed origins may read the response
+        for origin in ["null", "file://", "http://localhost:8080"] {
+            let head = from(origin);
+            assert!(head.starts_with("HTTP/1.1 200"), "{}", origin);
+            assert!(head.contains(
+                &format!("Access-Control-Allow-Origin: {}\r\n", origin)));
+        }
+
+        // Any other web page is turned away, including from the stream
+        let head = from("https://example.com");
+        assert!(head.starts_with("HTTP/1.1 403"));
+        assert!(!head.contains("Access-Control-Allow-Origin"));
+        let (status, _) = request(address, "GET /events HTTP/1.1\r\n\
+            Upgrade: websocket\r\n\
+            Connection: Upgrade\r\n\
+            Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+            Sec-WebSocket-Version: 13\r\n\
+            Origin: http://localhost:9090\r\n\r\n");
+        assert_eq!(status, 403);
+        assert_eq!(server.stats().clients, 0);
+        drop(event_tx);
+    }
+
+    #[test]
+    fn test_connection_limit() {
+        let (server, _event_tx) = start_server();
+        let address = server.local_addr();
+
+        // Connections that never send their request hold on to their slot
+        let idle: Vec<_> = (0..MAX_CONNECTIONS)
+            .map(|_| TcpStream::connect(address).unwrap())
+            .collect();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while server.stats().connections < MAX_CONNECTIONS {
+            assert!(Instant::now() < deadline, "Connections never accepted");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let mut refused = TcpStream::connect(address).unwrap();
+        refused.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let _ = refused.write_all(b"GET /stats HTTP/1.1\r\n\r\n");
+        let mut response = vec![];
+        let _ = refused.read_to_end(&mut response);
+        assert!(response.is_empty());
+
+        // Leaving frees the slots again
+        drop(idle);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while server.stats().connections > 0 {
+            assert!(Instant::now() < deadline, "Connections never released");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(get(address, "/stats").0, 200);
+    }
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use combat_logger::memory::{
+    DiffMode, MemoryReader, MemoryRegion, Snapshot, Value, ValueType,
+    WritePolicy,
+};
+use common::{run_test_binary, run_test_binary_with_args};
+
+/// Waits long enough for the counter of the test binary to move
+fn let_counter_run() {
+    std::thread::sleep(std::time::Duration::from_millis(100));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_and_diff() {
+        let test_process = run_test_binary_with_args(&["counter"]);
+        let start = test_process.start_addr;
+        let memory_reader = MemoryReader::from_pid(test_process.process_id)
+            .with_write_policy(WritePolicy::enabled());
+        let directory = tempfile::tempdir().unwrap();
+
+        // Only capture the page holding the counter, the kernel may have
+        // merged its mapping with neighbours such as thread stacks
+        let region = memory_reader.regions().unwrap()
+            .into_iter()
+            .find(|region| region.contains(start))
+            .unwrap();
+        let regions = [MemoryRegion {
+            range: start as u64..(start + test_process.size) as u64,
+            ..region
+        }];
+        memory_reader.write_bytes(start + 8, &7i32.to_le_bytes()).unwrap();
+
+        let first = directory.path().join("first.snap");
+        let captured = memory_reader.capture_snapshot(&regions, &first)
+            .unwrap();
+        assert_eq!(captured, regions[0].len());
+
+        let_counter_run();
+        memory_reader.write_bytes(start + 8, &3i32.to_le_bytes())
+            .unwrap();
+        let second = directory.path().join("second.snap");
+        memory_reader.capture_snapshot(&regions, &second).unwrap();
+
+        let first = Snapshot::open(&first).unwrap();
+        let second = Snapshot::open(&second).unwrap();
+
+        let increased = first.diff(&second, ValueType::U32,
+            DiffMode::Increased).unwrap();
+        assert_eq!(increased.len(), 1);
+        assert_eq!(increased[0].address, start);
+
+        let decreased = first.diff(&second, ValueType::I32,
+            DiffMode::Decreased).unwrap();
+        assert_eq!(decreased.len(), 1);
+        assert_eq!(decreased[0].address, start + 8);
+        assert_eq!(decreased[0].old, Value::I32(7));
+        assert_eq!(decreased[0].new, Value::I32(3));
+
+        let changed = first.diff(&second, ValueType::I32, DiffMode::Changed)
+            .unwrap();
+        assert_eq!(changed.len(), 2);
+
+        // Everything else in the region stayed the same
+        let unchanged = first.diff(&second, ValueType::I32,
+            DiffMode::Unchanged).unwrap();
+        assert_eq!(unchanged.len() + changed.len(),
+            regions[0].len() as usize / 4);
+
+        // Visiting the matches sees the same ones without keeping them
+        let mut first_unchanged = None;
+        let count = first.diff_each(&second, ValueType::I32,
+            DiffMode::Unchanged, |change| {
+                first_unchanged.get_or_insert(change);
+            }).unwrap();
+        assert_eq!(count, unchanged.len() as u64);
+        assert_eq!(first_unchanged, unchanged.first().copied());
+    }
+
+    #[test]
+    fn test_cli_snapshot_diff() {
+        let test_process = run_test_binary_with_args(&["counter"]);
+        let pid = test_process.process_id.to_string();
+        let directory = tempfile::tempdir().unwrap();
+        let first = directory.path().join("first.snap");
+        let second = directory.path().join("second.snap");
+
+        let snapshot = |path: &std::path::Path| {
+            let status = std::process::Command::new(
+                    env!("CARGO_BIN_EXE_combat_logger"))
+                .args(["snapshot", "--pid", &pid, "--writable"])
+                .arg(path)
+                .stdout(std::process::Stdio::null())
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        snapshot(&first);
+        let_counter_run();
+        snapshot(&second);
+
+        let output = std::process::Command::new(
+                env!("CARGO_BIN_EXE_combat_logger"))
+            .args(["diff", "--type", "u32", "--mode", "increased",
+                "--limit", "100000"])
+            .arg(&first)
+            .arg(&second)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let counter = format!("0x{:x} ", test_process.start_addr);
+        assert!(stdout.lines().any(|line| line.starts_with(&counter)),
+            "Counter missing from diff output:\n{}", stdout);
+    }
+
+    #[test]
+    fn test_capture_skips_unreadable() {
+        let test_process = run_test_binary();
+        let memory_reader = MemoryReader::from_pid(test_process.process_id);
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("gap.snap");
+
+        // A region that claims to extend past the mapped page keeps only
+        // the readable part
+        let mut region: MemoryRegion = memory_reader.regions().unwrap()
+            .into_iter()
+            .find(|region| region.contains(test_process.start_addr))
+            .unwrap();
+        region.range.start = test_process.start_addr as u64;
+        region.range.end = region.range.start + (4 << 20);
+
+        memory_reader.capture_snapshot(&[region.clone()], &path).unwrap();
+        let snapshot = Snapshot::open(&path).unwrap();
+        let first = &snapshot.entries[0].region;
+        assert_eq!(first.range.start, test_process.start_addr as u64);
+        assert!(first.range.end < region.range.end);
+    }
+}
@@ -0,0 +1,176 @@
+use combat_logger::memory::{
+    Error, MemoryReader, MemorySource, MockMemory, Pattern, Permissions,
+    PointerChain, Snapshot, SnapshotMemory, SnapshotWriter, WritePolicy,
+};
+
+/// Module base used by the mock game image
+const MODULE_BASE: usize = 0x1_4000_0000;
+
+/// Heap address used by the mock game image
+const HEAP_BASE: usize = 0x7f00_0000_0000;
+
+/// Builds a small address space with a module holding a pointer into a
+/// heap buffer, which holds a float
+fn mock_game() -> MockMemory {
+    let mut module = vec![0u8; 0x1000];
+    module[..4].copy_from_slice(b"MZ\x90\0");
+    module[0x1A0..0x1A8].copy_from_slice(&(HEAP_BASE as u64).to_le_bytes());
+
+    let mut heap = vec![0u8; 0x100];
+    heap[0x40..0x44].copy_from_slice(&1234.5f32.to_le_bytes());
+
+    MockMemory::new()
+        .with_module(MODULE_BASE, module, "/games/Diablo IV/Diablo IV.exe")
+        .with_buffer(HEAP_BASE, heap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_reads() {
+        let memory_reader = MemoryReader::with_source(mock_game());
+
+        assert_eq!(memory_reader.module_base("Diablo IV.exe").unwrap(),
+            MODULE_BASE);
+        assert_eq!(memory_reader.read_bytes(MODULE_BASE, 2).unwrap(), b"MZ");
+
+        let chain = PointerChain::new("Diablo IV.exe", &[0x1A0, 0x40]);
+        assert_eq!(memory_reader.read_chain::<f32>(&chain).unwrap(), 1234.5);
+
+        let pattern: Pattern = "4D 5A ?? 00".parse().unwrap();
+        assert_eq!(memory_reader.scan(&pattern).unwrap(), vec![MODULE_BASE]);
+
+        // Reads stop at the end of the mapped buffer
+        assert!(matches!(memory_reader.read_bytes(HEAP_BASE + 0xFC, 8),
+            Err(Error::ReadMemoryPartial(_, 4))));
+        assert!(matches!(memory_reader.read_bytes(0x1000, 8),
+            Err(Error::ReadMemoryFailed(0x1000, None))));
+
+        let results = memory_reader.read_batch(
+            &[(MODULE_BASE, 2), (0x10, 1), (HEAP_BASE + 0x40, 4)]);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap(), &1234.5f32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_mock_permissions() {
+        let read_only = Permissions {
+            read: true,
+            ..Default::default()
+        };
+        let memory = MockMemory::new()
+            .with_buffer(0x1000, vec![0; 0x10])
+            .with_region(0x1010, vec![7; 0x10], read_only, None);
+
+        // Adjacent regions read as one, but writes stop at read-only memory
+        assert_eq!(memory.read_bytes(0x1008, 0x10).unwrap(),
+            [[0; 8], [7; 8]].concat());
+        assert!(memory.write_bytes(0x1000, &[1; 4]).is_ok());
+        assert!(matches!(memory.write_bytes(0x100C, &[1; 8]),
+            Err(Error::WriteMemoryPartial(0x100C, 4))));
+        assert_eq!(memory.read_bytes(0x1000, 4).unwrap(), [1; 4]);
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let memory = mock_game();
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("game.snap");
+
+        let mut writer = SnapshotWriter::create(&path).unwrap();
+        for region in memory.regions().unwrap().iter().rev() {
+            let data = memory.read_bytes(region.range.start as usize,
+                region.len() as usize).unwrap();
+            writer.add_region(region, &data).unwrap();
+        }
+        writer.finish().unwrap();
+
+        // The index is ordered by address and keeps the mapping details
+        let snapshot = Snapshot::open(&path).unwrap();
+        assert_eq!(snapshot.entries.len(), 2);
+        assert_eq!(snapshot.entries[0].region, memory.regions().unwrap()[0]);
+        assert!(snapshot.captured_at > 0);
+
+        let memory_reader = MemoryReader::with_source(
+            SnapshotMemory::open(&path).unwrap())
+            .with_write_policy(WritePolicy::enabled());
+        let chain = PointerChain::new("Diablo IV.exe", &[0x1A0, 0x40]);
+        assert_eq!(memory_reader.read_chain::<f32>(&chain).unwrap(), 1234.5);
+        assert!(memory_reader.write_bytes(HEAP_BASE, &[0]).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_streamed_region() {
+        let memory = mock_game();
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("game.snap");
+
+        // The heap arrives in chunks and stops short of its end
+        let heap = memory.regions().unwrap()[1].clone();
+        let mut writer = SnapshotWriter::create(&path).unwrap();
+        writer.begin_region(heap.clone()).unwrap();
+        for chunk in memory.read_bytes(HEAP_BASE, 0x80).unwrap().chunks(0x10) {
+            writer.append_chunk(chunk).unwrap();
+        }
+        assert!(writer.append_chunk(&[0; 0x81]).is_err());
+        assert_eq!(writer.end_region().unwrap(), 0x80);
+        writer.finish().unwrap();
+
+        let snapshot = Snapshot::open(&path).unwrap();
+        assert_eq!(snapshot.entries.len(), 1);
+        assert_eq!(snapshot.entries[0].region.range,
+            heap.range.start..heap.range.start + 0x80);
+
+        let memory_reader = MemoryReader::with_source(
+            SnapshotMemory::open(&path).unwrap());
+        assert_eq!(memory_reader.read::<f32>(HEAP_BASE + 0x40).unwrap(),
+            1234.5);
+    }
+
+    #[test]
+    fn test_snapshot_rejects_other_files() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("not.snap");
+        std::fs::write(&path, b"definitely not a snapshot").unwrap();
+
+        assert!(Snapshot::open(&path).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_rejects_corrupt_sizes() {
+        let memory = mock_game();
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("game.snap");
+        let mut writer = SnapshotWriter::create(&path).unwrap();
+        for region in memory.regions().unwrap() {
+            let data = memory.read_bytes(region.range.start as usize,
+                region.len() as usize).unwrap();
+            writer.add_region(&region, &data).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let valid = std::fs::read(&path).unwrap();
+        let index = u64::from_le_bytes(
+            valid[valid.len() - 8..].try_into().unwrap()) as usize;
+        let corrupt = |at: usize, bytes: &[u8]| {
+            let mut file = valid.clone();
+            file[at..at + bytes.len()].copy_from_slice(bytes);
+            std::fs::write(&path, file).unwrap();
+            Snapshot::open(&path)
+        };
+
+        // Region count, pathname length, data length and index offset
+        // that point past the end of the file
+        let pathname_len = index + 4 + 8 + 8 + 1 + 8 + 4 + 4 + 8;
+        assert!(corrupt(index, &u32::MAX.to_le_bytes()).is_err());
+        assert!(corrupt(pathname_len, &u32::MAX.to_le_bytes()).is_err());
+        let data_len = pathname_len + 4 + "/games/Diablo IV/Diablo IV.exe"
+            .len() + 8;
+        assert!(corrupt(data_len, &u64::MAX.to_le_bytes()).is_err());
+        assert!(corrupt(valid.len() - 8, &u64::MAX.to_le_bytes()).is_err());
+        assert!(corrupt(0, &[]).is_ok());
+    }
+}
@@ -0,0 +1,277 @@
+mod common;
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use combat_logger::logger::{
+    compress_log, recover_log, Actor, Cast, CombatEvent, EventKind, GameInfo,
+    LogFormat, LogHeader, LogReader, LogWriter, Replay, SessionWriter,
+    Timestamp, ZoneChange,
+};
+
+/// Casts with a zone change every fifth event, 100ms apart
+fn events(count: u64) -> Vec<CombatEvent> {
+    (0..count)
+        .map(|i| {
+            let kind = if i % 5 == 0 {
+                let zone = format!("Zone {}", i);
+                EventKind::ZoneChange(ZoneChange { zone })
+            }
+            else {
+                EventKind::Cast(Cast {
+                    source: Actor::new(1, "Lilith"),
+                    skill: "Blizzard".to_string(),
+                    cooldown: None,
+                })
+            };
+            CombatEvent::new(Timestamp(i * 100_000), kind)
+        })
+        .collect()
+}
+
+/// Reads every event of a log
+fn read_all(path: &Path) -> Vec<CombatEvent> {
+    LogReader::open(path).unwrap().map(Result::unwrap).collect()
+}
+
+/// Writes a single log of the given events
+fn write_log(path: &Path, format: LogFormat, events: &[CombatEvent]) {
+    common::write_log(path, format,
+        LogHeader::new("crash", 0, 0, GameInfo::default()), events);
+}
+
+/// Appends bytes to a file, as a crash mid-write could leave behind
+fn append(path: &Path, bytes: &[u8]) {
+    OpenOptions::new().append(true).open(path).unwrap()
+        .write_all(bytes).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_by_size() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut writer = SessionWriter::new(directory.path(), LogFormat::Json)
+            .unwrap()
+            .max_file_size(1000);
+        writer.start_segment(0, GameInfo::default()).unwrap();
+        for event in events(40) {
+            writer.write(&event).unwrap();
+        }
+        writer.finish_segment().unwrap();
+
+        // Rotated files sort in the order they were written
+        let segments = writer.segments().to_vec();
+        assert!(segments.len() > 2, "{:?}", segments);
+        let mut sorted = segments.clone();
+        sorted.sort();
+        assert_eq!(sorted, segments);
+
+        let mut read = vec![];
+        for (part, path) in segments.iter().enumerate() {
+            let reader = LogReader::open(path).unwrap();
+            assert_eq!(reader.header().segment, 0);
+            assert_eq!(reader.header().part, part as u32);
+            read.extend(reader.map(Result::unwrap));
+        }
+        assert_eq!(read, events(40));
+    }
+
+    #[test]
+    fn test_rotate_by_zone_and_age() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut writer = SessionWriter::new(directory.path(), LogFormat::Binary)
+            .unwrap()
+            .rotate_on_zone();
+        writer.start_segment(3, GameInfo::default()).unwrap();
+        for event in events(12) {
+            writer.write(&event).unwrap();
+        }
+        writer.finish_segment().unwrap();
+
+        // The first zone change does not leave an empty file behind
+        let segments = writer.segments().to_vec();
+        assert_eq!(segments.len(), 3);
+        for path in &segments {
+            assert_eq!(read_all(path)[0].kind.name(), "zone_change");
+        }
+        assert!(segments[1].to_str().unwrap().ends_with("-003_001.d4log"));
+
+        let mut writer = SessionWriter::new(directory.path(), LogFormat::Json)
+            .unwrap()
+            .max_file_age(Duration::from_millis(20));
+        writer.start_segment(0, GameInfo::default()).unwrap();
+        writer.write(&events(2)[0]).unwrap();
+        std::thread::sleep(Duration::from_millis(30));
+        writer.write(&events(2)[1]).unwrap();
+        assert_eq!(writer.segments().len(), 2);
+    }
+
+    #[test]
+    fn test_compression() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut writer = SessionWriter::new(directory.path(), LogFormat::Json)
+            .unwrap()
+            .compress();
+        let path = writer.start_segment(0, GameInfo::default()).unwrap();
+        for event in events(20) {
+            writer.write(&event).unwrap();
+        }
+        writer.flush().unwrap();
+        assert_eq!(read_all(&path), events(20));
+        writer.finish_segment().unwrap();
+
+        // Only the compressed file is left
+        let compressed = writer.segments()[0].clone();
+        assert!(compressed.to_str().unwrap().ends_with(".jsonl.gz"));
+        assert!(!path.exists());
+        assert!(compressed.metadata().unwrap().len()
+            < serde_json::to_vec(&events(20)).unwrap().len() as u64);
+        assert_eq!(read_all(&compressed), events(20));
+
+        // Compressed logs can still be replayed from any point
+        let mut replay = Replay::open(&compressed).unwrap();
+        replay.seek(Timestamp(1_500_000)).unwrap();
+        assert_eq!(replay.count(), 5);
+
+        let plain = directory.path().join("plain.d4log");
+        write_log(&plain, LogFormat::Binary, &events(20));
+        let compressed = compress_log(&plain).unwrap();
+        assert_eq!(compressed, PathBuf::from(format!("{}.gz",
+            plain.display())));
+        assert_eq!(read_all(&compressed), events(20));
+    }
+
+    #[test]
+    fn test_recover_truncated() {
+        let directory = tempfile::tempdir().unwrap();
+        for format in [LogFormat::Json, LogFormat::Binary] {
+            let path = directory.path().join(
+                format!("crash.{}", format.extension()));
+            write_log(&path, format, &events(10));
+            let intact = path.metadata().unwrap().len();
+            assert_eq!(recover_log(&path).unwrap(), None);
+
+            // Half of a record, then zeros the filesystem may leave
+            let mut writer = LogWriter::new(vec![], format,
+                LogHeader::new("crash", 0, 0, GameInfo::default())).unwrap();
+            let start = writer.bytes_written() as usize;
+            writer.write(&events(11)[10]).unwrap();
+            let record = writer.into_inner().unwrap().split_off(start);
+            append(&path, &record[..record.len() / 2]);
+            let recovery = recover_log(&path).unwrap().unwrap();
+            assert_eq!(recovery.recovered_len, intact);
+            assert_eq!(read_all(&path), events(10));
+
+            append(&path, &[0; 64]);
+            assert!(recover_log(&path).unwrap().is_some());
+            assert_eq!(read_all(&path), events(10));
+        }
+    }
+
+    #[test]
+    fn test_recover_corrupt_record() {
+        let directory = tempfile::tempdir().unwrap();
+        for format in [LogFormat::Json, LogFormat::Binary] {
+            let path = directory.path().join(
+                format!("corrupt.{}", format.extension()));
+            write_log(&path, format, &events(10));
+
+            // Damage the sixth record, which is complete and followed by
+            // intact ones
+            let mut reader = LogReader::open(&path).unwrap();
+            for _ in 0..5 {
+                reader.read_event().unwrap();
+            }
+            let start = reader.offset();
+            let mut bytes = std::fs::read(&path).unwrap();
+            let payload = start as usize
+                + if format == LogFormat::Binary { 8 } else { 0 };
+            bytes[payload] ^= 0xff;
+            std::fs::write(&path, &bytes).unwrap();
+
+            // Nothing is cut, so the records after it are still there
+            assert_eq!(recover_log(&path).unwrap(), None);
+            assert_eq!(std::fs::read(&path).unwrap(), bytes);
+            let read: Vec<_> = LogReader::open(&path).unwrap().collect();
+            assert!(read[5].is_err());
+            let after: Vec<_> = read.into_iter().skip(6)
+                .map(Result::unwrap)
+                .collect();
+            assert_eq!(after, events(10)[6..]);
+        }
+    }
+
+    #[test]
+    fn test_recover_reports_damaged_length() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("damaged.d4log");
+        write_log(&path, LogFormat::Binary, &events(10));
+
+        // A length no writer produces, with intact records after it
+        let mut reader = LogReader::open(&path).unwrap();
+        for _ in 0..3 {
+            reader.read_event().unwrap();
+        }
+        let start = reader.offset();
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[start as usize + 3] = 0xff;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let recovery = recover_log(&path).unwrap().unwrap();
+        assert_eq!(recovery.damaged_at, Some(start));
+        assert_eq!(recovery.recovered_len, recovery.original_len);
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_recover_skips_open_logs() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut running = SessionWriter::new(directory.path(), LogFormat::Json)
+            .unwrap();
+        let path = running.start_segment(0, GameInfo::default()).unwrap();
+        for event in events(5) {
+            running.write(&event).unwrap();
+        }
+        running.flush().unwrap();
+
+        // Half of a record, as a buffer flushed mid-record leaves it
+        append(&path, b"{\"timestamp\":9000");
+        let len = path.metadata().unwrap().len();
+        let second = SessionWriter::new(directory.path(), LogFormat::Json)
+            .unwrap();
+        assert!(second.recovered().is_empty());
+        assert_eq!(path.metadata().unwrap().len(), len);
+
+        // Once closed, the log is recovered like any other
+        drop(running);
+        let third = SessionWriter::new(directory.path(), LogFormat::Json)
+            .unwrap();
+        assert_eq!(third.recovered().len(), 1);
+        assert_eq!(read_all(&path), events(5));
+    }
+
+    #[test]
+    fn test_recover_on_startup() {
+        let directory = tempfile::tempdir().unwrap();
+        let cut = directory.path().join("cut.jsonl");
+        write_log(&cut, LogFormat::Json, &events(5));
+        append(&cut, b"{\"timestamp\":9000");
+
+        // A log cut off inside its header holds nothing worth keeping
+        let empty = directory.path().join("empty.d4log");
+        std::fs::write(&empty, b"D4CL\x01\x00").unwrap();
+
+        let writer = SessionWriter::new(directory.path(), LogFormat::Json)
+            .unwrap();
+        let recovered: Vec<&Path> = writer.recovered().iter()
+            .map(|recovery| recovery.path.as_path())
+            .collect();
+        assert_eq!(recovered, [cut.as_path(), empty.as_path()]);
+        assert_eq!(read_all(&cut), events(5));
+        assert!(!empty.exists());
+    }
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use combat_logger::memory::{
+    Error, MemoryReader, MockMemory, StringEncoding, WritePolicy,
+};
+use common::run_test_binary;
+
+/// Heap address used by the mock strings
+const HEAP_BASE: usize = 0x7f00_0000_0000;
+
+/// Encodes a string as UTF-16LE bytes
+fn utf16(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+/// Builds a heap holding strings in every supported layout
+fn mock_strings() -> MockMemory {
+    let mut heap = vec![0u8; 0x1000];
+    heap[..6].copy_from_slice(b"Lilith");
+    heap[0x10..0x14].copy_from_slice(b"ab\xffc");
+
+    // A UTF-16 string ending in a character with a zero low byte
+    let name = utf16("Sanctuary \u{0100}");
+    heap[0x100..0x100 + name.len()].copy_from_slice(&name);
+
+    // An unpaired surrogate
+    heap[0x200..0x206].copy_from_slice(&utf16("x_y"));
+    heap[0x202..0x204].copy_from_slice(&0xD800u16.to_le_bytes());
+
+    // (pointer, length) pairs pointing at the strings above
+    let sized = [(0x100, 11), (0x0, 6), (0x0, 0), (0x200, 3)];
+    for (i, (offset, len)) in sized.iter().enumerate() {
+        let pair = 0x800 + i * 16;
+        let pointer = if *len == 0 { 0 } else { (HEAP_BASE + offset) as u64 };
+        heap[pair..pair + 8].copy_from_slice(&pointer.to_le_bytes());
+        heap[pair + 8..pair + 16].copy_from_slice(&(*len as u64).to_le_bytes());
+    }
+    heap[0x840..0x848].copy_from_slice(&(HEAP_BASE as u64).to_le_bytes());
+    heap[0x848..0x850].copy_from_slice(&u64::MAX.to_le_bytes());
+
+    // A string ending right before the unmapped page after the heap
+    heap[0xFF8..0xFFE].copy_from_slice(b"Diablo");
+
+    MockMemory::new().with_buffer(HEAP_BASE, heap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8_strings() {
+        let memory_reader = MemoryReader::with_source(mock_strings());
+        let base = HEAP_BASE as u64;
+
+        assert_eq!(memory_reader.read_string(base..base + 0x100).unwrap(),
+            "Lilith");
+        assert_eq!(memory_reader.read_string(base..base + 3).unwrap(), "Lil");
+
+        // Invalid UTF-8 is reported as such, not as a failed read
+        let result = memory_reader.read_string(base + 0x10..base + 0x20);
+        assert!(matches!(result, Err(Error::UTF8Conversion(_))),
+            "Unexpected result {:?}", result);
+        assert_eq!(memory_reader.read_string_lossy(base + 0x10..base + 0x20)
+            .unwrap(), "ab\u{FFFD}c");
+
+        // Reads stop at the terminator before an unmapped page
+        assert_eq!(memory_reader.read_string(base + 0xFF8..base + 0x1100)
+            .unwrap(), "Diablo");
+
+        // Empty and reversed ranges hold nothing
+        assert_eq!(memory_reader.read_string(base..base).unwrap(), "");
+        assert_eq!(memory_reader.read_string(base + 4..base).unwrap(), "");
+        assert_eq!(memory_reader.read_utf16(base + 4..base + 1).unwrap(), "");
+    }
+
+    #[test]
+    fn test_utf16_strings() {
+        let memory_reader = MemoryReader::with_source(mock_strings());
+        let base = HEAP_BASE as u64;
+
+        assert_eq!(memory_reader.read_utf16(base + 0x100..base + 0x400)
+            .unwrap(), "Sanctuary \u{0100}");
+
+        // Odd trailing bytes are not part of the string
+        assert_eq!(memory_reader.read_utf16(base + 0x100..base + 0x105)
+            .unwrap(), "Sa");
+
+        let result = memory_reader.read_utf16(base + 0x200..base + 0x300);
+        assert!(matches!(result, Err(Error::UTF16Conversion(_))),
+            "Unexpected result {:?}", result);
+        assert_eq!(memory_reader.read_utf16_lossy(base + 0x200..base + 0x300)
+            .unwrap(), "x\u{FFFD}y");
+    }
+
+    #[test]
+    fn test_sized_strings() {
+        let memory_reader = MemoryReader::with_source(mock_strings());
+
+        assert_eq!(memory_reader.read_sized_string(HEAP_BASE + 0x800,
+            StringEncoding::Utf16).unwrap(), "Sanctuary \u{0100}");
+        assert_eq!(memory_reader.read_sized_string(HEAP_BASE + 0x810,
+            StringEncoding::Utf8).unwrap(), "Lilith");
+        assert_eq!(memory_reader.read_sized_string(HEAP_BASE + 0x820,
+            StringEncoding::Utf8).unwrap(), "");
+
+        let result = memory_reader.read_sized_string(HEAP_BASE + 0x830,
+            StringEncoding::Utf16);
+        assert!(matches!(result, Err(Error::UTF16Conversion(_))),
+            "Unexpected result {:?}", result);
+        assert_eq!(memory_reader.read_sized_string_lossy(HEAP_BASE + 0x830,
+            StringEncoding::Utf16).unwrap(), "x\u{FFFD}y");
+
+        // A garbage length is rejected before anything is allocated
+        let result = memory_reader.read_sized_string(HEAP_BASE + 0x840,
+            StringEncoding::Utf16);
+        assert!(matches!(result, Err(Error::ParseStr(_))),
+            "Unexpected result {:?}", result);
+    }
+
+    #[test]
+    fn test_live_utf16() {
+        let test_process = run_test_binary();
+        let start = test_process.start_addr;
+        let memory_reader = MemoryReader::from_pid(test_process.process_id)
+            .with_write_policy(WritePolicy::enabled());
+
+        // Long enough to span several chunks
+        let text = "Horadric Cube ".repeat(20);
+        let mut data = utf16(&text);
+        data.extend([0, 0]);
+        memory_reader.write_bytes(start, &data).unwrap();
+
+        let end = (start + test_process.size) as u64;
+        assert_eq!(memory_reader.read_utf16(start as u64..end).unwrap(),
+            text);
+    }
+}
@@ -0,0 +1,150 @@
+mod common;
+
+use combat_logger::memory::{
+    MemoryReader, MemoryRegion, MockMemory, ScanCondition, ScanFilter,
+    ValueScanner, WritePolicy,
+};
+use common::run_test_binary_with_args;
+
+/// Base address of the mock heap
+const HEAP_BASE: usize = 0x10_0000_0000;
+
+/// Size of the mock heap, spanning several scan chunks
+const HEAP_SIZE: usize = 3 << 20;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_narrow_i32() {
+        let memory_reader = MemoryReader::with_source(
+            MockMemory::new().with_buffer(HEAP_BASE, vec![0; HEAP_SIZE]))
+            .with_write_policy(WritePolicy::enabled());
+        let regions = memory_reader.regions().unwrap();
+        let health = HEAP_BASE + (1 << 20) + 0x40;
+        let decoy = HEAP_BASE + 0x1234;
+        memory_reader.write_bytes(health, &500i32.to_le_bytes()).unwrap();
+        memory_reader.write_bytes(decoy, &500i32.to_le_bytes()).unwrap();
+
+        let mut scanner = ValueScanner::<i32>::new();
+        let found = scanner.first_scan(&memory_reader, &regions,
+            ScanCondition::Exact(500)).unwrap();
+        assert_eq!(found, 2);
+
+        // Take 20 damage, the decoy stays the same
+        memory_reader.write_bytes(health, &480i32.to_le_bytes()).unwrap();
+        assert_eq!(scanner.next_scan(&memory_reader,
+            ScanFilter::DecreasedBy(20)).unwrap(), 1);
+        assert_eq!(scanner.candidates().collect::<Vec<_>>(),
+            vec![(health, 480)]);
+
+        assert_eq!(scanner.next_scan(&memory_reader, ScanFilter::Unchanged)
+            .unwrap(), 1);
+        assert_eq!(scanner.next_scan(&memory_reader, ScanFilter::Changed)
+            .unwrap(), 0);
+        assert!(scanner.is_empty());
+    }
+
+    #[test]
+    fn test_dense_candidates() {
+        let memory_reader = MemoryReader::with_source(
+            MockMemory::new().with_buffer(HEAP_BASE, vec![0; HEAP_SIZE]))
+            .with_write_policy(WritePolicy::enabled());
+        let regions = memory_reader.regions().unwrap();
+
+        // Every slot matches a range scan over zeroed memory
+        let mut scanner = ValueScanner::<i32>::new();
+        let found = scanner.first_scan(&memory_reader, &regions,
+            ScanCondition::Range(-1, 1)).unwrap();
+        assert_eq!(found, HEAP_SIZE / 4);
+
+        let changed = [HEAP_BASE, HEAP_BASE + (2 << 20) - 4,
+            HEAP_BASE + HEAP_SIZE - 4];
+        for address in changed {
+            memory_reader.write_bytes(address, &7i32.to_le_bytes()).unwrap();
+        }
+        assert_eq!(scanner.next_scan(&memory_reader, ScanFilter::Increased)
+            .unwrap(), 3);
+        let addresses: Vec<usize> = scanner.candidates()
+            .map(|(address, _)| address)
+            .collect();
+        assert_eq!(addresses, changed);
+    }
+
+    #[test]
+    fn test_float_tolerance() {
+        let memory_reader = MemoryReader::with_source(
+            MockMemory::new().with_buffer(HEAP_BASE, vec![0; HEAP_SIZE]))
+            .with_write_policy(WritePolicy::enabled());
+        let regions = memory_reader.regions().unwrap();
+
+        // A value straddling the first chunk boundary, found unaligned
+        let address = HEAP_BASE + (1 << 20) - 2;
+        memory_reader.write_bytes(address, &99.9996f32.to_le_bytes())
+            .unwrap();
+
+        let mut exact = ValueScanner::<f32>::new().alignment(1);
+        assert_eq!(exact.first_scan(&memory_reader, &regions,
+            ScanCondition::Exact(100.0)).unwrap(), 0);
+
+        let mut scanner = ValueScanner::<f32>::new()
+            .alignment(1)
+            .tolerance(0.001);
+        assert_eq!(scanner.first_scan(&memory_reader, &regions,
+            ScanCondition::Exact(100.0)).unwrap(), 1);
+        assert_eq!(scanner.candidates().next().unwrap().0, address);
+
+        memory_reader.write_bytes(address, &87.5f32.to_le_bytes()).unwrap();
+        assert_eq!(scanner.next_scan(&memory_reader,
+            ScanFilter::Equals(87.5)).unwrap(), 1);
+        assert_eq!(scanner.next_scan(&memory_reader,
+            ScanFilter::InRange(0.0, 50.0)).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_f64_unreadable_region() {
+        let memory_reader = MemoryReader::with_source(
+            MockMemory::new().with_buffer(HEAP_BASE, vec![0; 0x1000]))
+            .with_write_policy(WritePolicy::enabled());
+        memory_reader.write_bytes(HEAP_BASE + 8, &2.5f64.to_le_bytes())
+            .unwrap();
+
+        // Regions that cannot be read just hold no candidates
+        let mut regions = memory_reader.regions().unwrap();
+        let unmapped = MemoryRegion {
+            range: 0x1000..0x2000,
+            ..regions[0].clone()
+        };
+        regions.insert(0, unmapped);
+
+        let mut scanner = ValueScanner::<f64>::new();
+        assert_eq!(scanner.first_scan(&memory_reader, &regions,
+            ScanCondition::Range(2.0, 3.0)).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_live_counter() {
+        let test_process = run_test_binary_with_args(&["counter"]);
+        let start = test_process.start_addr;
+        let memory_reader = MemoryReader::from_pid(test_process.process_id);
+        let region = memory_reader.regions().unwrap()
+            .into_iter()
+            .find(|region| region.contains(start))
+            .unwrap();
+        let regions = [MemoryRegion {
+            range: start as u64..(start + test_process.size) as u64,
+            ..region
+        }];
+
+        // The counter may not have started yet, so include zero
+        let mut scanner = ValueScanner::<i32>::new();
+        scanner.first_scan(&memory_reader, &regions,
+            ScanCondition::Range(0, i32::MAX)).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        scanner.next_scan(&memory_reader, ScanFilter::Increased).unwrap();
+
+        assert_eq!(scanner.candidates().map(|(address, _)| address)
+            .collect::<Vec<_>>(), vec![start]);
+    }
+}
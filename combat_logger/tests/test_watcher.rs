@@ -0,0 +1,167 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+use combat_logger::memory::{
+    MemoryReader, MemoryRegion, MemorySource, MockMemory, PointerChain,
+    Result, Value, ValueType, Watcher,
+};
+use common::run_test_binary_with_args;
+
+/// Module base used by the mock game image
+const MODULE_BASE: usize = 0x1_4000_0000;
+
+/// Heap address used by the mock game image
+const HEAP_BASE: usize = 0x7f00_0000_0000;
+
+/// Mock memory shared with the test, optionally slowed down to make the
+/// watcher fall behind its schedule
+struct SharedMemory {
+    memory: Arc<MockMemory>,
+    delay: Duration,
+}
+
+impl MemorySource for SharedMemory {
+    fn read_bytes(&self, address: usize, len: usize) -> Result<Vec<u8>> {
+        self.memory.read_bytes(address, len)
+    }
+
+    fn write_bytes(&self, address: usize, data: &[u8]) -> Result<()> {
+        self.memory.write_bytes(address, data)
+    }
+
+    fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        self.memory.regions()
+    }
+
+    fn read_batch(&self, requests: &[(usize, usize)])
+            -> Vec<Result<Vec<u8>>> {
+        std::thread::sleep(self.delay);
+        self.memory.read_batch(requests)
+    }
+}
+
+/// Builds a module holding a pointer to a heap buffer
+fn mock_game() -> Arc<MockMemory> {
+    let mut module = vec![0u8; 0x1000];
+    module[0x1A0..0x1A8].copy_from_slice(&(HEAP_BASE as u64).to_le_bytes());
+
+    Arc::new(MockMemory::new()
+        .with_module(MODULE_BASE, module, "/games/Diablo IV/Diablo IV.exe")
+        .with_buffer(HEAP_BASE, vec![0; 0x100]))
+}
+
+/// Creates a watcher over shared mock memory
+fn watcher(memory: &Arc<MockMemory>, delay: Duration)
+        -> Watcher<SharedMemory> {
+    Watcher::new(MemoryReader::with_source(SharedMemory {
+        memory: Arc::clone(memory),
+        delay,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Generous timeout for events, so slow machines do not fail the tests
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn test_watch_changes() {
+        let memory = mock_game();
+        let interval = Duration::from_millis(2);
+        let chain = PointerChain::new("Diablo IV.exe", &[0x1A0, 0x40]);
+
+        let mut watcher = watcher(&memory, Duration::ZERO);
+        watcher
+            .watch_address("health", HEAP_BASE + 0x10, ValueType::F32,
+                interval)
+            .watch_chain("gold", chain, ValueType::U64, interval);
+        let (handle, events) = watcher.start();
+
+        // The first poll reports the initial values
+        let mut initial: Vec<_> = (0..2)
+            .map(|_| events.recv_timeout(TIMEOUT).unwrap())
+            .map(|event| (event.name, event.old, event.new))
+            .collect();
+        initial.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(initial, [
+            ("gold".to_string(), None, Value::U64(0)),
+            ("health".to_string(), None, Value::F32(0.0)),
+        ]);
+
+        // Later polls only report changes
+        memory.write_bytes(HEAP_BASE + 0x10, &250.0f32.to_le_bytes())
+            .unwrap();
+        let event = events.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!((event.name.as_str(), event.old, event.new),
+            ("health", Some(Value::F32(0.0)), Value::F32(250.0)));
+
+        memory.write_bytes(HEAP_BASE + 0x40, &1000u64.to_le_bytes())
+            .unwrap();
+        let later = events.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!((later.name.as_str(), later.old, later.new),
+            ("gold", Some(Value::U64(0)), Value::U64(1000)));
+        assert!(later.timestamp > event.timestamp);
+
+        let stats = handle.stop();
+        assert_eq!(stats.events, 4);
+        assert_eq!(stats.read_errors, 0);
+        assert!(stats.polls >= 4);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_unreadable_watch() {
+        let memory = mock_game();
+        let mut watcher = watcher(&memory, Duration::ZERO);
+        watcher.watch_address("missing", 0x1000, ValueType::U32,
+            Duration::from_millis(1));
+        let (handle, events) = watcher.start();
+
+        std::thread::sleep(Duration::from_millis(20));
+        let stats = handle.stop();
+        assert!(stats.read_errors > 0);
+        assert_eq!(stats.read_errors, stats.polls);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_missed_ticks() {
+        // Each poll takes three intervals, so ticks in between are skipped
+        // rather than polled in a burst
+        let memory = mock_game();
+        let mut watcher = watcher(&memory, Duration::from_millis(30));
+        watcher.watch_address("health", HEAP_BASE, ValueType::U32,
+            Duration::from_millis(10));
+        let (handle, _events) = watcher.start();
+
+        std::thread::sleep(Duration::from_millis(200));
+        let stats = handle.stop();
+        assert!(stats.polls >= 2);
+        assert!(stats.missed_ticks >= stats.polls,
+            "Unexpected stats {:?}", stats);
+    }
+
+    #[test]
+    fn test_live_counter() {
+        let test_process = run_test_binary_with_args(&["counter"]);
+        let memory_reader = MemoryReader::from_pid(test_process.process_id);
+
+        let mut watcher = Watcher::new(memory_reader);
+        watcher.watch_address("counter", test_process.start_addr,
+            ValueType::U32, Duration::from_millis(10));
+        let (handle, events) = watcher.start();
+
+        // Every event continues from the previous one
+        let mut last = None;
+        for _ in 0..5 {
+            let event = events.recv_timeout(TIMEOUT).unwrap();
+            assert_eq!(event.old, last);
+            assert!(last.is_none_or(|last| event.new > last));
+            last = Some(event.new);
+        }
+        handle.stop();
+    }
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use combat_logger::memory::{
+    AllowedRegion, Error, MemoryReader, MockMemory, WriteMode, WritePolicy,
+    WriteRecord,
+};
+use common::run_test_binary;
+
+/// Module base used by the mock game image
+const MODULE_BASE: usize = 0x1_4000_0000;
+
+/// Heap address used by the mock game image
+const HEAP_BASE: usize = 0x7f00_0000_0000;
+
+/// Builds a read-only module next to two writable heap buffers
+fn mock_game() -> MockMemory {
+    MockMemory::new()
+        .with_module(MODULE_BASE, vec![0x90; 0x1000],
+            "/games/Diablo IV/Diablo IV.exe")
+        .with_buffer(HEAP_BASE, vec![0; 0x1000])
+        .with_buffer(HEAP_BASE + 0x1000, vec![0; 0x1000])
+}
+
+/// Checks that a write was denied by the policy
+fn assert_denied(result: combat_logger::memory::Result<()>) {
+    assert!(matches!(result, Err(Error::WriteDenied(..))),
+        "Unexpected result {:?}", result);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_only_by_default() {
+        let memory_reader = MemoryReader::with_source(mock_game());
+        assert_eq!(memory_reader.write_policy().mode, WriteMode::ReadOnly);
+        assert_denied(memory_reader.write_bytes(HEAP_BASE, &[1, 2, 3]));
+        assert_eq!(memory_reader.read_bytes(HEAP_BASE, 3).unwrap(), [0; 3]);
+
+        // Mapping new contents is setup, not a write through the policy
+        let region = memory_reader.regions().unwrap()[1].clone();
+        memory_reader.insert_region(region, vec![7; 0x1000]);
+        assert_eq!(memory_reader.read_bytes(HEAP_BASE, 3).unwrap(), [7; 3]);
+        assert_denied(memory_reader.write_bytes(HEAP_BASE, &[1, 2, 3]));
+
+        // An allowlist does not change why a read-only reader refuses
+        let memory_reader = MemoryReader::with_source(mock_game())
+            .with_write_policy(WritePolicy::read_only()
+                .allow(AllowedRegion::Module("Diablo IV.exe".to_string())));
+        let result = memory_reader.write_bytes(HEAP_BASE, &[1]);
+        assert!(matches!(&result, Err(Error::WriteDenied(_, reason))
+            if reason == "reader is read-only"), "Unexpected result {:?}",
+            result);
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let memory_reader = MemoryReader::with_source(mock_game())
+            .with_write_policy(WritePolicy::dry_run());
+
+        memory_reader.write_bytes(HEAP_BASE + 4, &[1, 2]).unwrap();
+        memory_reader.write_bytes(0x1000, &[3]).unwrap();
+        assert_eq!(memory_reader.read_bytes(HEAP_BASE + 4, 2).unwrap(),
+            [0, 0]);
+
+        assert_eq!(memory_reader.take_write_log(), [
+            WriteRecord {
+                address: HEAP_BASE + 4,
+                data: vec![1, 2],
+                original: Some(vec![0, 0]),
+            },
+            WriteRecord {
+                address: 0x1000,
+                data: vec![3],
+                original: None,
+            },
+        ]);
+        assert!(memory_reader.take_write_log().is_empty());
+    }
+
+    #[test]
+    fn test_allowlist() {
+        let memory_reader = MemoryReader::with_source(mock_game())
+            .with_write_policy(WritePolicy::enabled()
+                .allow(AllowedRegion::Range(
+                    HEAP_BASE as u64 + 0x800..HEAP_BASE as u64 + 0x1800))
+                .allow(AllowedRegion::Module("Diablo IV.exe".to_string())));
+
+        // Writes may span adjacent writable regions inside the allowlist
+        memory_reader.write_bytes(HEAP_BASE + 0xFFE, &[7; 4]).unwrap();
+        assert_eq!(memory_reader.read_bytes(HEAP_BASE + 0xFFE, 4).unwrap(),
+            [7; 4]);
+
+        assert_denied(memory_reader.write_bytes(HEAP_BASE, &[1]));
+        assert_denied(memory_reader.write_bytes(HEAP_BASE + 0x17FF, &[1, 2]));
+        assert_denied(memory_reader.write_bytes(usize::MAX, &[1, 2]));
+
+        // The module is allowed but mapped read-only
+        assert_denied(memory_reader.write_bytes(MODULE_BASE, &[0xCC]));
+        assert_eq!(memory_reader.read_bytes(MODULE_BASE, 1).unwrap(), [0x90]);
+
+        // Dry runs go through the same checks
+        let memory_reader = memory_reader.with_write_policy(
+            WritePolicy::dry_run().allow(AllowedRegion::Range(0..0x10)));
+        assert_denied(memory_reader.write_bytes(HEAP_BASE, &[1]));
+        assert!(memory_reader.take_write_log().is_empty());
+    }
+
+    #[test]
+    fn test_transaction() {
+        let memory_reader = MemoryReader::with_source(mock_game())
+            .with_write_policy(WritePolicy::enabled());
+
+        // Overlapping writes are undone in reverse order
+        {
+            let mut transaction = memory_reader.transaction();
+            transaction.write_bytes(HEAP_BASE, &[1, 1, 1, 1]).unwrap();
+            transaction.write_bytes(HEAP_BASE + 2, &[2, 2, 2, 2]).unwrap();
+            assert_eq!(memory_reader.read_bytes(HEAP_BASE, 6).unwrap(),
+                [1, 1, 2, 2, 2, 2]);
+            assert!(transaction.write_bytes(0x1000, &[3]).is_err());
+        }
+        assert_eq!(memory_reader.read_bytes(HEAP_BASE, 6).unwrap(), [0; 6]);
+
+        let mut transaction = memory_reader.transaction();
+        transaction.write_bytes(HEAP_BASE, &[5]).unwrap();
+        transaction.rollback().unwrap();
+        assert_eq!(memory_reader.read_bytes(HEAP_BASE, 1).unwrap(), [0]);
+
+        let mut transaction = memory_reader.transaction();
+        transaction.write_bytes(HEAP_BASE, &[9]).unwrap();
+        transaction.commit();
+        assert_eq!(memory_reader.read_bytes(HEAP_BASE, 1).unwrap(), [9]);
+
+        // A read-only reader cannot start writing through a transaction
+        let memory_reader = memory_reader.with_write_policy(
+            WritePolicy::read_only());
+        assert_denied(memory_reader.transaction().write_bytes(HEAP_BASE, &[1]));
+    }
+
+    #[test]
+    fn test_live_allowlist() {
+        let test_process = run_test_binary();
+        let start = test_process.start_addr;
+        let page = start as u64..(start + test_process.size) as u64;
+        let memory_reader = MemoryReader::from_pid(test_process.process_id)
+            .with_write_policy(WritePolicy::enabled()
+                .allow(AllowedRegion::Range(page)));
+
+        {
+            let mut transaction = memory_reader.transaction();
+            transaction.write_bytes(start, &[0xAB; 16]).unwrap();
+            assert_eq!(memory_reader.read_bytes(start, 16).unwrap(),
+                [0xAB; 16]);
+        }
+        assert_eq!(memory_reader.read_bytes(start, 16).unwrap(), [0; 16]);
+        assert_denied(memory_reader.write_bytes(start - 1, &[0; 2]));
+    }
+}